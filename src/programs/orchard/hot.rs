@@ -0,0 +1,9 @@
+use engine::Builder;
+use hot_reload::HotReload;
+
+fn main() -> std::io::Result<()> {
+	let mut builder = Builder::new();
+	HotReload::load(&mut builder, "orchard")?
+		.name("Orchard")
+		.run()
+}
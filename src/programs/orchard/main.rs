@@ -0,0 +1,4 @@
+use engine::define_run_module;
+use orchard::Orchard;
+
+define_run_module!(Orchard, "Orchard");
@@ -3,12 +3,12 @@ use {
 		Component,
 		Named,
 		Query,
-		ScheduleBlock,
+		Schedule,
 		System,
 		World,
 	},
 	engine::{
-		define_run_module,
+		define_library_module,
 		Builder,
 		Engine,
 		Module,
@@ -34,12 +34,15 @@ impl Module for Orchard {
 		let game: &Game = Engine::module().unwrap();
 		{
 			let mut schedule = game.schedule.lock().unwrap();
-			*schedule = ScheduleBlock::new()
-				.system(InputSystem)
-				.system(DebugSystem)
-				.system(BipedMovementSystem)
-				.system(PlayerControllerSystem)
-				.system(PhysicsSystem);
+			*schedule = Schedule::new()
+				.variable(|block| {
+					block
+						.system(InputSystem)
+						.system(DebugSystem)
+						.system(BipedMovementSystem)
+						.system(PlayerControllerSystem)
+				})
+				.fixed(|block| block.system(PhysicsSystem));
 		}
 
 		let window = Engine::window().unwrap();
@@ -302,4 +305,4 @@ impl System for PlayerControllerSystem {
 	}
 }
 
-define_run_module!(Orchard, "Orchard");
+define_library_module!(Orchard);
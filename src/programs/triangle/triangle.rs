@@ -12,11 +12,13 @@ use {
 		Format,
 		Gpu,
 		GraphicsPipeline,
-		GraphicsRecorder,
-		Layout,
 		MemoryType,
+		PresentLayer,
+		PresentPass,
+		RenderGraph,
 		Shader,
 		ShaderVariant,
+		TextureId,
 	},
 	math::{
 		Color,
@@ -70,13 +72,15 @@ static VERTICES: &[Vertex] = &[
 
 impl Module for Triangle {
 	fn new() -> Self {
-		let binary = gpu::compile("vertex.hlsl", SHADER, "VSMain", ShaderVariant::Vertex).unwrap();
+		let binary =
+			gpu::compile("vertex.hlsl", SHADER, "VSMain", ShaderVariant::Vertex, &[]).unwrap();
 		let vertex_shader = Shader::builder(&binary, ShaderVariant::Vertex)
 			.main("VSMain")
 			.spawn()
 			.unwrap();
 
-		let binary = gpu::compile("pixel.hlsl", SHADER, "PSMain", ShaderVariant::Pixel).unwrap();
+		let binary =
+			gpu::compile("pixel.hlsl", SHADER, "PSMain", ShaderVariant::Pixel, &[]).unwrap();
 		let pixel_shader = Shader::builder(&binary, ShaderVariant::Pixel)
 			.main("PSMain")
 			.spawn()
@@ -97,24 +101,24 @@ impl Module for Triangle {
 	}
 
 	fn depends_on(builder: &mut Builder) -> &mut Builder {
-		builder.module::<Gpu>().display(|| {
-			let triangle: &Triangle = Engine::module().unwrap();
-			let device = Gpu::device();
-			let backbuffer = device.acquire_backbuffer().unwrap();
-
-			let receipt = GraphicsRecorder::new()
-				.render_pass(&[&backbuffer], |ctx| {
-					ctx.clear_color(Color::BLACK)
-						.set_pipeline(&triangle.pipeline)
-						.set_vertex_buffer(&triangle.vertices)
-						.draw(VERTICES.len(), 0)
-				})
-				.texture_barrier(&backbuffer, Layout::ColorAttachment, Layout::Present)
-				.finish()
-				.submit();
+		builder
+			.module::<Gpu>()
+			.register(PresentPass::new(PresentLayer::GAME, Triangle::present))
+			.display(Gpu::present)
+	}
+}
 
-			device.display(&[receipt]);
-		})
+impl Triangle {
+	fn present(graph: &mut RenderGraph, backbuffer: TextureId) {
+		let triangle: &Triangle = Engine::module().unwrap();
+		graph
+			.add_pass("triangle")
+			.color(backbuffer)
+			.render(move |ctx, _| {
+				ctx.set_pipeline(&triangle.pipeline)
+					.set_vertex_buffer(&triangle.vertices)
+					.draw(VERTICES.len(), 0);
+			});
 	}
 }
 
@@ -53,6 +53,17 @@ impl<T: Cache> CacheRef<T> {
 	}
 }
 
+impl<T: Cache> CacheRef<T> {
+	/// Writes the cache back to disk. Caches that change after startup must use interior mutability and call
+	/// this once they have been updated.
+	pub fn save(&self) {
+		let contents = bincode::serialize(self.deref()).unwrap();
+		if let Err(err) = fs::write(&T::variant().path, contents) {
+			error!(CACHE_CATEGORY, "{}", err);
+		}
+	}
+}
+
 impl<T: Cache> Deref for CacheRef<T> {
 	type Target = T;
 	fn deref(&self) -> &Self::Target {
@@ -2,6 +2,8 @@
 
 use {
 	engine::{
+		define_log_category,
+		warn,
 		Engine,
 		Module,
 	},
@@ -22,6 +24,8 @@ use {
 	},
 };
 
+define_log_category!(Config, CONFIG_CATEGORY);
+
 pub struct ConfigManager {
 	pub(crate) entries: HashMap<TypeId, Box<dyn Any>>,
 }
@@ -98,10 +102,75 @@ impl Module for ConfigManager {
 			}
 		}
 
+		// Apply overrides passed on the command line as `name.field=value`
+		let variants: HashMap<&str, &ConfigVariant> = files_to_variants
+			.values()
+			.flat_map(|variants| variants.values())
+			.map(|variant| (variant.name.as_str(), variant))
+			.collect();
+		for (key, value) in Engine::arguments().config.iter() {
+			let (name, field) = key.split_once('.').unwrap_or((key, ""));
+			let variant = match variants.get(name) {
+				Some(variant) => variant,
+				None => {
+					warn!(
+						CONFIG_CATEGORY,
+						"Unknown config \"{}\" in override \"{}\"", name, key
+					);
+					continue;
+				}
+			};
+
+			let entry = entries.get_mut(&variant.id).unwrap();
+			let mut table = (variant.serialize)(entry);
+			if !set_value(&mut table, field, parse_value(value)) {
+				warn!(CONFIG_CATEGORY, "Invalid config override \"{}\"", key);
+				continue;
+			}
+			*entry = (variant.deserialize)(table);
+		}
+
 		Self { entries }
 	}
 }
 
+// Parses `value` as a toml value falling back to a string so `name=Billy` works without quotes
+fn parse_value(value: &str) -> Value {
+	format!("value = {}", value)
+		.parse::<Value>()
+		.ok()
+		.and_then(|it| it.get("value").cloned())
+		.unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+// Sets the member at the dotted `path` in `root`. An empty path replaces `root`.
+fn set_value(root: &mut Value, path: &str, value: Value) -> bool {
+	if path.is_empty() {
+		*root = value;
+		return true;
+	}
+
+	let mut current = root;
+	let mut members = path.split('.').peekable();
+	while let Some(member) = members.next() {
+		let table = match current.as_table_mut() {
+			Some(table) => table,
+			None => return false,
+		};
+
+		if members.peek().is_none() {
+			table.insert(member.to_string(), value);
+			return true;
+		}
+
+		current = match table.get_mut(member) {
+			Some(next) => next,
+			None => return false,
+		};
+	}
+	false
+}
+
 const CONFIG_PATH: &str = "config/";
 
 const DEFAULT_CONFIG_FILE: &str = "# Example Config File
@@ -156,11 +225,17 @@ pub trait Config: Serialize + DeserializeOwned + 'static + Default {
 			Box::new(value)
 		}
 
+		fn my_serialize<T: Config>(value: &Box<dyn Any>) -> Value {
+			let value: &T = value.downcast_ref().unwrap();
+			Value::try_from(value).unwrap()
+		}
+
 		ConfigVariant {
 			file: Self::FILE,
 			name,
 			id,
 			deserialize: my_deserialize::<Self>,
+			serialize: my_serialize::<Self>,
 			default: my_default::<Self>,
 		}
 	}
@@ -173,5 +248,6 @@ pub struct ConfigVariant {
 	id: TypeId,
 
 	deserialize: fn(Value) -> Box<dyn Any>,
+	serialize: fn(&Box<dyn Any>) -> Value,
 	default: fn() -> Box<dyn Any>,
 }
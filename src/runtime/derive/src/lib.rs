@@ -10,6 +10,85 @@ use syn::{
 	Ident,
 };
 
+#[proc_macro_derive(GpuConstants)]
+pub fn derive_gpu_constants(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	expand_derive_gpu_constants(&input)
+		.unwrap_or_else(|err| err.to_compile_error())
+		.into()
+}
+
+fn expand_derive_gpu_constants(input: &DeriveInput) -> syn::Result<TokenStream2> {
+	let ident = &input.ident;
+
+	if !input.generics.params.is_empty() {
+		return Err(syn::Error::new_spanned(
+			&input.generics,
+			"GpuConstants can not be derived for generic types",
+		));
+	}
+
+	let repr_c = input
+		.attrs
+		.iter()
+		.any(|attr| attr.path.is_ident("repr") && attr.tokens.to_string().contains('C'));
+	if !repr_c {
+		return Err(syn::Error::new_spanned(
+			ident,
+			"GpuConstants requires #[repr(C)] so the layout matches the shader",
+		));
+	}
+
+	let fields = match &input.data {
+		Data::Struct(data) => match &data.fields {
+			Fields::Named(fields) => &fields.named,
+			_ => {
+				return Err(syn::Error::new_spanned(
+					ident,
+					"GpuConstants requires named fields",
+				))
+			}
+		},
+		_ => {
+			return Err(syn::Error::new_spanned(
+				ident,
+				"GpuConstants can only be derived for structs",
+			))
+		}
+	};
+
+	// Fields starting with an underscore are padding and are not part of the pipeline constants
+	let members: Vec<_> = fields
+		.iter()
+		.filter_map(|field| {
+			let name = field.ident.as_ref()?;
+			if name.to_string().starts_with('_') {
+				None
+			} else {
+				Some((name, &field.ty))
+			}
+		})
+		.collect();
+
+	let names: Vec<_> = members.iter().map(|(name, _)| name).collect();
+	let types: Vec<_> = members.iter().map(|(_, ty)| ty).collect();
+
+	Ok(quote! {
+		impl gpu::GpuConstants for #ident {
+			const MEMBERS: &'static [(&'static str, gpu::Constant)] = &[
+				#((stringify!(#names), <#types as gpu::GpuConstant>::CONSTANT)),*
+			];
+		}
+
+		// Fails to compile if the fields are not laid out the way the generated shader struct reads them
+		const _: () = gpu::check_std430_layout(
+			&[#(<#types as gpu::GpuConstant>::CONSTANT),*],
+			&[#(::core::mem::offset_of!(#ident, #names)),*],
+			::core::mem::size_of::<#ident>(),
+		);
+	})
+}
+
 #[proc_macro_derive(Resource)]
 pub fn derive_editable(input: TokenStream) -> TokenStream {
 	let input = parse_macro_input!(input as DeriveInput);
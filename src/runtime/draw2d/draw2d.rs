@@ -7,6 +7,7 @@ use {
 		Buffer,
 		BufferUsage,
 		Gpu,
+		RingBuffer,
 		Texture,
 	},
 	math::{
@@ -186,6 +187,8 @@ impl Painter {
 		self
 	}
 
+	/// Draws `texture` through its bindless index. Panics if `texture` was not created with
+	/// [`gpu::TextureUsage::SAMPLED`]. Running out of bindless slots is reported when the texture is created.
 	pub fn textured_rect(
 		&mut self,
 		rect: impl Into<Rect>,
@@ -196,7 +199,9 @@ impl Painter {
 		let rect = rect.into();
 		let uv = uv.into();
 
-		let texture = texture.bindless().unwrap_or_default();
+		let texture = texture
+			.bindless()
+			.expect("Textures drawn by the painter must be sampled");
 		let color = color.into();
 
 		let bottom_left = {
@@ -398,7 +403,51 @@ impl Painter {
 		Ok((vertex_buffer, index_buffer))
 	}
 
+	/// Like [`Painter::finish`] but reuses buffers between frames. The returned buffers may be longer than what
+	/// was painted so draw with [`Painter::index_count`].
+	pub fn finish_into(
+		&self,
+		vertices: &mut RingBuffer<PainterVertex>,
+		indices: &mut RingBuffer<u32>,
+	) -> gpu::Result<(Buffer<PainterVertex>, Buffer<u32>)> {
+		let vertex_buffer = vertices.upload(&self.vertices)?.clone();
+		let index_buffer = indices.upload(&self.indices)?.clone();
+		Ok((vertex_buffer, index_buffer))
+	}
+
+	pub fn index_count(&self) -> usize {
+		self.indices.len()
+	}
+
 	pub fn is_empty(&self) -> bool {
 		self.indices.is_empty()
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use gpu::Instance;
+
+	#[test]
+	fn paints_into_ring_buffers() {
+		let device = Instance::new().unwrap().create_device(None).unwrap();
+		let mut vertices = RingBuffer::new_in(BufferUsage::VERTEX, 2, &device);
+		let mut indices = RingBuffer::new_in(BufferUsage::INDEX, 2, &device);
+
+		let mut painter = Painter::new();
+		painter
+			.fill_rect((0.0, 0.0, 10.0, 10.0), Color::RED)
+			.fill_rect((10.0, 0.0, 20.0, 10.0), Color::WHITE);
+		let (vertex_buffer, index_buffer) =
+			painter.finish_into(&mut vertices, &mut indices).unwrap();
+
+		// Ring buffers grow to a power of two so only the painted indices are compared
+		assert_eq!(vertex_buffer.len(), 8);
+		assert_eq!(painter.index_count(), 12);
+		assert_eq!(
+			index_buffer.read().unwrap()[..12],
+			[0, 1, 3, 0, 3, 2, 4, 5, 7, 4, 7, 6]
+		);
+	}
+}
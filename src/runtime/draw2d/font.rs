@@ -74,8 +74,9 @@ impl FontCollection {
 				.ok()?;
 
 			let size_metrics = self.face.size_metrics()?;
-			let max_dim =
-				((size_metrics.height >> 6) + 1) * (Self::NUM_GLYPHS as f32).sqrt().ceil() as i32;
+			// FreeType positions are c_long which is 64 bit on Linux
+			let line_height = (size_metrics.height >> 6) as i32;
+			let max_dim = (line_height + 1) * (Self::NUM_GLYPHS as f32).sqrt().ceil() as i32;
 			let mut tex_width = 1;
 			while tex_width < max_dim {
 				tex_width <<= 1
@@ -105,7 +106,7 @@ impl FontCollection {
 
 				if pen_x + bmp_width >= tex_width {
 					pen_x = 0;
-					pen_y -= (size_metrics.height >> 6) + 4;
+					pen_y -= line_height + 4;
 				}
 
 				for row in 0..bmp_rows {
@@ -115,8 +116,8 @@ impl FontCollection {
 
 						let alpha = bmp_buffer[(row * bmp_pitch + col) as usize];
 						let color: u32 = (alpha as u32) << 24
-							| (alpha as u32) << 16 | (alpha as u32) << 8
-							| (alpha as u32);
+							| (alpha as u32) << 16
+							| (alpha as u32) << 8 | (alpha as u32);
 
 						pixels[(y * tex_width + x) as usize] = color;
 					}
@@ -174,13 +175,13 @@ impl FontCollection {
 			.spawn()
 			.ok()?;
 
-			gpu::GraphicsRecorder::new()
+			// Rendering waits on the upload instead of the thread asking for the font
+			let receipt = gpu::TransferRecorder::new()
 				.texture_barrier(&atlas, Layout::Undefined, Layout::TransferDst)
 				.copy_buffer_to_texture(&atlas, &pixel_buffer)
 				.texture_barrier(&atlas, Layout::TransferDst, Layout::ShaderReadOnly)
-				.finish()
-				.submit()
-				.wait();
+				.submit();
+			gpu::Gpu::device().wait_before_rendering(&receipt);
 
 			fonts.insert(
 				(size, (dpi * 96.0) as u32),
@@ -213,7 +214,7 @@ pub(crate) struct FontImporter {}
 impl Importer for FontImporter {
 	type Target = FontCollection;
 
-	fn import(&self, bytes: &[u8]) -> resources::Result<Self::Target> {
+	fn import(&self, bytes: &[u8], _path: &std::path::Path) -> resources::Result<Self::Target> {
 		Ok(FontCollection::new(bytes.to_vec())?)
 	}
 
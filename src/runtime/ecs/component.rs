@@ -110,6 +110,10 @@ pub(crate) trait DynamicStorage: Send + Sync + DynamicStorageClone + 'static {
 	fn insert_box(&mut self, entity: Entity, value: &Box<dyn Any>);
 	fn remove(&mut self, entity: Entity) -> bool;
 	fn contains(&self, entity: Entity) -> bool;
+	fn clear(&mut self);
+
+	fn serialize(&self) -> ron::Result<Vec<(Entity, String)>>;
+	fn insert_serialized(&mut self, entity: Entity, value: &str) -> ron::Result<()>;
 
 	fn as_any_mut(&mut self) -> &mut dyn Any;
 	fn as_any(&self) -> &dyn Any;
@@ -194,8 +198,7 @@ impl<T: Component> DynamicStorage for Storage<T> {
 	fn remove(&mut self, entity: Entity) -> bool {
 		let index = self
 			.entity_to_index
-			.get(&entity)
-			.cloned()
+			.remove(&entity)
 			.unwrap_or(self.components.len());
 
 		if self.components.len() <= index {
@@ -210,6 +213,28 @@ impl<T: Component> DynamicStorage for Storage<T> {
 		self.entity_to_index.contains_key(&entity)
 	}
 
+	fn clear(&mut self) {
+		self.components.clear();
+		self.available.clear();
+		self.entity_to_index.clear();
+	}
+
+	fn serialize(&self) -> ron::Result<Vec<(Entity, String)>> {
+		let mut result = Vec::with_capacity(self.entity_to_index.len());
+		for (entity, index) in self.entity_to_index.iter() {
+			if let Some(t) = &self.components[*index] {
+				result.push((*entity, ron::to_string(&*t.borrow())?));
+			}
+		}
+		Ok(result)
+	}
+
+	fn insert_serialized(&mut self, entity: Entity, value: &str) -> ron::Result<()> {
+		let t: T = ron::from_str(value)?;
+		self.insert(entity, t);
+		Ok(())
+	}
+
 	fn insert_box(&mut self, entity: Entity, value: &Box<dyn Any>) {
 		let t: &T = value.downcast_ref().unwrap();
 		self.insert(entity, t.clone())
@@ -239,6 +264,19 @@ impl ComponentsContainer {
 		Self { map }
 	}
 
+	pub(crate) fn serialize(&self, variant_id: ComponentId) -> ron::Result<Vec<(Entity, String)>> {
+		match self.map.get(&variant_id) {
+			Some(storage) => storage.read().unwrap().serialize(),
+			None => Ok(Vec::new()),
+		}
+	}
+
+	pub(crate) fn clear(&self) {
+		for storage in self.map.values() {
+			storage.write().unwrap().clear();
+		}
+	}
+
 	pub fn read_id(&self, variant_id: ComponentId) -> AnyReadStorage<'_> {
 		AnyReadStorage {
 			read: self
@@ -315,12 +353,14 @@ impl<'a> AnyWriteStorage<'a> {
 			.insert(entity, t)
 	}
 
-	// FIXME: Use this for deserialization
-	#[allow(dead_code)]
 	pub(crate) fn insert_box(&mut self, entity: Entity, value: &Box<dyn Any>) {
 		self.write.insert_box(entity, value)
 	}
 
+	pub(crate) fn insert_serialized(&mut self, entity: Entity, value: &str) -> ron::Result<()> {
+		self.write.insert_serialized(entity, value)
+	}
+
 	pub fn contains(&self, entity: Entity) -> bool {
 		self.write.contains(entity)
 	}
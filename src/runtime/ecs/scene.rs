@@ -40,7 +40,7 @@ pub(crate) struct SceneImporter {}
 impl Importer for SceneImporter {
 	type Target = Scene;
 
-	fn import(&self, bytes: &[u8]) -> resources::Result<Self::Target> {
+	fn import(&self, bytes: &[u8], _path: &std::path::Path) -> resources::Result<Self::Target> {
 		let id_key = Value::String("id".to_string());
 		let components_key = Value::String("components".to_string());
 
@@ -60,3 +60,44 @@ impl ScheduleBlock {
 		}
 	}
 }
+
+/// Phase of the frame a [`ScheduleBlock`] runs in
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Phase {
+	/// Runs zero or more times a frame with a constant delta. Used for simulation such as physics.
+	Fixed,
+	/// Runs once a frame with the scaled frame delta. Used for input, gameplay and presentation.
+	Variable,
+}
+
+/// Set of systems split into the [`Phase`]s of a frame
+#[derive(Default)]
+pub struct Schedule {
+	fixed: ScheduleBlock,
+	variable: ScheduleBlock,
+}
+
+impl Schedule {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	#[must_use]
+	pub fn fixed(mut self, block: impl FnOnce(ScheduleBlock) -> ScheduleBlock) -> Self {
+		self.fixed = block(self.fixed);
+		self
+	}
+
+	#[must_use]
+	pub fn variable(mut self, block: impl FnOnce(ScheduleBlock) -> ScheduleBlock) -> Self {
+		self.variable = block(self.variable);
+		self
+	}
+
+	pub fn execute(&self, world: &World, phase: Phase, dt: f32) {
+		match phase {
+			Phase::Fixed => self.fixed.execute(world, dt),
+			Phase::Variable => self.variable.execute(world, dt),
+		}
+	}
+}
@@ -10,15 +10,33 @@ use {
 		EntityContainer,
 		EntityInfo,
 		ReadStorage,
+		Scene,
 		WriteStorage,
 	},
-	engine::Engine,
+	engine::{
+		define_log_category,
+		warn,
+		Engine,
+	},
+	serde::ron,
 	std::sync::{
 		Mutex,
 		MutexGuard,
 	},
 };
 
+define_log_category!(Ecs, ECS_CATEGORY);
+
+/// Serialized copy of every entity and component in a [`World`]
+///
+/// Components are stored as RON text so a snapshot outlives the code that created it. This is what
+/// carries game state across a hot reload.
+#[derive(Default, Clone)]
+pub struct WorldSnapshot {
+	entities: Vec<Entity>,
+	components: HashMap<ComponentId, Vec<(Entity, String)>>,
+}
+
 pub struct World {
 	pub variants: HashMap<ComponentId, ComponentVariant>,
 	pub(crate) components: ComponentsContainer,
@@ -82,6 +100,91 @@ impl World {
 		T::on_added(self, entity, storage);
 	}
 
+	/// Serializes every component of every entity using its registered [`ComponentVariant`]
+	pub fn snapshot(&self) -> ron::Result<WorldSnapshot> {
+		let entities = self.entities.lock().unwrap();
+
+		let mut components = HashMap::with_capacity(self.variants.len());
+		for id in self.variants.keys() {
+			components.insert(*id, self.components.serialize(*id)?);
+		}
+
+		Ok(WorldSnapshot {
+			entities: entities.keys().copied().collect(),
+			components,
+		})
+	}
+
+	/// Replaces the contents of the world with a [`WorldSnapshot`]
+	///
+	/// Components that are no longer registered or fail to deserialize are skipped with a warning.
+	/// [`Component::on_added`] is not called as the snapshot already contains the resulting state.
+	pub fn restore(&self, snapshot: &WorldSnapshot) {
+		self.clear();
+
+		let mut entities = self.entities.lock().unwrap();
+		for entity in snapshot.entities.iter() {
+			entities.entry(*entity).or_default();
+		}
+
+		for (id, values) in snapshot.components.iter() {
+			let variant = match self.variants.get(id) {
+				Some(variant) => variant,
+				None => continue,
+			};
+
+			let mut storage = self.components.write_id(*id);
+			let mask = id.to_mask();
+			for (entity, value) in values.iter() {
+				match storage.insert_serialized(*entity, value) {
+					Ok(_) => entities.entry(*entity).or_default().components |= mask,
+					Err(err) => warn!(
+						ECS_CATEGORY,
+						"Failed to restore \"{}\" on entity {:?}: {}", variant.name, entity, err
+					),
+				}
+			}
+		}
+	}
+
+	/// Adds every entity of `scene` keeping the ids it was saved with
+	///
+	/// Components that are no longer registered are skipped. [`Component::on_added`] is not called.
+	pub fn load_scene(&self, scene: &Scene) {
+		let mut entities = self.entities.lock().unwrap();
+		for entry in scene.entities.iter() {
+			let info = entities.entry(entry.id).or_default();
+			for (id, component) in entry.components.iter() {
+				if !self.variants.contains_key(id) {
+					continue;
+				}
+
+				self.components
+					.write_id(*id)
+					.insert_box(entry.id, component);
+				info.components |= id.to_mask();
+			}
+		}
+	}
+
+	/// Removes every entity and component. The singleton entity is kept without components.
+	pub fn clear(&self) {
+		let mut entities = self.entities.lock().unwrap();
+		self.components.clear();
+		entities.clear();
+		entities.insert(self.singleton, EntityInfo::default());
+	}
+
+	/// Rebuilds the variant table and component storages from the currently registered [`ComponentVariant`]s
+	///
+	/// This drops all component data. Take a [`WorldSnapshot`] first to keep it.
+	pub fn reload_variants(&mut self) {
+		let variants: &[ComponentVariant] = Engine::register();
+		self.variants = variants.iter().map(|v| (v.id, v.clone())).collect();
+		self.components = ComponentsContainer::new();
+		self.clear();
+	}
+
 	pub fn remove<T: Component>(&self, storage: &mut WriteStorage<'_, T>, entity: Entity) -> bool {
 		let mut entities = self.entities.lock().unwrap();
 		let info = entities.get_mut(&entity).unwrap();
@@ -132,3 +235,43 @@ impl<'a> EntityBuilder<'a> {
 		self.entity
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		crate::Named,
+		engine::Builder,
+	};
+
+	#[test]
+	fn snapshot_round_trips() {
+		Builder::new().register(Named::variant()).test().unwrap();
+
+		let mut world = World::new();
+		let entity = {
+			let mut names = world.write::<Named>();
+			world.insert(&mut names, world.singleton, Named::new("singleton"));
+			world
+				.spawn()
+				.with(Named::new("entity"), &mut names)
+				.finish()
+		};
+		let empty = world.spawn().finish();
+
+		let snapshot = world.snapshot().unwrap();
+		world.spawn().finish();
+		world.reload_variants();
+		world.restore(&snapshot);
+
+		let entities = world.entities.lock().unwrap();
+		assert_eq!(entities.len(), 3);
+		assert!(entities.contains_key(&empty));
+		assert_eq!(entities[&entity].components, Named::VARIANT_ID.to_mask());
+
+		let names = world.read::<Named>();
+		assert_eq!(names.get(world.singleton).unwrap().name, "singleton");
+		assert_eq!(names.get(entity).unwrap().name, "entity");
+		assert!(names.get(empty).is_none());
+	}
+}
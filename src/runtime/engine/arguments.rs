@@ -0,0 +1,291 @@
+use crate::{
+	Uuid,
+	Verbosity,
+};
+
+use std::{
+	collections::HashMap,
+	error::Error,
+	fmt,
+	path::PathBuf,
+};
+
+/// Command line flag that can be registered on a [`Builder`] to be parsed at launch
+///
+/// [`Builder`]: crate::Builder
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Argument {
+	pub name: &'static str,
+	pub value: Option<&'static str>,
+	pub help: &'static str,
+}
+
+impl Argument {
+	/// Creates a flag that is either present or not. Passed as `--name`
+	pub const fn flag(name: &'static str, help: &'static str) -> Self {
+		Self {
+			name,
+			value: None,
+			help,
+		}
+	}
+
+	/// Creates a flag that takes a value. Passed as `--name value` or `--name=value`
+	pub const fn value(name: &'static str, value: &'static str, help: &'static str) -> Self {
+		Self {
+			name,
+			value: Some(value),
+			help,
+		}
+	}
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ArgumentError {
+	Unknown(String),
+	MissingValue(&'static str),
+	Invalid { name: &'static str, value: String },
+}
+
+impl fmt::Display for ArgumentError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Unknown(arg) => write!(f, "unknown argument \"{}\"", arg),
+			Self::MissingValue(name) => write!(f, "argument \"--{}\" requires a value", name),
+			Self::Invalid { name, value } => {
+				write!(f, "invalid value \"{}\" for argument \"--{}\"", value, name)
+			}
+		}
+	}
+}
+
+impl Error for ArgumentError {}
+
+/// Launch options parsed from the command line
+///
+/// Contains the engine's builtin flags as fields. Flags registered by modules through
+/// [`Argument`] are queried with [`Arguments::is_set`] and [`Arguments::value`].
+#[derive(Clone, Debug, Default)]
+pub struct Arguments {
+	/// Directory used as the working directory instead of guessing from the executable path
+	pub project_dir: Option<PathBuf>,
+	/// Scene that should be loaded at start
+	pub scene: Option<Uuid>,
+	/// Run without creating a window or calling the display callback
+	pub headless: bool,
+	/// Lowest verbosity that is logged
+	pub log_level: Verbosity,
+	/// Config overrides given as `name.field=value`
+	pub config: Vec<(String, String)>,
+	/// Number of frames to run before shutting down
+	pub frames: Option<u64>,
+	/// Print the usage and exit
+	pub help: bool,
+
+	values: HashMap<&'static str, Vec<String>>,
+}
+
+impl Arguments {
+	pub const BUILTIN: &'static [Argument] = &[
+		Argument::value("project-dir", "PATH", "Directory of the project to run"),
+		Argument::value("scene", "UUID", "Scene to load at start"),
+		Argument::flag("headless", "Run without a window"),
+		Argument::value(
+			"log-level",
+			"LEVEL",
+			"Lowest log verbosity. One of debug, info, warning or error",
+		),
+		Argument::value(
+			"config",
+			"KEY=VALUE",
+			"Override a config value. Can be passed multiple times",
+		),
+		Argument::value("frames", "N", "Number of frames to run before exiting"),
+		Argument::flag("help", "Print this message"),
+	];
+
+	/// Parses `args` against the builtin flags and `registered`. `args` should not contain the executable path.
+	pub fn parse(
+		args: impl IntoIterator<Item = String>,
+		registered: &[Argument],
+	) -> Result<Self, ArgumentError> {
+		let mut result = Self::default();
+
+		let mut args = args.into_iter();
+		while let Some(arg) = args.next() {
+			let stripped = arg
+				.strip_prefix("--")
+				.ok_or_else(|| ArgumentError::Unknown(arg.clone()))?;
+			let (name, inline) = match stripped.split_once('=') {
+				Some((name, value)) => (name, Some(value.to_string())),
+				None => (stripped, None),
+			};
+
+			let argument = Self::BUILTIN
+				.iter()
+				.chain(registered.iter())
+				.find(|it| it.name == name)
+				.ok_or_else(|| ArgumentError::Unknown(arg.clone()))?;
+
+			let value = match (argument.value, inline) {
+				(Some(_), inline) => Some(
+					inline
+						.or_else(|| args.next())
+						.ok_or(ArgumentError::MissingValue(argument.name))?,
+				),
+				// Flags do not take a value so `--flag=value` is rejected instead of dropping the value
+				(None, Some(value)) => {
+					return Err(ArgumentError::Invalid {
+						name: argument.name,
+						value,
+					})
+				}
+				(None, None) => None,
+			};
+
+			let invalid = |value: &str| ArgumentError::Invalid {
+				name: argument.name,
+				value: value.to_string(),
+			};
+
+			match (argument.name, value) {
+				("project-dir", Some(value)) => result.project_dir = Some(PathBuf::from(value)),
+				("scene", Some(value)) => {
+					let scene = serde::ron::from_str(&format!("\"{}\"", value))
+						.map_err(|_| invalid(&value))?;
+					result.scene = Some(scene);
+				}
+				("headless", None) => result.headless = true,
+				("log-level", Some(value)) => {
+					result.log_level = value.parse().map_err(|_| invalid(&value))?
+				}
+				("config", Some(value)) => {
+					let (key, value) = value.split_once('=').ok_or_else(|| invalid(&value))?;
+					result.config.push((key.to_string(), value.to_string()));
+				}
+				("frames", Some(value)) => {
+					result.frames = Some(value.parse().map_err(|_| invalid(&value))?)
+				}
+				("help", None) => result.help = true,
+				(name, value) => result.values.entry(name).or_default().extend(value),
+			}
+		}
+
+		Ok(result)
+	}
+
+	/// Returns true if a registered flag was passed
+	pub fn is_set(&self, name: &str) -> bool {
+		self.values.contains_key(name)
+	}
+
+	/// Returns the last value passed for a registered flag
+	pub fn value(&self, name: &str) -> Option<&str> {
+		self.values(name).last().map(|it| it.as_str())
+	}
+
+	/// Returns every value passed for a registered flag
+	pub fn values(&self, name: &str) -> &[String] {
+		self.values.get(name).map(|it| &it[..]).unwrap_or(&[])
+	}
+
+	/// Returns the help message listing the builtin flags and `registered`
+	pub fn usage(name: &str, registered: &[Argument]) -> String {
+		let mut result = format!("Usage: {} [OPTIONS]\n\nOptions:\n", name);
+		for it in Self::BUILTIN.iter().chain(registered.iter()) {
+			let flag = match it.value {
+				Some(value) => format!("--{} <{}>", it.name, value),
+				None => format!("--{}", it.name),
+			};
+			result.push_str(&format!("    {:<28} {}\n", flag, it.help));
+		}
+		result
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse(args: &[&str], registered: &[Argument]) -> Result<Arguments, ArgumentError> {
+		Arguments::parse(args.iter().map(|it| it.to_string()), registered)
+	}
+
+	#[test]
+	fn builtin() {
+		let arguments = parse(
+			&[
+				"--headless",
+				"--frames",
+				"10",
+				"--log-level=warning",
+				"--config",
+				"game.default_scene=\"{1}\"",
+				"--scene",
+				"{123456-789a-bcde-f012-3456789abcde}",
+			],
+			&[],
+		)
+		.unwrap();
+
+		assert!(arguments.headless);
+		assert_eq!(arguments.frames, Some(10));
+		assert_eq!(arguments.log_level, Verbosity::Warning);
+		assert_eq!(
+			arguments.config,
+			vec![("game.default_scene".to_string(), "\"{1}\"".to_string())]
+		);
+		assert!(arguments.scene.is_some());
+	}
+
+	#[test]
+	fn registered() {
+		let registered = [
+			Argument::flag("wireframe", ""),
+			Argument::value("seed", "N", ""),
+		];
+		let arguments = parse(&["--wireframe", "--seed", "4", "--seed=5"], &registered).unwrap();
+
+		assert!(arguments.is_set("wireframe"));
+		assert!(!arguments.is_set("headless"));
+		assert_eq!(arguments.value("seed"), Some("5"));
+		assert_eq!(arguments.values("seed"), &["4", "5"]);
+	}
+
+	#[test]
+	fn errors() {
+		assert_eq!(
+			parse(&["--wireframe"], &[]).unwrap_err(),
+			ArgumentError::Unknown("--wireframe".to_string())
+		);
+		assert_eq!(
+			parse(&["--frames"], &[]).unwrap_err(),
+			ArgumentError::MissingValue("frames")
+		);
+		assert_eq!(
+			parse(&["--frames", "ten"], &[]).unwrap_err(),
+			ArgumentError::Invalid {
+				name: "frames",
+				value: "ten".to_string()
+			}
+		);
+	}
+
+	#[test]
+	fn flags_reject_values() {
+		assert_eq!(
+			parse(&["--help=no"], &[]).unwrap_err(),
+			ArgumentError::Invalid {
+				name: "help",
+				value: "no".to_string()
+			}
+		);
+		assert_eq!(
+			parse(&["--wireframe="], &[Argument::flag("wireframe", "")]).unwrap_err(),
+			ArgumentError::Invalid {
+				name: "wireframe",
+				value: String::new()
+			}
+		);
+	}
+}
@@ -1,7 +1,11 @@
 use crate::{
+	Argument,
+	Arguments,
 	Engine,
-	Event,
+	EventBus,
 	Module,
+	Priority,
+	Propagation,
 };
 
 use std::{
@@ -10,6 +14,7 @@ use std::{
 		TypeId,
 	},
 	collections::HashMap,
+	io,
 	time::Instant,
 };
 
@@ -29,11 +34,14 @@ pub struct Builder {
 	pub(crate) modules: Vec<ModuleEntry>,
 	pub(crate) name: Option<String>,
 
-	pub(crate) process_input: Vec<Box<dyn Fn(&Event) + 'static>>,
+	pub(crate) events: EventBus,
+	pub(crate) fixed_tick: Vec<Box<dyn Fn(f32) + 'static>>,
 	pub(crate) tick: Vec<Box<dyn Fn(f32) + 'static>>,
 	pub(crate) display: Option<Box<dyn Fn() + 'static>>, // There can only be one display method
 
 	pub(crate) registers: Option<HashMap<TypeId, Box<dyn Any>>>,
+
+	pub(crate) args: Option<Vec<String>>,
 }
 
 impl Builder {
@@ -43,12 +51,15 @@ impl Builder {
 			modules: Vec::with_capacity(32),
 			name: None,
 
-			process_input: Vec::with_capacity(8),
+			events: EventBus::new(),
+			fixed_tick: Vec::with_capacity(8),
 			tick: Vec::with_capacity(8),
 			display: None,
 
 			registers: Some(HashMap::with_capacity(64)),
 
+			args: None,
+
 			creation: Instant::now(),
 		}
 	}
@@ -85,11 +96,29 @@ impl Builder {
 		self
 	}
 
-	pub fn process_input(&mut self, f: impl Fn(&Event) + 'static) -> &mut Self {
-		self.process_input.push(Box::new(f));
+	/// Adds a listener for events of type `T`. See [`Event`] for the events emitted by the engine.
+	///
+	/// [`Event`]: crate::Event
+	pub fn listen<T: 'static>(
+		&mut self,
+		priority: Priority,
+		f: impl Fn(&T) -> Propagation + 'static,
+	) -> &mut Self {
+		self.events.listen(priority, f);
 		self
 	}
 
+	/// Adds a callback run zero or more times a frame with [`Time::fixed_delta`]. Fixed ticks run before variable ticks.
+	///
+	/// [`Time::fixed_delta`]: crate::Time::fixed_delta
+	pub fn fixed_tick(&mut self, f: impl Fn(f32) + 'static) -> &mut Self {
+		self.fixed_tick.push(Box::new(f));
+		self
+	}
+
+	/// Adds a callback run once a frame with the scaled [`Time::delta`]
+	///
+	/// [`Time::delta`]: crate::Time::delta
 	pub fn tick(&mut self, f: impl Fn(f32) + 'static) -> &mut Self {
 		self.tick.push(Box::new(f));
 		self
@@ -123,16 +152,52 @@ impl Builder {
 		self
 	}
 
+	/// Overrides the command line arguments. The process arguments are used by [`Builder::run`] and [`Builder::spawn`] otherwise
+	pub fn args(&mut self, args: impl IntoIterator<Item = impl Into<String>>) -> &mut Self {
+		self.args = Some(args.into_iter().map(|it| it.into()).collect());
+		self
+	}
+
+	pub(crate) fn parse_args(&mut self, test: bool) -> io::Result<Arguments> {
+		let args = match self.args.take() {
+			Some(args) => args,
+			None if test => Vec::new(),
+			None => std::env::args().skip(1).collect(),
+		};
+
+		let registered = self
+			.registers
+			.as_ref()
+			.unwrap()
+			.get(&TypeId::of::<Argument>())
+			.map(|it| &it.downcast_ref::<Vec<Argument>>().unwrap()[..])
+			.unwrap_or(&[]);
+
+		let arguments = Arguments::parse(args, registered)
+			.map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+		if arguments.help {
+			let name = self.name.as_deref().unwrap_or("project");
+			print!("{}", Arguments::usage(name, registered));
+		}
+		Ok(arguments)
+	}
+
 	pub fn run(&mut self) -> Result<(), std::io::Error> {
 		Engine::run(self)
 	}
 
+	/// Exits the process after printing the usage when `--help` is passed
 	pub fn spawn(&mut self) -> Result<(), std::io::Error> {
-		Engine::spawn(self, None, false)
+		let arguments = self.parse_args(false)?;
+		if arguments.help {
+			std::process::exit(0);
+		}
+		Engine::spawn(self, None, arguments, false)
 	}
 
 	pub fn test(&mut self) -> Result<(), std::io::Error> {
-		Engine::spawn(self, None, true)
+		let arguments = self.parse_args(true)?;
+		Engine::spawn(self, None, arguments, true)
 	}
 }
 
@@ -2,18 +2,26 @@
 #![feature(string_remove_matches)]
 #![feature(backtrace)]
 
+mod arguments;
 mod builder;
+mod event;
 mod log;
 mod module;
+mod panic_hook;
+mod time;
 mod uuid;
 
 #[cfg(test)]
 mod test;
 
 pub use {
+	arguments::*,
 	builder::*,
+	event::*,
 	log::*,
 	module::*,
+	panic_hook::*,
+	time::*,
 	uuid::*,
 };
 
@@ -25,10 +33,14 @@ use std::{
 	backtrace::Backtrace,
 	collections::HashMap,
 	panic,
-	sync::atomic::{
-		AtomicBool,
-		AtomicI32,
-		Ordering,
+	sync::{
+		atomic::{
+			AtomicBool,
+			AtomicI32,
+			Ordering,
+		},
+		Mutex,
+		MutexGuard,
 	},
 	thread::ThreadId,
 	time::Instant,
@@ -38,13 +50,11 @@ use os::{
 	winit::event::{
 		DeviceEvent,
 		ElementState,
-		MouseButton,
 		MouseScrollDelta,
 	},
 	ControlFlow,
 	Event as WinEvent,
 	EventLoop,
-	Input,
 	Window,
 	WindowBuilder,
 	WindowEvent,
@@ -52,7 +62,9 @@ use os::{
 
 pub use os::input;
 
-static mut ENGINE: Option<Engine> = None;
+// Libraries loaded by a host executable have their own copy of this static. They are pointed at the host's
+// engine through `Engine::attach`.
+static mut ENGINE: *mut Engine = std::ptr::null_mut();
 
 pub const ENGINE_NAME: &str = "Newport";
 pub const ENGINE_VERSION: &str = "0.0.0";
@@ -70,7 +82,10 @@ pub struct Engine {
 
 	window: Option<Window>,
 
+	arguments: Arguments,
+	events: EventBus,
 	logger: Logger,
+	time: Mutex<Time>,
 
 	main: ThreadId,
 }
@@ -79,17 +94,21 @@ impl Engine {
 	pub(crate) fn spawn(
 		builder: &mut Builder,
 		window: Option<Window>,
+		arguments: Arguments,
 		test: bool,
 	) -> Result<(), std::io::Error> {
 		unsafe {
 			// Use this to mark when registration finished. This must happen before anything else.
 			let registration_finish_time = Instant::now()
 				.duration_since(builder.creation)
-				.as_secs_f64() * 1000.0;
+				.as_secs_f64()
+				* 1000.0;
 
-			// Ensure that we're working in the projects workspace.
+			// Ensure that we're working in the projects workspace. Guess it from the exe path if not given.
 			let exe_path = std::env::current_exe()?;
-			let new_working_directory = if test {
+			let new_working_directory = if let Some(project_dir) = &arguments.project_dir {
+				project_dir.as_path()
+			} else if test {
 				exe_path
 					.parent()
 					.unwrap()
@@ -113,7 +132,7 @@ impl Engine {
 			// Ensure we have a valid name for the project. This is used for a variety of things
 			let name = builder.name.take().unwrap_or_else(|| "project".to_string());
 
-			ENGINE = Some(Engine {
+			ENGINE = Box::into_raw(Box::new(Engine {
 				name,
 				modules: HashMap::with_capacity(builder.modules.len()),
 				registers: builder.registers.take().unwrap(),
@@ -123,10 +142,13 @@ impl Engine {
 
 				window,
 
+				arguments,
+				events: std::mem::take(&mut builder.events),
 				logger: Logger::new(),
+				time: Mutex::new(Time::new()),
 
 				main: std::thread::current().id(),
-			});
+			}));
 
 			// Set the new panic hook after engine initialization so we can use logger
 			panic::set_hook(Box::new(move |info| {
@@ -153,10 +175,9 @@ impl Engine {
 					location
 				);
 
-				// Sadly this must be here due to aftermath in the GPU module
-				// Aftermath runs on another thread and needs time to catch gpu hangs
-				// TODO: One day maybe remove this somehow?
-				std::thread::sleep(std::time::Duration::from_millis(3000));
+				for it in Engine::register::<PanicHook>() {
+					it.call();
+				}
 			}));
 
 			info!(
@@ -164,7 +185,7 @@ impl Engine {
 				"Registration process took {:.2}ms", registration_finish_time
 			);
 
-			let engine = ENGINE.as_mut().unwrap();
+			let engine = Engine::as_mut();
 
 			info!(ENGINE_CATEGORY, "Starting module initialization.");
 			let now = Instant::now();
@@ -188,10 +209,24 @@ impl Engine {
 	}
 
 	pub(crate) fn run(builder: &mut Builder) -> Result<(), std::io::Error> {
-		let display = builder.display.take();
-		let process_input = std::mem::take(&mut builder.process_input);
+		let arguments = builder.parse_args(false)?;
+		if arguments.help {
+			return Ok(());
+		}
+
+		let fixed_tick = std::mem::take(&mut builder.fixed_tick);
 		let tick = std::mem::take(&mut builder.tick);
 
+		// Headless runs never create an event loop so they work without a display server
+		if arguments.headless {
+			let frames = arguments.frames;
+			Engine::spawn(builder, None, arguments, false)?;
+			Engine::run_headless(frames, &fixed_tick, &tick);
+			return Ok(());
+		}
+
+		let display = builder.display.take();
+
 		let name = match &builder.name {
 			Some(name) => name.clone(),
 			None => "project".to_string(),
@@ -208,9 +243,11 @@ impl Engine {
 			None => None,
 		};
 
-		Engine::spawn(builder, window, false)?;
+		let frames = arguments.frames;
+		Engine::spawn(builder, window, arguments, false)?;
 		let engine = Engine::as_ref();
 
+		let mut total_frames = 0;
 		let mut frame_count = 0;
 		let mut time = 0.0;
 		let mut displayed = false;
@@ -231,14 +268,53 @@ impl Engine {
 					event: WindowEvent::CloseRequested,
 					..
 				} => {
-					*control_flow = ControlFlow::Exit;
-
-					// Set the window to be invisible immedietely
-					if let Some(window) = Engine::window() {
-						window.set_maximized(false);
-						window.set_visible(false);
+					// Listeners can consume the request to keep the app open. i.e. to ask to save changes
+					if Engine::emit(&Event::ShutdownRequested) == Propagation::Continue {
+						*control_flow = ControlFlow::Exit;
+
+						// Set the window to be invisible immedietely
+						if let Some(window) = Engine::window() {
+							window.set_maximized(false);
+							window.set_visible(false);
+						}
 					}
 				}
+				WinEvent::WindowEvent {
+					event: WindowEvent::ScaleFactorChanged { scale_factor, .. },
+					..
+				} => {
+					let event = Event::ScaleFactorChanged(scale_factor);
+					Engine::emit(&event);
+				}
+				WinEvent::WindowEvent {
+					event: WindowEvent::HoveredFile(path),
+					..
+				} => {
+					let event = Event::FileHovered(path);
+					Engine::emit(&event);
+				}
+				WinEvent::WindowEvent {
+					event: WindowEvent::HoveredFileCancelled,
+					..
+				} => {
+					let event = Event::FileHoverCancelled;
+					Engine::emit(&event);
+				}
+				WinEvent::WindowEvent {
+					event: WindowEvent::DroppedFile(path),
+					..
+				} => {
+					let event = Event::FileDropped(path);
+					Engine::emit(&event);
+				}
+				WinEvent::Suspended => {
+					let event = Event::Suspended;
+					Engine::emit(&event);
+				}
+				WinEvent::Resumed => {
+					let event = Event::Resumed;
+					Engine::emit(&event);
+				}
 				WinEvent::WindowEvent {
 					event: WindowEvent::KeyboardInput { input, .. },
 					..
@@ -250,7 +326,7 @@ impl Engine {
 							pressed: input.state == ElementState::Pressed,
 						};
 
-						process_input.iter().for_each(|process| process(&event));
+						Engine::emit(&event);
 					}
 				}
 				WinEvent::DeviceEvent {
@@ -259,38 +335,33 @@ impl Engine {
 				} => {
 					let event = Event::MouseMotion(delta.0 as f32, delta.1 as f32);
 
-					process_input.iter().for_each(|process| process(&event));
+					Engine::emit(&event);
 				}
 				WinEvent::WindowEvent {
 					event: WindowEvent::MouseInput { button, state, .. },
 					..
 				} => {
-					let mouse_button = match button {
-						MouseButton::Left => os::MOUSE_BUTTON_LEFT,
-						MouseButton::Right => os::MOUSE_BUTTON_RIGHT,
-						MouseButton::Middle => os::MOUSE_BUTTON_MIDDLE,
-						_ => unimplemented!(),
-					};
+					let mouse_button = os::mouse_button_to_input(button);
 					let event = Event::MouseButton {
 						mouse_button,
 						pressed: state == ElementState::Pressed,
 					};
 
-					process_input.iter().for_each(|process| process(&event));
+					Engine::emit(&event);
 				}
 				WinEvent::WindowEvent {
 					event: WindowEvent::CursorMoved { position, .. },
 					..
 				} => {
 					let event = Event::MouseMove(position.x as f32, height - position.y as f32);
-					process_input.iter().for_each(|process| process(&event));
+					Engine::emit(&event);
 				}
 				WinEvent::WindowEvent {
 					event: WindowEvent::Resized(size),
 					..
 				} => {
 					let event = Event::Resized(size.width, size.height);
-					process_input.iter().for_each(|process| process(&event));
+					Engine::emit(&event);
 				}
 				WinEvent::WindowEvent {
 					event: WindowEvent::MouseWheel { delta, .. },
@@ -302,14 +373,14 @@ impl Engine {
 							Event::MouseWheel(dif.x as f32, dif.y as f32)
 						}
 					};
-					process_input.iter().for_each(|process| process(&event));
+					Engine::emit(&event);
 				}
 				WinEvent::WindowEvent {
 					event: WindowEvent::ReceivedCharacter(c),
 					..
 				} => {
 					let event = Event::Char(c);
-					process_input.iter().for_each(|process| process(&event));
+					Engine::emit(&event);
 				}
 				WinEvent::WindowEvent {
 					event: WindowEvent::Focused(focused),
@@ -320,21 +391,21 @@ impl Engine {
 					} else {
 						Event::FocusLost
 					};
-					process_input.iter().for_each(|process| process(&event));
+					Engine::emit(&event);
 				}
 				WinEvent::WindowEvent {
 					event: WindowEvent::CursorEntered { .. },
 					..
 				} => {
 					let event = Event::MouseEnter;
-					process_input.iter().for_each(|process| process(&event));
+					Engine::emit(&event);
 				}
 				WinEvent::WindowEvent {
 					event: WindowEvent::CursorLeft { .. },
 					..
 				} => {
 					let event = Event::MouseLeave;
-					process_input.iter().for_each(|process| process(&event));
+					Engine::emit(&event);
 				}
 				WinEvent::MainEventsCleared => {
 					let now = Instant::now();
@@ -350,7 +421,14 @@ impl Engine {
 					}
 					frame_count += 1;
 
-					tick.iter().for_each(|tick| tick(dt));
+					Engine::step(dt, &fixed_tick, &tick);
+
+					total_frames += 1;
+					let out_of_frames =
+						frames.map(|frames| total_frames >= frames).unwrap_or(false);
+					if out_of_frames || !engine.is_running.load(Ordering::Relaxed) {
+						*control_flow = ControlFlow::Exit;
+					}
 
 					if !displayed {
 						if let Some(window) = engine.window.as_ref() {
@@ -386,6 +464,43 @@ impl Engine {
 		});
 	}
 
+	/// Advances [`Time`] by `dt` and runs the tick callbacks for the frame
+	fn step(dt: f32, fixed_tick: &[Box<dyn Fn(f32)>], tick: &[Box<dyn Fn(f32)>]) {
+		let (steps, fixed_delta, delta) = {
+			let mut time = Engine::time();
+			let steps = time.advance(dt);
+			(steps, time.fixed_delta(), time.delta())
+		};
+		for _ in 0..steps {
+			fixed_tick.iter().for_each(|tick| tick(fixed_delta));
+		}
+		tick.iter().for_each(|tick| tick(delta));
+	}
+
+	/// Runs the tick callbacks until shut down or `frames` have run. Nothing is displayed
+	fn run_headless(
+		frames: Option<u64>,
+		fixed_tick: &[Box<dyn Fn(f32)>],
+		tick: &[Box<dyn Fn(f32)>],
+	) {
+		let engine = Engine::as_ref();
+
+		let mut total_frames = 0;
+		let mut last_frame_time = Instant::now();
+		while engine.is_running.load(Ordering::Relaxed) {
+			let now = Instant::now();
+			let dt = now.duration_since(last_frame_time).as_secs_f32();
+			last_frame_time = now;
+
+			Engine::step(dt, fixed_tick, tick);
+
+			total_frames += 1;
+			if frames.map(|frames| total_frames >= frames).unwrap_or(false) {
+				break;
+			}
+		}
+	}
+
 	/// Returns the global [`Engine`] as a ref
 	fn as_ref() -> &'static Engine {
 		unsafe { ENGINE.as_ref().unwrap() }
@@ -395,6 +510,55 @@ impl Engine {
 		ENGINE.as_mut().unwrap()
 	}
 
+	/// Returns a pointer to the global [`Engine`] to be handed to a dynamically loaded library
+	pub fn as_ptr() -> *mut Engine {
+		unsafe { ENGINE }
+	}
+
+	/// Points this copy of the engine crate at an [`Engine`] owned by a host executable
+	///
+	/// # Safety
+	///
+	/// `engine` must come from [`Engine::as_ptr`] of a host built with the same compiler and engine source.
+	pub unsafe fn attach(engine: *mut Engine) {
+		ENGINE = engine;
+	}
+
+	/// Adds the registers and modules of `builder` to the running [`Engine`]
+	///
+	/// Modules that are already running are kept unless their id is in `replace` in which case they are
+	/// dropped and spawned again. Registers of a type found in `builder` replace the existing ones. Tick
+	/// and display callbacks are ignored as the run loop has already started.
+	///
+	/// # Safety
+	///
+	/// Modules can be accessed on any thread. This must be called on the main thread while no module references are held.
+	pub unsafe fn extend(builder: &mut Builder, replace: &[TypeId]) {
+		let engine = Engine::as_mut();
+
+		for (id, register) in builder.registers.take().unwrap().drain() {
+			engine.registers.insert(id, register);
+		}
+
+		for id in replace.iter() {
+			engine.modules.remove(id);
+		}
+
+		builder.modules.drain(..).for_each(|it| {
+			if engine.modules.contains_key(&it.id) {
+				return;
+			}
+
+			let now = Instant::now();
+			engine.modules.insert(it.id, (it.spawn)());
+			let dur = Instant::now().duration_since(now).as_secs_f64() * 1000.0;
+			info!(
+				ENGINE_CATEGORY,
+				"{} initialization took {:.2}ms.", it.name, dur
+			);
+		});
+	}
+
 	/// Returns an immutable reference
 	pub fn module<'a, T: Module>() -> Option<&'a T> {
 		let engine = Engine::as_ref();
@@ -465,22 +629,28 @@ impl Engine {
 		Builder::new()
 	}
 
+	/// Returns the [`Arguments`] parsed at launch
+	pub fn arguments<'a>() -> &'a Arguments {
+		&Engine::as_ref().arguments
+	}
+
+	/// Returns the engine's [`Time`]. Do not hold on to the guard across ticks.
+	pub fn time<'a>() -> MutexGuard<'a, Time> {
+		Engine::as_ref().time.lock().unwrap()
+	}
+
+	/// Calls every listener registered for `T` through [`Builder::listen`]. Must be called on the main thread.
+	pub fn emit<T: 'static>(event: &T) -> Propagation {
+		let engine = Engine::as_ref();
+		assert_eq!(
+			std::thread::current().id(),
+			engine.main,
+			"Engine::emit must be called on the main thread"
+		);
+		engine.events.emit(event)
+	}
+
 	pub fn logger<'a>() -> &'a Logger {
 		&Engine::as_ref().logger
 	}
 }
-
-#[derive(Clone, Copy, Debug)]
-pub enum Event {
-	FocusGained,
-	FocusLost,
-	Key { key: Input, pressed: bool },
-	Resized(u32, u32),
-	Char(char),
-	MouseWheel(f32, f32),
-	MouseButton { mouse_button: Input, pressed: bool },
-	MouseMove(f32, f32),
-	MouseLeave,
-	MouseEnter,
-	MouseMotion(f32, f32),
-}
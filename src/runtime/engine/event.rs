@@ -0,0 +1,183 @@
+use os::Input;
+
+use std::{
+	any::{
+		Any,
+		TypeId,
+	},
+	collections::HashMap,
+	path::PathBuf,
+};
+
+/// Window, input and lifecycle events emitted by the run loop
+#[derive(Clone, Debug)]
+pub enum Event {
+	FocusGained,
+	FocusLost,
+	Key {
+		key: Input,
+		pressed: bool,
+	},
+	Resized(u32, u32),
+	ScaleFactorChanged(f64),
+	Char(char),
+	MouseWheel(f32, f32),
+	MouseButton {
+		mouse_button: Input,
+		pressed: bool,
+	},
+	MouseMove(f32, f32),
+	MouseLeave,
+	MouseEnter,
+	MouseMotion(f32, f32),
+	FileHovered(PathBuf),
+	FileHoverCancelled,
+	FileDropped(PathBuf),
+	Suspended,
+	Resumed,
+	/// The window was asked to close. Consuming this event keeps the engine running.
+	ShutdownRequested,
+}
+
+/// Returned by listeners to decide if lower priority listeners receive an event
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Propagation {
+	Continue,
+	Consume,
+}
+
+/// Order listeners are called in. Higher priorities are called first.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Priority(pub i32);
+
+impl Priority {
+	/// Used by engine modules that must see window events before anything else can consume them
+	pub const ENGINE: Self = Self(1000);
+	/// Used by user interface modules so they can swallow input before the game sees it
+	pub const GUI: Self = Self(100);
+	pub const DEFAULT: Self = Self(0);
+	pub const GAME: Self = Self(-100);
+}
+
+impl Default for Priority {
+	fn default() -> Self {
+		Self::DEFAULT
+	}
+}
+
+struct Listener<T> {
+	priority: Priority,
+	f: Box<dyn Fn(&T) -> Propagation + 'static>,
+}
+
+/// Typed event dispatcher. Any `'static` type can be used as an event.
+///
+/// Listeners are called from highest to lowest [`Priority`] and in registration order within a priority.
+#[derive(Default)]
+pub struct EventBus {
+	listeners: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl EventBus {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn listen<T: 'static>(
+		&mut self,
+		priority: Priority,
+		f: impl Fn(&T) -> Propagation + 'static,
+	) {
+		let listeners = self
+			.listeners
+			.entry(TypeId::of::<T>())
+			.or_insert_with(|| Box::new(Vec::<Listener<T>>::new()))
+			.downcast_mut::<Vec<Listener<T>>>()
+			.unwrap();
+
+		let index = listeners
+			.iter()
+			.position(|it| it.priority < priority)
+			.unwrap_or(listeners.len());
+		listeners.insert(
+			index,
+			Listener {
+				priority,
+				f: Box::new(f),
+			},
+		);
+	}
+
+	/// Calls every listener of `T` until one consumes the event
+	pub fn emit<T: 'static>(&self, event: &T) -> Propagation {
+		let listeners = match self.listeners.get(&TypeId::of::<T>()) {
+			Some(listeners) => listeners.downcast_ref::<Vec<Listener<T>>>().unwrap(),
+			None => return Propagation::Continue,
+		};
+
+		for it in listeners.iter() {
+			if (it.f)(event) == Propagation::Consume {
+				return Propagation::Consume;
+			}
+		}
+		Propagation::Continue
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::{
+		cell::RefCell,
+		rc::Rc,
+	};
+
+	#[test]
+	fn priority_order() {
+		let order = Rc::new(RefCell::new(Vec::new()));
+		let mut bus = EventBus::new();
+
+		for (name, priority) in [
+			("game", Priority::GAME),
+			("gui", Priority::GUI),
+			("default", Priority::DEFAULT),
+		] {
+			let order = order.clone();
+			bus.listen(priority, move |_: &u32| {
+				order.borrow_mut().push(name);
+				Propagation::Continue
+			});
+		}
+
+		assert_eq!(bus.emit(&0u32), Propagation::Continue);
+		assert_eq!(&order.borrow()[..], &["gui", "default", "game"]);
+	}
+
+	#[test]
+	fn consume() {
+		let reached = Rc::new(RefCell::new(false));
+		let mut bus = EventBus::new();
+
+		bus.listen(Priority::GUI, |event: &Event| match event {
+			Event::MouseButton { .. } => Propagation::Consume,
+			_ => Propagation::Continue,
+		});
+		{
+			let reached = reached.clone();
+			bus.listen(Priority::GAME, move |_: &Event| {
+				*reached.borrow_mut() = true;
+				Propagation::Continue
+			});
+		}
+
+		let click = Event::MouseButton {
+			mouse_button: os::MOUSE_BUTTON_LEFT,
+			pressed: true,
+		};
+		assert_eq!(bus.emit(&click), Propagation::Consume);
+		assert!(!*reached.borrow());
+
+		assert_eq!(bus.emit(&Event::FocusGained), Propagation::Continue);
+		assert!(*reached.borrow());
+	}
+}
@@ -18,13 +18,29 @@ use std::io::Write;
 use std::sync::Mutex;
 
 /// Level of verbosity in a log
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Default)]
 pub enum Verbosity {
+	#[default]
 	Debug,
 	Info,
 	Warning,
 	Error,
 }
 
+impl std::str::FromStr for Verbosity {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"debug" => Ok(Self::Debug),
+			"info" => Ok(Self::Info),
+			"warning" | "warn" => Ok(Self::Warning),
+			"error" => Ok(Self::Error),
+			_ => Err(()),
+		}
+	}
+}
+
 pub struct Entry {
 	pub verbosity: Verbosity,
 	pub category: Category,
@@ -94,6 +110,10 @@ impl Logger {
 static LOGS_PATH: &str = "target/logs/";
 
 pub fn log(verbosity: Verbosity, category: Category, message: String) {
+	if verbosity < Engine::arguments().log_level {
+		return;
+	}
+
 	let logger: &Logger = Engine::logger();
 
 	let date = SystemDate::now();
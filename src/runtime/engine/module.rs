@@ -26,3 +26,29 @@ macro_rules! define_run_module {
 		}
 	};
 }
+
+/// Exports the entry points used by a host executable to load `$module` from a `cdylib`
+///
+/// The host calls `newport_library_attach` with its engine and then `newport_library_register` with a
+/// [`Builder`] to collect `$module` and its dependencies. The returned id is the module swapped on reload.
+///
+/// Both entry points use the C calling convention but still pass Rust types such as [`Builder`] and
+/// [`std::any::TypeId`]. The host and the library must be built by the same compiler with the same engine.
+#[macro_export]
+macro_rules! define_library_module {
+	($module:ident) => {
+		#[no_mangle]
+		pub unsafe extern "C" fn newport_library_attach(engine: *mut $crate::Engine) {
+			$crate::Engine::attach(engine)
+		}
+
+		#[no_mangle]
+		#[allow(improper_ctypes_definitions)]
+		pub extern "C" fn newport_library_register(
+			builder: &mut $crate::Builder,
+		) -> std::any::TypeId {
+			builder.module::<$module>();
+			std::any::TypeId::of::<$module>()
+		}
+	};
+}
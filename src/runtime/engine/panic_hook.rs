@@ -0,0 +1,18 @@
+/// Called by the engine's panic hook once the panic has been logged
+///
+/// Registered with [`Builder::register`] by modules that need to flush or wait on something before the process
+/// goes down. Hooks run in registration order on the panicking thread.
+///
+/// [`Builder::register`]: crate::Builder::register
+#[derive(Copy, Clone)]
+pub struct PanicHook(fn());
+
+impl PanicHook {
+	pub fn new(hook: fn()) -> Self {
+		Self(hook)
+	}
+
+	pub(crate) fn call(&self) {
+		(self.0)()
+	}
+}
@@ -0,0 +1,231 @@
+/// Frame timing shared by the whole engine
+///
+/// Every frame the run loop calls [`Time::advance`] with the wall clock delta. The delta is replaced by
+/// [`Time::locked_delta`] when set, clamped to [`Time::max_delta`], scaled by [`Time::scale`] and
+/// accumulated. Fixed ticks run once for every [`Time::fixed_delta`] in the accumulator while variable
+/// ticks run once with the scaled delta.
+#[derive(Clone, Debug)]
+pub struct Time {
+	fixed_delta: f32,
+	max_delta: f32,
+	locked_delta: Option<f32>,
+	scale: f32,
+
+	paused: bool,
+	pending_steps: u32,
+
+	accumulator: f32,
+	delta: f32,
+	unscaled_delta: f32,
+	elapsed: f64,
+
+	frame: u64,
+	fixed_frame: u64,
+}
+
+impl Time {
+	pub const DEFAULT_FIXED_DELTA: f32 = 1.0 / 60.0;
+	pub const DEFAULT_MAX_DELTA: f32 = 0.25;
+
+	pub fn new() -> Self {
+		Self {
+			fixed_delta: Self::DEFAULT_FIXED_DELTA,
+			max_delta: Self::DEFAULT_MAX_DELTA,
+			locked_delta: None,
+			scale: 1.0,
+
+			paused: false,
+			pending_steps: 0,
+
+			accumulator: 0.0,
+			delta: 0.0,
+			unscaled_delta: 0.0,
+			elapsed: 0.0,
+
+			frame: 0,
+			fixed_frame: 0,
+		}
+	}
+
+	/// Advances time by the wall clock delta `dt`. Returns how many fixed ticks should run this frame.
+	pub fn advance(&mut self, dt: f32) -> u32 {
+		self.frame += 1;
+		let dt = self.locked_delta.unwrap_or(dt);
+		self.unscaled_delta = dt.max(0.0).min(self.max_delta);
+
+		let steps = if self.paused {
+			// Single stepping runs exactly one fixed tick regardless of how much time passed
+			self.delta = 0.0;
+			if self.pending_steps > 0 {
+				self.pending_steps -= 1;
+				self.delta = self.fixed_delta;
+				1
+			} else {
+				0
+			}
+		} else {
+			self.delta = self.unscaled_delta * self.scale;
+			self.accumulator += self.delta;
+
+			let mut steps = 0;
+			while self.accumulator >= self.fixed_delta {
+				self.accumulator -= self.fixed_delta;
+				steps += 1;
+			}
+			steps
+		};
+
+		self.elapsed += self.delta as f64;
+		self.fixed_frame += steps as u64;
+		steps
+	}
+
+	/// Scaled delta of the current frame. Zero while paused.
+	pub fn delta(&self) -> f32 {
+		self.delta
+	}
+
+	/// Clamped wall clock delta of the current frame. Not affected by scale or pause.
+	pub fn unscaled_delta(&self) -> f32 {
+		self.unscaled_delta
+	}
+
+	pub fn fixed_delta(&self) -> f32 {
+		self.fixed_delta
+	}
+
+	pub fn set_fixed_delta(&mut self, fixed_delta: f32) {
+		assert!(fixed_delta > 0.0, "Fixed delta must be greater than zero");
+		self.fixed_delta = fixed_delta;
+	}
+
+	pub fn max_delta(&self) -> f32 {
+		self.max_delta
+	}
+
+	/// Sets the largest wall clock delta a single frame can advance by. Prevents a long frame from running
+	/// an unbounded number of fixed ticks.
+	pub fn set_max_delta(&mut self, max_delta: f32) {
+		self.max_delta = max_delta;
+	}
+
+	pub fn locked_delta(&self) -> Option<f32> {
+		self.locked_delta
+	}
+
+	/// Makes every frame advance by `locked_delta` no matter how much wall clock time passed. Used to record
+	/// image sequences that play back at a steady rate even though capturing each frame is slow.
+	pub fn set_locked_delta(&mut self, locked_delta: Option<f32>) {
+		if let Some(locked_delta) = locked_delta {
+			assert!(locked_delta > 0.0, "Locked delta must be greater than zero");
+		}
+		self.locked_delta = locked_delta;
+	}
+
+	pub fn scale(&self) -> f32 {
+		self.scale
+	}
+
+	pub fn set_scale(&mut self, scale: f32) {
+		assert!(scale >= 0.0, "Time scale can not be negative");
+		self.scale = scale;
+	}
+
+	pub fn is_paused(&self) -> bool {
+		self.paused
+	}
+
+	pub fn set_paused(&mut self, paused: bool) {
+		self.paused = paused;
+		self.pending_steps = 0;
+	}
+
+	/// Runs a single fixed tick on the next frame while paused
+	pub fn step(&mut self) {
+		if self.paused {
+			self.pending_steps += 1;
+		}
+	}
+
+	/// Fraction of a fixed tick left in the accumulator. Used to interpolate between the last two fixed states when rendering.
+	pub fn alpha(&self) -> f32 {
+		self.accumulator / self.fixed_delta
+	}
+
+	/// Total scaled time in seconds
+	pub fn elapsed(&self) -> f64 {
+		self.elapsed
+	}
+
+	pub fn frame(&self) -> u64 {
+		self.frame
+	}
+
+	pub fn fixed_frame(&self) -> u64 {
+		self.fixed_frame
+	}
+}
+
+impl Default for Time {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fixed_steps() {
+		let mut time = Time::new();
+		time.set_fixed_delta(0.1);
+
+		assert_eq!(time.advance(0.05), 0);
+		assert_eq!(time.advance(0.1), 1);
+		assert!((time.alpha() - 0.5).abs() < 0.001);
+		assert_eq!(time.advance(0.2), 2);
+		assert_eq!(time.fixed_frame(), 3);
+	}
+
+	#[test]
+	fn clamp_and_scale() {
+		let mut time = Time::new();
+		time.set_fixed_delta(0.1);
+		time.set_max_delta(0.25);
+
+		assert_eq!(time.advance(10.0), 2);
+		assert_eq!(time.unscaled_delta(), 0.25);
+
+		time.set_scale(0.5);
+		time.advance(0.2);
+		assert!((time.delta() - 0.1).abs() < 0.001);
+	}
+
+	#[test]
+	fn pause_and_step() {
+		let mut time = Time::new();
+		time.set_paused(true);
+
+		assert_eq!(time.advance(1.0), 0);
+		assert_eq!(time.delta(), 0.0);
+
+		time.step();
+		assert_eq!(time.advance(0.001), 1);
+		assert_eq!(time.delta(), time.fixed_delta());
+		assert_eq!(time.advance(1.0), 0);
+	}
+
+	#[test]
+	fn locked_delta() {
+		let mut time = Time::new();
+		time.set_fixed_delta(0.1);
+		time.set_locked_delta(Some(0.1));
+
+		assert_eq!(time.advance(2.0), 1);
+		assert_eq!(time.unscaled_delta(), 0.1);
+
+		time.set_locked_delta(None);
+		assert_eq!(time.advance(0.2), 2);
+	}
+}
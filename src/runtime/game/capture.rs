@@ -0,0 +1,339 @@
+use {
+	crate::Game,
+	engine::{
+		define_log_category,
+		info,
+		warn,
+		Builder,
+		Engine,
+		Event,
+		Module,
+		Priority,
+		Propagation,
+	},
+	gpu::{
+		Buffer,
+		BufferUsage,
+		Format,
+		Gpu,
+		Layout,
+		MemoryType,
+		PresentLayer,
+		PresentPass,
+		RenderGraph,
+		TextureId,
+	},
+	input::{
+		Input,
+		KEY_F12,
+	},
+	std::{
+		collections::VecDeque,
+		error::Error,
+		fs::{
+			self,
+			File,
+		},
+		io::BufWriter,
+		path::{
+			Path,
+			PathBuf,
+		},
+		sync::Mutex,
+		time::{
+			SystemTime,
+			UNIX_EPOCH,
+		},
+	},
+};
+
+define_log_category!(Capture, CAPTURE_CATEGORY);
+
+/// Directory screenshots taken with the key binding are written to
+pub const CAPTURE_DIRECTORY: &str = "target/captures";
+
+/// What a [`Screenshot`] reads back
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CaptureSource {
+	/// The scene rendered by [`Game`] without anything drawn over it
+	Scene,
+	/// The backbuffer once every [`PresentPass`] has drawn into it
+	Backbuffer,
+}
+
+/// Request to write one or more frames to PNG files. Queued with [`Capture::request`]
+#[derive(Clone, Debug)]
+pub struct Screenshot {
+	source: CaptureSource,
+	path: PathBuf,
+	frames: u32,
+	delta: f32,
+}
+
+impl Screenshot {
+	pub fn new(source: CaptureSource, path: impl Into<PathBuf>) -> Self {
+		Self {
+			source,
+			path: path.into(),
+			frames: 1,
+			delta: 0.0,
+		}
+	}
+
+	/// Records `frames` consecutive frames instead of one. Time advances by exactly `delta` between them so the
+	/// sequence plays back at a steady rate however long each capture takes. Files are numbered by frame.
+	pub fn sequence(mut self, frames: u32, delta: f32) -> Self {
+		assert!(frames > 0, "Image sequences must have at least one frame");
+		assert!(
+			delta > 0.0,
+			"Image sequences must advance time between frames"
+		);
+		self.frames = frames;
+		self.delta = delta;
+		self
+	}
+
+	fn frame_path(&self, frame: u32) -> PathBuf {
+		if self.frames == 1 {
+			return self.path.clone();
+		}
+		let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+		self.path
+			.with_file_name(format!("{}_{:04}.png", stem, frame))
+	}
+}
+
+struct Recording {
+	screenshot: Screenshot,
+	frame: u32,
+}
+
+// Backbuffer copied by the capture present pass. Read once the GPU has finished the frame
+struct Readback {
+	path: PathBuf,
+	buffer: Buffer<u8>,
+	format: Format,
+	width: u32,
+	height: u32,
+}
+
+struct CaptureState {
+	binding: Option<Input>,
+	binding_down: bool,
+
+	queue: VecDeque<Screenshot>,
+	recording: Option<Recording>,
+
+	// Path of the frame the next present should copy the backbuffer for
+	backbuffer: Option<PathBuf>,
+	readbacks: Vec<Readback>,
+}
+
+/// Writes rendered frames to PNG files. Requests come from [`Capture::request`] or the key binding and are
+/// handled one at a time after the scene is rendered.
+pub struct Capture(Mutex<CaptureState>);
+
+impl Capture {
+	fn get<'a>() -> &'a Capture {
+		Engine::module().expect("Engine must depend on the Capture module to capture frames")
+	}
+
+	pub fn request(screenshot: Screenshot) {
+		Self::get().0.lock().unwrap().queue.push_back(screenshot);
+	}
+
+	/// Sets the key that captures the backbuffer into [`CAPTURE_DIRECTORY`]. `None` disables it. Defaults to F12
+	pub fn set_binding(binding: Option<Input>) {
+		let mut state = Self::get().0.lock().unwrap();
+		state.binding = binding;
+		state.binding_down = false;
+	}
+
+	/// Returns true while requests are queued or an image sequence is being recorded
+	pub fn is_capturing() -> bool {
+		let state = Self::get().0.lock().unwrap();
+		state.recording.is_some() || !state.queue.is_empty()
+	}
+
+	fn tick() {
+		let mut state = Self::get().0.lock().unwrap();
+
+		// Copies recorded by the last present are only safe to read once the GPU is done with the frame
+		if !state.readbacks.is_empty() {
+			Gpu::device().wait_for_idle();
+			for it in state.readbacks.drain(..) {
+				let result = it
+					.buffer
+					.read()
+					.map_err(|err| err.into())
+					.and_then(|bytes| {
+						write_png(&it.path, it.width, it.height, &it.format.to_rgba_u8(bytes))
+					});
+				report(&it.path, result);
+			}
+		}
+		if let Some(path) = state.backbuffer.take() {
+			warn!(
+				CAPTURE_CATEGORY,
+				"Nothing was presented so {} was not captured",
+				path.display()
+			);
+		}
+
+		if state.recording.is_none() {
+			let screenshot = match state.queue.pop_front() {
+				Some(screenshot) => screenshot,
+				None => return,
+			};
+			if screenshot.frames > 1 {
+				Engine::time().set_locked_delta(Some(screenshot.delta));
+			}
+			state.recording = Some(Recording {
+				screenshot,
+				frame: 0,
+			});
+		}
+
+		let recording = state.recording.as_mut().unwrap();
+		let path = recording.screenshot.frame_path(recording.frame);
+		let source = recording.screenshot.source;
+
+		recording.frame += 1;
+		if recording.frame == recording.screenshot.frames {
+			if recording.screenshot.frames > 1 {
+				Engine::time().set_locked_delta(None);
+			}
+			state.recording = None;
+		}
+
+		match source {
+			CaptureSource::Scene => report(&path, capture_scene(&path)),
+			CaptureSource::Backbuffer => state.backbuffer = Some(path),
+		}
+	}
+
+	fn present(graph: &mut RenderGraph, backbuffer: TextureId) {
+		let mut state = Self::get().0.lock().unwrap();
+		let path = match state.backbuffer.take() {
+			Some(path) => path,
+			None => return,
+		};
+
+		let texture = graph
+			.imported_texture(backbuffer)
+			.expect("Backbuffer must be imported")
+			.clone();
+		let (width, height, format) = (texture.width(), texture.height(), texture.format());
+		let buffer = match Buffer::new(
+			BufferUsage::TRANSFER_DST,
+			MemoryType::HostVisible,
+			format.size_of(width, height),
+		) {
+			Ok(buffer) => buffer,
+			Err(err) => return report(&path, Err(err.into())),
+		};
+
+		let id = graph.import_buffer(&buffer);
+		let dst = buffer.clone();
+		graph
+			.add_pass("capture")
+			.copy_from(backbuffer)
+			.write_buffer(id)
+			.commands(move |recorder, resources| {
+				recorder.copy_texture_to_buffer(&dst, resources.texture(backbuffer))
+			});
+
+		state.readbacks.push(Readback {
+			path,
+			buffer,
+			format,
+			width,
+			height,
+		});
+	}
+}
+
+impl Module for Capture {
+	fn new() -> Self {
+		Self(Mutex::new(CaptureState {
+			binding: Some(KEY_F12),
+			binding_down: false,
+
+			queue: VecDeque::new(),
+			recording: None,
+
+			backbuffer: None,
+			readbacks: Vec::new(),
+		}))
+	}
+
+	fn depends_on(builder: &mut Builder) -> &mut Builder {
+		builder
+			.listen(Priority::GAME, |event: &Event| {
+				let capture: Option<&Capture> = Engine::module();
+				if let (Event::Key { key, pressed }, Some(capture)) = (event, capture) {
+					let mut state = capture.0.lock().unwrap();
+					if state.binding == Some(*key) {
+						// Held keys repeat so only the first press takes a screenshot
+						if *pressed && !state.binding_down {
+							state.queue.push_back(Screenshot::new(
+								CaptureSource::Backbuffer,
+								default_path(),
+							));
+						}
+						state.binding_down = *pressed;
+					}
+				}
+				Propagation::Continue
+			})
+			.register(PresentPass::new(PresentLayer::CAPTURE, Capture::present))
+			.tick(|_| Capture::tick())
+	}
+}
+
+fn default_path() -> PathBuf {
+	let now = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_millis();
+	Path::new(CAPTURE_DIRECTORY).join(format!("screenshot_{}.png", now))
+}
+
+fn capture_scene(path: &Path) -> Result<(), Box<dyn Error>> {
+	let game: &Game = Engine::module().unwrap();
+	let scene = game
+		.renderer
+		.to_display()
+		.ok_or("no scene has been rendered yet")?;
+
+	let texture = &scene.diffuse_buffer;
+	let pixels = texture.read_pixels(Layout::ShaderReadOnly)?;
+	write_png(path, texture.width(), texture.height(), &pixels)
+}
+
+fn write_png(path: &Path, width: u32, height: u32, pixels: &[u8]) -> Result<(), Box<dyn Error>> {
+	if let Some(parent) = path.parent() {
+		fs::create_dir_all(parent)?;
+	}
+
+	let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+	encoder.set_color(png::ColorType::Rgba);
+	encoder.set_depth(png::BitDepth::Eight);
+
+	let mut writer = encoder.write_header()?;
+	writer.write_image_data(pixels)?;
+	writer.finish()?;
+	Ok(())
+}
+
+fn report(path: &Path, result: Result<(), Box<dyn Error>>) {
+	match result {
+		Ok(()) => info!(CAPTURE_CATEGORY, "Captured {}", path.display()),
+		Err(err) => warn!(
+			CAPTURE_CATEGORY,
+			"Failed to capture {} due to {}",
+			path.display(),
+			err
+		),
+	}
+}
@@ -1,20 +1,33 @@
 use {
+	config::{
+		Config,
+		ConfigManager,
+	},
 	draw2d::Draw2d,
 	ecs::{
 		Component,
 		Ecs,
-		ScheduleBlock,
+		Phase,
+		Scene,
+		Schedule,
 		World,
 	},
 	engine::{
+		define_log_category,
+		warn,
 		Builder,
 		Engine,
 		Module,
+		Uuid,
 	},
 	gpu::{
 		Gpu,
 		GraphicsPipeline,
-		GraphicsRecorder,
+		Layout,
+		PresentLayer,
+		PresentPass,
+		RenderGraph,
+		TextureId,
 	},
 	input::*,
 	math::*,
@@ -23,9 +36,14 @@ use {
 		Importer,
 		Resource,
 	},
+	serde::{
+		Deserialize,
+		Serialize,
+	},
 	std::sync::Mutex,
 };
 
+mod capture;
 mod debug;
 mod render;
 mod transform;
@@ -36,6 +54,12 @@ pub(crate) use {
 };
 
 pub use {
+	capture::{
+		Capture,
+		CaptureSource,
+		Screenshot,
+		CAPTURE_DIRECTORY,
+	},
 	debug::{
 		DebugManager,
 		DebugSystem,
@@ -49,18 +73,46 @@ pub use {
 	transform::Transform,
 };
 
+define_log_category!(Game, GAME_CATEGORY);
+
+pub const CONFIG_FILE: &str = "game.toml";
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct GameConfig {
+	/// Scene loaded into the world when the game starts. `--scene` takes precedence
+	pub default_scene: Option<Uuid>,
+}
+
+impl Config for GameConfig {
+	const NAME: &'static str = "Game";
+	const FILE: &'static str = CONFIG_FILE;
+}
+
 pub struct Game {
 	pub world: World,
-	pub schedule: Mutex<ScheduleBlock>,
+	pub schedule: Mutex<Schedule>,
 	renderer: Renderer,
 
 	present_pipeline: Handle<GraphicsPipeline>,
 }
 impl Module for Game {
 	fn new() -> Self {
+		let world = World::new();
+
+		let scene = Engine::arguments()
+			.scene
+			.or(ConfigManager::read::<GameConfig>().default_scene);
+		if let Some(scene) = scene {
+			match Handle::<Scene>::find_or_load(scene) {
+				Ok(scene) => world.load_scene(&scene.read()),
+				Err(err) => warn!(GAME_CATEGORY, "Failed to load scene {:?}: {}", scene, err),
+			}
+		}
+
 		Self {
-			world: World::new(),
-			schedule: Mutex::new(ScheduleBlock::new()),
+			world,
+			schedule: Mutex::new(Schedule::new()),
 			renderer: Renderer::new(),
 
 			#[cfg(not(feature = "editor"))]
@@ -74,6 +126,8 @@ impl Module for Game {
 			.module::<Draw2d>()
 			.module::<Ecs>()
 			.module::<GameInput>()
+			.module::<ConfigManager>()
+			.register(GameConfig::variant())
 			.register(Transform::variant())
 			.register(Camera::variant())
 			.register(Mesh::variant())
@@ -81,6 +135,15 @@ impl Module for Game {
 			.register(MeshFilter::variant())
 			.register(DebugManager::variant())
 			.register(DirectionalLight::variant())
+			.register(PresentPass::new(PresentLayer::GAME, Game::present))
+			.fixed_tick(|delta_time| {
+				let Game {
+					world, schedule, ..
+				} = Engine::module().unwrap();
+
+				let schedule = schedule.lock().unwrap();
+				schedule.execute(world, Phase::Fixed, delta_time);
+			})
 			.tick(|delta_time| {
 				let Game {
 					world,
@@ -89,55 +152,50 @@ impl Module for Game {
 					..
 				} = Engine::module().unwrap();
 
-				let window = Engine::window().unwrap();
-				let viewport = window.inner_size();
-				let viewport = Vec2::new(viewport.width as f32, viewport.height as f32);
-
 				{
-					{
-						let schedule = schedule.lock().unwrap();
-						schedule.execute(world, delta_time);
-						let scene = DrawList::build(world, viewport);
-						renderer.push_scene(scene);
-					}
+					let schedule = schedule.lock().unwrap();
+					schedule.execute(world, Phase::Variable, delta_time);
+				}
+
+				// Headless runs have no window to render the scene for
+				if let Some(window) = Engine::window() {
+					let viewport = window.inner_size();
+					let viewport = Vec2::new(viewport.width as f32, viewport.height as f32);
+					renderer.push_scene(DrawList::build(world, viewport));
 					renderer.render_scene();
-				};
+				}
 				renderer.advance_frame();
 			})
-			.display(|| {
-				let game: &Game = Engine::module().unwrap();
-
-				let device = Gpu::device();
-				let backbuffer = device
-					.acquire_backbuffer()
-					.expect("Swapchain failed to find a back buffer");
-
-				let pipeline = game.present_pipeline.read();
-				let receipt = match game.renderer.to_display() {
-					Some(scene) => GraphicsRecorder::new()
-						.render_pass(&[&backbuffer], |ctx| {
-							ctx.clear_color(Color::BLACK)
-								.set_pipeline(&pipeline)
-								.set_texture("texture", &scene.diffuse_buffer)
-								.draw(3, 0)
-						})
-						.texture_barrier(
-							&backbuffer,
-							gpu::Layout::ColorAttachment,
-							gpu::Layout::Present,
-						)
-						.submit(),
-					None => GraphicsRecorder::new()
-						.render_pass(&[&backbuffer], |ctx| ctx.clear_color(Color::BLACK))
-						.texture_barrier(
-							&backbuffer,
-							gpu::Layout::ColorAttachment,
-							gpu::Layout::Present,
-						)
-						.submit(),
-				};
-
-				device.display(&[receipt]);
-			})
+			// After the game tick so captures see the scene rendered this frame
+			.module::<Capture>()
+			.display(Gpu::present)
+	}
+}
+
+impl Game {
+	/// Draws the last rendered scene into the backbuffer
+	fn present(graph: &mut RenderGraph, backbuffer: TextureId) {
+		let game: &Game = Engine::module().unwrap();
+
+		let scene = match game.renderer.to_display() {
+			Some(scene) => scene,
+			None => return,
+		};
+		let diffuse = graph.import_texture(
+			&scene.diffuse_buffer,
+			Layout::ShaderReadOnly,
+			Layout::ShaderReadOnly,
+		);
+
+		let pipeline = game.present_pipeline.read();
+		graph
+			.add_pass("game")
+			.color(backbuffer)
+			.read(diffuse)
+			.render(move |ctx, resources| {
+				ctx.set_pipeline(&pipeline)
+					.set_texture("texture", resources.texture(diffuse))
+					.draw(3, 0);
+			});
 	}
 }
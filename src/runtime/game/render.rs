@@ -14,12 +14,16 @@ use {
 		Buffer,
 		BufferUsage,
 		Format,
+		Gpu,
+		GpuConstants,
 		GraphicsPipeline,
-		GraphicsRecorder,
 		Layout,
 		MemoryType,
+		RenderGraph,
+		RingBuffer,
 		Texture,
 		TextureUsage,
+		TransferRecorder,
 	},
 	math::{
 		Color,
@@ -107,7 +111,8 @@ pub(crate) struct MeshGltfImporter {}
 impl Importer for MeshGltfImporter {
 	type Target = Mesh;
 
-	fn import(&self, bytes: &[u8]) -> resources::Result<Self::Target> {
+	fn import(&self, bytes: &[u8], path: &std::path::Path) -> resources::Result<Self::Target> {
+		let name = path.display();
 		let (gltf, buffers, _images) = gltf::import_slice(bytes)?;
 
 		let mut vertex_count = 0;
@@ -169,37 +174,46 @@ impl Importer for MeshGltfImporter {
 			}
 		}
 
-		let transfer_vertex = gpu::Buffer::new(
+		let transfer_vertex = gpu::Buffer::builder(
 			BufferUsage::TRANSFER_SRC,
 			MemoryType::HostVisible,
 			vertices.len(),
-		)?;
+		)
+		.debug_name(format!("{} vertices staging", name))
+		.spawn()?;
 		transfer_vertex.copy_to(&vertices[..]).unwrap();
 
-		let transfer_index = gpu::Buffer::new(
+		let transfer_index = gpu::Buffer::builder(
 			BufferUsage::TRANSFER_SRC,
 			MemoryType::HostVisible,
 			indices.len(),
-		)?;
+		)
+		.debug_name(format!("{} indices staging", name))
+		.spawn()?;
 		transfer_index.copy_to(&indices[..]).unwrap();
 
-		let vertex_buffer = gpu::Buffer::new(
+		let vertex_buffer = gpu::Buffer::builder(
 			BufferUsage::TRANSFER_DST | BufferUsage::VERTEX,
 			MemoryType::DeviceLocal,
 			vertices.len(),
-		)?;
+		)
+		.debug_name(format!("{} vertices", name))
+		.spawn()?;
 
-		let index_buffer = gpu::Buffer::new(
+		let index_buffer = gpu::Buffer::builder(
 			BufferUsage::TRANSFER_DST | BufferUsage::INDEX,
 			MemoryType::DeviceLocal,
 			indices.len(),
-		)?;
+		)
+		.debug_name(format!("{} indices", name))
+		.spawn()?;
 
-		GraphicsRecorder::new()
+		// Rendering waits on the upload instead of the importing thread
+		let receipt = TransferRecorder::new()
 			.copy_buffer_to_buffer(&vertex_buffer, &transfer_vertex)
 			.copy_buffer_to_buffer(&index_buffer, &transfer_index)
-			.submit()
-			.wait();
+			.submit();
+		Gpu::device().wait_before_rendering(&receipt);
 
 		Ok(Self::Target {
 			vertices,
@@ -230,8 +244,8 @@ pub struct DirectionalLight {
 
 impl Component for DirectionalLight {}
 
-#[derive(Clone, Debug)]
-#[allow(dead_code)]
+#[derive(Clone, Debug, GpuConstants)]
+#[repr(C)]
 struct DrawListData {
 	model: Mat4,
 	color: Color,
@@ -335,29 +349,65 @@ struct RendererInner {
 	current: usize,
 }
 
-pub struct Renderer(Mutex<RendererInner>);
+// TODO: Should this be done in the scene building part?
+#[allow(dead_code)]
+struct DebugVertex {
+	position: Vec3,
+	color: Color,
+}
+
+#[derive(GpuConstants)]
+#[repr(C)]
+struct CameraProperties {
+	view: Mat4,
+	position: Vec3,
+	_padding: f32,
+}
+
+// Resources reused between frames so rendering a scene does not allocate
+struct RendererBuffers {
+	debug_vertices: RingBuffer<DebugVertex>,
+	world_transforms: RingBuffer<DrawListData>,
+	camera: RingBuffer<CameraProperties>,
+
+	targets: [Option<RenderedScene>; 8], // Indexed like frames
+}
+
+pub struct Renderer {
+	inner: Mutex<RendererInner>,
+	buffers: Mutex<RendererBuffers>,
+}
 
 impl Renderer {
 	pub fn new() -> Self {
-		Self(Mutex::new(RendererInner {
-			frames: [
-				Frame::None,
-				Frame::None,
-				Frame::None,
-				Frame::None,
-				Frame::None,
-				Frame::None,
-				Frame::None,
-				Frame::None,
-			],
-			current: 8,
-		}))
+		Self {
+			inner: Mutex::new(RendererInner {
+				frames: [
+					Frame::None,
+					Frame::None,
+					Frame::None,
+					Frame::None,
+					Frame::None,
+					Frame::None,
+					Frame::None,
+					Frame::None,
+				],
+				current: 8,
+			}),
+			buffers: Mutex::new(RendererBuffers {
+				debug_vertices: RingBuffer::new(BufferUsage::VERTEX, 2),
+				world_transforms: RingBuffer::new(BufferUsage::CONSTANTS, 2),
+				camera: RingBuffer::new(BufferUsage::CONSTANTS, 2),
+
+				targets: Default::default(),
+			}),
+		}
 	}
 }
 
 impl Renderer {
 	pub fn push_scene(&self, scene: DrawList) {
-		let mut inner = self.0.lock().unwrap();
+		let mut inner = self.inner.lock().unwrap();
 
 		let current = inner.current;
 		let len = inner.frames.len();
@@ -367,7 +417,7 @@ impl Renderer {
 
 	pub fn render_scene(&self) {
 		let frame = {
-			let mut inner = self.0.lock().unwrap();
+			let mut inner = self.inner.lock().unwrap();
 
 			let current = inner.current - 1;
 			let len = inner.frames.len();
@@ -380,12 +430,11 @@ impl Renderer {
 			_ => return,
 		};
 
-		// TODO: Should this be done in the scene building part?
-		#[allow(dead_code)]
-		struct DebugVertex {
-			position: Vec3,
-			color: Color,
+		// Minimized windows have no area to allocate render targets for
+		if scene.viewport.x < 1.0 || scene.viewport.y < 1.0 {
+			return;
 		}
+
 		fn debug_batch_line(
 			vertices: &mut Vec<DebugVertex>,
 			a: Point3,
@@ -781,46 +830,56 @@ impl Renderer {
 				_ => unimplemented!(),
 			}
 		}
+		let mut buffers = self.buffers.lock().unwrap();
+		let buffers = &mut *buffers;
+
 		let debug_vertex_buffer = if debug_vertices.is_empty() {
 			None
 		} else {
-			let buffer = Buffer::new(
-				BufferUsage::VERTEX,
-				MemoryType::HostVisible,
-				debug_vertices.len(),
-			)
-			.unwrap();
-			buffer.copy_to(&debug_vertices).unwrap();
-			Some(buffer)
+			Some(buffers.debug_vertices.upload(&debug_vertices).unwrap())
 		};
 
-		let world_transforms_buffer = Buffer::new(
-			BufferUsage::CONSTANTS,
-			MemoryType::HostVisible,
-			scene.world_transforms.len(),
-		)
-		.unwrap();
-		world_transforms_buffer
-			.copy_to(&scene.world_transforms)
+		let world_transforms_buffer = buffers
+			.world_transforms
+			.upload(&scene.world_transforms)
 			.unwrap();
 
-		let diffuse_buffer = Texture::new(
-			TextureUsage::SAMPLED | TextureUsage::COLOR_ATTACHMENT,
-			Format::RGBA_U8,
-			scene.viewport.x as u32,
-			scene.viewport.y as u32,
-			1,
-		)
-		.unwrap();
-
-		let depth_buffer = Texture::new(
-			TextureUsage::DEPTH_ATTACHMENT,
-			Format::Depth24_Stencil8,
-			scene.viewport.x as u32,
-			scene.viewport.y as u32,
-			1,
-		)
-		.unwrap();
+		// Render targets are only recreated when the viewport changes size
+		let slot = (self.inner.lock().unwrap().current - 1) % buffers.targets.len();
+		let (width, height) = (scene.viewport.x as u32, scene.viewport.y as u32);
+		let target = match buffers.targets[slot].take() {
+			Some(target)
+				if target.diffuse_buffer.width() == width
+					&& target.diffuse_buffer.height() == height =>
+			{
+				target
+			}
+			_ => RenderedScene {
+				diffuse_buffer: Texture::new(
+					TextureUsage::SAMPLED
+						| TextureUsage::COLOR_ATTACHMENT
+						| TextureUsage::TRANSFER_SRC,
+					Format::RGBA_U8,
+					width,
+					height,
+					1,
+				)
+				.unwrap(),
+				depth_buffer: Texture::new(
+					TextureUsage::DEPTH_ATTACHMENT,
+					Format::Depth24_Stencil8,
+					width,
+					height,
+					1,
+				)
+				.unwrap(),
+			},
+		};
+		buffers.targets[slot] = Some(target.clone());
+		let RenderedScene {
+			diffuse_buffer,
+			depth_buffer,
+		} = target;
 
 		let camera_world_mat4 = scene.camera_transform.world_mat4();
 
@@ -835,19 +894,14 @@ impl Renderer {
 			w_column: Vec4::new(0.0, 0.0, 0.0, 1.0),
 		};
 
-		#[allow(dead_code)]
-		struct CameraProperties {
-			view: Mat4,
-			position: Vec3,
-		}
-
 		let camera_position = camera_world_mat4.w_column.xyz();
 
-		let view_buffer = Buffer::new(BufferUsage::CONSTANTS, MemoryType::HostVisible, 1).unwrap();
-		view_buffer
-			.copy_to(&[CameraProperties {
+		let view_buffer = buffers
+			.camera
+			.upload(&[CameraProperties {
 				view: proj * axis_adjustment * view,
 				position: camera_position,
+				_padding: 0.0,
 			}])
 			.unwrap();
 
@@ -855,10 +909,17 @@ impl Renderer {
 			Handle::find_or_load("{063952B6-40B8-4D22-A26F-339185008B76}").unwrap();
 		let debug_pipeline = debug_pipeline.read();
 
-		GraphicsRecorder::new()
-			.texture_barrier(&diffuse_buffer, Layout::Undefined, Layout::ColorAttachment)
-			.texture_barrier(&depth_buffer, Layout::Undefined, Layout::DepthAttachment)
-			.render_pass(&[&diffuse_buffer, &depth_buffer], |ctx| {
+		let mut graph = RenderGraph::new();
+		let diffuse =
+			graph.import_texture(&diffuse_buffer, Layout::Undefined, Layout::ShaderReadOnly);
+		let depth = graph.import_texture(&depth_buffer, Layout::Undefined, Layout::DepthAttachment);
+
+		graph
+			.add_pass("scene")
+			.color(diffuse)
+			.depth(depth)
+			.statistics()
+			.render(|ctx, _| {
 				ctx.clear_color(Color::BLACK).clear_depth(1.0);
 
 				// Draw all the meshes in the world with their given pipeline
@@ -869,8 +930,8 @@ impl Renderer {
 					ctx.set_pipeline(&pipeline)
 						.set_vertex_buffer(&mesh.vertex_buffer)
 						.set_index_buffer(&mesh.index_buffer)
-						.set_constants("imports", &world_transforms_buffer, index)
-						.set_constants("camera", &view_buffer, 0)
+						.set_constants("imports", world_transforms_buffer, index)
+						.set_constants("camera", view_buffer, 0)
 						.draw_indexed(mesh.indices.len(), 0);
 				}
 
@@ -878,22 +939,14 @@ impl Renderer {
 				// TODO: Should this be disabled on cooked build?
 				if let Some(buffer) = debug_vertex_buffer {
 					ctx.set_pipeline(&debug_pipeline)
-						.set_vertex_buffer(&buffer)
-						.set_constants("camera", &view_buffer, 0)
-						.draw(buffer.len(), 0);
+						.set_vertex_buffer(buffer)
+						.set_constants("camera", view_buffer, 0)
+						.draw(debug_vertices.len(), 0);
 				}
+			});
+		graph.execute().wait();
 
-				ctx
-			})
-			.texture_barrier(
-				&diffuse_buffer,
-				Layout::ColorAttachment,
-				Layout::ShaderReadOnly,
-			)
-			.submit()
-			.wait();
-
-		let mut inner = self.0.lock().unwrap();
+		let mut inner = self.inner.lock().unwrap();
 
 		let current = inner.current - 1;
 		let len = inner.frames.len();
@@ -906,7 +959,7 @@ impl Renderer {
 	}
 
 	pub fn to_display(&self) -> Option<RenderedScene> {
-		let inner = self.0.lock().unwrap();
+		let inner = self.inner.lock().unwrap();
 		let current = inner.current - 2;
 		let len = inner.frames.len();
 
@@ -918,7 +971,7 @@ impl Renderer {
 	}
 
 	pub fn advance_frame(&self) {
-		let mut inner = self.0.lock().unwrap();
+		let mut inner = self.inner.lock().unwrap();
 		inner.current += 1;
 	}
 }
@@ -0,0 +1,247 @@
+use std::{
+	collections::VecDeque,
+	fmt,
+};
+
+/// Number of slots in each bindless array
+pub(crate) const BINDLESS_CAPACITY: u32 = 2048;
+
+/// Kind of resource a bindless slot refers to. Each kind has its own array of slots
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BindlessKind {
+	Buffer,
+	Texture,
+	Sampler,
+}
+
+/// A bindless slot in use by a live resource
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BindlessSlot {
+	pub kind: BindlessKind,
+	pub index: u32,
+	/// Debug name of the resource or a short description when it has none
+	pub name: String,
+}
+
+/// Every live bindless slot of a [`crate::Device`]. See [`crate::Device::bindless_view`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BindlessView {
+	/// Sorted by kind then index
+	pub slots: Vec<BindlessSlot>,
+	/// Slots released by dropped resources that are waiting for the GPU to stop using them
+	pub retired: usize,
+}
+
+impl BindlessView {
+	pub fn count(&self, kind: BindlessKind) -> usize {
+		self.slots.iter().filter(|it| it.kind == kind).count()
+	}
+}
+
+impl fmt::Display for BindlessView {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for kind in [
+			BindlessKind::Buffer,
+			BindlessKind::Texture,
+			BindlessKind::Sampler,
+		] {
+			writeln!(f, "{:?}s {}/{}", kind, self.count(kind), BINDLESS_CAPACITY)?;
+			for it in self.slots.iter().filter(|it| it.kind == kind) {
+				writeln!(f, "  {:>4} {}", it.index, it.name)?;
+			}
+		}
+		writeln!(f, "{} retired", self.retired)
+	}
+}
+
+struct Entry<T> {
+	value: T,
+	name: String,
+}
+
+/// Hands out bindless indices from a free list. Released indices are only reused once the work submitted before
+/// the release has finished so descriptors are never overwritten while the GPU may still read them. `T` is
+/// what the back end needs to write the slot's descriptor.
+pub(crate) struct BindlessHeap<T> {
+	capacity: u32,
+	slots: Vec<Option<Entry<T>>>,
+	free: Vec<u32>,
+	// Released slots with the work id that must finish before they are free. Oldest first
+	retired: VecDeque<(usize, u32)>,
+	// Slots whose descriptor must be written before the next submission
+	dirty: Vec<u32>,
+}
+
+impl<T> BindlessHeap<T> {
+	pub fn new(capacity: u32) -> Self {
+		Self {
+			capacity,
+			slots: Vec::new(),
+			free: Vec::new(),
+			retired: VecDeque::new(),
+			dirty: Vec::new(),
+		}
+	}
+
+	/// Returns `None` when every slot is in use or waiting to be recycled
+	pub fn allocate(&mut self, value: T, name: String) -> Option<u32> {
+		let index = match self.free.pop() {
+			Some(index) => index,
+			None if (self.slots.len() as u32) < self.capacity => {
+				self.slots.push(None);
+				self.slots.len() as u32 - 1
+			}
+			None => return None,
+		};
+
+		self.slots[index as usize] = Some(Entry { value, name });
+		self.dirty.push(index);
+		Some(index)
+	}
+
+	/// Frees `index` once every submission with an id below `work` has finished. The slot's descriptor is left
+	/// alone until then as those submissions may still read it.
+	pub fn release(&mut self, index: u32, work: usize) {
+		let slot = &mut self.slots[index as usize];
+		assert!(slot.is_some(), "Bindless slot {} was released twice", index);
+		*slot = None;
+
+		self.retired.push_back((work, index));
+	}
+
+	/// Makes slots available again once their work has finished and marks them to be written with a null
+	/// resource. `pending` is the id of the oldest submission that may still be executing.
+	pub fn recycle(&mut self, pending: usize) {
+		while let Some((work, index)) = self.retired.front().copied() {
+			if work > pending {
+				break;
+			}
+			self.retired.pop_front();
+			self.free.push(index);
+			self.dirty.push(index);
+		}
+	}
+
+	pub fn set_name(&mut self, index: u32, name: &str) {
+		if let Some(entry) = &mut self.slots[index as usize] {
+			entry.name = name.to_string();
+		}
+	}
+
+	pub fn get(&self, index: u32) -> Option<&T> {
+		self.slots[index as usize].as_ref().map(|it| &it.value)
+	}
+
+	/// Returns every slot changed since the last call in index order
+	pub fn take_dirty(&mut self) -> Vec<u32> {
+		let mut dirty = std::mem::take(&mut self.dirty);
+		dirty.sort_unstable();
+		dirty.dedup();
+		dirty
+	}
+
+	pub fn retired(&self) -> usize {
+		self.retired.len()
+	}
+
+	pub fn view(&self, kind: BindlessKind) -> impl Iterator<Item = BindlessSlot> + '_ {
+		self.slots
+			.iter()
+			.enumerate()
+			.filter_map(move |(index, it)| {
+				it.as_ref().map(|it| BindlessSlot {
+					kind,
+					index: index as u32,
+					name: it.name.clone(),
+				})
+			})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[cfg(backend = "null")]
+	use crate::{
+		test_device,
+		Format,
+		GraphicsRecorder,
+		Texture,
+		TextureUsage,
+	};
+
+	#[test]
+	fn recycles_slots_after_their_work() {
+		let mut heap = BindlessHeap::new(3);
+		let a = heap.allocate((), "a".to_string()).unwrap();
+		let b = heap.allocate((), "b".to_string()).unwrap();
+		assert_eq!(heap.take_dirty(), [a, b]);
+
+		// Submissions before 4 may still read `a` so it is neither rewritten nor handed out until they finish
+		heap.release(a, 4);
+		assert!(heap.take_dirty().is_empty());
+		assert_eq!(heap.allocate((), "c".to_string()), Some(2));
+		assert_eq!(heap.allocate((), "d".to_string()), None);
+
+		heap.recycle(3);
+		assert_eq!(heap.retired(), 1);
+		assert_eq!(heap.take_dirty(), [2]);
+		heap.recycle(4);
+		assert_eq!(heap.retired(), 0);
+		assert_eq!(heap.take_dirty(), [a]);
+		assert_eq!(heap.allocate((), "d".to_string()), Some(a));
+
+		let names: Vec<String> = heap.view(BindlessKind::Texture).map(|it| it.name).collect();
+		assert_eq!(names, ["d", "b", "c"]);
+	}
+
+	#[test]
+	#[cfg(backend = "null")]
+	fn recycles_bindless_slots_after_submission() {
+		let device = test_device();
+		let texture =
+			|| Texture::new_in(TextureUsage::SAMPLED, Format::RGBA_U8, 4, 4, 1, &device).unwrap();
+
+		let a = texture();
+		let index = a.bindless().unwrap();
+		GraphicsRecorder::new_in(&device).submit();
+		assert_eq!(
+			device.take_bindless_writes(),
+			[(BindlessKind::Texture, index, true)]
+		);
+
+		// The slot keeps its descriptor while work up to the drop may read it and is neither written nor handed out
+		drop(a);
+		let b = texture();
+		assert_ne!(b.bindless(), Some(index));
+		GraphicsRecorder::new_in(&device).submit();
+		assert_eq!(
+			device.take_bindless_writes(),
+			[(BindlessKind::Texture, b.bindless().unwrap(), true)]
+		);
+
+		// Once that work has finished the slot is written with null and can be reused
+		GraphicsRecorder::new_in(&device).submit();
+		assert_eq!(
+			device.take_bindless_writes(),
+			[(BindlessKind::Texture, index, false)]
+		);
+
+		let c = texture();
+		assert_eq!(c.bindless(), Some(index));
+		GraphicsRecorder::new_in(&device).submit();
+		assert_eq!(
+			device.take_bindless_writes(),
+			[(BindlessKind::Texture, index, true)]
+		);
+
+		// Slots that did not change are not written again
+		GraphicsRecorder::new_in(&device).submit();
+		assert!(device.take_bindless_writes().is_empty());
+
+		let view = device.bindless_view();
+		assert_eq!(view.count(BindlessKind::Texture), 2);
+		assert_eq!(view.slots[0].name, "RGBA_U8 4x4");
+	}
+}
@@ -20,6 +20,9 @@ bitflags! {
 		const VERTEX            = 0b000100;
 		const INDEX             = 0b001000;
 		const CONSTANTS         = 0b010000;
+		const STORAGE           = 0b100000;
+		/// Holds arguments for indirect draws
+		const INDIRECT          = 0b1000000;
 	}
 }
 
@@ -29,6 +32,7 @@ pub struct BufferBuilder<'a, T: Sized> {
 	pub(crate) memory: MemoryType,
 	pub(crate) len: usize,
 	pub(crate) device: Option<&'a Device>,
+	pub(crate) debug_name: Option<String>,
 	pub(crate) phantom: PhantomData<T>,
 }
 
@@ -38,32 +42,53 @@ impl<'a, T: Sized> BufferBuilder<'a, T> {
 		self
 	}
 
+	/// Names the buffer in GPU captures and validation messages
+	pub fn debug_name(mut self, name: impl ToString) -> Self {
+		self.debug_name = Some(name.to_string());
+		self
+	}
+
 	pub fn spawn(self) -> Result<Buffer<T>> {
 		let device = match self.device {
 			Some(device) => device,
 			None => Gpu::device(),
 		};
 
+		let api = api::Buffer::new(
+			device.0.clone(),
+			self.usage,
+			self.memory,
+			std::mem::size_of::<T>() * self.len,
+		)?;
+		if let Some(name) = &self.debug_name {
+			api.set_debug_name(name);
+		}
+
 		Ok(Buffer {
-			api: api::Buffer::new(
-				device.0.clone(),
-				self.usage,
-				self.memory,
-				std::mem::size_of::<T>() * self.len,
-			)?,
+			api,
 			phantom: PhantomData,
 			len: self.len,
 		})
 	}
 }
 
-#[derive(Clone)]
 pub struct Buffer<T: Sized> {
 	pub(crate) api: Arc<api::Buffer>,
 	pub(crate) phantom: PhantomData<T>,
 	pub(crate) len: usize,
 }
 
+// Cloning only shares the GPU buffer so `T` does not need to be Clone
+impl<T: Sized> Clone for Buffer<T> {
+	fn clone(&self) -> Self {
+		Self {
+			api: self.api.clone(),
+			phantom: PhantomData,
+			len: self.len,
+		}
+	}
+}
+
 impl<T: Sized> Buffer<T> {
 	pub fn new(usage: BufferUsage, memory: MemoryType, len: usize) -> Result<Buffer<T>> {
 		Buffer::builder(usage, memory, len).spawn()
@@ -82,10 +107,30 @@ impl<T: Sized> Buffer<T> {
 		self.api.copy_to::<T>(data)
 	}
 
+	/// Copies the contents of the buffer back to the CPU. The buffer must have been created with
+	/// [`MemoryType::HostVisible`] and any work writing to it must have finished.
+	pub fn read(&self) -> Result<Vec<T>>
+	where
+		T: Copy,
+	{
+		let mut result = Vec::with_capacity(self.len);
+		self.api
+			.copy_from::<T>(&mut result.spare_capacity_mut()[..self.len])?;
+
+		// UNSAFE: Every element up to len was just written by copy_from
+		unsafe { result.set_len(self.len) };
+		Ok(result)
+	}
+
 	pub fn bindless(&self) -> Option<u32> {
 		self.api.bindless()
 	}
 
+	#[cfg(backend = "null")]
+	pub fn id(&self) -> crate::ResourceId {
+		self.api.id
+	}
+
 	pub fn len(&self) -> usize {
 		self.len
 	}
@@ -100,7 +145,37 @@ impl<T: Sized> Buffer<T> {
 			memory,
 			len,
 			device: None,
+			debug_name: None,
 			phantom: PhantomData,
 		}
 	}
 }
+
+#[cfg(all(test, backend = "null"))]
+mod tests {
+	use crate::*;
+
+	#[test]
+	fn reads_back() {
+		let device = test_device();
+
+		let buffer: Buffer<u32> = Buffer::new_in(
+			BufferUsage::TRANSFER_DST,
+			MemoryType::HostVisible,
+			4,
+			&device,
+		)
+		.unwrap();
+		buffer.copy_to(&[1, 2, 3, 4]).unwrap();
+		assert_eq!(buffer.read().unwrap(), vec![1, 2, 3, 4]);
+
+		let local: Buffer<u32> = Buffer::new_in(
+			BufferUsage::TRANSFER_DST,
+			MemoryType::DeviceLocal,
+			4,
+			&device,
+		)
+		.unwrap();
+		assert_eq!(local.read(), Err(api::Error::NotHostVisible));
+	}
+}
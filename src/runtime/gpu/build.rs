@@ -5,8 +5,33 @@ use std::{
 };
 
 fn main() {
+	// The null back end wins when both features are enabled. Cargo merges features across a build so this is
+	// the only way for dependents to test against it while the rest of the workspace enables vulkan.
+	let null = env::var("CARGO_FEATURE_NULL").is_ok() || env::var("CARGO_FEATURE_VULKAN").is_err();
+	let backend = if null { "null" } else { "vulkan" };
+	println!("cargo:rustc-check-cfg=cfg(backend, values(\"null\", \"vulkan\"))");
+	println!("cargo:rustc-cfg=backend=\"{}\"", backend);
+
+	// Only the vulkan backend compiles shaders through dxc
+	if null {
+		return;
+	}
+
+	let library = match env::var("CARGO_CFG_TARGET_OS").as_deref() {
+		Ok("windows") => "dxcompiler.dll",
+		Ok("macos") => "libdxcompiler.dylib",
+		_ => "libdxcompiler.so",
+	};
+
+	// A bundled compiler is optional. Without one it is found at runtime through DXC_PATH or the Vulkan SDK
+	let source = PathBuf::from("bin").join(library);
+	println!("cargo:rerun-if-changed={}", source.display());
+	if !source.exists() {
+		return;
+	}
+
 	let out_dir = env::var("OUT_DIR").unwrap();
 	let mut path = PathBuf::from(out_dir);
-	path.push("dxcompiler.dll");
-	fs::copy("bin/dxcompiler.dll", &path).unwrap();
+	path.push(library);
+	fs::copy(&source, &path).unwrap();
 }
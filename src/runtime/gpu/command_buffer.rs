@@ -3,14 +3,26 @@ use crate::*;
 pub struct GraphicsCommandBuffer(pub(crate) api::GraphicsCommandBuffer);
 
 impl GraphicsCommandBuffer {
+	/// Returns every command recorded so far
+	#[cfg(backend = "null")]
+	pub fn commands(&self) -> &[Command] {
+		&self.0.commands
+	}
+
+	/// Returns the load and store ops of every render pass attachment recorded so far
+	#[cfg(backend = "null")]
+	pub fn attachments(&self) -> &[RecordedAttachment] {
+		&self.0.attachments
+	}
+
 	pub fn submit(self) -> Receipt {
 		let device = self.0.owner.clone();
-		device.submit_graphics(vec![self.0], &[])
+		device.submit(vec![self.0], &[])
 	}
 
 	pub fn submit_but_wait_on(self, receipts: &[Receipt]) -> Receipt {
 		let device = self.0.owner.clone();
-		device.submit_graphics(vec![self.0], receipts)
+		device.submit(vec![self.0], receipts)
 	}
 }
 
@@ -22,7 +34,11 @@ impl GraphicsRecorder {
 	}
 
 	pub fn new_in(device: &Device) -> Self {
-		let mut inner = api::GraphicsCommandBuffer::new(device.0.clone()).unwrap();
+		Self::new_on(device, Queue::Graphics)
+	}
+
+	fn new_on(device: &Device, queue: Queue) -> Self {
+		let mut inner = api::GraphicsCommandBuffer::new(device.0.clone(), queue).unwrap();
 		inner.begin();
 		GraphicsRecorder(inner)
 	}
@@ -38,9 +54,43 @@ impl GraphicsRecorder {
 		self
 	}
 
+	/// Copies every array layer of the first mip of `dst` from `src`. `dst` must be in [`Layout::TransferDst`].
 	pub fn copy_buffer_to_texture<T: Sized>(mut self, dst: &Texture, src: &Buffer<T>) -> Self {
 		self.0
-			.copy_buffer_to_texture(dst.0.clone(), src.api.clone());
+			.copy_buffer_to_texture(dst.0.clone(), src.api.clone(), 1);
+		self
+	}
+
+	/// Copies every mip of `dst` from `src`. Mips are packed from largest to smallest with every array layer of
+	/// a mip stored next to each other. `dst` must be in [`Layout::TransferDst`].
+	pub fn copy_buffer_to_texture_mips<T: Sized>(mut self, dst: &Texture, src: &Buffer<T>) -> Self {
+		let mip_levels = dst.mip_levels();
+		assert!(
+			src.api.size >= dst.extent().size_in_bytes(dst.format(), mip_levels),
+			"Buffer is too small to fill every mip of the texture"
+		);
+
+		self.0
+			.copy_buffer_to_texture(dst.0.clone(), src.api.clone(), mip_levels);
+		self
+	}
+
+	/// Fills every mip after the first by downsampling the one before it. `texture` must be in
+	/// [`Layout::TransferDst`] and is left in [`Layout::TransferSrc`].
+	pub fn generate_mips(mut self, texture: &Texture) -> Self {
+		assert!(
+			texture
+				.0
+				.usage
+				.contains(TextureUsage::TRANSFER_SRC | TextureUsage::TRANSFER_DST),
+			"Texture can only generate mips if usage is marked with TRANSFER_SRC and TRANSFER_DST"
+		);
+		assert!(
+			!texture.format().is_compressed(),
+			"Mips can not be generated for compressed formats"
+		);
+
+		self.0.generate_mips(texture.0.clone());
 		self
 	}
 
@@ -50,23 +100,150 @@ impl GraphicsRecorder {
 		self
 	}
 
+	/// Copies every pixel of `src` into `dst` tightly packed. `src` must be in [`Layout::TransferSrc`].
+	pub fn copy_texture_to_buffer<T: Sized>(mut self, dst: &Buffer<T>, src: &Texture) -> Self {
+		assert!(
+			src.0
+				.usage
+				.intersects(TextureUsage::TRANSFER_SRC | TextureUsage::SWAPCHAIN),
+			"Texture can only be copied from if usage is marked with TRANSFER_SRC"
+		);
+		assert!(
+			dst.api.size >= src.size_in_bytes(),
+			"Buffer is too small to hold the texture"
+		);
+
+		self.0
+			.copy_texture_to_buffer(dst.api.clone(), src.0.clone());
+		self
+	}
+
+	/// Records a render pass that stores every attachment. Previous contents are not loaded so the pass must draw
+	/// over or clear every pixel it uses.
 	pub fn render_pass(
-		mut self,
+		self,
 		attachments: &[&Texture],
 		pass: impl FnOnce(&mut RenderPassRecorder) -> &mut RenderPassRecorder,
 	) -> Self {
-		let mut a = Vec::with_capacity(attachments.len());
-		attachments.iter().for_each(|e| a.push(e.0.clone()));
+		let attachments: Vec<Attachment> =
+			attachments.iter().map(|it| Attachment::new(it)).collect();
+		self.render_pass_with(&attachments, pass)
+	}
+
+	/// Records a render pass with per attachment load and store ops and resolve targets. Every attachment must
+	/// have the same size and sample count.
+	pub fn render_pass_with(
+		mut self,
+		attachments: &[Attachment],
+		pass: impl FnOnce(&mut RenderPassRecorder) -> &mut RenderPassRecorder,
+	) -> Self {
+		assert!(!attachments.is_empty(), "Render pass needs an attachment");
+		let first = attachments[0].texture;
+		for it in attachments.iter() {
+			assert!(
+				it.texture.width() == first.width() && it.texture.height() == first.height(),
+				"Every attachment of a render pass must be the same size"
+			);
+			assert_eq!(
+				it.texture.samples(),
+				first.samples(),
+				"Every attachment of a render pass must have the same sample count"
+			);
+			it.validate();
+		}
 
+		let a: Vec<RenderPassAttachment> = attachments.iter().map(|it| it.to_api()).collect();
 		self.0.begin_render_pass(&a[..]).unwrap();
-		let mut recorder = RenderPassRecorder(self);
+		let mut recorder = RenderPassRecorder {
+			recorder: self,
+			pipeline: None,
+			samples: first.samples(),
+			markers: 0,
+		};
 		pass(&mut recorder);
-		let mut result = recorder.0;
+		assert_eq!(
+			recorder.markers, 0,
+			"Markers pushed in a render pass must be popped before it ends"
+		);
+		let mut result = recorder.recorder;
 		result.0.end_render_pass();
 		result
 	}
 
+	/// Binds `pipeline` for the dispatches recorded in `pass`. Writes made by the pass are visible to every
+	/// command recorded after it.
+	pub fn compute_pass(
+		mut self,
+		pipeline: &ComputePipeline,
+		pass: impl FnOnce(&mut ComputePassRecorder) -> &mut ComputePassRecorder,
+	) -> Self {
+		self.0.bind_compute_pipeline(pipeline.0.clone());
+		let mut recorder = ComputePassRecorder {
+			recorder: self,
+			pipeline: ComputePipeline(pipeline.0.clone()),
+		};
+		pass(&mut recorder);
+		let mut result = recorder.recorder;
+		result.0.end_compute_pass();
+		result
+	}
+
+	/// Starts measuring the GPU time of the commands recorded until the matching [`GraphicsRecorder::end_timer`].
+	/// Timers can be nested. Results show up in [`Device::last_report`] once the command buffer has finished.
+	pub fn begin_timer(mut self, name: &str) -> Self {
+		self.0.begin_timer(name);
+		self
+	}
+
+	/// Ends the most recently begun timer that is still open
+	pub fn end_timer(mut self) -> Self {
+		self.0.end_timer();
+		self
+	}
+
+	/// Starts counting primitives and shader invocations of the commands recorded until
+	/// [`GraphicsRecorder::end_statistics`]. Statistics can not be nested.
+	pub fn begin_statistics(mut self, name: &str) -> Self {
+		self.0.begin_statistics(name);
+		self
+	}
+
+	pub fn end_statistics(mut self) -> Self {
+		self.0.end_statistics();
+		self
+	}
+
+	/// Opens a labeled region of commands that GPU capture tools like RenderDoc show as a group. Markers can be
+	/// nested and must be popped before the command buffer is finished.
+	pub fn push_marker(mut self, name: &str) -> Self {
+		self.0.push_marker(name);
+		self
+	}
+
+	/// Closes the most recently pushed marker
+	pub fn pop_marker(mut self) -> Self {
+		self.0.pop_marker();
+		self
+	}
+
+	/// Returns every command recorded so far
+	#[cfg(backend = "null")]
+	pub fn commands(&self) -> &[Command] {
+		&self.0.commands
+	}
+
+	/// Returns the load and store ops of every render pass attachment recorded so far
+	#[cfg(backend = "null")]
+	pub fn attachments(&self) -> &[RecordedAttachment] {
+		&self.0.attachments
+	}
+
 	pub fn finish(mut self) -> GraphicsCommandBuffer {
+		self.0.queries.assert_closed();
+		assert_eq!(
+			self.0.markers, 0,
+			"Every marker must be popped before the command buffer is finished"
+		);
 		self.0.end();
 		GraphicsCommandBuffer(self.0)
 	}
@@ -86,61 +263,599 @@ impl Default for GraphicsRecorder {
 	}
 }
 
-pub struct RenderPassRecorder(GraphicsRecorder);
+/// Records copies for [`Queue::Transfer`]. Uploads submitted here do not wait on rendering. Wait on the
+/// returned [`Receipt`] before using the resources on another queue.
+pub struct TransferRecorder(GraphicsRecorder);
+
+impl TransferRecorder {
+	pub fn new() -> Self {
+		Self::new_in(Gpu::device())
+	}
+
+	pub fn new_in(device: &Device) -> Self {
+		Self(GraphicsRecorder::new_on(device, Queue::Transfer))
+	}
+
+	pub fn texture_barrier(
+		self,
+		texture: &Texture,
+		old_layout: Layout,
+		new_layout: Layout,
+	) -> Self {
+		Self(self.0.texture_barrier(texture, old_layout, new_layout))
+	}
+
+	/// See [`GraphicsRecorder::copy_buffer_to_texture`]
+	pub fn copy_buffer_to_texture<T: Sized>(self, dst: &Texture, src: &Buffer<T>) -> Self {
+		Self(self.0.copy_buffer_to_texture(dst, src))
+	}
+
+	/// See [`GraphicsRecorder::copy_buffer_to_texture_mips`]
+	pub fn copy_buffer_to_texture_mips<T: Sized>(self, dst: &Texture, src: &Buffer<T>) -> Self {
+		Self(self.0.copy_buffer_to_texture_mips(dst, src))
+	}
+
+	pub fn copy_buffer_to_buffer<T: Sized>(self, dst: &Buffer<T>, src: &Buffer<T>) -> Self {
+		Self(self.0.copy_buffer_to_buffer(dst, src))
+	}
+
+	/// See [`GraphicsRecorder::copy_texture_to_buffer`]
+	pub fn copy_texture_to_buffer<T: Sized>(self, dst: &Buffer<T>, src: &Texture) -> Self {
+		Self(self.0.copy_texture_to_buffer(dst, src))
+	}
+
+	/// See [`GraphicsRecorder::push_marker`]
+	pub fn push_marker(self, name: &str) -> Self {
+		Self(self.0.push_marker(name))
+	}
+
+	pub fn pop_marker(self) -> Self {
+		Self(self.0.pop_marker())
+	}
+
+	/// Returns every command recorded so far
+	#[cfg(backend = "null")]
+	pub fn commands(&self) -> &[Command] {
+		self.0.commands()
+	}
+
+	pub fn submit(self) -> Receipt {
+		self.0.submit()
+	}
+
+	/// Starts once the work of every receipt has finished. Receipts can come from any queue
+	pub fn submit_but_wait_on(self, receipts: &[Receipt]) -> Receipt {
+		self.0.submit_but_wait_on(receipts)
+	}
+}
+
+impl Default for TransferRecorder {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Records compute passes and copies for [`Queue::Compute`] so they can run alongside rendering. Wait on the
+/// returned [`Receipt`] before using the results on another queue.
+pub struct ComputeRecorder(GraphicsRecorder);
+
+impl ComputeRecorder {
+	pub fn new() -> Self {
+		Self::new_in(Gpu::device())
+	}
+
+	pub fn new_in(device: &Device) -> Self {
+		Self(GraphicsRecorder::new_on(device, Queue::Compute))
+	}
+
+	pub fn texture_barrier(
+		self,
+		texture: &Texture,
+		old_layout: Layout,
+		new_layout: Layout,
+	) -> Self {
+		Self(self.0.texture_barrier(texture, old_layout, new_layout))
+	}
+
+	pub fn copy_buffer_to_buffer<T: Sized>(self, dst: &Buffer<T>, src: &Buffer<T>) -> Self {
+		Self(self.0.copy_buffer_to_buffer(dst, src))
+	}
+
+	/// See [`GraphicsRecorder::compute_pass`]
+	pub fn compute_pass(
+		self,
+		pipeline: &ComputePipeline,
+		pass: impl FnOnce(&mut ComputePassRecorder) -> &mut ComputePassRecorder,
+	) -> Self {
+		Self(self.0.compute_pass(pipeline, pass))
+	}
+
+	/// See [`GraphicsRecorder::begin_timer`]
+	pub fn begin_timer(self, name: &str) -> Self {
+		Self(self.0.begin_timer(name))
+	}
+
+	pub fn end_timer(self) -> Self {
+		Self(self.0.end_timer())
+	}
+
+	/// See [`GraphicsRecorder::push_marker`]
+	pub fn push_marker(self, name: &str) -> Self {
+		Self(self.0.push_marker(name))
+	}
+
+	pub fn pop_marker(self) -> Self {
+		Self(self.0.pop_marker())
+	}
+
+	/// Returns every command recorded so far
+	#[cfg(backend = "null")]
+	pub fn commands(&self) -> &[Command] {
+		self.0.commands()
+	}
+
+	pub fn submit(self) -> Receipt {
+		self.0.submit()
+	}
+
+	/// Starts once the work of every receipt has finished. Receipts can come from any queue
+	pub fn submit_but_wait_on(self, receipts: &[Receipt]) -> Receipt {
+		self.0.submit_but_wait_on(receipts)
+	}
+}
+
+impl Default for ComputeRecorder {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+pub struct RenderPassRecorder {
+	recorder: GraphicsRecorder,
+	pipeline: Option<GraphicsPipeline>, // Bound pipeline used to validate vertices and constants
+	samples: u32,
+	markers: u32, // Markers pushed in this render pass that have not been popped
+}
 
 impl RenderPassRecorder {
+	/// See [`GraphicsRecorder::push_marker`]. Markers pushed in a render pass must also be popped in it
+	pub fn push_marker(&mut self, name: &str) -> &mut Self {
+		self.markers += 1;
+		self.recorder.0.push_marker(name);
+		self
+	}
+
+	pub fn pop_marker(&mut self) -> &mut Self {
+		assert!(self.markers > 0, "No marker was pushed in this render pass");
+		self.markers -= 1;
+		self.recorder.0.pop_marker();
+		self
+	}
+
 	pub fn clear_color(&mut self, color: impl Into<Color>) -> &mut Self {
-		self.0 .0.clear_color(color.into());
+		self.recorder.0.clear_color(color.into());
 		self
 	}
 
 	pub fn clear_depth(&mut self, depth: f32) -> &mut Self {
-		self.0 .0.clear_depth(depth);
+		self.recorder.0.clear_depth(depth);
 		self
 	}
 
 	pub fn set_pipeline(&mut self, pipeline: &GraphicsPipeline) -> &mut Self {
-		self.0 .0.bind_pipeline(pipeline.0.clone());
+		assert_eq!(
+			pipeline.samples(),
+			self.samples,
+			"Pipeline sample count does not match the render pass attachments"
+		);
+		self.pipeline = Some(GraphicsPipeline(pipeline.0.clone()));
+		self.recorder.0.bind_pipeline(pipeline.0.clone());
 		self
 	}
 
 	pub fn set_scissor(&mut self, scissor: Option<Rect>) -> &mut Self {
-		self.0 .0.bind_scissor(scissor);
+		self.recorder.0.bind_scissor(scissor);
+		self
+	}
+
+	/// Sets the area of the attachments that is drawn to. `None` covers the whole attachment. The scissor is
+	/// set separately and is not changed.
+	pub fn set_viewport(&mut self, viewport: Option<Rect>) -> &mut Self {
+		self.recorder.0.bind_viewport(viewport);
 		self
 	}
 
 	pub fn set_vertex_buffer<T: Sized>(&mut self, buffer: &Buffer<T>) -> &mut Self {
-		self.0 .0.bind_vertex_buffer(buffer.api.clone());
+		let stride = self.pipeline.as_ref().map(|it| it.vertex_stride());
+		if let Some(stride) = stride.filter(|it| *it > 0) {
+			assert_eq!(
+				std::mem::size_of::<T>(),
+				stride,
+				"Vertex type does not match the attributes declared by the bound pipeline"
+			);
+		}
+		self.recorder.0.bind_vertex_buffer(buffer.api.clone());
 		self
 	}
 
 	pub fn set_index_buffer<T: Sized>(&mut self, buffer: &Buffer<T>) -> &mut Self {
-		self.0 .0.bind_index_buffer(buffer.api.clone());
+		self.recorder.0.bind_index_buffer(buffer.api.clone());
 		self
 	}
 
 	pub fn draw(&mut self, vertex_count: usize, first_vertex: usize) -> &mut Self {
-		self.0 .0.draw(vertex_count, first_vertex);
+		self.recorder.0.draw(vertex_count, first_vertex);
 		self
 	}
 
 	pub fn draw_indexed(&mut self, index_count: usize, first_index: usize) -> &mut Self {
-		self.0 .0.draw_indexed(index_count, first_index);
+		self.recorder.0.draw_indexed(index_count, first_index);
+		self
+	}
+
+	/// Draws `instance_count` instances. Shaders read the instance through `SV_InstanceID`
+	pub fn draw_instanced(
+		&mut self,
+		vertex_count: usize,
+		first_vertex: usize,
+		instance_count: usize,
+		first_instance: usize,
+	) -> &mut Self {
+		self.recorder
+			.0
+			.draw_instanced(vertex_count, first_vertex, instance_count, first_instance);
+		self
+	}
+
+	pub fn draw_indexed_instanced(
+		&mut self,
+		index_count: usize,
+		first_index: usize,
+		instance_count: usize,
+		first_instance: usize,
+	) -> &mut Self {
+		self.recorder.0.draw_indexed_instanced(
+			index_count,
+			first_index,
+			instance_count,
+			first_instance,
+		);
 		self
 	}
 
-	pub fn set_constants<T: Sized>(
+	/// Draws `draw_count` times using the arguments in `buffer` starting at element `first`. The arguments can be
+	/// written by a compute pass.
+	pub fn draw_indirect(
+		&mut self,
+		buffer: &Buffer<DrawIndirect>,
+		first: usize,
+		draw_count: usize,
+	) -> &mut Self {
+		check_indirect(buffer, first, draw_count);
+		self.recorder
+			.0
+			.draw_indirect(buffer.api.clone(), first, draw_count);
+		self
+	}
+
+	pub fn draw_indexed_indirect(
+		&mut self,
+		buffer: &Buffer<DrawIndexedIndirect>,
+		first: usize,
+		draw_count: usize,
+	) -> &mut Self {
+		check_indirect(buffer, first, draw_count);
+		self.recorder
+			.0
+			.draw_indexed_indirect(buffer.api.clone(), first, draw_count);
+		self
+	}
+
+	/// Binds element `index` of `buffer` to the `name` constants of the bound pipeline. `T` must declare the
+	/// same members as the pipeline.
+	pub fn set_constants<T: GpuConstants>(
 		&mut self,
 		name: &str,
 		buffer: &Buffer<T>,
 		index: usize,
 	) -> &mut Self {
-		self.0 .0.bind_constants(name, buffer.api.clone(), index);
+		let pipeline = self
+			.pipeline
+			.as_ref()
+			.expect("A pipeline must be set before its constants");
+		check_constants::<T>(name, pipeline.constants(name));
+
+		self.recorder
+			.0
+			.bind_constants(name, buffer.api.clone(), index);
 		self
 	}
 
 	pub fn set_texture(&mut self, name: &str, texture: &Texture) -> &mut Self {
-		self.0 .0.bind_texture(name, texture.0.clone());
+		self.recorder.0.bind_texture(name, texture.0.clone());
+		self
+	}
+
+	pub fn set_buffer<T: Sized>(&mut self, name: &str, buffer: &Buffer<T>) -> &mut Self {
+		self.recorder.0.bind_buffer(name, buffer.api.clone());
 		self
 	}
 }
+
+pub struct ComputePassRecorder {
+	recorder: GraphicsRecorder,
+	pipeline: ComputePipeline,
+}
+
+impl ComputePassRecorder {
+	/// Binds element `index` of `buffer` to the `name` constants of the pipeline. `T` must declare the same
+	/// members as the pipeline.
+	pub fn set_constants<T: GpuConstants>(
+		&mut self,
+		name: &str,
+		buffer: &Buffer<T>,
+		index: usize,
+	) -> &mut Self {
+		check_constants::<T>(name, self.pipeline.constants(name));
+
+		self.recorder
+			.0
+			.bind_constants(name, buffer.api.clone(), index);
+		self
+	}
+
+	pub fn set_texture(&mut self, name: &str, texture: &Texture) -> &mut Self {
+		self.recorder.0.bind_texture(name, texture.0.clone());
+		self
+	}
+
+	pub fn set_buffer<T: Sized>(&mut self, name: &str, buffer: &Buffer<T>) -> &mut Self {
+		self.recorder.0.bind_buffer(name, buffer.api.clone());
+		self
+	}
+
+	/// Dispatches `x * y * z` thread groups
+	pub fn dispatch(&mut self, x: u32, y: u32, z: u32) -> &mut Self {
+		self.recorder.0.dispatch(x, y, z);
+		self
+	}
+}
+
+fn check_constants<T: GpuConstants>(name: &str, members: Option<&[ConstantMember]>) {
+	let members =
+		members.unwrap_or_else(|| panic!("Pipeline does not declare constants named {:?}", name));
+	assert!(
+		T::matches(members),
+		"{} does not match the {:?} constants declared by the pipeline. Expected {:?}",
+		std::any::type_name::<T>(),
+		name,
+		members
+	);
+}
+
+fn check_indirect<T>(buffer: &Buffer<T>, first: usize, draw_count: usize) {
+	assert!(
+		buffer.api.usage.contains(BufferUsage::INDIRECT),
+		"Buffer can only be used for indirect draws if usage is marked with INDIRECT"
+	);
+	assert!(
+		first + draw_count <= buffer.len(),
+		"Indirect draws read past the end of the buffer"
+	);
+}
+
+/// Arguments of a single [`RenderPassRecorder::draw_indirect`] draw. Matches `VkDrawIndirectCommand`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DrawIndirect {
+	pub vertex_count: u32,
+	pub instance_count: u32,
+	pub first_vertex: u32,
+	pub first_instance: u32,
+}
+
+/// Arguments of a single [`RenderPassRecorder::draw_indexed_indirect`] draw. Matches
+/// `VkDrawIndexedIndirectCommand`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DrawIndexedIndirect {
+	pub index_count: u32,
+	pub instance_count: u32,
+	pub first_index: u32,
+	pub vertex_offset: i32,
+	pub first_instance: u32,
+}
+
+#[cfg(all(test, backend = "null"))]
+mod tests {
+	use crate::*;
+
+	#[test]
+	fn records_render_pass() {
+		let device = test_device();
+
+		let texture = Texture::new_in(
+			TextureUsage::COLOR_ATTACHMENT | TextureUsage::SAMPLED,
+			Format::RGBA_U8,
+			4,
+			4,
+			1,
+			&device,
+		)
+		.unwrap();
+		let vertices: Buffer<f32> =
+			Buffer::new_in(BufferUsage::VERTEX, MemoryType::HostVisible, 6, &device).unwrap();
+		let shader = Shader::builder(b"", ShaderVariant::Vertex)
+			.device(&device)
+			.spawn()
+			.unwrap();
+		let pipeline = GraphicsPipeline::builder()
+			.attachments(&[Format::RGBA_U8])
+			.shaders(&[shader])
+			.device(&device)
+			.spawn()
+			.unwrap();
+
+		let recorder = GraphicsRecorder::new_in(&device)
+			.texture_barrier(&texture, Layout::Undefined, Layout::ColorAttachment)
+			.render_pass(&[&texture], |ctx| {
+				ctx.clear_color(Color::BLACK)
+					.set_pipeline(&pipeline)
+					.set_vertex_buffer(&vertices)
+					.draw(3, 0)
+			});
+
+		assert_eq!(
+			recorder.commands(),
+			&[
+				Command::TextureBarrier {
+					texture: texture.id(),
+					old_layout: Layout::Undefined,
+					new_layout: Layout::ColorAttachment,
+				},
+				Command::BeginRenderPass {
+					attachments: vec![texture.id()]
+				},
+				Command::ClearColor(Color::BLACK),
+				Command::BindPipeline(pipeline.id()),
+				Command::BindVertexBuffer(vertices.id()),
+				Command::Draw {
+					vertex_count: 3,
+					first_vertex: 0
+				},
+				Command::EndRenderPass,
+			]
+		);
+
+		assert!(recorder.submit().wait());
+		assert_eq!(device.take_submitted().len(), 1);
+		assert!(device.take_submitted().is_empty());
+	}
+
+	#[test]
+	fn copies_on_submit() {
+		let device = test_device();
+
+		let src: Buffer<u32> = Buffer::new_in(
+			BufferUsage::TRANSFER_SRC,
+			MemoryType::HostVisible,
+			4,
+			&device,
+		)
+		.unwrap();
+		let dst: Buffer<u32> = Buffer::new_in(
+			BufferUsage::TRANSFER_DST,
+			MemoryType::DeviceLocal,
+			4,
+			&device,
+		)
+		.unwrap();
+		src.copy_to(&[1, 2, 3, 4]).unwrap();
+		assert_eq!(src.copy_to(&[0; 5]), Err(api::Error::OutOfBounds));
+
+		let recorder = GraphicsRecorder::new_in(&device).copy_buffer_to_buffer(&dst, &src);
+		assert_eq!(&dst.api.memory.lock().unwrap()[..], &[0; 16]);
+
+		recorder.submit().wait();
+		assert_eq!(
+			*dst.api.memory.lock().unwrap(),
+			*src.api.memory.lock().unwrap()
+		);
+	}
+
+	#[test]
+	fn records_markers() {
+		let device = test_device();
+		let texture = Texture::builder(TextureUsage::COLOR_ATTACHMENT, Format::RGBA_U8, 4, 4, 1)
+			.device(&device)
+			.debug_name("target")
+			.spawn()
+			.unwrap();
+
+		let recorder = GraphicsRecorder::new_in(&device)
+			.push_marker("frame")
+			.render_pass(&[&texture], |ctx| ctx.push_marker("clear").pop_marker())
+			.pop_marker();
+
+		assert_eq!(
+			recorder.commands(),
+			&[
+				Command::PushMarker("frame".to_string()),
+				Command::BeginRenderPass {
+					attachments: vec![texture.id()]
+				},
+				Command::PushMarker("clear".to_string()),
+				Command::PopMarker,
+				Command::EndRenderPass,
+				Command::PopMarker,
+			]
+		);
+		recorder.finish();
+	}
+
+	#[test]
+	#[should_panic(expected = "Markers pushed in a render pass must be popped before it ends")]
+	fn render_pass_markers_must_be_popped() {
+		let device = test_device();
+		let texture = Texture::builder(TextureUsage::COLOR_ATTACHMENT, Format::RGBA_U8, 4, 4, 1)
+			.device(&device)
+			.spawn()
+			.unwrap();
+
+		GraphicsRecorder::new_in(&device).render_pass(&[&texture], |ctx| ctx.push_marker("clear"));
+	}
+
+	#[test]
+	fn submits_to_other_queues() {
+		let device = test_device();
+		assert!(device.has_dedicated_queue(Queue::Graphics));
+		assert!(!device.has_dedicated_queue(Queue::Transfer));
+
+		let src: Buffer<u32> = Buffer::new_in(
+			BufferUsage::TRANSFER_SRC,
+			MemoryType::HostVisible,
+			4,
+			&device,
+		)
+		.unwrap();
+		let dst: Buffer<u32> = Buffer::new_in(
+			BufferUsage::TRANSFER_DST | BufferUsage::STORAGE,
+			MemoryType::DeviceLocal,
+			4,
+			&device,
+		)
+		.unwrap();
+		src.copy_to(&[1, 2, 3, 4]).unwrap();
+
+		let shader = Shader::builder(b"", ShaderVariant::Compute)
+			.device(&device)
+			.spawn()
+			.unwrap();
+		let pipeline = ComputePipeline::builder(&shader)
+			.resource("input", PipelineResource::StorageBuffer)
+			.device(&device)
+			.spawn()
+			.unwrap();
+
+		let upload = TransferRecorder::new_in(&device)
+			.copy_buffer_to_buffer(&dst, &src)
+			.submit();
+		ComputeRecorder::new_in(&device)
+			.compute_pass(&pipeline, |ctx| {
+				ctx.set_buffer("input", &dst).dispatch(1, 1, 1)
+			})
+			.submit_but_wait_on(&[upload])
+			.wait();
+
+		assert_eq!(
+			*dst.api.memory.lock().unwrap(),
+			*src.api.memory.lock().unwrap()
+		);
+		let submitted = device.take_submitted();
+		assert_eq!(submitted.len(), 2);
+		assert_eq!(
+			submitted[1].last(),
+			Some(&Command::Dispatch { x: 1, y: 1, z: 1 })
+		);
+	}
+}
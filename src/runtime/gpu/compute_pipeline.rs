@@ -0,0 +1,325 @@
+use crate::*;
+
+use std::{
+	collections::HashMap,
+	path::Path,
+	sync::Arc,
+};
+
+use resources::{
+	Importer,
+	Resource,
+};
+
+use serde::{
+	self as serde,
+	ron,
+	Deserialize,
+	Serialize,
+};
+
+pub struct ComputePipelineDescription {
+	pub shader: Shader,
+
+	pub constants: HashMap<String, Vec<ConstantMember>>,
+	pub resources: HashMap<String, PipelineResource>,
+}
+
+impl ComputePipelineDescription {
+	pub(crate) fn push_constant_size(&self) -> usize {
+		self.constants.len() * 4 + self.resources.len() * 4
+	}
+}
+
+pub struct ComputePipelineBuilder<'a> {
+	description: ComputePipelineDescription,
+	device: Option<&'a Device>,
+	debug_name: Option<String>,
+}
+
+impl<'a> ComputePipelineBuilder<'a> {
+	pub fn device(mut self, device: &'a Device) -> Self {
+		self.device = Some(device);
+		self
+	}
+
+	pub fn constant(mut self, name: impl ToString, members: Vec<ConstantMember>) -> Self {
+		self.description.constants.insert(name.to_string(), members);
+		self
+	}
+
+	pub fn constants(mut self, constants: HashMap<String, Vec<ConstantMember>>) -> Self {
+		self.description.constants = constants;
+		self
+	}
+
+	pub fn resource(mut self, name: impl ToString, resource: PipelineResource) -> Self {
+		self.description
+			.resources
+			.insert(name.to_string(), resource);
+		self
+	}
+
+	pub fn resources(mut self, resources: HashMap<String, PipelineResource>) -> Self {
+		self.description.resources = resources;
+		self
+	}
+
+	/// Names the pipeline in GPU captures and validation messages
+	pub fn debug_name(mut self, name: impl ToString) -> Self {
+		self.debug_name = Some(name.to_string());
+		self
+	}
+
+	pub fn spawn(self) -> Result<ComputePipeline> {
+		let device = match self.device {
+			Some(device) => device,
+			None => Gpu::device(),
+		};
+
+		let pipeline = api::ComputePipeline::new(device.0.clone(), self.description)?;
+		mark_pipeline_cache_dirty();
+		if let Some(name) = &self.debug_name {
+			pipeline.set_debug_name(name);
+		}
+
+		Ok(ComputePipeline(pipeline))
+	}
+}
+
+#[derive(Resource)]
+pub struct ComputePipeline(pub(crate) Arc<api::ComputePipeline>);
+
+impl ComputePipeline {
+	#[cfg(backend = "null")]
+	pub fn id(&self) -> ResourceId {
+		self.0.id
+	}
+
+	/// Returns the members of the constants named `name`
+	pub fn constants(&self, name: &str) -> Option<&[ConstantMember]> {
+		self.0.description.constants.get(name).map(|it| &it[..])
+	}
+
+	pub fn builder(shader: &Shader) -> ComputePipelineBuilder<'_> {
+		assert_eq!(
+			shader.variant(),
+			ShaderVariant::Compute,
+			"ComputePipeline requires a compute shader"
+		);
+
+		ComputePipelineBuilder {
+			description: ComputePipelineDescription {
+				shader: shader.clone(),
+
+				constants: Default::default(),
+				resources: Default::default(),
+			},
+			device: None,
+			debug_name: None,
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum ComputeSystemSemantics {
+	DispatchThreadId,
+	GroupThreadId,
+	GroupId,
+	GroupIndex,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ComputeShader {
+	/// Number of threads in a single group. Passed to `numthreads`
+	#[serde(default = "ComputeShader::default_thread_group_size")]
+	pub thread_group_size: (u32, u32, u32),
+
+	#[serde(default)]
+	pub system_semantics: Vec<ComputeSystemSemantics>,
+
+	pub code: String,
+}
+
+impl ComputeShader {
+	fn default_thread_group_size() -> (u32, u32, u32) {
+		(1, 1, 1)
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "ComputePipeline")]
+pub struct ComputePipelineFile {
+	#[serde(default)]
+	pub constants: HashMap<String, Vec<ConstantMember>>,
+
+	#[serde(default)]
+	pub resources: HashMap<String, PipelineResource>,
+
+	#[serde(default)]
+	pub common: String,
+
+	/// Declared before the shader is compiled
+	#[serde(default)]
+	pub defines: Vec<(String, String)>,
+
+	pub compute_shader: ComputeShader,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ComputePipelineImporter {}
+
+// Name compiler messages use for code written in a compute pipeline file
+const COMPUTE_PIPELINE_FILE: &str = "compute_pipeline";
+
+impl Importer for ComputePipelineImporter {
+	type Target = ComputePipeline;
+
+	fn import(&self, bytes: &[u8], path: &Path) -> resources::Result<Self::Target> {
+		let contents = std::str::from_utf8(bytes)?;
+		let file = ron::from_str(contents)?;
+
+		let ComputePipelineFile {
+			constants,
+			resources,
+			common,
+			defines,
+			compute_shader,
+		} = file;
+
+		let ComputeShader {
+			thread_group_size,
+			system_semantics,
+			code,
+		} = compute_shader;
+
+		let common = UserCode::find(contents, None, "common", &common);
+		let mut source = shader_header(
+			&common,
+			COMPUTE_PIPELINE_FILE,
+			"compute.hlsl",
+			&constants,
+			&resources,
+		);
+
+		// Generate the ComputeInput based off of the requested semantics
+		if !system_semantics.is_empty() {
+			source.push_str("struct ComputeInput {\n");
+			for semantic in system_semantics.iter() {
+				let line = match semantic {
+					ComputeSystemSemantics::DispatchThreadId => {
+						"uint3 dispatch_thread_id : SV_DispatchThreadID;\n"
+					}
+					ComputeSystemSemantics::GroupThreadId => {
+						"uint3 group_thread_id : SV_GroupThreadID;\n"
+					}
+					ComputeSystemSemantics::GroupId => "uint3 group_id : SV_GroupID;\n",
+					ComputeSystemSemantics::GroupIndex => "uint group_index : SV_GroupIndex;\n",
+				};
+				source.push_str(line);
+			}
+			source.push_str("};\n\n");
+		}
+
+		let (x, y, z) = thread_group_size;
+		source.push_str(&format!("[numthreads({}, {}, {})]\n", x, y, z));
+		if !system_semantics.is_empty() {
+			source.push_str("void main( ComputeInput input ) {\n");
+		} else {
+			source.push_str("void main( ) {\n");
+		}
+		UserCode::find(contents, Some("compute_shader"), "code", &code).push_to(
+			&mut source,
+			COMPUTE_PIPELINE_FILE,
+			"compute.hlsl",
+		);
+		source.push_str("}\n");
+
+		// Compile to binary and then pass to device
+		let defines: Vec<(&str, &str)> = defines
+			.iter()
+			.map(|(name, value)| (name.as_str(), value.as_str()))
+			.collect();
+		let binary = compile_pipeline_shader(
+			"compute.hlsl",
+			&source,
+			ShaderVariant::Compute,
+			&defines,
+			COMPUTE_PIPELINE_FILE,
+		)?;
+		let shader = Shader::builder(&binary, ShaderVariant::Compute).spawn()?;
+
+		let pipeline = ComputePipeline::builder(&shader)
+			.constants(constants)
+			.resources(resources)
+			.debug_name(path.display())
+			.spawn()
+			.map_err(|err| -> Box<dyn std::error::Error + 'static> { Box::new(err) })?;
+
+		Ok(pipeline)
+	}
+
+	fn export(&self, _resource: &Self::Target, _file: &mut std::fs::File) -> resources::Result<()> {
+		Ok(())
+	}
+}
+
+#[cfg(all(test, backend = "null"))]
+mod tests {
+	use crate::*;
+
+	#[test]
+	fn records_compute_pass() {
+		let device = test_device();
+
+		let output: Buffer<u32> =
+			Buffer::new_in(BufferUsage::STORAGE, MemoryType::DeviceLocal, 64, &device).unwrap();
+		let shader = Shader::builder(b"", ShaderVariant::Compute)
+			.device(&device)
+			.spawn()
+			.unwrap();
+		let pipeline = ComputePipeline::builder(&shader)
+			.resource("output", PipelineResource::StorageBuffer)
+			.device(&device)
+			.spawn()
+			.unwrap();
+
+		let recorder = GraphicsRecorder::new_in(&device).compute_pass(&pipeline, |ctx| {
+			ctx.set_buffer("output", &output).dispatch(8, 1, 1)
+		});
+
+		assert_eq!(
+			recorder.commands(),
+			&[
+				Command::BindComputePipeline(pipeline.id()),
+				Command::BindBuffer {
+					name: "output".to_string(),
+					buffer: output.id(),
+				},
+				Command::Dispatch { x: 8, y: 1, z: 1 },
+			]
+		);
+	}
+
+	#[test]
+	#[should_panic(expected = "STORAGE")]
+	fn storage_requires_usage() {
+		let device = test_device();
+
+		let output: Buffer<u32> =
+			Buffer::new_in(BufferUsage::CONSTANTS, MemoryType::DeviceLocal, 64, &device).unwrap();
+		let shader = Shader::builder(b"", ShaderVariant::Compute)
+			.device(&device)
+			.spawn()
+			.unwrap();
+		let pipeline = ComputePipeline::builder(&shader)
+			.resource("output", PipelineResource::StorageBuffer)
+			.device(&device)
+			.spawn()
+			.unwrap();
+
+		GraphicsRecorder::new_in(&device).compute_pass(&pipeline, |ctx| {
+			ctx.set_buffer("output", &output).dispatch(8, 1, 1)
+		});
+	}
+}
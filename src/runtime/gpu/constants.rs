@@ -0,0 +1,166 @@
+use crate::{
+	Constant,
+	ConstantMember,
+};
+
+use math::{
+	Color,
+	Mat4,
+	Vec2,
+	Vec3,
+	Vec4,
+};
+
+pub use derive::GpuConstants;
+
+/// Rust type that can be a member of a [`GpuConstants`] struct
+pub trait GpuConstant {
+	const CONSTANT: Constant;
+}
+
+macro_rules! gpu_constant {
+	($($ty:ty => $constant:ident),* $(,)?) => {
+		$(impl GpuConstant for $ty {
+			const CONSTANT: Constant = Constant::$constant;
+		})*
+	};
+}
+
+gpu_constant!(
+	u32 => Uint32,
+	i32 => Int32,
+	f32 => Float32,
+	Vec2 => Vector2,
+	Vec3 => Vector3,
+	Vec4 => Vector4,
+	Color => Color,
+	Mat4 => Matrix4,
+);
+
+/// Struct that matches a table of `constants` in a pipeline file. Derive it with `#[derive(GpuConstants)]` on a
+/// `#[repr(C)]` struct. The derive checks at compile time that fields are laid out like std430. Fields
+/// starting with `_` are treated as padding.
+pub trait GpuConstants: Sized + 'static {
+	/// Name and type of every field that is not padding in declaration order
+	const MEMBERS: &'static [(&'static str, Constant)];
+
+	fn members() -> Vec<ConstantMember> {
+		Self::MEMBERS
+			.iter()
+			.map(|(name, variant)| ConstantMember(name.to_string(), *variant))
+			.collect()
+	}
+
+	/// Returns true if `members` declare the same types in the same order
+	fn matches(members: &[ConstantMember]) -> bool {
+		members.len() == Self::MEMBERS.len()
+			&& members
+				.iter()
+				.zip(Self::MEMBERS.iter())
+				.all(|(ConstantMember(_, a), (_, b))| a == b)
+	}
+}
+
+/// Returns the std430 offset of `members[index]`. Returns the size of the whole struct if `index` is out of
+/// bounds.
+pub const fn std430_offset(members: &[Constant], index: usize) -> usize {
+	let mut offset = 0;
+	let mut i = 0;
+	while i < members.len() {
+		offset = round_up(offset, members[i].alignment());
+		if i == index {
+			return offset;
+		}
+		offset += members[i].size();
+		i += 1;
+	}
+	round_up(offset, std430_alignment(members))
+}
+
+/// Size of a struct holding `members` including the padding at the end
+pub const fn std430_size(members: &[Constant]) -> usize {
+	std430_offset(members, usize::MAX)
+}
+
+const fn std430_alignment(members: &[Constant]) -> usize {
+	let mut result = 1;
+	let mut i = 0;
+	while i < members.len() {
+		if members[i].alignment() > result {
+			result = members[i].alignment();
+		}
+		i += 1;
+	}
+	result
+}
+
+const fn round_up(value: usize, alignment: usize) -> usize {
+	value.div_ceil(alignment) * alignment
+}
+
+/// Called by the `GpuConstants` derive to fail compilation when a struct does not match std430
+#[doc(hidden)]
+pub const fn check_std430_layout(members: &[Constant], offsets: &[usize], size: usize) {
+	let mut i = 0;
+	while i < members.len() {
+		if offsets[i] != std430_offset(members, i) {
+			panic!("GpuConstants field is not at its std430 offset. Add padding fields before it");
+		}
+		i += 1;
+	}
+	if size != std430_size(members) {
+		panic!("GpuConstants struct size does not match std430. Add padding fields at the end");
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(GpuConstants)]
+	#[repr(C)]
+	#[allow(dead_code)]
+	struct Camera {
+		view: Mat4,
+		position: Vec3,
+		_padding: f32,
+	}
+
+	#[derive(GpuConstants)]
+	#[repr(C)]
+	#[allow(dead_code)]
+	struct Light {
+		intensity: f32,
+		_padding: [f32; 3],
+		direction: Vec3,
+		range: f32,
+	}
+
+	#[test]
+	fn std430_layout() {
+		use Constant::*;
+
+		assert_eq!(std430_size(&[Matrix4, Vector3]), 80);
+		assert_eq!(std430_offset(&[Float32, Vector3], 1), 16);
+		assert_eq!(std430_offset(&[Vector3, Float32], 1), 12);
+		assert_eq!(std430_size(&[Float32, Vector2]), 16);
+
+		assert_eq!(Camera::MEMBERS, [("view", Matrix4), ("position", Vector3)]);
+		assert_eq!(
+			Light::MEMBERS,
+			[
+				("intensity", Float32),
+				("direction", Vector3),
+				("range", Float32)
+			]
+		);
+		assert!(Camera::matches(&[
+			ConstantMember("view".to_string(), Matrix4),
+			ConstantMember("position".to_string(), Vector3),
+		]));
+		assert!(!Camera::matches(&[ConstantMember(
+			"view".to_string(),
+			Matrix4
+		)]));
+	}
+}
@@ -1,20 +1,54 @@
 use crate::{
 	api,
+	BindlessView,
+	GpuReport,
 	GraphicsCommandBuffer,
+	MemoryStats,
 	Receipt,
 	Result,
 	Texture,
 };
 
+use serde::{
+	Deserialize,
+	Serialize,
+};
+
 use std::sync::Arc;
 
+/// How presented frames are synchronized with the display
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum PresentMode {
+	/// Waits for the vertical blank. Never tears and is always supported
+	#[default]
+	Fifo,
+	/// Waits for the vertical blank but replaces the queued frame instead of blocking. Falls back to
+	/// [`PresentMode::Fifo`] when unsupported
+	Mailbox,
+	/// Presents right away and may tear. Falls back to [`PresentMode::Fifo`] when unsupported
+	Immediate,
+}
+
+/// Hardware queue command buffers are submitted to. Work meant for a queue the device does not have runs on
+/// the graphics queue instead. See [`Device::has_dedicated_queue`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Queue {
+	Graphics,
+	/// Async compute that can overlap with rendering. Recorded with [`crate::ComputeRecorder`]
+	Compute,
+	/// Copies that can overlap with rendering. Recorded with [`crate::TransferRecorder`]
+	Transfer,
+}
+
 #[derive(Clone)]
 pub struct Device(pub(crate) Arc<api::Device>);
 
 impl Device {
 	// @TODO: This should return a reference to a Texture
-	pub fn acquire_backbuffer(&self) -> Result<Texture> {
-		Ok(Texture(self.0.acquire_backbuffer()?))
+	/// Returns `None` while the window is minimized or the swapchain could not be recreated. The frame should
+	/// be skipped without calling [`Device::display`].
+	pub fn acquire_backbuffer(&self) -> Result<Option<Texture>> {
+		Ok(self.0.acquire_backbuffer()?.map(Texture))
 	}
 
 	pub fn submit_graphics(
@@ -27,14 +61,117 @@ impl Device {
 			.drain(..)
 			.for_each(|x| api_buffers.push(x.0));
 
-		self.0.submit_graphics(api_buffers, wait_on)
+		self.0.submit(api_buffers, wait_on)
+	}
+
+	/// Makes the next submission to [`Queue::Graphics`] wait on `receipt` without blocking the calling thread.
+	/// Importers use this so resources uploaded on another queue are ready by the time they are first drawn.
+	pub fn wait_before_rendering(&self, receipt: &Receipt) {
+		self.0.wait_before_rendering(receipt)
+	}
+
+	/// Returns true if work submitted to `queue` runs on its own hardware queue instead of the graphics queue
+	pub fn has_dedicated_queue(&self, queue: Queue) -> bool {
+		self.0.has_dedicated_queue(queue)
 	}
 
 	pub fn display(&self, wait_on: &[Receipt]) {
 		self.0.display(wait_on)
 	}
 
+	/// Recreates the swapchain to match a window of `width` by `height`. A size of zero, such as a minimized
+	/// window, suspends presenting until the next resize. Driven by [`engine::Event::Resized`] when using
+	/// the [`crate::Gpu`] module.
+	pub fn resize(&self, width: u32, height: u32) {
+		self.0.resize(width, height)
+	}
+
+	/// Mode the swapchain was asked to present with. The device may fall back to [`PresentMode::Fifo`]
+	pub fn present_mode(&self) -> PresentMode {
+		self.0.present_mode()
+	}
+
+	/// Recreates the swapchain if `present_mode` differs from the current one
+	pub fn set_present_mode(&self, present_mode: PresentMode) {
+		self.0.set_present_mode(present_mode)
+	}
+
+	/// Number of frames that may be queued on the GPU before [`Device::display`] blocks
+	pub fn frames_in_flight(&self) -> usize {
+		self.0.frames_in_flight()
+	}
+
+	pub fn set_frames_in_flight(&self, frames_in_flight: usize) {
+		assert!(frames_in_flight > 0, "At least one frame must be in flight");
+		self.0.set_frames_in_flight(frames_in_flight)
+	}
+
 	pub fn wait_for_idle(&self) {
 		self.0.wait_for_idle()
 	}
+
+	/// Gives a GPU crash dump tool running alongside the driver time to write its dump. Returns straight away
+	/// when none is loaded
+	pub fn wait_for_crash_dump(&self) {
+		self.0.wait_for_crash_dump()
+	}
+
+	/// Returns how much memory is reserved from the driver and how much of it is used by resources
+	pub fn memory_stats(&self) -> MemoryStats {
+		self.0.memory_stats()
+	}
+
+	/// Lists every live bindless slot with the debug name of its resource
+	pub fn bindless_view(&self) -> BindlessView {
+		self.0.bindless_view()
+	}
+
+	/// Merges pipeline cache data from a previous run. Data from another driver or device is ignored
+	pub fn load_pipeline_cache(&self, data: &[u8]) -> Result<()> {
+		self.0.load_pipeline_cache(data)
+	}
+
+	/// Returns the pipeline cache data so it can be loaded on the next run
+	pub fn pipeline_cache(&self) -> Result<Vec<u8>> {
+		self.0.pipeline_cache_data()
+	}
+
+	/// Returns the timers and pipeline statistics of the last finished submission that recorded any. Only that
+	/// report is kept so it reads as the most recent frame. Queries are read once their work is done so results
+	/// trail recording by a few frames.
+	pub fn last_report(&self) -> GpuReport {
+		self.0.last_report()
+	}
+
+	/// Returns the bindless slots written since the last call. The flag is false for released slots
+	#[cfg(backend = "null")]
+	pub fn take_bindless_writes(&self) -> Vec<(crate::BindlessKind, u32, bool)> {
+		self.0.take_bindless_writes()
+	}
+
+	/// Returns the commands of every command buffer submitted since the last call
+	#[cfg(backend = "null")]
+	pub fn take_submitted(&self) -> Vec<Vec<crate::Command>> {
+		self.0.take_submitted()
+	}
+}
+
+/// Creates a device on the null backend for the crate's tests
+#[cfg(all(test, backend = "null"))]
+pub(crate) fn test_device() -> Device {
+	crate::Instance::new().unwrap().create_device(None).unwrap()
+}
+
+#[cfg(all(test, backend = "null"))]
+mod tests {
+	use crate::*;
+
+	#[test]
+	fn round_trips_pipeline_cache() {
+		let device = test_device();
+		assert!(device.pipeline_cache().unwrap().is_empty());
+
+		device.load_pipeline_cache(&[1, 2, 3]).unwrap();
+		assert_eq!(device.pipeline_cache().unwrap(), [1, 2, 3]);
+	}
 }
@@ -1,6 +1,15 @@
-//! This crate is the HAL for the GPU. Currently Vulkan is the only back end available. The design and architecture
+//! This crate is the HAL for the GPU. Vulkan is the default back end. The design and architecture
 //! was originally concepted after reading http://alextardif.com/RenderingAbstractionLayers.html
 //!
+//! Building with the `null` feature or without the `vulkan` feature selects the null back end. It does not talk
+//! to a GPU at all. Every command is recorded into a `Command` log and buffer contents are kept in host memory
+//! so renderer logic can be tested on machines without a Vulkan device. `null` takes precedence over `vulkan`
+//! so crates can enable it from their dev-dependencies.
+//!
+//! Nvidia Aftermath crash dumps are opt in through the `aftermath` feature and only available on Windows. On
+//! every platform the device fault report (VK_EXT_device_fault) and breadcrumb markers (VK_AMD_buffer_marker)
+//! are logged when the driver supports them. See [`GpuConfig::crash_diagnostics`].
+//!
 //! # Warning
 //!
 //! This package is still in a very early state. The API is currently super volatile. I would not
@@ -24,8 +33,6 @@
 //! * Gather, submit, and wait on command work from various passes, in a multicore-compatible way
 //! * Automatic device memory management
 
-#![feature(in_band_lifetimes)]
-
 use math::{
 	Color,
 	Rect,
@@ -38,37 +45,83 @@ use serde::{
 };
 
 use std::convert::Into;
+use std::hash::{
+	Hash,
+	Hasher,
+};
+use std::sync::{
+	atomic::{
+		AtomicBool,
+		Ordering,
+	},
+	RwLock,
+};
+
+// Lets `#[derive(GpuConstants)]` refer to this crate as `gpu` from inside it
+extern crate self as gpu;
 
-#[cfg(feature = "vulkan")]
+#[cfg(backend = "vulkan")]
 mod vk;
 
-#[cfg(feature = "vulkan")]
+#[cfg(backend = "vulkan")]
 use vk as api;
 
+#[cfg(backend = "null")]
+mod null;
+
+#[cfg(backend = "null")]
+use null as api;
+
+#[cfg(backend = "null")]
+pub use null::{
+	Command,
+	RecordedAttachment,
+	ResourceId,
+};
+
+mod bindless;
 mod buffer;
 mod command_buffer;
+mod compute_pipeline;
+mod constants;
 mod device;
 mod graphics_pipeline;
 mod instance;
+mod memory;
+mod query;
 mod receipt;
+mod reflect;
+mod render_graph;
+mod render_pass;
+mod ring_buffer;
 mod shader;
 mod texture;
+mod texture_file;
+mod texture_image;
 
 pub use {
+	bindless::*,
 	buffer::*,
 	command_buffer::*,
+	compute_pipeline::*,
+	constants::*,
 	device::*,
 	graphics_pipeline::*,
 	instance::*,
+	memory::*,
+	query::*,
 	receipt::*,
+	render_graph::*,
+	render_pass::*,
+	ring_buffer::*,
 	shader::*,
 	texture::*,
 };
 
 /// Type of memory allocations that buffers or textures can be allocated from
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum MemoryType {
-	/// Able to be uploaded to by mapping memory. Slower to access. Faster to write to
+	/// Able to be uploaded to and read back by mapping memory. Slower to access. Faster to write to
 	HostVisible,
 	/// Able to be uploaded to by using commands. Faster to access. Slower to write to
 	DeviceLocal,
@@ -77,12 +130,30 @@ pub enum MemoryType {
 pub type Result<T> = std::result::Result<T, api::Error>;
 
 #[derive(Serialize, Deserialize, Copy, Clone)]
+#[serde(default)]
 pub struct SamplerDescription {
 	min_filter: Filter,
 	mag_filter: Filter,
+	/// Filter used to blend between mips
+	mip_filter: Filter,
 	address_u: Wrap,
 	address_v: Wrap,
 	address_w: Wrap,
+	/// Anisotropic filtering is enabled when greater than 1. Clamped to what the device supports
+	max_anisotropy: f32,
+}
+
+// Anisotropy is hashed by its bits so descriptions can key pipeline variants
+impl Hash for SamplerDescription {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.min_filter.hash(state);
+		self.mag_filter.hash(state);
+		self.mip_filter.hash(state);
+		self.address_u.hash(state);
+		self.address_v.hash(state);
+		self.address_w.hash(state);
+		self.max_anisotropy.to_bits().hash(state);
+	}
 }
 
 impl Default for SamplerDescription {
@@ -90,18 +161,36 @@ impl Default for SamplerDescription {
 		Self {
 			min_filter: Filter::Linear,
 			mag_filter: Filter::Linear,
+			mip_filter: Filter::Linear,
 
 			address_u: Wrap::Clamp,
 			address_v: Wrap::Clamp,
 			address_w: Wrap::Clamp,
+
+			max_anisotropy: 1.0,
 		}
 	}
 }
 
+use cache::{
+	Cache,
+	CacheRef,
+};
+use config::{
+	Config,
+	ConfigManager,
+	ENGINE_CONFIG_FILE,
+};
 use engine::{
+	define_log_category,
+	warn,
 	Builder,
 	Engine,
+	Event,
 	Module,
+	PanicHook,
+	Priority,
+	Propagation,
 };
 use resources::{
 	Importer,
@@ -109,32 +198,160 @@ use resources::{
 	ResourceManager,
 };
 
+define_log_category!(Gpu, GPU_CATEGORY);
+
+/// Driver pipeline cache data so pipelines are not compiled from scratch every launch
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct PipelineCache {
+	data: RwLock<Vec<u8>>,
+}
+
+impl Cache for PipelineCache {
+	fn new() -> Self {
+		Self::default()
+	}
+
+	fn reload(&mut self) -> bool {
+		false
+	}
+}
+
+/// Set when a pipeline is created so the cache is only written out when it can have changed
+static PIPELINE_CACHE_DIRTY: AtomicBool = AtomicBool::new(false);
+
+/// Records that the driver pipeline cache gained entries and should be written on the next save
+pub(crate) fn mark_pipeline_cache_dirty() {
+	PIPELINE_CACHE_DIRTY.store(true, Ordering::Relaxed);
+}
+
+/// Writes the pipeline cache of `device` to disk if a pipeline was created since the last save. Called on shutdown
+/// and when the [`Gpu`] module drops.
+pub(crate) fn save_pipeline_cache(device: &Device) {
+	if !PIPELINE_CACHE_DIRTY.swap(false, Ordering::Relaxed) {
+		return;
+	}
+
+	let cache = match CacheRef::<PipelineCache>::new() {
+		Some(cache) => cache,
+		None => return,
+	};
+
+	match device.pipeline_cache() {
+		Ok(data) => {
+			*cache.data.write().unwrap() = data;
+			cache.save();
+		}
+		Err(err) => warn!(GPU_CATEGORY, "Failed to read the pipeline cache: {:?}", err),
+	}
+}
+
+/// Read from the `gpu` table of the engine config file when the [`Gpu`] module is created
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct GpuConfig {
+	pub present_mode: PresentMode,
+	/// Number of frames that may be queued on the GPU before presenting blocks
+	pub frames_in_flight: usize,
+	/// Enables the Vulkan validation layer when it is installed. On by default in debug builds
+	pub validation: bool,
+	/// Logs the device fault report and the debug markers the GPU was executing when the device is lost. On by
+	/// default in debug builds
+	pub crash_diagnostics: bool,
+}
+
+impl Default for GpuConfig {
+	fn default() -> Self {
+		Self {
+			present_mode: PresentMode::Fifo,
+			frames_in_flight: 2,
+			validation: cfg!(debug_assertions),
+			crash_diagnostics: cfg!(debug_assertions),
+		}
+	}
+}
+
+impl Config for GpuConfig {
+	const NAME: &'static str = "Gpu";
+	const FILE: &'static str = ENGINE_CONFIG_FILE;
+}
+
 pub struct Gpu {
 	device: Device,
 }
 
 impl Gpu {
-	pub fn device() -> &'a Device {
+	pub fn device<'a>() -> &'a Device {
 		let gpu: &Gpu = Engine::module()
 			.expect("Engine must depend on Gpu module if the global device is to be used. ");
 		&gpu.device
 	}
 }
 
+impl Drop for Gpu {
+	fn drop(&mut self) {
+		// Headless runs and early exits never see ShutdownRequested
+		save_pipeline_cache(&self.device);
+	}
+}
+
 impl Module for Gpu {
 	fn new() -> Self {
-		let instance = Instance::new().unwrap();
+		let config = ConfigManager::read::<GpuConfig>();
+		let instance = Instance::builder()
+			.validation(config.validation)
+			.crash_diagnostics(config.crash_diagnostics)
+			.spawn()
+			.unwrap();
 		let device = instance.create_device(Engine::window()).unwrap();
 
+		device.set_present_mode(config.present_mode);
+		device.set_frames_in_flight(config.frames_in_flight.max(1));
+
+		if let Some(cache) = CacheRef::<PipelineCache>::new() {
+			let data = cache.data.read().unwrap();
+			if let Err(err) = device.load_pipeline_cache(&data) {
+				warn!(GPU_CATEGORY, "Failed to load the pipeline cache: {:?}", err);
+			}
+		}
+
 		Self { device }
 	}
 
 	fn depends_on(builder: &mut Builder) -> &mut Builder {
 		builder
+			.module::<ConfigManager>()
+			.register(GpuConfig::variant())
+			.listen(Priority::ENGINE, |event: &Event| {
+				let gpu: Option<&Gpu> = Engine::module();
+				if let (Event::Resized(width, height), Some(gpu)) = (event, gpu) {
+					gpu.device.resize(*width, *height);
+				}
+				Propagation::Continue
+			})
+			.register(PanicHook::new(|| {
+				let gpu: Option<&Gpu> = Engine::module();
+				if let Some(gpu) = gpu {
+					gpu.device.wait_for_crash_dump();
+				}
+			}))
 			.module::<ResourceManager>()
-			.register(GraphicsPipeline::variant())
+			.listen(Priority(i32::MIN), |event: &Event| {
+				// Lowest priority so this only runs when nothing kept the engine open
+				let gpu: Option<&Gpu> = Engine::module();
+				if let (Event::ShutdownRequested, Some(gpu)) = (event, gpu) {
+					save_pipeline_cache(&gpu.device);
+				}
+				Propagation::Continue
+			})
+			.register(ShaderCache::variant())
+			.register(PipelineCache::variant())
+			.register(<GraphicsPipeline as Resource>::variant())
 			.register(GraphicsPipelineImporter::variant(&["graphics_pipeline"]))
+			.register(<ComputePipeline as Resource>::variant())
+			.register(ComputePipelineImporter::variant(&["compute_pipeline"]))
 			.register(Texture::variant())
-			.register(TextureImporter::variant(&["png", "psd", "jpg"]))
+			.register(TextureImporter::variant(&[
+				"png", "psd", "jpg", "hdr", "dds", "ktx2",
+			]))
 	}
 }
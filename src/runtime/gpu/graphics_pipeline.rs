@@ -1,7 +1,17 @@
 use crate::*;
 
 use std::{
-	collections::HashMap,
+	collections::{
+		hash_map::DefaultHasher,
+		HashMap,
+	},
+	error,
+	fmt,
+	hash::{
+		Hash,
+		Hasher,
+	},
+	path::Path,
 	sync::Arc,
 };
 
@@ -19,7 +29,7 @@ use serde::{
 
 use bitflags::bitflags;
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum DrawMode {
 	Fill,
 	Line,
@@ -33,7 +43,7 @@ bitflags! {
 	}
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum CompareOp {
 	Never,
 	Less,           // A < B
@@ -45,7 +55,7 @@ pub enum CompareOp {
 	Always,
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum BlendOp {
 	Add,
 	Subtract,
@@ -54,7 +64,7 @@ pub enum BlendOp {
 	Max,
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum BlendFactor {
 	Zero,
 	One,
@@ -66,6 +76,17 @@ pub enum BlendFactor {
 
 	SrcAlpha,
 	OneMinusSrcAlpha,
+	DstAlpha,
+	OneMinusDstAlpha,
+
+	/// Reads the color of the pipeline's `blend_constants`
+	ConstantColor,
+	OneMinusConstantColor,
+	/// Reads the alpha of the pipeline's `blend_constants`
+	ConstantAlpha,
+	OneMinusConstantAlpha,
+
+	SrcAlphaSaturate,
 }
 
 bitflags! {
@@ -77,6 +98,87 @@ bitflags! {
 	}
 }
 
+/// Blend state of a single color attachment
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AttachmentBlend {
+	pub blend_enabled: bool,
+
+	pub src_color_blend_factor: BlendFactor,
+	pub dst_color_blend_factor: BlendFactor,
+	pub color_blend_op: BlendOp,
+
+	pub src_alpha_blend_factor: BlendFactor,
+	pub dst_alpha_blend_factor: BlendFactor,
+	pub alpha_blend_op: BlendOp,
+
+	pub color_mask: ColorMask,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum StencilOp {
+	Keep,
+	Zero,
+	Replace,
+	IncrementAndClamp,
+	DecrementAndClamp,
+	Invert,
+	IncrementAndWrap,
+	DecrementAndWrap,
+}
+
+/// Stencil test of one face. `compare` is done between `reference` and the stored value, both masked by
+/// `compare_mask`.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct StencilFaceState {
+	pub compare: CompareOp,
+	/// Applied when the stencil test fails
+	pub fail_op: StencilOp,
+	/// Applied when both the stencil and depth tests pass
+	pub pass_op: StencilOp,
+	/// Applied when the stencil test passes and the depth test fails
+	pub depth_fail_op: StencilOp,
+
+	pub reference: u32,
+	pub compare_mask: u32,
+	pub write_mask: u32,
+}
+
+impl Default for StencilFaceState {
+	fn default() -> Self {
+		Self {
+			compare: CompareOp::Always,
+			fail_op: StencilOp::Keep,
+			pass_op: StencilOp::Keep,
+			depth_fail_op: StencilOp::Keep,
+
+			reference: 0,
+			compare_mask: 0xff,
+			write_mask: 0xff,
+		}
+	}
+}
+
+/// Offsets depth values written by a pipeline. Used to stop shadow acne and decals from fighting
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct DepthBias {
+	pub constant_factor: f32,
+	pub slope_factor: f32,
+	/// Largest bias that can be applied. 0.0 does not clamp
+	pub clamp: f32,
+}
+
+// Factors are hashed by their bits so descriptions can key pipeline variants
+impl Hash for DepthBias {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.constant_factor.to_bits().hash(state);
+		self.slope_factor.to_bits().hash(state);
+		self.clamp.to_bits().hash(state);
+	}
+}
+
+#[derive(Clone)]
 pub struct GraphicsPipelineDescription {
 	pub attachments: Vec<Format>,
 
@@ -87,6 +189,9 @@ pub struct GraphicsPipelineDescription {
 	pub draw_mode: DrawMode,
 	pub line_width: f32,
 
+	/// Samples per pixel of every attachment the pipeline draws to
+	pub samples: u32,
+
 	pub cull_mode: CullMode,
 	pub color_mask: ColorMask,
 
@@ -100,9 +205,21 @@ pub struct GraphicsPipelineDescription {
 	pub dst_alpha_blend_factor: BlendFactor,
 	pub alpha_blend_op: BlendOp,
 
+	/// Overrides the blend state above for the color attachment at the same index
+	pub attachment_blends: Vec<Option<AttachmentBlend>>,
+	/// Color read by the constant blend factors
+	pub blend_constants: [f32; 4],
+
 	pub depth_test: bool,
 	pub depth_write: bool,
 	pub depth_compare: CompareOp,
+	/// Clamps depth to the viewport instead of clipping. Ignored when the device does not support it
+	pub depth_clamp: bool,
+	pub depth_bias: Option<DepthBias>,
+
+	pub stencil_test: bool,
+	pub stencil_front: StencilFaceState,
+	pub stencil_back: StencilFaceState,
 
 	pub constants: HashMap<String, Vec<ConstantMember>>,
 	pub resources: HashMap<String, PipelineResource>,
@@ -112,14 +229,113 @@ impl GraphicsPipelineDescription {
 	pub(crate) fn push_constant_size(&self) -> usize {
 		self.constants.len() * 4 + self.resources.len() * 4
 	}
+
+	/// Blend state of the color attachment at `index`
+	pub fn attachment_blend(&self, index: usize) -> AttachmentBlend {
+		match self.attachment_blends.get(index) {
+			Some(Some(blend)) => *blend,
+			_ => AttachmentBlend {
+				blend_enabled: self.blend_enabled,
+
+				src_color_blend_factor: self.src_color_blend_factor,
+				dst_color_blend_factor: self.dst_color_blend_factor,
+				color_blend_op: self.color_blend_op,
+
+				src_alpha_blend_factor: self.src_alpha_blend_factor,
+				dst_alpha_blend_factor: self.dst_alpha_blend_factor,
+				alpha_blend_op: self.alpha_blend_op,
+
+				color_mask: self.color_mask,
+			},
+		}
+	}
+}
+
+// Keys pipeline variants. Every field is destructured so new state can not be left out of the hash
+impl Hash for GraphicsPipelineDescription {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		let Self {
+			attachments,
+			shaders,
+			vertex_attributes,
+			draw_mode,
+			line_width,
+			samples,
+			cull_mode,
+			color_mask,
+			blend_enabled,
+			src_color_blend_factor,
+			dst_color_blend_factor,
+			color_blend_op,
+			src_alpha_blend_factor,
+			dst_alpha_blend_factor,
+			alpha_blend_op,
+			attachment_blends,
+			blend_constants,
+			depth_test,
+			depth_write,
+			depth_compare,
+			depth_clamp,
+			depth_bias,
+			stencil_test,
+			stencil_front,
+			stencil_back,
+			constants,
+			resources,
+		} = self;
+
+		attachments.hash(state);
+		for it in shaders.iter() {
+			Arc::as_ptr(&it.0).hash(state);
+		}
+		vertex_attributes.hash(state);
+		draw_mode.hash(state);
+		line_width.to_bits().hash(state);
+		samples.hash(state);
+		cull_mode.hash(state);
+		color_mask.hash(state);
+		blend_enabled.hash(state);
+		src_color_blend_factor.hash(state);
+		dst_color_blend_factor.hash(state);
+		color_blend_op.hash(state);
+		src_alpha_blend_factor.hash(state);
+		dst_alpha_blend_factor.hash(state);
+		alpha_blend_op.hash(state);
+		attachment_blends.hash(state);
+		for it in blend_constants.iter() {
+			it.to_bits().hash(state);
+		}
+		depth_test.hash(state);
+		depth_write.hash(state);
+		depth_compare.hash(state);
+		depth_clamp.hash(state);
+		depth_bias.hash(state);
+		stencil_test.hash(state);
+		stencil_front.hash(state);
+		stencil_back.hash(state);
+
+		// Maps are hashed in name order as their iteration order is random
+		let mut constants: Vec<_> = constants.iter().collect();
+		constants.sort_by(|a, b| a.0.cmp(b.0));
+		constants.hash(state);
+		let mut resources: Vec<_> = resources.iter().collect();
+		resources.sort_by(|a, b| a.0.cmp(b.0));
+		resources.hash(state);
+	}
 }
 
 pub struct GraphicsPipelineBuilder<'a> {
 	description: GraphicsPipelineDescription,
 	device: Option<&'a Device>,
+	debug_name: Option<String>,
 }
 
 impl<'a> GraphicsPipelineBuilder<'a> {
+	pub fn device(mut self, device: &'a Device) -> Self {
+		self.device = Some(device);
+		self
+	}
+
 	pub fn attachments(mut self, attachments: &[Format]) -> Self {
 		self.description.attachments = attachments.to_vec();
 		self
@@ -145,6 +361,11 @@ impl<'a> GraphicsPipelineBuilder<'a> {
 		self
 	}
 
+	pub fn samples(mut self, samples: u32) -> Self {
+		self.description.samples = samples;
+		self
+	}
+
 	pub fn cull_mode(mut self, cull_mode: CullMode) -> Self {
 		self.description.cull_mode = cull_mode;
 		self
@@ -190,6 +411,26 @@ impl<'a> GraphicsPipelineBuilder<'a> {
 		self
 	}
 
+	/// Overrides the blend state of the color attachment at `index`
+	pub fn attachment_blend(mut self, index: usize, blend: AttachmentBlend) -> Self {
+		let blends = &mut self.description.attachment_blends;
+		if blends.len() <= index {
+			blends.resize(index + 1, None);
+		}
+		blends[index] = Some(blend);
+		self
+	}
+
+	pub fn attachment_blends(mut self, attachment_blends: Vec<Option<AttachmentBlend>>) -> Self {
+		self.description.attachment_blends = attachment_blends;
+		self
+	}
+
+	pub fn blend_constants(mut self, blend_constants: [f32; 4]) -> Self {
+		self.description.blend_constants = blend_constants;
+		self
+	}
+
 	pub fn depth_test(mut self, depth_test: bool) -> Self {
 		self.description.depth_test = depth_test;
 		self
@@ -205,6 +446,38 @@ impl<'a> GraphicsPipelineBuilder<'a> {
 		self
 	}
 
+	pub fn depth_clamp(mut self, depth_clamp: bool) -> Self {
+		self.description.depth_clamp = depth_clamp;
+		self
+	}
+
+	pub fn depth_bias(mut self, depth_bias: Option<DepthBias>) -> Self {
+		self.description.depth_bias = depth_bias;
+		self
+	}
+
+	pub fn stencil_test(mut self, stencil_test: bool) -> Self {
+		self.description.stencil_test = stencil_test;
+		self
+	}
+
+	/// Sets the stencil state of both faces
+	pub fn stencil(mut self, stencil: StencilFaceState) -> Self {
+		self.description.stencil_front = stencil;
+		self.description.stencil_back = stencil;
+		self
+	}
+
+	pub fn stencil_front(mut self, stencil_front: StencilFaceState) -> Self {
+		self.description.stencil_front = stencil_front;
+		self
+	}
+
+	pub fn stencil_back(mut self, stencil_back: StencilFaceState) -> Self {
+		self.description.stencil_back = stencil_back;
+		self
+	}
+
 	pub fn constant(mut self, name: impl ToString, members: Vec<ConstantMember>) -> Self {
 		self.description.constants.insert(name.to_string(), members);
 		self
@@ -227,23 +500,93 @@ impl<'a> GraphicsPipelineBuilder<'a> {
 		self
 	}
 
+	/// Names the pipeline in GPU captures and validation messages
+	pub fn debug_name(mut self, name: impl ToString) -> Self {
+		self.debug_name = Some(name.to_string());
+		self
+	}
+
 	pub fn spawn(self) -> Result<GraphicsPipeline> {
 		let device = match self.device {
 			Some(device) => device,
 			None => Gpu::device(),
 		};
 
-		Ok(GraphicsPipeline(api::GraphicsPipeline::new(
-			device.0.clone(),
-			self.description,
-		)?))
+		let pipeline = api::GraphicsPipeline::new(device.0.clone(), self.description, None)?;
+		mark_pipeline_cache_dirty();
+		if let Some(name) = &self.debug_name {
+			pipeline.set_debug_name(name);
+		}
+
+		Ok(GraphicsPipeline(pipeline))
 	}
 }
 
 #[derive(Resource)]
 pub struct GraphicsPipeline(pub(crate) Arc<api::GraphicsPipeline>);
 
+fn hash_description(description: &GraphicsPipelineDescription) -> u64 {
+	let mut hasher = DefaultHasher::new();
+	description.hash(&mut hasher);
+	hasher.finish()
+}
+
 impl GraphicsPipeline {
+	#[cfg(backend = "null")]
+	pub fn id(&self) -> ResourceId {
+		self.0.id
+	}
+
+	/// Returns the members of the constants named `name`
+	pub fn constants(&self, name: &str) -> Option<&[ConstantMember]> {
+		self.0.description.constants.get(name).map(|it| &it[..])
+	}
+
+	pub fn samples(&self) -> u32 {
+		self.0.description.samples
+	}
+
+	/// Size in bytes of a single vertex read by the pipeline. Vertices are tightly packed attributes.
+	pub fn vertex_stride(&self) -> usize {
+		self.0
+			.description
+			.vertex_attributes
+			.iter()
+			.map(|it| it.size())
+			.sum()
+	}
+
+	pub fn description(&self) -> &GraphicsPipelineDescription {
+		&self.0.description
+	}
+
+	/// Returns a pipeline with the render state changed by `f`. Variants are memoized by the hash of their
+	/// description so requesting the same state twice returns the same pipeline.
+	pub fn variant(
+		&self,
+		f: impl FnOnce(&mut GraphicsPipelineDescription),
+	) -> Result<GraphicsPipeline> {
+		let mut description = self.0.description.clone();
+		f(&mut description);
+
+		let hash = hash_description(&description);
+		if hash == hash_description(&self.0.description) {
+			return Ok(GraphicsPipeline(self.0.clone()));
+		}
+
+		let mut variants = self.0.variants.lock().unwrap();
+		if let Some(pipeline) = variants.get(&hash) {
+			return Ok(GraphicsPipeline(pipeline.clone()));
+		}
+
+		let pipeline =
+			api::GraphicsPipeline::new(self.0.owner.clone(), description, Some(&self.0))?;
+		variants.insert(hash, pipeline.clone());
+		mark_pipeline_cache_dirty();
+
+		Ok(GraphicsPipeline(pipeline))
+	}
+
 	pub fn builder<'a>() -> GraphicsPipelineBuilder<'a> {
 		GraphicsPipelineBuilder {
 			description: GraphicsPipelineDescription {
@@ -253,6 +596,8 @@ impl GraphicsPipeline {
 				draw_mode: DrawMode::Fill,
 				line_width: 1.0,
 
+				samples: 1,
+
 				cull_mode: CullMode::BACK,
 				color_mask: ColorMask::all(),
 
@@ -266,14 +611,24 @@ impl GraphicsPipeline {
 				dst_alpha_blend_factor: BlendFactor::One,
 				alpha_blend_op: BlendOp::Add,
 
+				attachment_blends: Default::default(),
+				blend_constants: [0.0; 4],
+
 				depth_test: false,
 				depth_write: false,
 				depth_compare: CompareOp::Always,
+				depth_clamp: false,
+				depth_bias: None,
+
+				stencil_test: false,
+				stencil_front: Default::default(),
+				stencil_back: Default::default(),
 
 				constants: Default::default(),
 				resources: Default::default(),
 			},
 			device: None,
+			debug_name: None,
 		}
 	}
 }
@@ -287,6 +642,12 @@ pub struct DepthStencilStates {
 	pub depth_compare: CompareOp,
 	#[serde(default = "DepthStencilStates::default_depth_stencil_format")]
 	pub depth_stencil_format: Format,
+	pub depth_clamp: bool,
+	pub depth_bias: Option<DepthBias>,
+	/// Enables the stencil test for both faces
+	pub stencil: Option<StencilFaceState>,
+	/// Replaces `stencil` for back faces
+	pub stencil_back: Option<StencilFaceState>,
 }
 
 impl DepthStencilStates {
@@ -306,6 +667,10 @@ impl Default for DepthStencilStates {
 			depth_write: false,
 			depth_compare: Self::default_depth_compare(),
 			depth_stencil_format: Self::default_depth_stencil_format(),
+			depth_clamp: false,
+			depth_bias: None,
+			stencil: None,
+			stencil_back: None,
 		}
 	}
 }
@@ -334,6 +699,10 @@ pub struct RenderStates {
 	#[serde(default = "RenderStates::default_line_width")]
 	pub line_width: f32,
 
+	/// Samples per pixel of the attachments. Must match the textures the pipeline draws to
+	#[serde(default = "RenderStates::default_samples")]
+	pub samples: u32,
+
 	#[serde(default)]
 	pub cull_mode: Vec<CullModeSerde>,
 
@@ -350,6 +719,10 @@ impl RenderStates {
 		1.0
 	}
 
+	fn default_samples() -> u32 {
+		1
+	}
+
 	fn default_color_mask() -> Vec<ColorMaskSerde> {
 		vec![
 			ColorMaskSerde::Red,
@@ -365,6 +738,7 @@ impl Default for RenderStates {
 		Self {
 			draw_mode: Self::default_draw_mode(),
 			line_width: Self::default_line_width(),
+			samples: Self::default_samples(),
 			cull_mode: Default::default(),
 			color_mask: Self::default_color_mask(),
 		}
@@ -393,10 +767,19 @@ impl BlendStates {
 	}
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct ConstantMember(String, Constant);
+/// Blend state of a single pixel shader export. Unset states use the pipeline's
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct AttachmentBlendStates {
+	pub color_blend: Option<BlendStates>,
+	pub alpha_blend: Option<BlendStates>,
+	pub color_mask: Option<Vec<ColorMaskSerde>>,
+}
 
-#[derive(Serialize, Deserialize, Copy, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ConstantMember(pub String, pub Constant);
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum Constant {
 	Uint32,
 	Int32,
@@ -424,7 +807,7 @@ impl Constant {
 		}
 	}
 
-	pub fn size(self) -> usize {
+	pub const fn size(self) -> usize {
 		match self {
 			Self::Uint32 | Self::Int32 | Self::Float32 => 4,
 			Self::Vector2 => 8,
@@ -434,12 +817,29 @@ impl Constant {
 			Self::Matrix4 => 16 * 4,
 		}
 	}
+
+	/// Alignment of the constant inside a constants struct using std430 rules
+	pub const fn alignment(self) -> usize {
+		match self {
+			Self::Uint32 | Self::Int32 | Self::Float32 => 4,
+			Self::Vector2 => 8,
+			Self::Vector3 | Self::Vector4 | Self::Color | Self::Matrix4 => 16,
+		}
+	}
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Copy, Clone, Hash)]
 pub enum PipelineResource {
 	Texture,
+	/// Texture created with more than one array layer
+	TextureArray,
+	/// Texture created as a cube map
+	TextureCube,
 	Sampler(SamplerDescription),
+	/// Buffer created with [`BufferUsage::STORAGE`] that shaders can write to
+	StorageBuffer,
+	/// Texture created with [`TextureUsage::STORAGE`] that shaders can write to
+	StorageTexture,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -477,6 +877,14 @@ pub struct GraphicsPipelineFile {
 	#[serde(default)]
 	pub alpha_blend: Option<BlendStates>,
 
+	/// Keyed by the name of the pixel shader export
+	#[serde(default)]
+	pub attachment_blends: HashMap<String, AttachmentBlendStates>,
+
+	/// Color read by the constant blend factors
+	#[serde(default)]
+	pub blend_constants: [f32; 4],
+
 	#[serde(default)]
 	pub depth_stencil_states: DepthStencilStates,
 
@@ -489,6 +897,10 @@ pub struct GraphicsPipelineFile {
 	#[serde(default)]
 	pub common: String,
 
+	/// Declared before both shaders are compiled
+	#[serde(default)]
+	pub defines: Vec<(String, String)>,
+
 	pub vertex_shader: VertexShader,
 	pub pixel_shader: PixelShader,
 }
@@ -496,12 +908,244 @@ pub struct GraphicsPipelineFile {
 #[derive(Serialize, Deserialize)]
 pub(crate) struct GraphicsPipelineImporter {}
 
+/// Compiler output for a single line. `line` is set when the message points into the pipeline file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShaderMessage {
+	pub line: Option<usize>,
+	pub column: Option<usize>,
+	pub message: String,
+}
+
+/// Reasons a `.graphics_pipeline` or `.compute_pipeline` file can fail to import
+#[derive(Debug)]
+pub enum PipelineImportError {
+	Compile {
+		variant: ShaderVariant,
+		messages: Vec<ShaderMessage>,
+	},
+	/// Attribute type that can not be read from a vertex buffer
+	UnsupportedAttribute { name: String, variant: Constant },
+	/// Blend states were given for a name the pixel shader does not export
+	UnknownAttachment { name: String },
+	/// The compiled vertex shader reads an input that does not match the declared attributes
+	VertexAttributeMismatch {
+		location: u32,
+		declared: Option<Constant>,
+		found: Constant,
+	},
+}
+
+impl fmt::Display for PipelineImportError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Compile { variant, messages } => {
+				write!(f, "failed to compile {:?} shader", variant)?;
+				for it in messages.iter() {
+					match (it.line, it.column) {
+						(Some(line), Some(column)) => {
+							write!(f, "\n  line {}:{}: {}", line, column, it.message)?
+						}
+						(Some(line), None) => write!(f, "\n  line {}: {}", line, it.message)?,
+						_ => write!(f, "\n  {}", it.message)?,
+					}
+				}
+				Ok(())
+			}
+			Self::UnsupportedAttribute { name, variant } => write!(
+				f,
+				"vertex attribute \"{}\" can not be a {:?}",
+				name, variant
+			),
+			Self::UnknownAttachment { name } => write!(
+				f,
+				"blend states are given for \"{}\" which is not a pixel shader export",
+				name
+			),
+			Self::VertexAttributeMismatch {
+				location,
+				declared: Some(declared),
+				found,
+			} => write!(
+				f,
+				"vertex shader reads a {:?} at location {} but the attribute is declared as {:?}",
+				found, location, declared
+			),
+			Self::VertexAttributeMismatch {
+				location, found, ..
+			} => write!(
+				f,
+				"vertex shader reads a {:?} at location {} which has no declared attribute",
+				found, location
+			),
+		}
+	}
+}
+
+impl error::Error for PipelineImportError {}
+
+/// Code written in a pipeline file along with the line it starts on
+pub(crate) struct UserCode<'a> {
+	pub code: &'a str,
+	pub line: Option<usize>,
+}
+
+impl<'a> UserCode<'a> {
+	/// Finds the line `code` starts on by looking for the string value of `field`. `parent` narrows the search
+	/// to a nested structure.
+	pub fn find(contents: &str, parent: Option<&str>, field: &str, code: &'a str) -> Self {
+		let line = (|| {
+			let start = match parent {
+				Some(parent) => find_field(contents, 0, parent)?,
+				None => 0,
+			};
+			let start = find_field(contents, start, field)?;
+
+			// Strings can contain escapes so match on the first line with text instead of the whole value
+			let (index, first) = code
+				.lines()
+				.enumerate()
+				.find(|(_, it)| !it.trim().is_empty())?;
+			let found = start + contents[start..].find(first.trim())?;
+			let line = contents[..found].matches('\n').count() + 1;
+			line.checked_sub(index)
+		})();
+
+		Self { code, line }
+	}
+
+	/// Appends the code with `#line` directives so compiler messages point at `file`. Following lines are
+	/// restored to `name`.
+	pub fn push_to(&self, source: &mut String, file: &str, name: &str) {
+		if let Some(line) = self.line {
+			source.push_str(&format!("#line {} \"{}\"\n", line, file));
+		}
+		source.push_str(self.code);
+		source.push('\n');
+		if self.line.is_some() {
+			let line = source.matches('\n').count() + 2;
+			source.push_str(&format!("#line {} \"{}\"\n", line, name));
+		}
+	}
+}
+
+// Returns the offset just after `name:` when `name` is used as a field rather than within a value
+fn find_field(contents: &str, start: usize, name: &str) -> Option<usize> {
+	let mut offset = start;
+	while let Some(found) = contents[offset..].find(name) {
+		let begin = offset + found;
+		let end = begin + name.len();
+		offset = end;
+
+		let before = contents[..begin].chars().next_back();
+		if matches!(before, Some(c) if c.is_alphanumeric() || c == '_') {
+			continue;
+		}
+		let after = contents[end..].trim_start();
+		if after.starts_with(':') {
+			return Some(contents.len() - after.len() + 1);
+		}
+	}
+	None
+}
+
+/// Turns compiler output into messages. `file` is the name given to code from the pipeline file.
+pub(crate) fn parse_shader_messages(output: &str, file: &str) -> Vec<ShaderMessage> {
+	let mut result: Vec<ShaderMessage> = output
+		.lines()
+		.filter_map(|line| {
+			let index = line.find(": error: ")?;
+			let message = line[index + 9..].trim().to_string();
+
+			// Locations are "name:line:column"
+			let mut parts = line[..index].rsplitn(3, ':');
+			let column = parts.next().and_then(|it| it.parse().ok());
+			let line_number = parts.next().and_then(|it| it.parse().ok());
+			let name = parts.next();
+
+			Some(if name == Some(file) {
+				ShaderMessage {
+					line: line_number,
+					column,
+					message,
+				}
+			} else {
+				ShaderMessage {
+					line: None,
+					column: None,
+					message: line.trim().to_string(),
+				}
+			})
+		})
+		.collect();
+
+	if result.is_empty() && !output.trim().is_empty() {
+		result.push(ShaderMessage {
+			line: None,
+			column: None,
+			message: output.trim().to_string(),
+		});
+	}
+	result
+}
+
+/// Compiles a stage of a pipeline file turning compiler output into an import error
+pub(crate) fn compile_pipeline_shader(
+	name: &str,
+	source: &str,
+	variant: ShaderVariant,
+	defines: &[(&str, &str)],
+	file: &str,
+) -> std::result::Result<Vec<u8>, PipelineImportError> {
+	shader::compile(name, source, "main", variant, defines).map_err(|output| {
+		PipelineImportError::Compile {
+			variant,
+			messages: parse_shader_messages(&output, file),
+		}
+	})
+}
+
+/// Checks every input the compiled vertex shader reads against the attributes it was generated from
+pub(crate) fn validate_vertex_inputs(
+	binary: &[u8],
+	attributes: &[Constant],
+) -> std::result::Result<(), PipelineImportError> {
+	let inputs = match reflect::stage_inputs(binary) {
+		Some(inputs) => inputs,
+		None => return Ok(()),
+	};
+
+	for (location, found) in inputs {
+		let declared = attributes.get(location as usize).copied();
+		let matches = match declared {
+			Some(Constant::Color) => found == Constant::Vector4,
+			Some(declared) => declared == found,
+			None => false,
+		};
+		if !matches {
+			return Err(PipelineImportError::VertexAttributeMismatch {
+				location,
+				declared,
+				found,
+			});
+		}
+	}
+	Ok(())
+}
+
+// Name compiler messages use for code written in a graphics pipeline file
+const GRAPHICS_PIPELINE_FILE: &str = "graphics_pipeline";
+
 static SHADER_HEADER: &str = "
     #define NULL 0
     ByteAddressBuffer _all_buffers[]  : register(t0);
     Texture2D         _all_textures[] : register(t1);
     SamplerState      _all_samplers[] : register(s2);
 
+    [[vk::binding(0, 0)]] RWByteAddressBuffer _all_storage_buffers[];
+    [[vk::binding(3, 0)]] RWTexture2D<float4> _all_storage_textures[];
+    [[vk::binding(4, 0)]] Texture2DArray      _all_texture_arrays[];
+    [[vk::binding(5, 0)]] TextureCube         _all_texture_cubes[];
+
     ByteAddressBuffer index_buffers(uint index) {
         return _all_buffers[index];
     }
@@ -510,15 +1154,250 @@ static SHADER_HEADER: &str = "
         return _all_textures[index];
     }
 
+    Texture2DArray index_texture_arrays(uint index) {
+        return _all_texture_arrays[index];
+    }
+
+    TextureCube index_texture_cubes(uint index) {
+        return _all_texture_cubes[index];
+    }
+
     SamplerState index_samplers(uint index) {
         return _all_samplers[index];
     }
+
+    RWByteAddressBuffer index_storage_buffers(uint index) {
+        return _all_storage_buffers[index];
+    }
+
+    RWTexture2D<float4> index_storage_textures(uint index) {
+        return _all_storage_textures[index];
+    }
 ";
 
+/// Generates the bindless declarations, push constants and accessors shared by every stage of a pipeline.
+/// `file` and `name` are passed to [`UserCode::push_to`].
+fn push_padding(result: &mut String, count: &mut usize, bytes: usize) {
+	for _ in 0..bytes / 4 {
+		result.push_str(&format!("    uint _padding{};\n", count));
+		*count += 1;
+	}
+}
+
+pub(crate) fn shader_header(
+	common: &UserCode,
+	file: &str,
+	name: &str,
+	constants: &HashMap<String, Vec<ConstantMember>>,
+	resources: &HashMap<String, PipelineResource>,
+) -> String {
+	let mut result = SHADER_HEADER.to_string();
+	result.reserve(4096);
+
+	// TODO: Check if this should go after
+	result.push('\n');
+	common.push_to(&mut result, file, name);
+	result.push('\n');
+
+	// If we have imports then we need to fill out the constants and build boilerplate
+	if !constants.is_empty() || !resources.is_empty() {
+		// First thing to do is build the push constants structure
+		result.push_str("struct PushConstants {\n");
+		for name in constants.keys() {
+			result.push_str("    uint ");
+			result.push_str(name);
+			result.push_str(";\n");
+		}
+		for name in resources.keys() {
+			result.push_str("    uint ");
+			result.push_str(name);
+			result.push_str(";\n");
+		}
+		result.push_str("};\n");
+		result.push_str("[[vk::push_constant]] PushConstants push_constants;\n\n");
+
+		// Secondly we must now define structs for constants and accessor boilerplate
+		for (name, constants) in constants.iter() {
+			// Declare constants structure
+			result.push_str("struct ");
+
+			let name_capitalized = {
+				let mut c = name.chars();
+				match c.next() {
+					None => String::new(),
+					Some(f) => f.to_uppercase().chain(c).collect(),
+				}
+			};
+
+			result.push_str(&name_capitalized);
+			result.push_str(" {\n");
+
+			// Run through every item in constants and decalre it. The buffer is read tightly packed so padding
+			// is declared explicitly to match the std430 layout checked by `GpuConstants`
+			let variants: Vec<Constant> = constants.iter().map(|it| it.1).collect();
+			let mut offset = 0;
+			let mut padding = 0;
+			for (index, ConstantMember(name, variant)) in constants.iter().enumerate() {
+				let member_offset = std430_offset(&variants, index);
+				push_padding(&mut result, &mut padding, member_offset - offset);
+				offset = member_offset + variant.size();
+
+				result.push_str("    ");
+				result.push_str(variant.into_type_string());
+				result.push(' ');
+				result.push_str(name);
+				result.push_str(";\n");
+			}
+			push_padding(&mut result, &mut padding, std430_size(&variants) - offset);
+
+			result.push_str("};\n\n");
+
+			// Generate custom get method declaration
+			result.push_str(&name_capitalized);
+			result.push_str(" get_");
+			result.push_str(name);
+			result.push_str("() {\n");
+
+			// Grab the data from the buffer
+			result.push_str("ByteAddressBuffer buffer = index_buffers((push_constants.");
+			result.push_str(name);
+			result.push_str(" >> 16) & 0xffff);\n");
+
+			result.push_str(&name_capitalized);
+			result.push_str(" result = buffer.Load<");
+			result.push_str(&name_capitalized);
+			result.push_str(">((push_constants.");
+			result.push_str(name);
+			result.push_str(" & 0xffff) * sizeof(");
+			result.push_str(&name_capitalized);
+			result.push_str("));");
+
+			// Transpose any matrices
+			for ConstantMember(name, variant) in constants.iter() {
+				if *variant == Constant::Matrix4 {
+					result.push_str("result.");
+					result.push_str(name);
+					result.push_str(" = transpose(result.");
+					result.push_str(name);
+					result.push_str(");\n");
+				}
+			}
+
+			result.push_str("return result;\n}\n\n");
+		}
+
+		// Generate resource load boilerplate
+		for (name, resource) in resources.iter() {
+			// Generate custom load method declaration
+			let resource_type = match resource {
+				PipelineResource::Texture => "Texture2D",
+				PipelineResource::TextureArray => "Texture2DArray",
+				PipelineResource::TextureCube => "TextureCube",
+				PipelineResource::Sampler { .. } => "SamplerState",
+				PipelineResource::StorageBuffer => "RWByteAddressBuffer",
+				PipelineResource::StorageTexture => "RWTexture2D<float4>",
+			};
+
+			result.push_str(resource_type);
+			result.push_str(" load_");
+			result.push_str(name);
+			result.push_str("() {\n");
+
+			match resource {
+				PipelineResource::Texture => {
+					result.push_str("return index_textures(push_constants.");
+					result.push_str(name);
+					result.push_str(");")
+				}
+				PipelineResource::TextureArray => {
+					result.push_str("return index_texture_arrays(push_constants.");
+					result.push_str(name);
+					result.push_str(");\n")
+				}
+				PipelineResource::TextureCube => {
+					result.push_str("return index_texture_cubes(push_constants.");
+					result.push_str(name);
+					result.push_str(");\n")
+				}
+				PipelineResource::Sampler { .. } => {
+					result.push_str("return index_samplers(push_constants.");
+					result.push_str(name);
+					result.push_str(");\n")
+				}
+				PipelineResource::StorageBuffer => {
+					result.push_str("return index_storage_buffers(push_constants.");
+					result.push_str(name);
+					result.push_str(");\n")
+				}
+				PipelineResource::StorageTexture => {
+					result.push_str("return index_storage_textures(push_constants.");
+					result.push_str(name);
+					result.push_str(");\n")
+				}
+			}
+
+			result.push_str("}\n\n");
+		}
+	}
+
+	result
+}
+
+fn color_mask(mask: &[ColorMaskSerde]) -> ColorMask {
+	let mut result = ColorMask::empty();
+	for it in mask.iter() {
+		match it {
+			ColorMaskSerde::Red => result.insert(ColorMask::RED),
+			ColorMaskSerde::Green => result.insert(ColorMask::GREEN),
+			ColorMaskSerde::Blue => result.insert(ColorMask::BLUE),
+			ColorMaskSerde::Alpha => result.insert(ColorMask::ALPHA),
+		}
+	}
+	result
+}
+
+/// Applies the blend states given for each pixel shader export on top of the pipeline's `base` states
+fn resolve_attachment_blends(
+	exports: &[(String, Format)],
+	mut states: HashMap<String, AttachmentBlendStates>,
+	base: AttachmentBlend,
+) -> std::result::Result<Vec<Option<AttachmentBlend>>, PipelineImportError> {
+	let result = exports
+		.iter()
+		.map(|(name, _)| {
+			let states = states.remove(name)?;
+
+			let mut blend = base;
+			if let Some(color_blend) = states.color_blend {
+				blend.blend_enabled = true;
+				blend.src_color_blend_factor = color_blend.src_blend_factor;
+				blend.dst_color_blend_factor = color_blend.dst_blend_factor;
+				blend.color_blend_op = color_blend.blend_op;
+			}
+			if let Some(alpha_blend) = states.alpha_blend {
+				blend.blend_enabled = true;
+				blend.src_alpha_blend_factor = alpha_blend.src_blend_factor;
+				blend.dst_alpha_blend_factor = alpha_blend.dst_blend_factor;
+				blend.alpha_blend_op = alpha_blend.blend_op;
+			}
+			if let Some(mask) = &states.color_mask {
+				blend.color_mask = color_mask(mask);
+			}
+			Some(blend)
+		})
+		.collect();
+
+	// Anything left over does not match an export
+	match states.into_iter().next() {
+		Some((name, _)) => Err(PipelineImportError::UnknownAttachment { name }),
+		None => Ok(result),
+	}
+}
+
 impl Importer for GraphicsPipelineImporter {
 	type Target = GraphicsPipeline;
 
-	fn import(&self, bytes: &[u8]) -> resources::Result<Self::Target> {
+	fn import(&self, bytes: &[u8], path: &Path) -> resources::Result<Self::Target> {
 		let contents = std::str::from_utf8(bytes)?;
 		let file = ron::from_str(contents)?;
 
@@ -527,6 +1406,8 @@ impl Importer for GraphicsPipelineImporter {
 
 			color_blend,
 			alpha_blend,
+			attachment_blends,
+			blend_constants,
 
 			depth_stencil_states,
 
@@ -537,127 +1418,14 @@ impl Importer for GraphicsPipelineImporter {
 			resources,
 
 			common,
+			defines,
 		} = file;
 
-		let header = {
-			let mut result = SHADER_HEADER.to_string();
-			result.reserve(4096);
-
-			// TODO: Check if this should go after
-			result.push('\n');
-			result.push_str(&common);
-			result.push_str("\n\n");
-
-			// If we have imports then we need to fill out the constants and build boilerplate
-			if !constants.is_empty() || !resources.is_empty() {
-				// First thing to do is build the push constants structure
-				result.push_str("struct PushConstants {\n");
-				for (name, _) in constants.iter() {
-					result.push_str("    uint ");
-					result.push_str(name);
-					result.push_str(";\n");
-				}
-				for (name, _) in resources.iter() {
-					result.push_str("    uint ");
-					result.push_str(name);
-					result.push_str(";\n");
-				}
-				result.push_str("};\n");
-				result.push_str("[[vk::push_constant]] PushConstants push_constants;\n\n");
-
-				// Secondly we must now define structs for constants and accessor boilerplate
-				for (name, constants) in constants.iter() {
-					// Declare constants structure
-					result.push_str("struct ");
-
-					let name_capitalized = {
-						let mut c = name.chars();
-						match c.next() {
-							None => String::new(),
-							Some(f) => f.to_uppercase().chain(c).collect(),
-						}
-					};
-
-					result.push_str(&name_capitalized);
-					result.push_str(" {\n");
-
-					// Run through every item in constants and decalre it
-					for ConstantMember(name, variant) in constants.iter() {
-						result.push_str("    ");
-						result.push_str(variant.into_type_string());
-						result.push(' ');
-						result.push_str(name);
-						result.push_str(";\n");
-					}
-
-					result.push_str("};\n\n");
-
-					// Generate custom get method declaration
-					result.push_str(&name_capitalized);
-					result.push_str(" get_");
-					result.push_str(name);
-					result.push_str("() {\n");
-
-					// Grab the data from the buffer
-					result.push_str("ByteAddressBuffer buffer = index_buffers((push_constants.");
-					result.push_str(name);
-					result.push_str(" >> 16) & 0xffff);\n");
-
-					result.push_str(&name_capitalized);
-					result.push_str(" result = buffer.Load<");
-					result.push_str(&name_capitalized);
-					result.push_str(">((push_constants.");
-					result.push_str(name);
-					result.push_str(" & 0xffff) * sizeof(");
-					result.push_str(&name_capitalized);
-					result.push_str("));");
-
-					// Transpose any matrices
-					for ConstantMember(name, variant) in constants.iter() {
-						if *variant == Constant::Matrix4 {
-							result.push_str("result.");
-							result.push_str(name);
-							result.push_str(" = transpose(result.");
-							result.push_str(name);
-							result.push_str(");\n");
-						}
-					}
-
-					result.push_str("return result;\n}\n\n");
-				}
-
-				// Generate resource load boilerplate
-				for (name, resource) in resources.iter() {
-					// Generate custom load method declaration
-					let resource_type = match resource {
-						PipelineResource::Texture => "Texture2D",
-						PipelineResource::Sampler { .. } => "SamplerState",
-					};
-
-					result.push_str(resource_type);
-					result.push_str(" load_");
-					result.push_str(name);
-					result.push_str("() {\n");
-
-					match resource {
-						PipelineResource::Texture => {
-							result.push_str("return index_textures(push_constants.");
-							result.push_str(name);
-							result.push_str(");")
-						}
-						PipelineResource::Sampler { .. } => {
-							result.push_str("return index_samplers(push_constants.");
-							result.push_str(name);
-							result.push_str(");\n")
-						}
-					}
-
-					result.push_str("}\n\n");
-				}
-			}
-
-			result
-		};
+		let common = UserCode::find(contents, None, "common", &common);
+		let defines: Vec<(&str, &str)> = defines
+			.iter()
+			.map(|(name, value)| (name.as_str(), value.as_str()))
+			.collect();
 
 		let mut attachments: Vec<Format> = pixel_shader
 			.exports
@@ -670,6 +1438,16 @@ impl Importer for GraphicsPipelineImporter {
 			.iter()
 			.map(|ConstantMember(_, variant)| *variant)
 			.collect();
+		if let Some(ConstantMember(name, variant)) = vertex_shader
+			.attributes
+			.iter()
+			.find(|ConstantMember(_, variant)| *variant == Constant::Matrix4)
+		{
+			return Err(Box::new(PipelineImportError::UnsupportedAttribute {
+				name: name.clone(),
+				variant: *variant,
+			}));
+		}
 
 		let blend_enabled = color_blend.is_some() || alpha_blend.is_some();
 
@@ -685,6 +1463,25 @@ impl Importer for GraphicsPipelineImporter {
 			blend_op: BlendOp::Add,
 		});
 
+		let color_mask = color_mask(&render_states.color_mask);
+		let attachment_blends = resolve_attachment_blends(
+			&pixel_shader.exports,
+			attachment_blends,
+			AttachmentBlend {
+				blend_enabled,
+
+				src_color_blend_factor: color_blend.src_blend_factor,
+				dst_color_blend_factor: color_blend.dst_blend_factor,
+				color_blend_op: color_blend.blend_op,
+
+				src_alpha_blend_factor: alpha_blend.src_blend_factor,
+				dst_alpha_blend_factor: alpha_blend.dst_blend_factor,
+				alpha_blend_op: alpha_blend.blend_op,
+
+				color_mask,
+			},
+		)?;
+
 		// Generate the pixel shader first to have access to exports
 		let pixel_shader = {
 			let PixelShader { exports, code } = pixel_shader;
@@ -692,7 +1489,13 @@ impl Importer for GraphicsPipelineImporter {
 			let imports = &vertex_shader.exports;
 
 			// Start off with header
-			let mut source = header.clone();
+			let mut source = shader_header(
+				&common,
+				GRAPHICS_PIPELINE_FILE,
+				"pixel.hlsl",
+				&constants,
+				&resources,
+			);
 
 			source.push_str("struct PixelOutput {\n");
 			for (index, (name, format)) in exports.iter().enumerate() {
@@ -700,7 +1503,10 @@ impl Importer for GraphicsPipelineImporter {
 				name_uppercase.make_ascii_uppercase();
 
 				let variant = match format {
+					Format::R_U8 | Format::R_U16 => "float",
+					Format::RG_U8 | Format::RG_U16 => "float2",
 					Format::BGR_U8_SRGB
+					| Format::RGBA_U16
 					| Format::RGBA_F16
 					| Format::RGB_U8
 					| Format::RGB_U8_SRGB
@@ -737,14 +1543,23 @@ impl Importer for GraphicsPipelineImporter {
 				source.push_str("PixelOutput main( ) {\n");
 			}
 
-			source.push_str("PixelOutput output;");
+			source.push_str("PixelOutput output;\n");
 
-			source.push_str(&code);
-			source.push_str("\n}\n");
+			UserCode::find(contents, Some("pixel_shader"), "code", &code).push_to(
+				&mut source,
+				GRAPHICS_PIPELINE_FILE,
+				"pixel.hlsl",
+			);
+			source.push_str("}\n");
 
 			// Compile to binary and then pass to device
-			let binary =
-				shader::compile("pixel.hlsl", &source, "main", ShaderVariant::Pixel).unwrap();
+			let binary = compile_pipeline_shader(
+				"pixel.hlsl",
+				&source,
+				ShaderVariant::Pixel,
+				&defines,
+				GRAPHICS_PIPELINE_FILE,
+			)?;
 
 			Shader::builder(&binary, ShaderVariant::Pixel).spawn()?
 		};
@@ -759,7 +1574,13 @@ impl Importer for GraphicsPipelineImporter {
 			} = vertex_shader;
 
 			// Start off with header
-			let mut source = header;
+			let mut source = shader_header(
+				&common,
+				GRAPHICS_PIPELINE_FILE,
+				"vertex.hlsl",
+				&constants,
+				&resources,
+			);
 
 			// Generate VertexOutput always. There will always be position
 			source.push_str("struct VertexOutput {\n");
@@ -803,22 +1624,32 @@ impl Importer for GraphicsPipelineImporter {
 				source.push_str("};\n\n");
 
 				source.push_str("VertexOutput main( VertexInput input ) {\n");
-				source.push_str("VertexOutput output;");
+				source.push_str("VertexOutput output;\n");
 			} else {
 				source.push_str("VertexOutput main( ) {\n");
 			}
-			source.push_str(&code);
-			source.push_str("\n}\n");
+			UserCode::find(contents, Some("vertex_shader"), "code", &code).push_to(
+				&mut source,
+				GRAPHICS_PIPELINE_FILE,
+				"vertex.hlsl",
+			);
+			source.push_str("}\n");
 
 			// Compile to binary and then pass to device
-			let binary =
-				shader::compile("vertex.hlsl", &source, "main", ShaderVariant::Vertex).unwrap();
+			let binary = compile_pipeline_shader(
+				"vertex.hlsl",
+				&source,
+				ShaderVariant::Vertex,
+				&defines,
+				GRAPHICS_PIPELINE_FILE,
+			)?;
+			validate_vertex_inputs(&binary, &vertex_attributes)?;
 
 			Shader::builder(&binary, ShaderVariant::Vertex).spawn()?
 		};
 		let shaders = vec![pixel_shader, vertex_shader];
 
-		let (draw_mode, line_width, cull_mode, color_mask) = {
+		let (draw_mode, line_width, cull_mode) = {
 			let mut cull_mode = CullMode::empty();
 			for it in render_states.cull_mode.iter() {
 				match it {
@@ -827,34 +1658,24 @@ impl Importer for GraphicsPipelineImporter {
 				}
 			}
 
-			let mut color_mask = ColorMask::empty();
-			for it in render_states.color_mask.iter() {
-				match it {
-					ColorMaskSerde::Red => color_mask.insert(ColorMask::RED),
-					ColorMaskSerde::Green => color_mask.insert(ColorMask::GREEN),
-					ColorMaskSerde::Blue => color_mask.insert(ColorMask::BLUE),
-					ColorMaskSerde::Alpha => color_mask.insert(ColorMask::ALPHA),
-				}
-			}
-
-			(
-				render_states.draw_mode,
-				render_states.line_width,
-				cull_mode,
-				color_mask,
-			)
+			(render_states.draw_mode, render_states.line_width, cull_mode)
 		};
 
-		if depth_stencil_states.depth_test {
+		let stencil_test = depth_stencil_states.stencil.is_some();
+		let stencil_front = depth_stencil_states.stencil.unwrap_or_default();
+		let stencil_back = depth_stencil_states.stencil_back.unwrap_or(stencil_front);
+
+		if depth_stencil_states.depth_test || stencil_test {
 			attachments.push(depth_stencil_states.depth_stencil_format);
 		}
 
-		GraphicsPipeline::builder()
+		let pipeline = GraphicsPipeline::builder()
 			.attachments(&attachments)
 			.shaders(&shaders)
 			.vertex_attributes(&vertex_attributes)
 			.draw_mode(draw_mode)
 			.line_width(line_width)
+			.samples(render_states.samples)
 			.cull_mode(cull_mode)
 			.color_mask(color_mask)
 			.blend_enabled(blend_enabled)
@@ -864,16 +1685,207 @@ impl Importer for GraphicsPipelineImporter {
 			.src_alpha_blend_factor(alpha_blend.src_blend_factor)
 			.dst_alpha_blend_factor(alpha_blend.dst_blend_factor)
 			.alpha_blend_op(alpha_blend.blend_op)
+			.attachment_blends(attachment_blends)
+			.blend_constants(blend_constants)
 			.depth_test(depth_stencil_states.depth_test)
 			.depth_write(depth_stencil_states.depth_write)
 			.depth_compare(depth_stencil_states.depth_compare)
+			.depth_clamp(depth_stencil_states.depth_clamp)
+			.depth_bias(depth_stencil_states.depth_bias)
+			.stencil_test(stencil_test)
+			.stencil_front(stencil_front)
+			.stencil_back(stencil_back)
 			.constants(constants)
 			.resources(resources)
+			.debug_name(path.display())
 			.spawn()
-			.map_err(|err| -> Box<dyn std::error::Error + 'static> { Box::new(err) })
+			.map_err(|err| -> Box<dyn std::error::Error + 'static> { Box::new(err) })?;
+
+		Ok(pipeline)
 	}
 
 	fn export(&self, _resource: &Self::Target, _file: &mut std::fs::File) -> resources::Result<()> {
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const FILE: &str = r##"GraphicsPipeline(
+	common: r#"
+		float helper() { return 1.0; }
+	"#,
+
+	vertex_shader: (
+		code: r#"
+			output.position = float4(0.0, 0.0, 0.0, 1.0);
+			return output;
+		"#,
+	),
+
+	pixel_shader: (
+		exports: [("color", RGBA_U8)],
+		code: r#"
+			output.color = helper();
+			return output;
+		"#
+	),
+)"##;
+
+	#[test]
+	fn maps_code_to_file_lines() {
+		let code = "\n\t\t\toutput.color = helper();\n\t\t\treturn output;\n\t\t";
+		let pixel = UserCode::find(FILE, Some("pixel_shader"), "code", code);
+		assert_eq!(pixel.line, Some(15)); // Raw strings start on the line of the field
+
+		let code = "\n\t\t\toutput.position = float4(0.0, 0.0, 0.0, 1.0);\n";
+		let vertex = UserCode::find(FILE, Some("vertex_shader"), "code", code);
+		assert_eq!(vertex.line, Some(7));
+
+		let mut source = String::from("PixelOutput main() {\n");
+		pixel.push_to(&mut source, "graphics_pipeline", "pixel.hlsl");
+		source.push_str("}\n");
+
+		// Code is numbered from the file and the closing brace is restored to its line in the generated source
+		let lines: Vec<&str> = source.lines().collect();
+		assert_eq!(lines[1], "#line 15 \"graphics_pipeline\"");
+		assert_eq!(lines[3], "\t\t\toutput.color = helper();");
+		assert_eq!(lines[6], "#line 8 \"pixel.hlsl\"");
+		assert_eq!(lines[7], "}");
+	}
+
+	#[test]
+	fn parses_compiler_messages() {
+		let output = "graphics_pipeline:17:19: error: use of undeclared identifier 'helpr'\n\
+			\t\t\toutput.color = helpr();\n\
+			\t\t\t               ^\n\
+			pixel.hlsl:120:1: error: expected '}'\n";
+
+		assert_eq!(
+			parse_shader_messages(output, "graphics_pipeline"),
+			[
+				ShaderMessage {
+					line: Some(17),
+					column: Some(19),
+					message: "use of undeclared identifier 'helpr'".to_string(),
+				},
+				ShaderMessage {
+					line: None,
+					column: None,
+					message: "pixel.hlsl:120:1: error: expected '}'".to_string(),
+				},
+			]
+		);
+
+		let messages =
+			parse_shader_messages("Could not find libdxcompiler.so", "graphics_pipeline");
+		assert_eq!(messages[0].line, None);
+	}
+
+	#[test]
+	fn resolves_attachment_blends() {
+		let states: GraphicsPipelineFile = ron::from_str(
+			r#"GraphicsPipeline(
+				color_blend: Some((src_blend_factor: SrcAlpha, dst_blend_factor: OneMinusSrcAlpha)),
+				attachment_blends: {
+					"mask": (color_mask: Some([Red]), alpha_blend: Some((dst_blend_factor: ConstantAlpha))),
+				},
+				blend_constants: (0.0, 0.0, 0.0, 0.5),
+				depth_stencil_states: (
+					stencil: Some((compare: Equal, pass_op: IncrementAndClamp, reference: 1)),
+					depth_bias: Some((constant_factor: 1.25)),
+				),
+				vertex_shader: (code: ""),
+				pixel_shader: (exports: [("color", RGBA_U8), ("mask", RGBA_U8)], code: ""),
+			)"#,
+		)
+		.unwrap();
+
+		let stencil = states.depth_stencil_states.stencil.unwrap();
+		assert_eq!(stencil.compare, CompareOp::Equal);
+		assert_eq!(stencil.pass_op, StencilOp::IncrementAndClamp);
+		assert_eq!(stencil.fail_op, StencilOp::Keep);
+		assert_eq!(stencil.write_mask, 0xff);
+		assert_eq!(
+			states
+				.depth_stencil_states
+				.depth_bias
+				.unwrap()
+				.constant_factor,
+			1.25
+		);
+		assert_eq!(states.blend_constants[3], 0.5);
+
+		let base = AttachmentBlend {
+			blend_enabled: false,
+
+			src_color_blend_factor: BlendFactor::One,
+			dst_color_blend_factor: BlendFactor::One,
+			color_blend_op: BlendOp::Add,
+
+			src_alpha_blend_factor: BlendFactor::One,
+			dst_alpha_blend_factor: BlendFactor::One,
+			alpha_blend_op: BlendOp::Add,
+
+			color_mask: ColorMask::all(),
+		};
+		let blends =
+			resolve_attachment_blends(&states.pixel_shader.exports, states.attachment_blends, base)
+				.unwrap();
+		assert_eq!(
+			blends,
+			[
+				None,
+				Some(AttachmentBlend {
+					blend_enabled: true,
+					dst_alpha_blend_factor: BlendFactor::ConstantAlpha,
+					color_mask: ColorMask::RED,
+					..base
+				})
+			]
+		);
+
+		let mut unknown = HashMap::new();
+		unknown.insert("normal".to_string(), AttachmentBlendStates::default());
+		assert!(matches!(
+			resolve_attachment_blends(&states.pixel_shader.exports, unknown, base),
+			Err(PipelineImportError::UnknownAttachment { .. })
+		));
+	}
+
+	#[test]
+	#[cfg(backend = "null")]
+	fn memoizes_pipeline_variants() {
+		let device = test_device();
+
+		let shader = Shader::builder(b"", ShaderVariant::Vertex)
+			.device(&device)
+			.spawn()
+			.unwrap();
+		let pipeline = GraphicsPipeline::builder()
+			.attachments(&[Format::RGBA_U8])
+			.shaders(&[shader])
+			.device(&device)
+			.spawn()
+			.unwrap();
+
+		let unchanged = pipeline.variant(|_| {}).unwrap();
+		assert_eq!(unchanged.id(), pipeline.id());
+
+		let wireframe = pipeline
+			.variant(|it| it.draw_mode = DrawMode::Line)
+			.unwrap();
+		assert_ne!(wireframe.id(), pipeline.id());
+		assert_eq!(wireframe.description().draw_mode, DrawMode::Line);
+
+		let again = pipeline
+			.variant(|it| it.draw_mode = DrawMode::Line)
+			.unwrap();
+		assert_eq!(again.id(), wireframe.id());
+
+		let blended = pipeline.variant(|it| it.blend_enabled = true).unwrap();
+		assert_ne!(blended.id(), wireframe.id());
+	}
+}
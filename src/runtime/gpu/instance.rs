@@ -14,13 +14,45 @@ pub enum InstanceCreateError {
 	Unknown,
 }
 
+pub struct InstanceBuilder {
+	validation: bool,
+	crash_diagnostics: bool,
+}
+
+impl InstanceBuilder {
+	/// Enables the validation layer when it is installed. Messages are written to the log
+	pub fn validation(mut self, validation: bool) -> Self {
+		self.validation = validation;
+		self
+	}
+
+	/// Enables GPU fault reports and breadcrumb markers on devices that support them. Both are logged when the
+	/// device is lost.
+	pub fn crash_diagnostics(mut self, crash_diagnostics: bool) -> Self {
+		self.crash_diagnostics = crash_diagnostics;
+		self
+	}
+
+	pub fn spawn(self) -> Result<Instance, InstanceCreateError> {
+		let inner = api::Instance::new(self.validation, self.crash_diagnostics)?;
+		Ok(Instance(inner))
+	}
+}
+
 #[derive(Clone)]
 pub struct Instance(Arc<api::Instance>);
 
 impl Instance {
 	pub fn new() -> Result<Self, InstanceCreateError> {
-		let inner = api::Instance::new()?;
-		Ok(Self(inner))
+		Self::builder().spawn()
+	}
+
+	/// Validation and crash diagnostics are on by default in debug builds
+	pub fn builder() -> InstanceBuilder {
+		InstanceBuilder {
+			validation: cfg!(debug_assertions),
+			crash_diagnostics: cfg!(debug_assertions),
+		}
 	}
 
 	pub fn create_device(&self, window: Option<&Window>) -> crate::Result<Device> {
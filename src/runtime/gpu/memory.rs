@@ -0,0 +1,312 @@
+use std::ops::Range;
+
+/// Size of the blocks resources are sub allocated from
+pub(crate) const BLOCK_SIZE: u64 = 64 * 1024 * 1024;
+
+// Alignment that satisfies every resource. Used by back ends that do not report requirements
+#[cfg(backend = "null")]
+pub(crate) const DEFAULT_ALIGNMENT: u64 = 256;
+
+/// Memory used by resources of a single [`crate::MemoryType`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+	/// Bytes reserved from the driver
+	pub reserved: u64,
+	/// Bytes handed out to buffers and textures
+	pub used: u64,
+	/// Number of allocations made from the driver
+	pub blocks: usize,
+	/// Number of buffers and textures sub allocated from the blocks
+	pub allocations: usize,
+}
+
+impl std::ops::AddAssign for MemoryUsage {
+	fn add_assign(&mut self, rhs: Self) {
+		self.reserved += rhs.reserved;
+		self.used += rhs.used;
+		self.blocks += rhs.blocks;
+		self.allocations += rhs.allocations;
+	}
+}
+
+/// Snapshot of the memory a [`crate::Device`] has allocated. See [`crate::Device::memory_stats`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+	pub host_visible: MemoryUsage,
+	pub device_local: MemoryUsage,
+}
+
+/// First fit allocator over `size` bytes. Freed ranges are merged with their neighbours
+pub(crate) struct FreeList {
+	size: u64,
+	used: u64,
+	free: Vec<Range<u64>>, // Sorted by start
+}
+
+impl FreeList {
+	pub fn new(size: u64) -> Self {
+		Self {
+			size,
+			used: 0,
+			free: vec![Range {
+				start: 0,
+				end: size,
+			}],
+		}
+	}
+
+	pub fn size(&self) -> u64 {
+		self.size
+	}
+
+	pub fn used(&self) -> u64 {
+		self.used
+	}
+
+	/// Returns the offset of `size` bytes aligned to `alignment` or `None` if no range is large enough
+	pub fn allocate(&mut self, size: u64, alignment: u64) -> Option<u64> {
+		let alignment = alignment.max(1);
+		let (index, offset) = self.free.iter().enumerate().find_map(|(index, range)| {
+			let offset = range.start.div_ceil(alignment) * alignment;
+			if offset + size <= range.end {
+				Some((index, offset))
+			} else {
+				None
+			}
+		})?;
+
+		// Split the range keeping the alignment padding and the remainder free
+		let range = self.free.remove(index);
+		if offset + size < range.end {
+			self.free.insert(index, offset + size..range.end);
+		}
+		if range.start < offset {
+			self.free.insert(index, range.start..offset);
+		}
+
+		self.used += size;
+		Some(offset)
+	}
+
+	pub fn free(&mut self, offset: u64, size: u64) {
+		let index = self.free.partition_point(|range| range.start < offset);
+		self.free.insert(index, offset..offset + size);
+		self.used -= size;
+
+		// Merge with the next range then the previous one
+		if index + 1 < self.free.len() && self.free[index].end == self.free[index + 1].start {
+			self.free[index].end = self.free.remove(index + 1).end;
+		}
+		if index > 0 && self.free[index - 1].end == self.free[index].start {
+			self.free[index - 1].end = self.free.remove(index).end;
+		}
+	}
+}
+
+/// Location of an allocation made from a [`MemoryPool`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct SubAllocation {
+	pub block: u64,
+	pub offset: u64,
+	pub size: u64,
+}
+
+struct Block<M> {
+	id: u64,
+	memory: M,
+	free_list: FreeList,
+	allocations: usize,
+}
+
+/// Hands out ranges of large blocks so resources do not each need their own driver allocation. Requests larger
+/// than the block size get a block of their own. `M` is the back end's handle to a block.
+pub(crate) struct MemoryPool<M> {
+	block_size: u64,
+	blocks: Vec<Block<M>>,
+	next_id: u64,
+}
+
+impl<M> MemoryPool<M> {
+	pub fn new(block_size: u64) -> Self {
+		Self {
+			block_size,
+			blocks: Vec::new(),
+			next_id: 0,
+		}
+	}
+
+	/// Finds space for `size` bytes calling `create` with a block size when a new block is needed
+	pub fn allocate<E>(
+		&mut self,
+		size: u64,
+		alignment: u64,
+		create: impl FnOnce(u64) -> Result<M, E>,
+	) -> Result<(SubAllocation, &M), E> {
+		let found = self.blocks.iter_mut().enumerate().find_map(|(index, it)| {
+			it.free_list
+				.allocate(size, alignment)
+				.map(|offset| (index, offset))
+		});
+
+		let (index, offset) = match found {
+			Some(found) => found,
+			None => {
+				let block_size = self.block_size.max(size);
+				let mut free_list = FreeList::new(block_size);
+				let offset = free_list.allocate(size, alignment).unwrap();
+
+				self.blocks.push(Block {
+					id: self.next_id,
+					memory: create(block_size)?,
+					free_list,
+					allocations: 0,
+				});
+				self.next_id += 1;
+
+				(self.blocks.len() - 1, offset)
+			}
+		};
+
+		let block = &mut self.blocks[index];
+		block.allocations += 1;
+		Ok((
+			SubAllocation {
+				block: block.id,
+				offset,
+				size,
+			},
+			&block.memory,
+		))
+	}
+
+	/// Returns a block's memory once it is empty and should be released. A single empty block is kept around
+	/// so a resource being recreated every frame does not go back to the driver each time.
+	pub fn free(&mut self, allocation: SubAllocation) -> Option<M> {
+		let index = self
+			.blocks
+			.iter()
+			.position(|it| it.id == allocation.block)
+			.expect("Allocation was not made from this pool");
+
+		let block = &mut self.blocks[index];
+		block.free_list.free(allocation.offset, allocation.size);
+		block.allocations -= 1;
+
+		let oversized = block.free_list.size() > self.block_size;
+		let spare = self
+			.blocks
+			.iter()
+			.filter(|it| it.allocations == 0 && it.free_list.size() == self.block_size)
+			.count();
+		let block = &self.blocks[index];
+		if block.allocations == 0 && (oversized || spare > 1) {
+			Some(self.blocks.remove(index).memory)
+		} else {
+			None
+		}
+	}
+
+	pub fn usage(&self) -> MemoryUsage {
+		let mut result = MemoryUsage::default();
+		for it in self.blocks.iter() {
+			result.reserved += it.free_list.size();
+			result.used += it.free_list.used();
+			result.blocks += 1;
+			result.allocations += it.allocations;
+		}
+		result
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[cfg(backend = "null")]
+	use crate::{
+		test_device,
+		Buffer,
+		BufferUsage,
+		Format,
+		MemoryStats,
+		MemoryType,
+		Texture,
+		TextureUsage,
+	};
+
+	#[test]
+	fn free_list_merges() {
+		let mut list = FreeList::new(256);
+		let a = list.allocate(64, 1).unwrap();
+		let b = list.allocate(64, 1).unwrap();
+		let c = list.allocate(100, 32).unwrap();
+		assert_eq!((a, b, c), (0, 64, 128));
+		assert_eq!(list.allocate(64, 1), None);
+
+		list.free(a, 64);
+		list.free(c, 100);
+		list.free(b, 64);
+		assert_eq!(list.used(), 0);
+		assert_eq!(list.allocate(256, 1), Some(0));
+	}
+
+	#[test]
+	fn pool_reuses_blocks() {
+		let mut pool: MemoryPool<u64> = MemoryPool::new(1024);
+		let mut created = 0;
+		let mut create = |size| -> Result<u64, ()> {
+			created += 1;
+			Ok(size)
+		};
+
+		let (a, _) = pool.allocate(512, 256, &mut create).unwrap();
+		let (b, _) = pool.allocate(512, 256, &mut create).unwrap();
+		let (big, memory) = pool.allocate(4096, 256, &mut create).unwrap();
+		assert_eq!(*memory, 4096);
+		assert_eq!(a.block, b.block);
+		assert_eq!(
+			pool.usage(),
+			MemoryUsage {
+				reserved: 1024 + 4096,
+				used: 512 + 512 + 4096,
+				blocks: 2,
+				allocations: 3,
+			}
+		);
+
+		// Oversized blocks are released straight away while the last regular block is kept
+		assert_eq!(pool.free(big), Some(4096));
+		assert_eq!(pool.free(a), None);
+		assert_eq!(pool.free(b), None);
+		assert_eq!(pool.usage().blocks, 1);
+		assert_eq!(pool.usage().used, 0);
+
+		pool.allocate(1024, 256, &mut create).unwrap();
+		assert_eq!(created, 2);
+	}
+
+	#[test]
+	#[cfg(backend = "null")]
+	fn sub_allocates_and_reuses_blocks() {
+		let device = test_device();
+		assert_eq!(device.memory_stats(), MemoryStats::default());
+
+		let a: Buffer<u32> =
+			Buffer::new_in(BufferUsage::VERTEX, MemoryType::HostVisible, 16, &device).unwrap();
+		let b: Buffer<u32> =
+			Buffer::new_in(BufferUsage::INDEX, MemoryType::HostVisible, 16, &device).unwrap();
+		let texture =
+			Texture::new_in(TextureUsage::SAMPLED, Format::RGBA_U8, 4, 4, 1, &device).unwrap();
+
+		let stats = device.memory_stats();
+		assert_eq!(stats.host_visible.blocks, 1);
+		assert_eq!(stats.host_visible.allocations, 2);
+		assert_eq!(stats.host_visible.used, 128);
+		assert_eq!(stats.device_local.allocations, 1);
+
+		drop((a, b, texture));
+		let stats = device.memory_stats();
+		assert_eq!(stats.host_visible.allocations, 0);
+		assert_eq!(stats.host_visible.blocks, 1); // Kept around for the next allocation
+	}
+}
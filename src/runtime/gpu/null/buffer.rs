@@ -0,0 +1,119 @@
+use super::{
+	Device,
+	Error,
+	ResourceId,
+};
+use crate::{
+	BindlessKind,
+	BufferUsage,
+	MemoryType,
+	Result,
+	SubAllocation,
+};
+
+use std::{
+	mem::MaybeUninit,
+	sync::{
+		Arc,
+		Mutex,
+	},
+};
+
+pub struct Buffer {
+	pub owner: Arc<Device>,
+
+	pub id: ResourceId,
+	pub memory: Mutex<Vec<u8>>,
+	pub size: usize,
+
+	pub usage: BufferUsage,
+	pub memory_type: MemoryType,
+	allocation: SubAllocation,
+
+	pub bindless: Option<u32>, // Index into owner bindless buffer array
+}
+
+impl Buffer {
+	pub fn new(
+		owner: Arc<Device>,
+		usage: BufferUsage,
+		memory_type: MemoryType,
+		size: usize,
+	) -> Result<Arc<Buffer>> {
+		let bindless = if usage.intersects(BufferUsage::CONSTANTS | BufferUsage::STORAGE) {
+			Some(owner.allocate_bindless(BindlessKind::Buffer, format!("{} bytes", size))?)
+		} else {
+			None
+		};
+
+		let allocation = owner.allocate_memory(size, memory_type);
+
+		Ok(Arc::new(Buffer {
+			owner,
+
+			id: ResourceId::next(),
+			memory: Mutex::new(vec![0; size]),
+			size,
+
+			usage,
+			memory_type,
+			allocation,
+
+			bindless,
+		}))
+	}
+
+	pub fn copy_to<T>(&self, data: &[T]) -> Result<()> {
+		let len = std::mem::size_of_val(data);
+		if len > self.size {
+			return Err(Error::OutOfBounds);
+		}
+
+		let mut memory = self.memory.lock().unwrap();
+
+		// UNSAFE: Reading `len` bytes from a slice that is `len` bytes long
+		let bytes = unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, len) };
+		memory[..len].copy_from_slice(bytes);
+		Ok(())
+	}
+
+	pub fn copy_from<T>(&self, data: &mut [MaybeUninit<T>]) -> Result<()> {
+		if self.memory_type != MemoryType::HostVisible {
+			return Err(Error::NotHostVisible);
+		}
+
+		let len = std::mem::size_of_val(data);
+		if len > self.size {
+			return Err(Error::OutOfBounds);
+		}
+
+		let memory = self.memory.lock().unwrap();
+
+		// UNSAFE: Writing `len` bytes into a slice that is `len` bytes long
+		let bytes = unsafe { std::slice::from_raw_parts_mut(data.as_mut_ptr() as *mut u8, len) };
+		bytes.copy_from_slice(&memory[..len]);
+		Ok(())
+	}
+
+	pub fn bindless(&self) -> Option<u32> {
+		self.bindless
+	}
+
+	/// Debug names only show up in captures of real back ends
+	/// Debug names only show up in captures of real back ends and the bindless view
+	pub fn set_debug_name(&self, name: &str) {
+		if let Some(bindless) = self.bindless {
+			self.owner
+				.set_bindless_name(BindlessKind::Buffer, bindless, name);
+		}
+	}
+}
+
+impl Drop for Buffer {
+	fn drop(&mut self) {
+		if let Some(bindless) = self.bindless {
+			self.owner.release_bindless(BindlessKind::Buffer, bindless);
+		}
+		self.owner.free_memory(self.allocation, self.memory_type);
+	}
+}
@@ -0,0 +1,654 @@
+use super::{
+	Buffer,
+	ComputePipeline,
+	Device,
+	GraphicsPipeline,
+	ResourceId,
+	Texture,
+};
+use crate::{
+	BufferUsage,
+	ConstantMember,
+	Format,
+	Layout,
+	LoadOp,
+	PipelineResource,
+	Queries,
+	Queue,
+	RenderPassAttachment,
+	Result,
+	StoreOp,
+	TextureUsage,
+};
+
+use math::{
+	Color,
+	Rect,
+};
+
+use std::{
+	collections::HashMap,
+	sync::Arc,
+};
+
+/// Load and store ops of an attachment of a [`Command::BeginRenderPass`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedAttachment {
+	pub texture: ResourceId,
+	pub load: LoadOp,
+	pub store: StoreOp,
+	pub resolve: Option<ResourceId>,
+}
+
+/// Single command recorded by the null back end
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+	TextureBarrier {
+		texture: ResourceId,
+		old_layout: Layout,
+		new_layout: Layout,
+	},
+	BufferBarrier(ResourceId),
+	CopyBufferToTexture {
+		dst: ResourceId,
+		src: ResourceId,
+		mip_levels: u32,
+	},
+	CopyBufferToBuffer {
+		dst: ResourceId,
+		src: ResourceId,
+	},
+	CopyTextureToBuffer {
+		dst: ResourceId,
+		src: ResourceId,
+	},
+	GenerateMips(ResourceId),
+	BeginRenderPass {
+		attachments: Vec<ResourceId>,
+	},
+	EndRenderPass,
+	ClearColor(Color),
+	ClearDepth(f32),
+	BindPipeline(ResourceId),
+	BindComputePipeline(ResourceId),
+	BindScissor(Option<Rect>),
+	BindViewport(Option<Rect>),
+	BindVertexBuffer(ResourceId),
+	BindIndexBuffer(ResourceId),
+	BindConstants {
+		name: String,
+		buffer: ResourceId,
+		index: usize,
+	},
+	BindTexture {
+		name: String,
+		texture: ResourceId,
+	},
+	BindBuffer {
+		name: String,
+		buffer: ResourceId,
+	},
+	Draw {
+		vertex_count: usize,
+		first_vertex: usize,
+	},
+	DrawIndexed {
+		index_count: usize,
+		first_index: usize,
+	},
+	DrawInstanced {
+		vertex_count: usize,
+		first_vertex: usize,
+		instance_count: usize,
+		first_instance: usize,
+	},
+	DrawIndexedInstanced {
+		index_count: usize,
+		first_index: usize,
+		instance_count: usize,
+		first_instance: usize,
+	},
+	DrawIndirect {
+		buffer: ResourceId,
+		first: usize,
+		draw_count: usize,
+	},
+	DrawIndexedIndirect {
+		buffer: ResourceId,
+		first: usize,
+		draw_count: usize,
+	},
+	Dispatch {
+		x: u32,
+		y: u32,
+		z: u32,
+	},
+	BeginTimer(String),
+	EndTimer,
+	BeginStatistics(String),
+	EndStatistics,
+	PushMarker(String),
+	PopMarker,
+}
+
+// Copies are executed against host memory when the command buffer is submitted
+pub enum Transfer {
+	BufferToTexture {
+		dst: Arc<Texture>,
+		src: Arc<Buffer>,
+		size: usize,
+	},
+	BufferToBuffer {
+		dst: Arc<Buffer>,
+		src: Arc<Buffer>,
+	},
+	TextureToBuffer {
+		dst: Arc<Buffer>,
+		src: Arc<Texture>,
+	},
+	GenerateMips(Arc<Texture>),
+}
+
+impl Transfer {
+	pub fn execute(&self) {
+		let (dst, src, size) = match self {
+			Self::BufferToTexture { dst, src, size } => (&dst.memory, &src.memory, *size),
+			Self::BufferToBuffer { dst, src } => (&dst.memory, &src.memory, usize::MAX),
+			Self::TextureToBuffer { dst, src } => (&dst.memory, &src.memory, usize::MAX),
+			Self::GenerateMips(texture) => return generate_mips(texture),
+		};
+
+		let src = src.lock().unwrap().clone();
+		let mut dst = dst.lock().unwrap();
+		let len = src.len().min(dst.len()).min(size);
+		dst[..len].copy_from_slice(&src[..len]);
+	}
+}
+
+// Box filters each mip from the one above it. Only 8 bit formats are filtered, other formats are left untouched
+fn generate_mips(texture: &Texture) {
+	let channels = match texture.format {
+		Format::RGB_U8 | Format::RGB_U8_SRGB => 3,
+		Format::RGBA_U8 | Format::RGBA_U8_SRGB | Format::BGR_U8_SRGB => 4,
+		_ => return,
+	};
+
+	let extent = texture.extent;
+	let mut memory = texture.memory.lock().unwrap();
+	for mip in 1..extent.mip_levels {
+		let (src_width, src_height, _) = extent.mip_size(mip - 1);
+		let (dst_width, dst_height, _) = extent.mip_size(mip);
+		let src_offset = extent.size_in_bytes(texture.format, mip - 1);
+		let dst_offset = extent.size_in_bytes(texture.format, mip);
+
+		let src_layer_size = (src_width * src_height) as usize * channels;
+		let dst_layer_size = (dst_width * dst_height) as usize * channels;
+		for layer in 0..extent.array_layers as usize {
+			let src = src_offset + layer * src_layer_size;
+			let dst = dst_offset + layer * dst_layer_size;
+
+			for y in 0..dst_height {
+				for x in 0..dst_width {
+					for c in 0..channels {
+						let mut sum = 0;
+						for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+							let sx = (x * 2 + dx).min(src_width - 1);
+							let sy = (y * 2 + dy).min(src_height - 1);
+							let index = (sy * src_width + sx) as usize * channels + c;
+							sum += memory[src + index] as u32;
+						}
+
+						let index = (y * dst_width + x) as usize * channels + c;
+						memory[dst + index] = ((sum + 2) / 4) as u8;
+					}
+				}
+			}
+		}
+	}
+}
+
+pub enum BoundPipeline {
+	Graphics(Arc<GraphicsPipeline>),
+	Compute(Arc<ComputePipeline>),
+}
+
+impl BoundPipeline {
+	pub fn constants(&self) -> &HashMap<String, Vec<ConstantMember>> {
+		match self {
+			Self::Graphics(pipeline) => &pipeline.description.constants,
+			Self::Compute(pipeline) => &pipeline.description.constants,
+		}
+	}
+
+	pub fn resources(&self) -> &HashMap<String, PipelineResource> {
+		match self {
+			Self::Graphics(pipeline) => &pipeline.description.resources,
+			Self::Compute(pipeline) => &pipeline.description.resources,
+		}
+	}
+}
+
+pub struct GraphicsCommandBuffer {
+	pub owner: Arc<Device>,
+	pub queue: Queue,
+
+	pub commands: Vec<Command>,
+	pub transfers: Vec<Transfer>,
+	pub attachments: Vec<RecordedAttachment>, // Every attachment of every render pass in order
+
+	pub pipelines: Vec<Arc<GraphicsPipeline>>,
+	pub compute_pipelines: Vec<Arc<ComputePipeline>>,
+	pub textures: Vec<Arc<Texture>>,
+	pub buffers: Vec<Arc<Buffer>>,
+
+	pub current_attachments: Option<Vec<Arc<Texture>>>,
+	pub current_pipeline: Option<BoundPipeline>,
+
+	pub queries: Queries,
+	// Number of debug markers that have not been popped
+	pub markers: u32,
+}
+
+impl GraphicsCommandBuffer {
+	pub fn new(owner: Arc<Device>, queue: Queue) -> Result<GraphicsCommandBuffer> {
+		Ok(GraphicsCommandBuffer {
+			owner,
+			queue,
+
+			commands: Vec::new(),
+			transfers: Vec::new(),
+			attachments: Vec::new(),
+
+			pipelines: Vec::new(),
+			compute_pipelines: Vec::new(),
+			textures: Vec::new(),
+			buffers: Vec::new(),
+
+			current_attachments: None,
+			current_pipeline: None,
+
+			queries: Queries::default(),
+			markers: 0,
+		})
+	}
+
+	pub fn begin(&mut self) {
+		self.commands.clear();
+		self.transfers.clear();
+		self.attachments.clear();
+		self.queries = Queries::default();
+		self.markers = 0;
+	}
+
+	pub fn end(&mut self) {
+		assert!(
+			self.current_attachments.is_none(),
+			"Render pass must be ended before the command buffer"
+		);
+	}
+
+	pub fn begin_timer(&mut self, name: &str) {
+		self.queries.begin_timer(name);
+		self.commands.push(Command::BeginTimer(name.to_string()));
+	}
+
+	pub fn end_timer(&mut self) {
+		self.queries.end_timer();
+		self.commands.push(Command::EndTimer);
+	}
+
+	pub fn begin_statistics(&mut self, name: &str) {
+		self.queries.begin_statistics(name);
+		self.commands
+			.push(Command::BeginStatistics(name.to_string()));
+	}
+
+	pub fn end_statistics(&mut self) {
+		self.queries.end_statistics();
+		self.commands.push(Command::EndStatistics);
+	}
+
+	pub fn push_marker(&mut self, name: &str) {
+		self.markers += 1;
+		self.commands.push(Command::PushMarker(name.to_string()));
+	}
+
+	pub fn pop_marker(&mut self) {
+		assert!(self.markers > 0, "No marker was pushed");
+		self.markers -= 1;
+		self.commands.push(Command::PopMarker);
+	}
+
+	pub fn copy_buffer_to_texture(&mut self, dst: Arc<Texture>, src: Arc<Buffer>, mip_levels: u32) {
+		self.commands.push(Command::CopyBufferToTexture {
+			dst: dst.id,
+			src: src.id,
+			mip_levels,
+		});
+		let size = dst.extent.size_in_bytes(dst.format, mip_levels);
+		self.transfers
+			.push(Transfer::BufferToTexture { dst, src, size });
+	}
+
+	pub fn copy_buffer_to_buffer(&mut self, dst: Arc<Buffer>, src: Arc<Buffer>) {
+		assert_eq!(dst.size, src.size);
+
+		self.commands.push(Command::CopyBufferToBuffer {
+			dst: dst.id,
+			src: src.id,
+		});
+		self.transfers.push(Transfer::BufferToBuffer { dst, src });
+	}
+
+	pub fn copy_texture_to_buffer(&mut self, dst: Arc<Buffer>, src: Arc<Texture>) {
+		self.commands.push(Command::CopyTextureToBuffer {
+			dst: dst.id,
+			src: src.id,
+		});
+		self.transfers.push(Transfer::TextureToBuffer { dst, src });
+	}
+
+	pub fn generate_mips(&mut self, texture: Arc<Texture>) {
+		self.commands.push(Command::GenerateMips(texture.id));
+		self.transfers.push(Transfer::GenerateMips(texture.clone()));
+		self.textures.push(texture);
+	}
+
+	pub fn resource_barrier_texture(
+		&mut self,
+		texture: Arc<Texture>,
+		old_layout: Layout,
+		new_layout: Layout,
+	) {
+		self.commands.push(Command::TextureBarrier {
+			texture: texture.id,
+			old_layout,
+			new_layout,
+		});
+		self.textures.push(texture);
+	}
+
+	pub fn resource_barrier_buffer(&mut self, buffer: Arc<Buffer>) {
+		self.commands.push(Command::BufferBarrier(buffer.id));
+		self.buffers.push(buffer);
+	}
+
+	pub fn begin_render_pass(&mut self, attachments: &[RenderPassAttachment]) -> Result<()> {
+		assert!(!attachments.is_empty());
+
+		self.commands.push(Command::BeginRenderPass {
+			attachments: attachments.iter().map(|it| it.texture.id).collect(),
+		});
+		self.attachments
+			.extend(attachments.iter().map(|it| RecordedAttachment {
+				texture: it.texture.id,
+				load: it.load,
+				store: it.store,
+				resolve: it.resolve.as_ref().map(|it| it.id),
+			}));
+
+		// Clear loads are recorded as clears at the start of the pass
+		for it in attachments.iter().filter(|it| it.load == LoadOp::Clear) {
+			if it.texture.format.is_depth() {
+				self.commands.push(Command::ClearDepth(it.clear_depth));
+			} else {
+				self.commands.push(Command::ClearColor(it.clear_color));
+			}
+		}
+
+		for it in attachments.iter() {
+			self.textures.push(it.texture.clone());
+			if let Some(resolve) = &it.resolve {
+				self.textures.push(resolve.clone());
+			}
+		}
+		self.current_attachments = Some(attachments.iter().map(|it| it.texture.clone()).collect());
+
+		Ok(())
+	}
+
+	pub fn end_render_pass(&mut self) {
+		self.commands.push(Command::EndRenderPass);
+		self.current_attachments = None;
+	}
+
+	pub fn bind_scissor(&mut self, scissor: Option<Rect>) {
+		self.commands.push(Command::BindScissor(scissor));
+	}
+
+	pub fn bind_viewport(&mut self, viewport: Option<Rect>) {
+		self.commands.push(Command::BindViewport(viewport));
+	}
+
+	pub fn bind_pipeline(&mut self, pipeline: Arc<GraphicsPipeline>) {
+		self.commands.push(Command::BindPipeline(pipeline.id));
+		self.current_pipeline = Some(BoundPipeline::Graphics(pipeline.clone()));
+		self.pipelines.push(pipeline);
+	}
+
+	pub fn bind_compute_pipeline(&mut self, pipeline: Arc<ComputePipeline>) {
+		assert!(
+			self.current_attachments.is_none(),
+			"ComputePipeline can not be bound in a render pass"
+		);
+		self.commands
+			.push(Command::BindComputePipeline(pipeline.id));
+		self.current_pipeline = Some(BoundPipeline::Compute(pipeline.clone()));
+		self.compute_pipelines.push(pipeline);
+	}
+
+	pub fn end_compute_pass(&mut self) {
+		self.current_pipeline = None;
+	}
+
+	pub fn bind_vertex_buffer(&mut self, buffer: Arc<Buffer>) {
+		self.commands.push(Command::BindVertexBuffer(buffer.id));
+		self.buffers.push(buffer);
+	}
+
+	pub fn bind_index_buffer(&mut self, buffer: Arc<Buffer>) {
+		self.commands.push(Command::BindIndexBuffer(buffer.id));
+		self.buffers.push(buffer);
+	}
+
+	pub fn bind_constants(&mut self, name: &str, buffer: Arc<Buffer>, index: usize) {
+		let current_pipeline = self
+			.current_pipeline
+			.as_ref()
+			.expect("Pipeline must be bound to bind constants");
+		assert!(
+			current_pipeline.constants().contains_key(name),
+			"Pipeline has no constants named \"{}\"",
+			name
+		);
+		buffer
+			.bindless()
+			.expect("Buffer can only be bound if usage is marked with CONSTANTS");
+
+		self.commands.push(Command::BindConstants {
+			name: name.to_string(),
+			buffer: buffer.id,
+			index,
+		});
+		self.buffers.push(buffer);
+	}
+
+	pub fn bind_texture(&mut self, name: &str, texture: Arc<Texture>) {
+		let current_pipeline = self
+			.current_pipeline
+			.as_ref()
+			.expect("Pipeline must be bound to bind textures");
+		match current_pipeline.resources().get(name) {
+			Some(PipelineResource::Texture) => {
+				assert!(
+					texture.usage.contains(TextureUsage::SAMPLED),
+					"Texture can only be bound if usage is marked with SAMPLED"
+				);
+				assert!(
+					texture.extent.array_layers == 1,
+					"Texture arrays and cube maps can not be bound as a Texture"
+				);
+			}
+			Some(PipelineResource::TextureArray) => {
+				assert!(
+					texture.usage.contains(TextureUsage::SAMPLED),
+					"Texture can only be bound if usage is marked with SAMPLED"
+				);
+				assert!(
+					texture.extent.array_layers > 1 && !texture.extent.cube,
+					"Only textures with multiple array layers can be bound as a TextureArray"
+				);
+			}
+			Some(PipelineResource::TextureCube) => {
+				assert!(
+					texture.usage.contains(TextureUsage::SAMPLED),
+					"Texture can only be bound if usage is marked with SAMPLED"
+				);
+				assert!(
+					texture.extent.cube,
+					"Only cube maps can be bound as a TextureCube"
+				);
+			}
+			Some(PipelineResource::StorageTexture) => assert!(
+				texture.usage.contains(TextureUsage::STORAGE),
+				"Texture can only be bound as storage if usage is marked with STORAGE"
+			),
+			_ => panic!("Pipeline has no texture named \"{}\"", name),
+		}
+
+		self.commands.push(Command::BindTexture {
+			name: name.to_string(),
+			texture: texture.id,
+		});
+		self.textures.push(texture);
+	}
+
+	pub fn bind_buffer(&mut self, name: &str, buffer: Arc<Buffer>) {
+		let current_pipeline = self
+			.current_pipeline
+			.as_ref()
+			.expect("Pipeline must be bound to bind buffers");
+		assert!(
+			matches!(
+				current_pipeline.resources().get(name),
+				Some(PipelineResource::StorageBuffer)
+			),
+			"Pipeline has no storage buffer named \"{}\"",
+			name
+		);
+		assert!(
+			buffer.usage.contains(BufferUsage::STORAGE),
+			"Buffer can only be bound as storage if usage is marked with STORAGE"
+		);
+
+		self.commands.push(Command::BindBuffer {
+			name: name.to_string(),
+			buffer: buffer.id,
+		});
+		self.buffers.push(buffer);
+	}
+
+	pub fn draw(&mut self, vertex_count: usize, first_vertex: usize) {
+		assert!(
+			matches!(self.current_pipeline, Some(BoundPipeline::Graphics(_))),
+			"GraphicsPipeline must be bound to draw"
+		);
+		self.commands.push(Command::Draw {
+			vertex_count,
+			first_vertex,
+		});
+	}
+
+	pub fn draw_indexed(&mut self, index_count: usize, first_index: usize) {
+		assert!(
+			matches!(self.current_pipeline, Some(BoundPipeline::Graphics(_))),
+			"GraphicsPipeline must be bound to draw"
+		);
+		self.commands.push(Command::DrawIndexed {
+			index_count,
+			first_index,
+		});
+	}
+
+	pub fn draw_instanced(
+		&mut self,
+		vertex_count: usize,
+		first_vertex: usize,
+		instance_count: usize,
+		first_instance: usize,
+	) {
+		assert!(
+			matches!(self.current_pipeline, Some(BoundPipeline::Graphics(_))),
+			"GraphicsPipeline must be bound to draw"
+		);
+		self.commands.push(Command::DrawInstanced {
+			vertex_count,
+			first_vertex,
+			instance_count,
+			first_instance,
+		});
+	}
+
+	pub fn draw_indexed_instanced(
+		&mut self,
+		index_count: usize,
+		first_index: usize,
+		instance_count: usize,
+		first_instance: usize,
+	) {
+		assert!(
+			matches!(self.current_pipeline, Some(BoundPipeline::Graphics(_))),
+			"GraphicsPipeline must be bound to draw"
+		);
+		self.commands.push(Command::DrawIndexedInstanced {
+			index_count,
+			first_index,
+			instance_count,
+			first_instance,
+		});
+	}
+
+	pub fn draw_indirect(&mut self, buffer: Arc<Buffer>, first: usize, draw_count: usize) {
+		assert!(
+			matches!(self.current_pipeline, Some(BoundPipeline::Graphics(_))),
+			"GraphicsPipeline must be bound to draw"
+		);
+		self.commands.push(Command::DrawIndirect {
+			buffer: buffer.id,
+			first,
+			draw_count,
+		});
+		self.buffers.push(buffer);
+	}
+
+	pub fn draw_indexed_indirect(&mut self, buffer: Arc<Buffer>, first: usize, draw_count: usize) {
+		assert!(
+			matches!(self.current_pipeline, Some(BoundPipeline::Graphics(_))),
+			"GraphicsPipeline must be bound to draw"
+		);
+		self.commands.push(Command::DrawIndexedIndirect {
+			buffer: buffer.id,
+			first,
+			draw_count,
+		});
+		self.buffers.push(buffer);
+	}
+
+	pub fn dispatch(&mut self, x: u32, y: u32, z: u32) {
+		assert!(
+			matches!(self.current_pipeline, Some(BoundPipeline::Compute(_))),
+			"ComputePipeline must be bound to dispatch"
+		);
+		self.commands.push(Command::Dispatch { x, y, z });
+	}
+
+	pub fn clear_color(&mut self, color: Color) {
+		assert!(self.current_attachments.is_some());
+		self.commands.push(Command::ClearColor(color));
+	}
+
+	pub fn clear_depth(&mut self, depth: f32) {
+		assert!(self.current_attachments.is_some());
+		self.commands.push(Command::ClearDepth(depth));
+	}
+}
@@ -0,0 +1,34 @@
+use super::{
+	Device,
+	ResourceId,
+};
+use crate::{
+	ComputePipelineDescription,
+	Result,
+};
+
+use std::sync::Arc;
+
+pub struct ComputePipeline {
+	pub id: ResourceId,
+
+	pub description: ComputePipelineDescription,
+}
+
+impl ComputePipeline {
+	pub fn new(
+		_owner: Arc<Device>,
+		description: ComputePipelineDescription,
+	) -> Result<Arc<ComputePipeline>> {
+		assert!(description.push_constant_size() <= 128); // Min push contsant size
+
+		Ok(Arc::new(ComputePipeline {
+			id: ResourceId::next(),
+
+			description,
+		}))
+	}
+
+	/// Debug names only show up in captures of real back ends
+	pub fn set_debug_name(&self, _name: &str) {}
+}
@@ -0,0 +1,308 @@
+use super::{
+	Command,
+	Error,
+	GraphicsCommandBuffer,
+	Instance,
+	Receipt,
+	Texture,
+};
+use crate::{
+	BindlessKind,
+	BindlessView,
+	Format,
+	GpuReport,
+	MemoryPool,
+	MemoryStats,
+	MemoryType,
+	PresentMode,
+	Queue,
+	Result,
+	SubAllocation,
+	TextureExtent,
+	TextureUsage,
+};
+
+use std::sync::{
+	Arc,
+	Mutex,
+};
+
+use os::Window;
+
+use crate::bindless::{
+	BindlessHeap,
+	BINDLESS_CAPACITY,
+};
+use crate::memory::{
+	BLOCK_SIZE,
+	DEFAULT_ALIGNMENT,
+};
+
+pub struct BindlessInfo {
+	pub textures: BindlessHeap<()>,
+	pub buffers: BindlessHeap<()>,
+}
+
+pub struct Device {
+	pub has_surface: bool,
+	// None while the window is minimized
+	backbuffer: Mutex<Option<Arc<Texture>>>,
+	acquired: Mutex<bool>,
+	present_mode: Mutex<PresentMode>,
+	frames_in_flight: Mutex<usize>,
+
+	pub bindless_info: Mutex<BindlessInfo>,
+
+	// Host memory is kept per resource but still sub allocated so statistics match the other back ends
+	host_visible: Mutex<MemoryPool<()>>,
+	device_local: Mutex<MemoryPool<()>>,
+
+	// Nothing is compiled so the cache just round trips whatever was loaded
+	pipeline_cache: Mutex<Vec<u8>>,
+
+	// Commands of every submitted command buffer in submission order
+	pub submitted: Mutex<Vec<Vec<Command>>>,
+	// Bindless slots written by each submission and whether they held a resource or were written with null
+	bindless_writes: Mutex<Vec<(BindlessKind, u32, bool)>>,
+	report: Mutex<GpuReport>,
+}
+
+impl Device {
+	pub fn new(_instance: Arc<Instance>, window: Option<&Window>) -> Result<Arc<Self>> {
+		let result = Arc::new(Device {
+			has_surface: window.is_some(),
+			backbuffer: Mutex::new(None),
+			acquired: Mutex::new(false),
+			present_mode: Mutex::new(PresentMode::Fifo),
+			frames_in_flight: Mutex::new(2),
+
+			bindless_info: Mutex::new(BindlessInfo {
+				textures: BindlessHeap::new(BINDLESS_CAPACITY),
+				buffers: BindlessHeap::new(BINDLESS_CAPACITY),
+			}),
+
+			host_visible: Mutex::new(MemoryPool::new(BLOCK_SIZE)),
+			device_local: Mutex::new(MemoryPool::new(BLOCK_SIZE)),
+
+			pipeline_cache: Mutex::new(Vec::new()),
+
+			submitted: Mutex::new(Vec::new()),
+			bindless_writes: Mutex::new(Vec::new()),
+			report: Mutex::new(GpuReport::default()),
+		});
+
+		// Create a single backbuffer matching the size of the window
+		if let Some(window) = window {
+			let size = window.inner_size();
+			*result.backbuffer.lock().unwrap() =
+				Self::create_backbuffer(&result, size.width, size.height)?;
+		}
+
+		Ok(result)
+	}
+
+	fn create_backbuffer(
+		device: &Arc<Self>,
+		width: u32,
+		height: u32,
+	) -> Result<Option<Arc<Texture>>> {
+		if width == 0 || height == 0 {
+			return Ok(None);
+		}
+
+		let backbuffer = Texture::new(
+			device.clone(),
+			MemoryType::HostVisible,
+			TextureUsage::SWAPCHAIN,
+			Format::BGR_U8_SRGB,
+			TextureExtent::new(width, height, 1),
+		)?;
+		Ok(Some(backbuffer))
+	}
+
+	pub(crate) fn allocate_bindless(&self, kind: BindlessKind, name: String) -> Result<u32> {
+		let mut bindless = self.bindless_info.lock().unwrap();
+		let heap = match kind {
+			BindlessKind::Texture => &mut bindless.textures,
+			BindlessKind::Buffer => &mut bindless.buffers,
+			BindlessKind::Sampler => unreachable!("The null back end has no samplers"),
+		};
+		heap.allocate((), name).ok_or(Error::OutOfBindlessSlots)
+	}
+
+	// Work finishes before submit returns so released slots are recycled by the next submission
+	pub(crate) fn release_bindless(&self, kind: BindlessKind, index: u32) {
+		let mut bindless = self.bindless_info.lock().unwrap();
+		match kind {
+			BindlessKind::Texture => bindless.textures.release(index, 0),
+			BindlessKind::Buffer => bindless.buffers.release(index, 0),
+			BindlessKind::Sampler => unreachable!("The null back end has no samplers"),
+		}
+	}
+
+	pub(crate) fn set_bindless_name(&self, kind: BindlessKind, index: u32, name: &str) {
+		let mut bindless = self.bindless_info.lock().unwrap();
+		match kind {
+			BindlessKind::Texture => bindless.textures.set_name(index, name),
+			BindlessKind::Buffer => bindless.buffers.set_name(index, name),
+			BindlessKind::Sampler => unreachable!("The null back end has no samplers"),
+		}
+	}
+
+	pub fn bindless_view(&self) -> BindlessView {
+		let bindless = self.bindless_info.lock().unwrap();
+		let slots = bindless
+			.buffers
+			.view(BindlessKind::Buffer)
+			.chain(bindless.textures.view(BindlessKind::Texture))
+			.collect();
+		let retired = bindless.buffers.retired() + bindless.textures.retired();
+
+		BindlessView { slots, retired }
+	}
+
+	fn pool(&self, memory_type: MemoryType) -> &Mutex<MemoryPool<()>> {
+		match memory_type {
+			MemoryType::HostVisible => &self.host_visible,
+			MemoryType::DeviceLocal => &self.device_local,
+		}
+	}
+
+	pub(crate) fn allocate_memory(&self, size: usize, memory_type: MemoryType) -> SubAllocation {
+		let mut pool = self.pool(memory_type).lock().unwrap();
+		let result: std::result::Result<_, ()> =
+			pool.allocate(size as u64, DEFAULT_ALIGNMENT, |_| Ok(()));
+		result.unwrap().0
+	}
+
+	pub(crate) fn free_memory(&self, allocation: SubAllocation, memory_type: MemoryType) {
+		self.pool(memory_type).lock().unwrap().free(allocation);
+	}
+
+	pub fn memory_stats(&self) -> MemoryStats {
+		MemoryStats {
+			host_visible: self.host_visible.lock().unwrap().usage(),
+			device_local: self.device_local.lock().unwrap().usage(),
+		}
+	}
+
+	pub fn acquire_backbuffer(&self) -> Result<Option<Arc<Texture>>> {
+		assert!(self.has_surface);
+
+		let backbuffer = self.backbuffer.lock().unwrap().clone();
+		*self.acquired.lock().unwrap() = backbuffer.is_some();
+		Ok(backbuffer)
+	}
+
+	// Uploads are finished by the time they are submitted
+	pub fn wait_before_rendering(&self, _receipt: &Receipt) {}
+
+	pub fn submit(
+		&self,
+		command_buffers: Vec<GraphicsCommandBuffer>,
+		_wait_on: &[Receipt],
+	) -> Receipt {
+		let queue = command_buffers[0].queue;
+		assert!(
+			command_buffers.iter().all(|it| it.queue == queue),
+			"Command buffers submitted together must be recorded for the same queue"
+		);
+
+		// Every queue executes work in order on the calling thread so waiting is never needed
+		let mut submitted = self.submitted.lock().unwrap();
+		let mut report = GpuReport::default();
+		for it in command_buffers.into_iter() {
+			it.transfers.iter().for_each(|transfer| transfer.execute());
+
+			// Nothing runs on a GPU so every query reads as zero
+			let timestamps = vec![0; it.queries.timestamps() as usize];
+			let statistics = vec![[0; 5]; it.queries.statistics() as usize];
+			report.append(it.queries.resolve(&timestamps, &statistics));
+
+			submitted.push(it.commands);
+		}
+		if !report.is_empty() {
+			*self.report.lock().unwrap() = report;
+		}
+
+		// Only slots that changed are written like the descriptors of other back ends
+		let mut bindless = self.bindless_info.lock().unwrap();
+		let bindless = &mut *bindless;
+		let mut writes = self.bindless_writes.lock().unwrap();
+		for (kind, heap) in [
+			(BindlessKind::Buffer, &mut bindless.buffers),
+			(BindlessKind::Texture, &mut bindless.textures),
+		] {
+			for index in heap.take_dirty() {
+				writes.push((kind, index, heap.get(index).is_some()));
+			}
+			heap.recycle(usize::MAX);
+		}
+		Receipt
+	}
+
+	pub fn display(&self, _wait_on: &[Receipt]) {
+		assert!(self.has_surface);
+
+		let mut acquired = self.acquired.lock().unwrap();
+		assert!(*acquired, "Backbuffer was not acquired");
+		*acquired = false;
+	}
+
+	pub fn resize(self: &Arc<Self>, width: u32, height: u32) {
+		if !self.has_surface {
+			return;
+		}
+
+		*self.backbuffer.lock().unwrap() =
+			Self::create_backbuffer(self, width, height).expect("Failed to create backbuffer");
+	}
+
+	pub fn present_mode(&self) -> PresentMode {
+		*self.present_mode.lock().unwrap()
+	}
+
+	pub fn set_present_mode(&self, present_mode: PresentMode) {
+		*self.present_mode.lock().unwrap() = present_mode;
+	}
+
+	pub fn frames_in_flight(&self) -> usize {
+		*self.frames_in_flight.lock().unwrap()
+	}
+
+	pub fn set_frames_in_flight(&self, frames_in_flight: usize) {
+		*self.frames_in_flight.lock().unwrap() = frames_in_flight;
+	}
+
+	/// Every queue shares the single in order queue of the null back end
+	pub fn has_dedicated_queue(&self, queue: Queue) -> bool {
+		queue == Queue::Graphics
+	}
+
+	pub fn wait_for_idle(&self) {}
+
+	pub fn wait_for_crash_dump(&self) {}
+
+	pub fn load_pipeline_cache(&self, data: &[u8]) -> Result<()> {
+		*self.pipeline_cache.lock().unwrap() = data.to_vec();
+		Ok(())
+	}
+
+	pub fn pipeline_cache_data(&self) -> Result<Vec<u8>> {
+		Ok(self.pipeline_cache.lock().unwrap().clone())
+	}
+
+	pub fn last_report(&self) -> GpuReport {
+		self.report.lock().unwrap().clone()
+	}
+
+	/// Returns the bindless slots written since the last call. The flag is false for released slots
+	pub fn take_bindless_writes(&self) -> Vec<(BindlessKind, u32, bool)> {
+		std::mem::take(&mut *self.bindless_writes.lock().unwrap())
+	}
+
+	/// Returns the commands of every command buffer submitted since the last call
+	pub fn take_submitted(&self) -> Vec<Vec<Command>> {
+		std::mem::take(&mut *self.submitted.lock().unwrap())
+	}
+}
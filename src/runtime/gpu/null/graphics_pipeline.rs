@@ -0,0 +1,46 @@
+use super::{
+	Device,
+	ResourceId,
+};
+use crate::{
+	GraphicsPipelineDescription,
+	Result,
+};
+
+use std::collections::HashMap;
+use std::sync::{
+	Arc,
+	Mutex,
+};
+
+pub struct GraphicsPipeline {
+	pub owner: Arc<Device>,
+	pub id: ResourceId,
+
+	pub description: GraphicsPipelineDescription,
+	// Variants keyed by the hash of their description
+	pub variants: Mutex<HashMap<u64, Arc<GraphicsPipeline>>>,
+}
+
+impl GraphicsPipeline {
+	/// `base` is the pipeline a variant is derived from
+	pub fn new(
+		owner: Arc<Device>,
+		description: GraphicsPipelineDescription,
+		_base: Option<&GraphicsPipeline>,
+	) -> Result<Arc<GraphicsPipeline>> {
+		assert!(!description.shaders.is_empty());
+		assert!(description.push_constant_size() <= 128); // Min push contsant size
+
+		Ok(Arc::new(GraphicsPipeline {
+			owner,
+			id: ResourceId::next(),
+
+			description,
+			variants: Mutex::new(HashMap::new()),
+		}))
+	}
+
+	/// Debug names only show up in captures of real back ends
+	pub fn set_debug_name(&self, _name: &str) {}
+}
@@ -0,0 +1,14 @@
+use crate::InstanceCreateError;
+
+use std::sync::Arc;
+
+pub struct Instance;
+
+impl Instance {
+	pub fn new(
+		_validation: bool,
+		_crash_diagnostics: bool,
+	) -> Result<Arc<Self>, InstanceCreateError> {
+		Ok(Arc::new(Self))
+	}
+}
@@ -0,0 +1,68 @@
+use std::{
+	error,
+	fmt,
+	sync::atomic::{
+		AtomicU64,
+		Ordering,
+	},
+};
+
+mod instance;
+pub use instance::*;
+
+mod device;
+pub use device::*;
+
+mod receipt;
+pub use receipt::*;
+
+mod buffer;
+pub use buffer::*;
+
+mod texture;
+pub use texture::*;
+
+mod shader;
+pub use shader::*;
+
+mod graphics_pipeline;
+pub use graphics_pipeline::*;
+
+mod compute_pipeline;
+pub use compute_pipeline::*;
+
+mod command_buffer;
+pub use command_buffer::*;
+
+/// Unique id given to every resource created by the null back end. Used to identify resources in the [`Command`] log.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ResourceId(pub u64);
+
+impl ResourceId {
+	pub(crate) fn next() -> Self {
+		static LAST: AtomicU64 = AtomicU64::new(0);
+		Self(LAST.fetch_add(1, Ordering::Relaxed))
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/// Tried to write more bytes into a resource than it can hold
+	OutOfBounds,
+	/// Tried to map a resource that was not created with [`crate::MemoryType::HostVisible`]
+	NotHostVisible,
+	/// Every bindless slot is in use or waiting to be recycled
+	OutOfBindlessSlots,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::OutOfBounds => write!(f, "write is larger than the resource"),
+			Self::NotHostVisible => write!(f, "resource memory is not host visible"),
+			Self::OutOfBindlessSlots => write!(f, "no bindless slots are free"),
+		}
+	}
+}
+
+impl error::Error for Error {}
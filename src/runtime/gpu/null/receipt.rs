@@ -0,0 +1,13 @@
+/// Work submitted to the null back end is executed during submission so every receipt is already finished
+#[derive(Clone)]
+pub struct Receipt;
+
+impl Receipt {
+	pub fn wait(self) -> bool {
+		true
+	}
+
+	pub fn is_finished(&self) -> bool {
+		true
+	}
+}
@@ -0,0 +1,26 @@
+use super::Device;
+use crate::{
+	Result,
+	ShaderVariant,
+};
+
+use std::sync::Arc;
+
+pub struct Shader {
+	pub variant: ShaderVariant,
+	pub binary: Vec<u8>,
+}
+
+impl Shader {
+	pub fn new(
+		_owner: Arc<Device>,
+		binary: &[u8],
+		variant: ShaderVariant,
+		_main: String,
+	) -> Result<Arc<Shader>> {
+		Ok(Arc::new(Shader {
+			variant,
+			binary: binary.to_vec(),
+		}))
+	}
+}
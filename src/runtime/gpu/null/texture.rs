@@ -0,0 +1,113 @@
+use super::{
+	Device,
+	ResourceId,
+};
+use crate::{
+	BindlessKind,
+	Format,
+	MemoryType,
+	Result,
+	SubAllocation,
+	TextureExtent,
+	TextureUsage,
+};
+
+use std::sync::{
+	Arc,
+	Mutex,
+};
+
+pub struct Texture {
+	pub owner: Arc<Device>,
+
+	pub id: ResourceId,
+	pub memory: Mutex<Vec<u8>>,
+
+	pub memory_type: MemoryType,
+	allocation: Option<SubAllocation>, // Swapchain textures are not allocated from the device
+
+	pub usage: TextureUsage,
+	pub format: Format,
+
+	pub extent: TextureExtent,
+
+	// Index into the devices bindless array
+	pub bindless: Option<u32>,
+}
+
+impl Texture {
+	pub fn new(
+		owner: Arc<Device>,
+		memory_type: MemoryType,
+		usage: TextureUsage,
+		format: Format,
+		extent: TextureExtent,
+	) -> Result<Arc<Texture>> {
+		let size = extent.size_in_bytes(format, extent.mip_levels);
+		let allocation = if usage.contains(TextureUsage::SWAPCHAIN) {
+			None
+		} else {
+			Some(owner.allocate_memory(size, memory_type))
+		};
+
+		let bindless = if usage.intersects(TextureUsage::SAMPLED | TextureUsage::STORAGE) {
+			let name = format!("{:?} {}x{}", format, extent.width, extent.height);
+			Some(owner.allocate_bindless(BindlessKind::Texture, name)?)
+		} else {
+			None
+		};
+
+		Ok(Arc::new(Texture {
+			owner,
+
+			id: ResourceId::next(),
+			memory: Mutex::new(vec![0; size]),
+
+			memory_type,
+			allocation,
+
+			usage,
+			format,
+
+			extent,
+
+			bindless,
+		}))
+	}
+
+	pub fn format(&self) -> Format {
+		self.format
+	}
+	pub fn width(&self) -> u32 {
+		self.extent.width
+	}
+	pub fn height(&self) -> u32 {
+		self.extent.height
+	}
+	pub fn depth(&self) -> u32 {
+		self.extent.depth
+	}
+
+	pub fn bindless(&self) -> Option<u32> {
+		self.bindless
+	}
+
+	/// Debug names only show up in captures of real back ends and the bindless view
+	pub fn set_debug_name(&self, name: &str) {
+		if let Some(bindless) = self.bindless {
+			self.owner
+				.set_bindless_name(BindlessKind::Texture, bindless, name);
+		}
+	}
+}
+
+impl Drop for Texture {
+	fn drop(&mut self) {
+		if let Some(bindless) = self.bindless {
+			self.owner.release_bindless(BindlessKind::Texture, bindless);
+		}
+		if let Some(allocation) = self.allocation {
+			self.owner.free_memory(allocation, self.memory_type);
+		}
+	}
+}
@@ -0,0 +1,260 @@
+use std::{
+	fmt,
+	time::Duration,
+};
+
+/// GPU time spent between [`crate::GraphicsRecorder::begin_timer`] and [`crate::GraphicsRecorder::end_timer`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct GpuTiming {
+	pub name: String,
+	/// Number of timers this timer was nested in
+	pub depth: u32,
+	pub duration: Duration,
+}
+
+/// Counters collected between [`crate::GraphicsRecorder::begin_statistics`] and
+/// [`crate::GraphicsRecorder::end_statistics`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PipelineStatistics {
+	pub name: String,
+	pub input_primitives: u64,
+	pub vertex_invocations: u64,
+	pub clipping_primitives: u64,
+	pub fragment_invocations: u64,
+	pub compute_invocations: u64,
+}
+
+/// Timers and pipeline statistics of a submission that finished executing. See [`crate::Device::last_report`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GpuReport {
+	/// Every timer in the order it was begun
+	pub timings: Vec<GpuTiming>,
+	pub statistics: Vec<PipelineStatistics>,
+}
+
+impl GpuReport {
+	/// Total time of every timer named `name`
+	pub fn time(&self, name: &str) -> Duration {
+		self.timings
+			.iter()
+			.filter(|it| it.name == name)
+			.map(|it| it.duration)
+			.sum()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.timings.is_empty() && self.statistics.is_empty()
+	}
+
+	pub fn append(&mut self, mut other: GpuReport) {
+		self.timings.append(&mut other.timings);
+		self.statistics.append(&mut other.statistics);
+	}
+}
+
+impl fmt::Display for GpuReport {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for it in self.timings.iter() {
+			let indent = it.depth as usize * 2;
+			writeln!(
+				f,
+				"{:indent$}{:<width$} {:>8.3}ms",
+				"",
+				it.name,
+				it.duration.as_secs_f64() * 1000.0,
+				indent = indent,
+				width = 32 - indent.min(32)
+			)?;
+		}
+		for it in self.statistics.iter() {
+			writeln!(
+				f,
+				"{}: {} primitives, {} vertices, {} clipped, {} fragments, {} compute",
+				it.name,
+				it.input_primitives,
+				it.vertex_invocations,
+				it.clipping_primitives,
+				it.fragment_invocations,
+				it.compute_invocations
+			)?;
+		}
+		Ok(())
+	}
+}
+
+pub(crate) struct TimerQuery {
+	name: String,
+	depth: u32,
+	begin: u32,
+	end: u32,
+}
+
+/// Tracks the queries recorded into a command buffer. Back ends write timestamp and statistics queries at the
+/// indices handed out here and turn the raw results into a [`GpuReport`] once the command buffer has finished.
+#[derive(Default)]
+pub(crate) struct Queries {
+	timers: Vec<TimerQuery>,
+	open_timers: Vec<usize>,
+	timestamps: u32,
+
+	statistics: Vec<String>,
+	open_statistics: bool,
+}
+
+impl Queries {
+	/// Returns the index of the timestamp written at the start of the timer
+	pub fn begin_timer(&mut self, name: &str) -> u32 {
+		let begin = self.timestamps;
+		self.timestamps += 1;
+
+		self.open_timers.push(self.timers.len());
+		self.timers.push(TimerQuery {
+			name: name.to_string(),
+			depth: self.open_timers.len() as u32 - 1,
+			begin,
+			end: begin,
+		});
+		begin
+	}
+
+	/// Returns the index of the timestamp written at the end of the innermost open timer
+	pub fn end_timer(&mut self) -> u32 {
+		let index = self.open_timers.pop().expect("No timer was begun");
+
+		let end = self.timestamps;
+		self.timestamps += 1;
+
+		self.timers[index].end = end;
+		end
+	}
+
+	/// Returns the index of the statistics query
+	pub fn begin_statistics(&mut self, name: &str) -> u32 {
+		assert!(
+			!self.open_statistics,
+			"Pipeline statistics can not be nested"
+		);
+		self.open_statistics = true;
+
+		self.statistics.push(name.to_string());
+		self.statistics.len() as u32 - 1
+	}
+
+	pub fn end_statistics(&mut self) -> u32 {
+		assert!(self.open_statistics, "No pipeline statistics were begun");
+		self.open_statistics = false;
+
+		self.statistics.len() as u32 - 1
+	}
+
+	/// Number of timestamps written so far
+	pub fn timestamps(&self) -> u32 {
+		self.timestamps
+	}
+
+	/// Number of statistics queries begun so far
+	pub fn statistics(&self) -> u32 {
+		self.statistics.len() as u32
+	}
+
+	pub fn assert_closed(&self) {
+		assert!(
+			self.open_timers.is_empty(),
+			"Every timer must be ended before the command buffer is finished"
+		);
+		assert!(
+			!self.open_statistics,
+			"Pipeline statistics must be ended before the command buffer is finished"
+		);
+	}
+
+	/// Builds a report from raw timestamps in nanoseconds and the five counters of every statistics query
+	pub fn resolve(&self, timestamps: &[u64], statistics: &[[u64; 5]]) -> GpuReport {
+		let timings = self
+			.timers
+			.iter()
+			.map(|it| {
+				let begin = timestamps[it.begin as usize];
+				let end = timestamps[it.end as usize];
+				GpuTiming {
+					name: it.name.clone(),
+					depth: it.depth,
+					duration: Duration::from_nanos(end.saturating_sub(begin)),
+				}
+			})
+			.collect();
+
+		let statistics = self
+			.statistics
+			.iter()
+			.zip(statistics.iter())
+			.map(|(name, counters)| PipelineStatistics {
+				name: name.clone(),
+				input_primitives: counters[0],
+				vertex_invocations: counters[1],
+				clipping_primitives: counters[2],
+				fragment_invocations: counters[3],
+				compute_invocations: counters[4],
+			})
+			.collect();
+
+		GpuReport {
+			timings,
+			statistics,
+		}
+	}
+}
+
+#[cfg(all(test, backend = "null"))]
+mod tests {
+	use crate::*;
+
+	#[test]
+	fn reports_timers_and_statistics() {
+		let device = test_device();
+
+		GraphicsRecorder::new_in(&device)
+			.begin_timer("frame")
+			.begin_timer("shadow")
+			.end_timer()
+			.begin_statistics("scene")
+			.end_statistics()
+			.end_timer()
+			.submit();
+
+		let report = device.last_report();
+		assert_eq!(
+			report
+				.timings
+				.iter()
+				.map(|it| (it.name.as_str(), it.depth))
+				.collect::<Vec<_>>(),
+			vec![("frame", 0), ("shadow", 1)]
+		);
+		assert_eq!(report.statistics.len(), 1);
+		assert_eq!(report.statistics[0].name, "scene");
+		assert_eq!(report.time("shadow"), std::time::Duration::ZERO);
+
+		// Submissions without queries keep the last report and newer ones replace it
+		GraphicsRecorder::new_in(&device).submit();
+		assert_eq!(device.last_report(), report);
+
+		GraphicsRecorder::new_in(&device)
+			.begin_timer("next")
+			.end_timer()
+			.submit();
+		let report = device.last_report();
+		assert_eq!(report.timings.len(), 1);
+		assert_eq!(report.timings[0].name, "next");
+		assert!(report.statistics.is_empty());
+	}
+
+	#[test]
+	#[should_panic(expected = "Every timer must be ended")]
+	fn timers_must_be_ended() {
+		let device = test_device();
+		GraphicsRecorder::new_in(&device)
+			.begin_timer("frame")
+			.finish();
+	}
+}
@@ -0,0 +1,184 @@
+//! Minimal SPIR-V reflection used to validate generated shaders against their pipeline description
+
+use crate::Constant;
+
+use std::collections::HashMap;
+
+const MAGIC: u32 = 0x0723_0203;
+
+const OP_DECORATE: u32 = 71;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_VARIABLE: u32 = 59;
+
+const DECORATION_BUILT_IN: u32 = 11;
+const DECORATION_LOCATION: u32 = 30;
+
+const STORAGE_CLASS_INPUT: u32 = 1;
+
+#[derive(Copy, Clone)]
+enum Type {
+	Int { signed: bool },
+	Float,
+	Vector { component: u32, count: u32 },
+	Matrix { column: u32, count: u32 },
+	Pointer { storage_class: u32, pointee: u32 },
+}
+
+/// Returns the location and type of every stage input that is not a built in. Returns `None` if `binary` is
+/// not SPIR-V, which is the case on the null back end.
+pub(crate) fn stage_inputs(binary: &[u8]) -> Option<Vec<(u32, Constant)>> {
+	if !binary.len().is_multiple_of(4) || binary.len() < 20 {
+		return None;
+	}
+	let words: Vec<u32> = binary
+		.chunks_exact(4)
+		.map(|it| u32::from_le_bytes([it[0], it[1], it[2], it[3]]))
+		.collect();
+	if words[0] != MAGIC {
+		return None;
+	}
+
+	let mut types = HashMap::new();
+	let mut locations = HashMap::new();
+	let mut built_ins = Vec::new();
+	let mut inputs = Vec::new();
+
+	let mut index = 5;
+	while index < words.len() {
+		let count = (words[index] >> 16) as usize;
+		let opcode = words[index] & 0xffff;
+		if count == 0 || index + count > words.len() {
+			return None;
+		}
+		let operands = &words[index + 1..index + count];
+
+		match (opcode, operands) {
+			(OP_DECORATE, [target, DECORATION_LOCATION, location, ..]) => {
+				locations.insert(*target, *location);
+			}
+			(OP_DECORATE, [target, DECORATION_BUILT_IN, ..]) => built_ins.push(*target),
+			(OP_TYPE_INT, [result, 32, signed]) => {
+				types.insert(
+					*result,
+					Type::Int {
+						signed: *signed != 0,
+					},
+				);
+			}
+			(OP_TYPE_FLOAT, [result, 32]) => {
+				types.insert(*result, Type::Float);
+			}
+			(OP_TYPE_VECTOR, [result, component, count]) => {
+				types.insert(
+					*result,
+					Type::Vector {
+						component: *component,
+						count: *count,
+					},
+				);
+			}
+			(OP_TYPE_MATRIX, [result, column, count]) => {
+				types.insert(
+					*result,
+					Type::Matrix {
+						column: *column,
+						count: *count,
+					},
+				);
+			}
+			(OP_TYPE_POINTER, [result, storage_class, pointee]) => {
+				types.insert(
+					*result,
+					Type::Pointer {
+						storage_class: *storage_class,
+						pointee: *pointee,
+					},
+				);
+			}
+			(OP_VARIABLE, [result_type, result, STORAGE_CLASS_INPUT, ..]) => {
+				inputs.push((*result_type, *result))
+			}
+			_ => {}
+		}
+
+		index += count;
+	}
+
+	let constant = |id: u32| -> Option<Constant> {
+		let is_float = |id| matches!(types.get(&id), Some(Type::Float));
+		match types.get(&id)? {
+			Type::Int { signed: true } => Some(Constant::Int32),
+			Type::Int { signed: false } => Some(Constant::Uint32),
+			Type::Float => Some(Constant::Float32),
+			Type::Vector { component, count } if is_float(*component) => match count {
+				2 => Some(Constant::Vector2),
+				3 => Some(Constant::Vector3),
+				4 => Some(Constant::Vector4),
+				_ => None,
+			},
+			Type::Matrix { column, count: 4 } => match types.get(column)? {
+				Type::Vector {
+					component,
+					count: 4,
+				} if is_float(*component) => Some(Constant::Matrix4),
+				_ => None,
+			},
+			_ => None,
+		}
+	};
+
+	let mut result: Vec<(u32, Constant)> = inputs
+		.iter()
+		.filter(|(_, id)| !built_ins.contains(id))
+		.filter_map(|(pointer, id)| {
+			let location = *locations.get(id)?;
+			let pointee = match types.get(pointer)? {
+				Type::Pointer {
+					storage_class: STORAGE_CLASS_INPUT,
+					pointee,
+				} => *pointee,
+				_ => return None,
+			};
+			Some((location, constant(pointee)?))
+		})
+		.collect();
+	result.sort_by_key(|(location, _)| *location);
+	Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn instruction(words: &mut Vec<u32>, opcode: u32, operands: &[u32]) {
+		words.push(((operands.len() as u32 + 1) << 16) | opcode);
+		words.extend_from_slice(operands);
+	}
+
+	#[test]
+	fn reflects_vertex_inputs() {
+		let mut words = vec![MAGIC, 0x0001_0000, 0, 32, 0];
+		instruction(&mut words, OP_DECORATE, &[10, DECORATION_LOCATION, 1]);
+		instruction(&mut words, OP_DECORATE, &[11, DECORATION_LOCATION, 0]);
+		instruction(&mut words, OP_DECORATE, &[12, DECORATION_BUILT_IN, 42]);
+		instruction(&mut words, OP_TYPE_FLOAT, &[1, 32]);
+		instruction(&mut words, OP_TYPE_INT, &[2, 32, 0]);
+		instruction(&mut words, OP_TYPE_VECTOR, &[3, 1, 3]);
+		instruction(&mut words, OP_TYPE_POINTER, &[4, STORAGE_CLASS_INPUT, 3]);
+		instruction(&mut words, OP_TYPE_POINTER, &[5, STORAGE_CLASS_INPUT, 2]);
+		instruction(&mut words, OP_VARIABLE, &[4, 11, STORAGE_CLASS_INPUT]);
+		instruction(&mut words, OP_VARIABLE, &[5, 10, STORAGE_CLASS_INPUT]);
+		instruction(&mut words, OP_VARIABLE, &[5, 12, STORAGE_CLASS_INPUT]);
+
+		let binary: Vec<u8> = words.iter().flat_map(|it| it.to_le_bytes()).collect();
+		assert_eq!(
+			stage_inputs(&binary).unwrap(),
+			[(0, Constant::Vector3), (1, Constant::Uint32)]
+		);
+		assert!(stage_inputs(b"float4 main() {}").is_none());
+	}
+}
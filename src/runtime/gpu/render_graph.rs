@@ -0,0 +1,811 @@
+use crate::*;
+
+use engine::Engine;
+
+use std::sync::Arc;
+
+/// Handle to a texture used by a [`RenderGraph`]. Only valid for the graph that created it
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TextureId(usize);
+
+/// Handle to a buffer used by a [`RenderGraph`]. Only valid for the graph that created it
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BufferId(usize);
+
+/// Description of a texture that only lives for the duration of a [`RenderGraph`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TextureDescription {
+	pub format: Format,
+	pub width: u32,
+	pub height: u32,
+}
+
+/// How a pass uses a texture. Determines the layout the texture is transitioned to before the pass runs.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextureAccess {
+	Sampled,
+	StorageRead,
+	StorageWrite,
+	ColorAttachment,
+	DepthAttachment,
+	TransferSrc,
+	TransferDst,
+}
+
+impl TextureAccess {
+	fn layout(self) -> Layout {
+		match self {
+			Self::Sampled => Layout::ShaderReadOnly,
+			Self::StorageRead | Self::StorageWrite => Layout::General,
+			Self::ColorAttachment => Layout::ColorAttachment,
+			Self::DepthAttachment => Layout::DepthAttachment,
+			Self::TransferSrc => Layout::TransferSrc,
+			Self::TransferDst => Layout::TransferDst,
+		}
+	}
+
+	fn usage(self) -> TextureUsage {
+		match self {
+			Self::Sampled => TextureUsage::SAMPLED,
+			Self::StorageRead | Self::StorageWrite => TextureUsage::STORAGE,
+			Self::ColorAttachment => TextureUsage::COLOR_ATTACHMENT,
+			Self::DepthAttachment => TextureUsage::DEPTH_ATTACHMENT,
+			Self::TransferSrc => TextureUsage::TRANSFER_SRC,
+			Self::TransferDst => TextureUsage::TRANSFER_DST,
+		}
+	}
+
+	fn is_write(self) -> bool {
+		matches!(
+			self,
+			Self::StorageWrite | Self::ColorAttachment | Self::DepthAttachment | Self::TransferDst
+		)
+	}
+}
+
+enum GraphTexture {
+	Imported {
+		texture: Texture,
+		initial_layout: Layout,
+		final_layout: Layout,
+	},
+	Transient(TextureDescription),
+}
+
+type RenderFn<'a> = Box<dyn FnOnce(&mut RenderPassRecorder, &PassResources) + 'a>;
+type ComputeFn<'a> = Box<dyn FnOnce(&mut ComputePassRecorder, &PassResources) + 'a>;
+type CommandsFn<'a> = Box<dyn FnOnce(GraphicsRecorder, &PassResources) -> GraphicsRecorder + 'a>;
+
+enum PassRecord<'a> {
+	Render(RenderFn<'a>),
+	Compute(&'a ComputePipeline, ComputeFn<'a>),
+	Commands(CommandsFn<'a>),
+}
+
+struct Pass<'a> {
+	name: String,
+
+	textures: Vec<(TextureId, TextureAccess)>,
+	color_attachments: Vec<TextureId>,
+	depth_attachment: Option<TextureId>,
+
+	read_buffers: Vec<BufferId>,
+	write_buffers: Vec<BufferId>,
+
+	statistics: bool,
+
+	record: PassRecord<'a>,
+}
+
+impl<'a> Pass<'a> {
+	fn is_attachment(&self, texture: TextureId) -> bool {
+		self.color_attachments.contains(&texture) || self.depth_attachment == Some(texture)
+	}
+
+	fn writes(&self, texture: TextureId) -> bool {
+		self.textures
+			.iter()
+			.any(|(id, access)| *id == texture && access.is_write())
+	}
+}
+
+/// Textures allocated for a pass. Given to every pass when it is recorded
+pub struct PassResources<'a> {
+	textures: &'a [Option<Texture>],
+}
+
+impl<'a> PassResources<'a> {
+	/// Returns the texture behind `id`. Panics if the texture was never used by a pass that was kept
+	pub fn texture(&self, id: TextureId) -> &'a Texture {
+		self.textures[id.0]
+			.as_ref()
+			.expect("Texture is not used by any pass that was kept")
+	}
+}
+
+/// Declares the resources a pass uses. Finished by one of [`PassBuilder::render`], [`PassBuilder::compute`] or
+/// [`PassBuilder::commands`].
+pub struct PassBuilder<'g, 'a> {
+	graph: &'g mut RenderGraph<'a>,
+	pass: Pass<'a>,
+}
+
+impl<'g, 'a> PassBuilder<'g, 'a> {
+	fn access(mut self, texture: TextureId, access: TextureAccess) -> Self {
+		assert!(
+			!self.pass.textures.iter().any(|(it, _)| *it == texture),
+			"Pass \"{}\" uses a texture more than once",
+			self.pass.name
+		);
+		self.pass.textures.push((texture, access));
+		self
+	}
+
+	/// Renders into `texture` as the next color attachment. Contents are loaded when an earlier pass wrote to it or
+	/// it was imported with a defined layout.
+	pub fn color(mut self, texture: TextureId) -> Self {
+		self.pass.color_attachments.push(texture);
+		self.access(texture, TextureAccess::ColorAttachment)
+	}
+
+	/// Renders into `texture` as the depth attachment. Loaded the same way as [`PassBuilder::color`]
+	pub fn depth(mut self, texture: TextureId) -> Self {
+		assert!(
+			self.pass.depth_attachment.is_none(),
+			"Pass \"{}\" already has a depth attachment",
+			self.pass.name
+		);
+		self.pass.depth_attachment = Some(texture);
+		self.access(texture, TextureAccess::DepthAttachment)
+	}
+
+	/// Samples `texture` in a shader
+	pub fn read(self, texture: TextureId) -> Self {
+		self.access(texture, TextureAccess::Sampled)
+	}
+
+	/// Reads `texture` as a storage texture
+	pub fn read_storage(self, texture: TextureId) -> Self {
+		self.access(texture, TextureAccess::StorageRead)
+	}
+
+	/// Writes `texture` as a storage texture
+	pub fn write_storage(self, texture: TextureId) -> Self {
+		self.access(texture, TextureAccess::StorageWrite)
+	}
+
+	/// Copies out of `texture`
+	pub fn copy_from(self, texture: TextureId) -> Self {
+		self.access(texture, TextureAccess::TransferSrc)
+	}
+
+	/// Copies into `texture`
+	pub fn copy_to(self, texture: TextureId) -> Self {
+		self.access(texture, TextureAccess::TransferDst)
+	}
+
+	pub fn read_buffer(mut self, buffer: BufferId) -> Self {
+		self.pass.read_buffers.push(buffer);
+		self
+	}
+
+	pub fn write_buffer(mut self, buffer: BufferId) -> Self {
+		self.pass.write_buffers.push(buffer);
+		self
+	}
+
+	/// Collects [`PipelineStatistics`] for the pass under its name
+	pub fn statistics(mut self) -> Self {
+		self.pass.statistics = true;
+		self
+	}
+
+	/// Records a render pass into the declared attachments
+	pub fn render(mut self, f: impl FnOnce(&mut RenderPassRecorder, &PassResources) + 'a) {
+		assert!(
+			!self.pass.color_attachments.is_empty() || self.pass.depth_attachment.is_some(),
+			"Render pass \"{}\" has no attachments",
+			self.pass.name
+		);
+		self.pass.record = PassRecord::Render(Box::new(f));
+		self.graph.passes.push(self.pass);
+	}
+
+	/// Records a compute pass using `pipeline`
+	pub fn compute(
+		mut self,
+		pipeline: &'a ComputePipeline,
+		f: impl FnOnce(&mut ComputePassRecorder, &PassResources) + 'a,
+	) {
+		self.pass.record = PassRecord::Compute(pipeline, Box::new(f));
+		self.graph.passes.push(self.pass);
+	}
+
+	/// Records commands outside of a render pass such as copies
+	pub fn commands(
+		mut self,
+		f: impl FnOnce(GraphicsRecorder, &PassResources) -> GraphicsRecorder + 'a,
+	) {
+		self.pass.record = PassRecord::Commands(Box::new(f));
+		self.graph.passes.push(self.pass);
+	}
+}
+
+/// Records a frame as a list of passes that declare the resources they read and write.
+///
+/// When recorded the graph culls passes whose results are never used, allocates transient textures, and inserts
+/// the barriers between passes. A barrier is placed whenever a texture changes layout and between a write and any
+/// later access of the same texture or buffer. Imported resources are treated as outputs of the graph so any pass
+/// writing to them is always kept. Every pass is wrapped in a timer named after it so its GPU time shows up in
+/// [`Device::last_report`].
+pub struct RenderGraph<'a> {
+	device: Device,
+
+	textures: Vec<GraphTexture>,
+	buffers: Vec<Arc<api::Buffer>>,
+
+	passes: Vec<Pass<'a>>,
+}
+
+impl<'a> RenderGraph<'a> {
+	pub fn new() -> Self {
+		Self::new_in(Gpu::device())
+	}
+
+	pub fn new_in(device: &Device) -> Self {
+		Self {
+			device: device.clone(),
+
+			textures: Vec::with_capacity(16),
+			buffers: Vec::with_capacity(16),
+
+			passes: Vec::with_capacity(16),
+		}
+	}
+
+	/// Adds an existing texture currently in `initial_layout`. The texture is left in `final_layout` once the graph
+	/// has executed.
+	pub fn import_texture(
+		&mut self,
+		texture: &Texture,
+		initial_layout: Layout,
+		final_layout: Layout,
+	) -> TextureId {
+		self.textures.push(GraphTexture::Imported {
+			texture: texture.clone(),
+			initial_layout,
+			final_layout,
+		});
+		TextureId(self.textures.len() - 1)
+	}
+
+	/// Returns the texture behind `id` if it was imported. Transient textures only exist while the graph is recorded
+	pub fn imported_texture(&self, id: TextureId) -> Option<&Texture> {
+		match &self.textures[id.0] {
+			GraphTexture::Imported { texture, .. } => Some(texture),
+			GraphTexture::Transient(_) => None,
+		}
+	}
+
+	/// Adds a texture that is allocated when the graph is recorded and released afterwards. Transient textures with
+	/// matching descriptions share memory when their lifetimes do not overlap.
+	pub fn create_texture(&mut self, description: TextureDescription) -> TextureId {
+		self.textures.push(GraphTexture::Transient(description));
+		TextureId(self.textures.len() - 1)
+	}
+
+	/// Adds an existing buffer so passes can declare that they read or write it
+	pub fn import_buffer<T: Sized>(&mut self, buffer: &Buffer<T>) -> BufferId {
+		self.buffers.push(buffer.api.clone());
+		BufferId(self.buffers.len() - 1)
+	}
+
+	pub fn add_pass<'g>(&'g mut self, name: impl ToString) -> PassBuilder<'g, 'a> {
+		PassBuilder {
+			graph: self,
+			pass: Pass {
+				name: name.to_string(),
+
+				textures: Vec::new(),
+				color_attachments: Vec::new(),
+				depth_attachment: None,
+
+				read_buffers: Vec::new(),
+				write_buffers: Vec::new(),
+
+				statistics: false,
+
+				record: PassRecord::Commands(Box::new(|recorder, _| recorder)),
+			},
+		}
+	}
+
+	/// Returns whether `texture` holds anything before the graph runs
+	fn has_initial_contents(&self, texture: TextureId) -> bool {
+		matches!(
+			self.textures[texture.0],
+			GraphTexture::Imported { initial_layout, .. } if initial_layout != Layout::Undefined
+		)
+	}
+
+	/// Returns which passes contribute to an imported resource
+	///
+	/// Attachments that something wrote before the pass are treated as read as they are loaded. The passes
+	/// that wrote them are then kept as well.
+	fn cull(&self) -> Vec<bool> {
+		let mut needed_textures: Vec<bool> = self
+			.textures
+			.iter()
+			.map(|it| matches!(it, GraphTexture::Imported { .. }))
+			.collect();
+
+		let mut kept = vec![false; self.passes.len()];
+		for (index, pass) in self.passes.iter().enumerate().rev() {
+			// Buffers can only be imported so writing to one always keeps the pass
+			let writes_needed = pass
+				.textures
+				.iter()
+				.any(|(id, access)| access.is_write() && needed_textures[id.0])
+				|| !pass.write_buffers.is_empty();
+			if !writes_needed {
+				continue;
+			}
+
+			kept[index] = true;
+			for (id, access) in pass.textures.iter() {
+				let loaded = pass.is_attachment(*id)
+					&& (self.has_initial_contents(*id)
+						|| self.passes[..index].iter().any(|it| it.writes(*id)));
+				if !access.is_write() || loaded {
+					needed_textures[id.0] = true;
+				}
+			}
+		}
+		kept
+	}
+
+	/// Records every pass that was not culled into a single [`GraphicsRecorder`]
+	pub fn record(self) -> GraphicsRecorder {
+		let kept = self.cull();
+		// Only passes that survive culling give a texture contents so attachments are never loaded from culled work
+		let mut has_contents: Vec<bool> = (0..self.textures.len())
+			.map(|it| self.has_initial_contents(TextureId(it)))
+			.collect();
+		let RenderGraph {
+			device,
+			textures,
+			buffers,
+			passes,
+		} = self;
+
+		let passes: Vec<Pass<'a>> = passes
+			.into_iter()
+			.zip(kept)
+			.filter_map(|(pass, kept)| if kept { Some(pass) } else { None })
+			.collect();
+
+		// Find the usage and lifetime of every transient texture from the passes that are left
+		let mut usages = vec![TextureUsage::empty(); textures.len()];
+		let mut first_use = vec![usize::MAX; textures.len()];
+		let mut last_use = vec![0; textures.len()];
+		for (index, pass) in passes.iter().enumerate() {
+			for (id, access) in pass.textures.iter() {
+				usages[id.0] |= access.usage();
+				first_use[id.0] = first_use[id.0].min(index);
+				last_use[id.0] = index;
+			}
+		}
+
+		let mut allocated: Vec<Option<Texture>> = textures
+			.iter()
+			.map(|it| match it {
+				GraphTexture::Imported { texture, .. } => Some(texture.clone()),
+				GraphTexture::Transient(_) => None,
+			})
+			.collect();
+		let mut layouts: Vec<Layout> = textures
+			.iter()
+			.map(|it| match it {
+				GraphTexture::Imported { initial_layout, .. } => *initial_layout,
+				GraphTexture::Transient(_) => Layout::Undefined,
+			})
+			.collect();
+
+		// Whether the last access of every resource was a write. None until a pass uses the resource
+		let mut texture_writes: Vec<Option<bool>> = vec![None; textures.len()];
+		let mut buffer_writes: Vec<Option<bool>> = vec![None; buffers.len()];
+
+		let mut free: Vec<(TextureDescription, TextureUsage, Texture)> = Vec::new();
+		let mut recorder = GraphicsRecorder::new_in(&device);
+		for (index, pass) in passes.into_iter().enumerate() {
+			// Allocate transient textures on first use reusing any that are no longer needed
+			for (id, it) in textures.iter().enumerate() {
+				let description = match it {
+					GraphTexture::Transient(description) if first_use[id] == index => *description,
+					_ => continue,
+				};
+
+				let found = free
+					.iter()
+					.position(|(d, u, _)| *d == description && *u == usages[id]);
+				let texture = match found {
+					Some(found) => free.remove(found).2,
+					None => Texture::new_in(
+						usages[id],
+						description.format,
+						description.width,
+						description.height,
+						1,
+						&device,
+					)
+					.expect("Failed to allocate transient texture"),
+				};
+				allocated[id] = Some(texture);
+			}
+
+			// Every pass is timed so its cost shows up in the device report and grouped in GPU captures
+			recorder = recorder.push_marker(&pass.name).begin_timer(&pass.name);
+			if pass.statistics {
+				recorder = recorder.begin_statistics(&pass.name);
+			}
+
+			// Writes must finish before anything else touches the resource and reads before it is written again
+			let hazard =
+				|last: Option<bool>, write: bool| matches!(last, Some(last) if last || write);
+			for (id, access) in pass.textures.iter() {
+				let new_layout = access.layout();
+				let old_layout = layouts[id.0];
+				if old_layout != new_layout || hazard(texture_writes[id.0], access.is_write()) {
+					recorder = recorder.texture_barrier(
+						allocated[id.0].as_ref().unwrap(),
+						old_layout,
+						new_layout,
+					);
+					layouts[id.0] = new_layout;
+				}
+				texture_writes[id.0] = Some(access.is_write());
+			}
+			for (index, buffer) in buffers.iter().enumerate() {
+				let id = BufferId(index);
+				let write = pass.write_buffers.contains(&id);
+				if !write && !pass.read_buffers.contains(&id) {
+					continue;
+				}
+
+				if hazard(buffer_writes[index], write) {
+					recorder.0.resource_barrier_buffer(buffer.clone());
+				}
+				buffer_writes[index] = Some(write);
+			}
+
+			let resources = PassResources {
+				textures: &allocated,
+			};
+			recorder = match pass.record {
+				PassRecord::Render(f) => {
+					let has_contents = &has_contents;
+					let attachments: Vec<Attachment> = pass
+						.color_attachments
+						.iter()
+						.chain(pass.depth_attachment.iter())
+						.map(|id| {
+							let load = if has_contents[id.0] {
+								LoadOp::Load
+							} else {
+								LoadOp::DontCare
+							};
+							Attachment::new(resources.texture(*id)).load(load)
+						})
+						.collect();
+
+					recorder.render_pass_with(&attachments, |ctx| {
+						f(ctx, &resources);
+						ctx
+					})
+				}
+				PassRecord::Compute(pipeline, f) => recorder.compute_pass(pipeline, |ctx| {
+					f(ctx, &resources);
+					ctx
+				}),
+				PassRecord::Commands(f) => f(recorder, &resources),
+			};
+
+			for (id, access) in pass.textures.iter() {
+				if access.is_write() {
+					has_contents[id.0] = true;
+				}
+			}
+
+			if pass.statistics {
+				recorder = recorder.end_statistics();
+			}
+			recorder = recorder.end_timer().pop_marker();
+
+			// Release transient textures after their last use so later passes can alias them
+			for (id, it) in textures.iter().enumerate() {
+				if let GraphTexture::Transient(description) = it {
+					if last_use[id] == index && first_use[id] != usize::MAX {
+						let texture = allocated[id].clone().unwrap();
+						free.push((*description, usages[id], texture));
+					}
+				}
+			}
+		}
+
+		// Leave imported textures in the layout they were asked to end in
+		for (id, it) in textures.iter().enumerate() {
+			if let GraphTexture::Imported {
+				texture,
+				final_layout,
+				..
+			} = it
+			{
+				if layouts[id] != *final_layout {
+					recorder = recorder.texture_barrier(texture, layouts[id], *final_layout);
+				}
+			}
+		}
+
+		recorder
+	}
+
+	/// Records and submits the graph
+	pub fn execute(self) -> Receipt {
+		self.record().submit()
+	}
+}
+
+impl<'a> Default for RenderGraph<'a> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Order that [`PresentPass`]es are drawn into the backbuffer. Lower layers are drawn first.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PresentLayer(pub i32);
+
+impl PresentLayer {
+	pub const GAME: Self = Self(0);
+	pub const GUI: Self = Self(100);
+	/// Drawn last so the finished backbuffer can be copied out
+	pub const CAPTURE: Self = Self(i32::MAX);
+}
+
+/// Adds passes drawing into the backbuffer every time [`Gpu::present`] is called. Registered through
+/// [`engine::Builder::register`].
+#[derive(Clone)]
+pub struct PresentPass {
+	layer: PresentLayer,
+	record: fn(&mut RenderGraph, TextureId),
+}
+
+impl PresentPass {
+	pub fn new(layer: PresentLayer, record: fn(&mut RenderGraph, TextureId)) -> Self {
+		Self { layer, record }
+	}
+}
+
+impl Gpu {
+	/// Builds a single [`RenderGraph`] from every registered [`PresentPass`] and presents the result. Meant to be
+	/// used as the engine display callback.
+	pub fn present() {
+		let device = Gpu::device();
+		let backbuffer = device
+			.acquire_backbuffer()
+			.expect("Swapchain failed to find a back buffer");
+		// Nothing is drawn while the window is minimized
+		let backbuffer = match backbuffer {
+			Some(backbuffer) => backbuffer,
+			None => return,
+		};
+
+		let mut present_passes: Vec<&PresentPass> =
+			Engine::register::<PresentPass>().iter().collect();
+		present_passes.sort_by_key(|it| it.layer);
+
+		let mut graph = RenderGraph::new_in(device);
+		let target = graph.import_texture(&backbuffer, Layout::Undefined, Layout::Present);
+
+		// Start from black so layers only need to draw what they cover
+		graph.add_pass("clear").color(target).render(|ctx, _| {
+			ctx.clear_color(Color::BLACK);
+		});
+
+		for it in present_passes.iter() {
+			(it.record)(&mut graph, target);
+		}
+
+		let receipt = graph.execute();
+		device.display(&[receipt]);
+	}
+}
+
+#[cfg(all(test, backend = "null"))]
+mod tests {
+	use super::*;
+
+	fn loads(recorder: &GraphicsRecorder) -> Vec<LoadOp> {
+		recorder.attachments().iter().map(|it| it.load).collect()
+	}
+
+	#[test]
+	fn culls_and_transitions() {
+		let device = test_device();
+
+		let output = Texture::new_in(
+			TextureUsage::COLOR_ATTACHMENT | TextureUsage::SAMPLED,
+			Format::RGBA_U8,
+			4,
+			4,
+			1,
+			&device,
+		)
+		.unwrap();
+		let description = TextureDescription {
+			format: Format::RGBA_U8,
+			width: 4,
+			height: 4,
+		};
+
+		let mut graph = RenderGraph::new_in(&device);
+		let output_id = graph.import_texture(&output, Layout::Undefined, Layout::ShaderReadOnly);
+		let unused = graph.create_texture(description);
+		let transient = graph.create_texture(description);
+
+		graph.add_pass("unused").color(unused).render(|ctx, _| {
+			ctx.clear_color(Color::WHITE);
+		});
+		graph
+			.add_pass("transient")
+			.color(transient)
+			.render(|ctx, _| {
+				ctx.clear_color(Color::BLACK);
+			});
+		graph
+			.add_pass("output")
+			.read(transient)
+			.color(output_id)
+			.render(|_, _| {});
+
+		let recorder = graph.record();
+		let commands = recorder.commands().to_vec();
+		let transient = match &commands[2] {
+			Command::TextureBarrier { texture, .. } => *texture,
+			command => panic!("expected a barrier, found {:?}", command),
+		};
+
+		assert_eq!(
+			commands,
+			vec![
+				Command::PushMarker("transient".to_string()),
+				Command::BeginTimer("transient".to_string()),
+				Command::TextureBarrier {
+					texture: transient,
+					old_layout: Layout::Undefined,
+					new_layout: Layout::ColorAttachment,
+				},
+				Command::BeginRenderPass {
+					attachments: vec![transient]
+				},
+				Command::ClearColor(Color::BLACK),
+				Command::EndRenderPass,
+				Command::EndTimer,
+				Command::PopMarker,
+				Command::PushMarker("output".to_string()),
+				Command::BeginTimer("output".to_string()),
+				Command::TextureBarrier {
+					texture: transient,
+					old_layout: Layout::ColorAttachment,
+					new_layout: Layout::ShaderReadOnly,
+				},
+				Command::TextureBarrier {
+					texture: output.id(),
+					old_layout: Layout::Undefined,
+					new_layout: Layout::ColorAttachment,
+				},
+				Command::BeginRenderPass {
+					attachments: vec![output.id()]
+				},
+				Command::EndRenderPass,
+				Command::EndTimer,
+				Command::PopMarker,
+				Command::TextureBarrier {
+					texture: output.id(),
+					old_layout: Layout::ColorAttachment,
+					new_layout: Layout::ShaderReadOnly,
+				},
+			]
+		);
+		assert_eq!(loads(&recorder), vec![LoadOp::DontCare, LoadOp::DontCare]);
+	}
+
+	#[test]
+	fn orders_writes_before_later_access() {
+		let device = test_device();
+
+		let output = Texture::new_in(
+			TextureUsage::COLOR_ATTACHMENT | TextureUsage::STORAGE,
+			Format::RGBA_U8,
+			4,
+			4,
+			1,
+			&device,
+		)
+		.unwrap();
+		let target =
+			Texture::new_in(TextureUsage::STORAGE, Format::RGBA_U8, 4, 4, 1, &device).unwrap();
+		let buffer: Buffer<u32> =
+			Buffer::new_in(BufferUsage::STORAGE, MemoryType::DeviceLocal, 16, &device).unwrap();
+
+		let mut graph = RenderGraph::new_in(&device);
+		let output_id = graph.import_texture(&output, Layout::Undefined, Layout::General);
+		let target_id = graph.import_texture(&target, Layout::General, Layout::General);
+		let buffer_id = graph.import_buffer(&buffer);
+
+		// Drawing over the first pass loads its contents so neither pass is culled
+		graph
+			.add_pass("first")
+			.color(output_id)
+			.read_buffer(buffer_id)
+			.render(|_, _| {});
+		graph.add_pass("second").color(output_id).render(|_, _| {});
+		graph
+			.add_pass("write")
+			.write_storage(output_id)
+			.write_buffer(buffer_id)
+			.commands(|recorder, _| recorder);
+		graph
+			.add_pass("read")
+			.read_storage(output_id)
+			.read_buffer(buffer_id)
+			.write_storage(target_id)
+			.commands(|recorder, _| recorder);
+		// Reads after reads are not ordered but writes after writes are
+		graph
+			.add_pass("read again")
+			.read_storage(output_id)
+			.read_buffer(buffer_id)
+			.write_storage(target_id)
+			.commands(|recorder, _| recorder);
+
+		let barrier = |texture: &Texture, old_layout, new_layout| Command::TextureBarrier {
+			texture: texture.id(),
+			old_layout,
+			new_layout,
+		};
+		let recorder = graph.record();
+		let commands: Vec<Command> = recorder
+			.commands()
+			.iter()
+			.filter(|it| {
+				matches!(
+					it,
+					Command::TextureBarrier { .. }
+						| Command::BufferBarrier(_)
+						| Command::BeginRenderPass { .. }
+				)
+			})
+			.cloned()
+			.collect();
+		assert_eq!(
+			commands,
+			vec![
+				barrier(&output, Layout::Undefined, Layout::ColorAttachment),
+				Command::BeginRenderPass {
+					attachments: vec![output.id()]
+				},
+				barrier(&output, Layout::ColorAttachment, Layout::ColorAttachment),
+				Command::BeginRenderPass {
+					attachments: vec![output.id()]
+				},
+				barrier(&output, Layout::ColorAttachment, Layout::General),
+				Command::BufferBarrier(buffer.id()),
+				barrier(&output, Layout::General, Layout::General),
+				Command::BufferBarrier(buffer.id()),
+				barrier(&target, Layout::General, Layout::General),
+			]
+		);
+		assert_eq!(loads(&recorder), vec![LoadOp::DontCare, LoadOp::Load]);
+	}
+}
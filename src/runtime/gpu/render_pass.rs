@@ -0,0 +1,316 @@
+use crate::{
+	api,
+	Texture,
+};
+
+use math::Color;
+
+use std::sync::Arc;
+
+/// What happens to the contents of an attachment when a render pass begins
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LoadOp {
+	/// Keep what was drawn before. The attachment must already be in its attachment layout
+	Load,
+	/// Fill with the clear value of the [`Attachment`]
+	Clear,
+	/// Contents are undefined. Use when every pixel is drawn over
+	DontCare,
+}
+
+/// What happens to the contents of an attachment when a render pass ends
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum StoreOp {
+	Store,
+	/// Contents are discarded. Use for transient depth buffers or multisampled textures that are resolved
+	DontCare,
+}
+
+/// Texture drawn to by a render pass and what to do with its contents. Contents are stored but only loaded when
+/// asked for with [`Attachment::load`], the same as textures passed directly to
+/// [`crate::GraphicsRecorder::render_pass`].
+#[derive(Clone)]
+pub struct Attachment<'a> {
+	pub(crate) texture: &'a Texture,
+	pub(crate) load: LoadOp,
+	pub(crate) store: StoreOp,
+	pub(crate) clear_color: Color,
+	pub(crate) clear_depth: f32,
+	pub(crate) resolve: Option<&'a Texture>,
+}
+
+impl<'a> Attachment<'a> {
+	pub fn new(texture: &'a Texture) -> Self {
+		Self {
+			texture,
+			load: LoadOp::DontCare,
+			store: StoreOp::Store,
+			clear_color: Color::BLACK,
+			clear_depth: 1.0,
+			resolve: None,
+		}
+	}
+
+	pub fn load(mut self, load: LoadOp) -> Self {
+		self.load = load;
+		self
+	}
+
+	pub fn store(mut self, store: StoreOp) -> Self {
+		self.store = store;
+		self
+	}
+
+	/// Clears a color attachment to `color` when the render pass begins
+	pub fn clear_color(mut self, color: impl Into<Color>) -> Self {
+		self.load = LoadOp::Clear;
+		self.clear_color = color.into();
+		self
+	}
+
+	/// Clears a depth attachment to `depth` when the render pass begins
+	pub fn clear_depth(mut self, depth: f32) -> Self {
+		self.load = LoadOp::Clear;
+		self.clear_depth = depth;
+		self
+	}
+
+	/// Resolves the multisampled attachment into `texture` when the render pass ends. `texture` must have a
+	/// single sample and match the format and size of the attachment.
+	pub fn resolve(mut self, texture: &'a Texture) -> Self {
+		self.resolve = Some(texture);
+		self
+	}
+
+	pub(crate) fn validate(&self) {
+		let texture = self.texture;
+		if let Some(resolve) = self.resolve {
+			assert!(
+				texture.format().is_color(),
+				"Only color attachments can be resolved"
+			);
+			assert!(
+				texture.samples() > 1 && resolve.samples() == 1,
+				"Resolve requires a multisampled attachment and a single sampled target"
+			);
+			assert!(
+				resolve.format() == texture.format()
+					&& resolve.width() == texture.width()
+					&& resolve.height() == texture.height(),
+				"Resolve target must match the format and size of the attachment"
+			);
+		}
+	}
+
+	pub(crate) fn to_api(&self) -> RenderPassAttachment {
+		RenderPassAttachment {
+			texture: self.texture.0.clone(),
+			load: self.load,
+			store: self.store,
+			clear_color: self.clear_color,
+			clear_depth: self.clear_depth,
+			resolve: self.resolve.map(|it| it.0.clone()),
+		}
+	}
+}
+
+impl<'a> From<&'a Texture> for Attachment<'a> {
+	fn from(texture: &'a Texture) -> Self {
+		Self::new(texture)
+	}
+}
+
+/// [`Attachment`] handed to the back end
+pub(crate) struct RenderPassAttachment {
+	pub texture: Arc<api::Texture>,
+	pub load: LoadOp,
+	pub store: StoreOp,
+	pub clear_color: Color,
+	pub clear_depth: f32,
+	pub resolve: Option<Arc<api::Texture>>,
+}
+
+#[cfg(all(test, backend = "null"))]
+mod tests {
+	use crate::*;
+
+	#[test]
+	fn only_loads_when_asked() {
+		let device = test_device();
+
+		let color = Texture::new_in(
+			TextureUsage::COLOR_ATTACHMENT,
+			Format::RGBA_U8,
+			4,
+			4,
+			1,
+			&device,
+		)
+		.unwrap();
+		let depth = Texture::new_in(
+			TextureUsage::DEPTH_ATTACHMENT,
+			Format::Depth16,
+			4,
+			4,
+			1,
+			&device,
+		)
+		.unwrap();
+
+		let recorder = GraphicsRecorder::new_in(&device)
+			.render_pass(&[&color, &depth], |ctx| ctx)
+			.render_pass_with(
+				&[
+					Attachment::new(&color).load(LoadOp::Load),
+					Attachment::new(&depth),
+				],
+				|ctx| ctx,
+			);
+
+		let attachment = |texture: &Texture, load| RecordedAttachment {
+			texture: texture.id(),
+			load,
+			store: StoreOp::Store,
+			resolve: None,
+		};
+		assert_eq!(
+			recorder.attachments(),
+			&[
+				attachment(&color, LoadOp::DontCare),
+				attachment(&depth, LoadOp::DontCare),
+				attachment(&color, LoadOp::Load),
+				attachment(&depth, LoadOp::DontCare),
+			]
+		);
+	}
+
+	#[test]
+	fn records_multisampled_instanced_and_indirect_draws() {
+		let device = test_device();
+
+		let color = Texture::builder(TextureUsage::COLOR_ATTACHMENT, Format::RGBA_U8, 8, 8, 1)
+			.samples(4)
+			.device(&device)
+			.spawn()
+			.unwrap();
+		let depth = Texture::builder(TextureUsage::DEPTH_ATTACHMENT, Format::Depth16, 8, 8, 1)
+			.samples(4)
+			.device(&device)
+			.spawn()
+			.unwrap();
+		let resolved = Texture::new_in(
+			TextureUsage::COLOR_ATTACHMENT | TextureUsage::SAMPLED,
+			Format::RGBA_U8,
+			8,
+			8,
+			1,
+			&device,
+		)
+		.unwrap();
+
+		let arguments: Buffer<DrawIndirect> =
+			Buffer::new_in(BufferUsage::INDIRECT, MemoryType::HostVisible, 2, &device).unwrap();
+		let shader = Shader::builder(b"", ShaderVariant::Vertex)
+			.device(&device)
+			.spawn()
+			.unwrap();
+		let pipeline = GraphicsPipeline::builder()
+			.attachments(&[Format::RGBA_U8, Format::Depth16])
+			.shaders(&[shader])
+			.samples(4)
+			.device(&device)
+			.spawn()
+			.unwrap();
+
+		let viewport = Rect::from_min_max((0.0, 0.0), (4.0, 4.0));
+		let recorder = GraphicsRecorder::new_in(&device).render_pass_with(
+			&[
+				Attachment::new(&color)
+					.clear_color(Color::BLACK)
+					.store(StoreOp::DontCare)
+					.resolve(&resolved),
+				Attachment::new(&depth)
+					.clear_depth(1.0)
+					.store(StoreOp::DontCare),
+			],
+			|ctx| {
+				ctx.set_pipeline(&pipeline)
+					.set_viewport(Some(viewport))
+					.draw_instanced(3, 0, 10, 0)
+					.draw_indirect(&arguments, 0, 2)
+			},
+		);
+
+		assert_eq!(
+			recorder.commands(),
+			&[
+				Command::BeginRenderPass {
+					attachments: vec![color.id(), depth.id()]
+				},
+				Command::ClearColor(Color::BLACK),
+				Command::ClearDepth(1.0),
+				Command::BindPipeline(pipeline.id()),
+				Command::BindViewport(Some(viewport)),
+				Command::DrawInstanced {
+					vertex_count: 3,
+					first_vertex: 0,
+					instance_count: 10,
+					first_instance: 0,
+				},
+				Command::DrawIndirect {
+					buffer: arguments.id(),
+					first: 0,
+					draw_count: 2,
+				},
+				Command::EndRenderPass,
+			]
+		);
+		assert_eq!(
+			recorder.attachments(),
+			&[
+				RecordedAttachment {
+					texture: color.id(),
+					load: LoadOp::Clear,
+					store: StoreOp::DontCare,
+					resolve: Some(resolved.id()),
+				},
+				RecordedAttachment {
+					texture: depth.id(),
+					load: LoadOp::Clear,
+					store: StoreOp::DontCare,
+					resolve: None,
+				},
+			]
+		);
+	}
+
+	#[test]
+	#[should_panic(expected = "sample count")]
+	fn pipeline_samples_must_match_render_pass() {
+		let device = test_device();
+
+		let texture = Texture::new_in(
+			TextureUsage::COLOR_ATTACHMENT,
+			Format::RGBA_U8,
+			4,
+			4,
+			1,
+			&device,
+		)
+		.unwrap();
+		let shader = Shader::builder(b"", ShaderVariant::Vertex)
+			.device(&device)
+			.spawn()
+			.unwrap();
+		let pipeline = GraphicsPipeline::builder()
+			.attachments(&[Format::RGBA_U8])
+			.shaders(&[shader])
+			.samples(4)
+			.device(&device)
+			.spawn()
+			.unwrap();
+
+		GraphicsRecorder::new_in(&device)
+			.render_pass(&[&texture], |ctx| ctx.set_pipeline(&pipeline));
+	}
+}
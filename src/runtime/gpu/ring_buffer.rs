@@ -0,0 +1,92 @@
+use crate::{
+	Buffer,
+	BufferUsage,
+	Device,
+	MemoryType,
+	Result,
+};
+
+/// Host visible buffers reused across frames for data that is uploaded once a frame. Each upload moves to the
+/// next buffer so the GPU can still read the previous frames while the current one is written. Buffers only
+/// grow, so after the first few frames uploading does not allocate.
+pub struct RingBuffer<T: Sized> {
+	usage: BufferUsage,
+	device: Option<Device>,
+	buffers: Vec<Option<Buffer<T>>>,
+	current: usize,
+}
+
+impl<T: Sized> RingBuffer<T> {
+	/// `frames` must be larger than the number of frames that can be in flight
+	pub fn new(usage: BufferUsage, frames: usize) -> Self {
+		assert!(frames > 0, "Ring buffer needs at least one frame");
+		Self {
+			usage,
+			device: None,
+			buffers: (0..frames).map(|_| None).collect(),
+			current: frames - 1,
+		}
+	}
+
+	pub fn new_in(usage: BufferUsage, frames: usize, device: &Device) -> Self {
+		Self {
+			device: Some(device.clone()),
+			..Self::new(usage, frames)
+		}
+	}
+
+	/// Copies `data` into the next buffer growing it if it is too small. The returned buffer may be longer than
+	/// `data`.
+	pub fn upload(&mut self, data: &[T]) -> Result<&Buffer<T>> {
+		self.current = (self.current + 1) % self.buffers.len();
+
+		let slot = &mut self.buffers[self.current];
+		let too_small = match slot {
+			Some(buffer) => buffer.len() < data.len(),
+			None => true,
+		};
+		if too_small {
+			let len = data.len().next_power_of_two().max(1);
+			let mut builder = Buffer::builder(self.usage, MemoryType::HostVisible, len);
+			if let Some(device) = &self.device {
+				builder = builder.device(device);
+			}
+			*slot = Some(builder.spawn()?);
+		}
+
+		let buffer = slot.as_ref().unwrap();
+		buffer.copy_to(data)?;
+		Ok(buffer)
+	}
+
+	/// Returns the buffer written by the last upload
+	pub fn current(&self) -> Option<&Buffer<T>> {
+		self.buffers[self.current].as_ref()
+	}
+
+	pub fn frames(&self) -> usize {
+		self.buffers.len()
+	}
+}
+
+#[cfg(all(test, backend = "null"))]
+mod tests {
+	use crate::*;
+
+	#[test]
+	fn grows_and_reuses_buffers() {
+		let device = test_device();
+
+		let mut ring = RingBuffer::new_in(BufferUsage::VERTEX, 2, &device);
+		for _ in 0..8 {
+			ring.upload(&[1u32, 2, 3]).unwrap();
+		}
+		assert_eq!(ring.current().unwrap().read().unwrap()[..3], [1, 2, 3]);
+		assert_eq!(ring.current().unwrap().len(), 4);
+		assert_eq!(device.memory_stats().host_visible.allocations, 2);
+
+		ring.upload(&[0; 5]).unwrap();
+		assert_eq!(ring.current().unwrap().len(), 8);
+		assert_eq!(device.memory_stats().host_visible.allocations, 2);
+	}
+}
@@ -1,17 +1,44 @@
-use hassle_rs::{
-	Dxc,
-	DxcCompiler,
-	DxcIncludeHandler,
-	DxcLibrary,
+#[cfg(backend = "vulkan")]
+use {
+	hassle_rs::{
+		Dxc,
+		DxcCompiler,
+		DxcLibrary,
+	},
+	std::env,
 };
 
-use std::sync::Arc;
 use std::{
-	env,
-	path,
+	collections::{
+		hash_map::DefaultHasher,
+		HashMap,
+		HashSet,
+	},
+	fs,
+	hash::{
+		Hash,
+		Hasher,
+	},
+	path::{
+		Path,
+		PathBuf,
+	},
+	sync::{
+		Arc,
+		RwLock,
+	},
 };
 
-use path::PathBuf;
+use cache::{
+	Cache,
+	CacheRef,
+};
+use engine::Engine;
+use resources::Collection;
+use serde::{
+	Deserialize,
+	Serialize,
+};
 
 use crate::{
 	api,
@@ -20,10 +47,11 @@ use crate::{
 	Result,
 };
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ShaderVariant {
 	Vertex,
 	Pixel,
+	Compute,
 }
 
 pub struct ShaderBuilder<'a> {
@@ -64,6 +92,16 @@ impl<'a> ShaderBuilder<'a> {
 pub struct Shader(pub(crate) Arc<api::Shader>);
 
 impl Shader {
+	pub fn variant(&self) -> ShaderVariant {
+		self.0.variant
+	}
+
+	/// Returns the binary the shader was created from. Importers on the null back end store the generated source.
+	#[cfg(backend = "null")]
+	pub fn binary(&self) -> &[u8] {
+		&self.0.binary
+	}
+
 	pub fn builder(binary: &'_ [u8], variant: ShaderVariant) -> ShaderBuilder<'_> {
 		ShaderBuilder {
 			binary,
@@ -75,101 +113,298 @@ impl Shader {
 	}
 }
 
-// This is copied from utils.rs in hassle-rs
-struct DefaultIncludeHandler {}
-
-impl DxcIncludeHandler for DefaultIncludeHandler {
-	fn load_source(&self, filename: String) -> Option<String> {
-		use std::io::Read;
-		match std::fs::File::open(filename) {
-			Ok(mut f) => {
-				let mut content = String::new();
-				f.read_to_string(&mut content).unwrap();
-				Some(content)
+/// Compiled shader binaries keyed by a hash of their preprocessed source so pipelines are not recompiled every
+/// launch
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct ShaderCache {
+	binaries: RwLock<HashMap<u64, Vec<u8>>>,
+}
+
+impl Cache for ShaderCache {
+	fn new() -> Self {
+		Self::default()
+	}
+
+	fn reload(&mut self) -> bool {
+		false
+	}
+}
+
+// Bump when the arguments given to the compiler change so stale binaries are not reused
+const SHADER_CACHE_VERSION: u32 = 1;
+
+/// Compiles HLSL `source` into a binary for the current back end.
+///
+/// `defines` are declared before the source. `#include "file"` is resolved relative to the including file and
+/// then every registered resource [`Collection`]. Each file is included at most once. Results are kept in the
+/// shader cache when the engine has one.
+pub fn compile(
+	name: &str,
+	source: &str,
+	main: &str,
+	variant: ShaderVariant,
+	defines: &[(&str, &str)],
+) -> std::result::Result<Vec<u8>, String> {
+	let include_directories: Vec<PathBuf> = Engine::register::<Collection>()
+		.iter()
+		.map(|it| it.path().to_path_buf())
+		.collect();
+	let source = preprocess(name, source, defines, &include_directories)?;
+
+	let mut hasher = DefaultHasher::new();
+	SHADER_CACHE_VERSION.hash(&mut hasher);
+	cfg!(backend = "vulkan").hash(&mut hasher);
+	source.hash(&mut hasher);
+	main.hash(&mut hasher);
+	variant.hash(&mut hasher);
+	let key = hasher.finish();
+
+	let cache = CacheRef::<ShaderCache>::new();
+	if let Some(cache) = &cache {
+		if let Some(binary) = cache.binaries.read().unwrap().get(&key) {
+			return Ok(binary.clone());
+		}
+	}
+
+	let binary = compile_preprocessed(name, &source, main, variant)?;
+
+	if let Some(cache) = &cache {
+		cache.binaries.write().unwrap().insert(key, binary.clone());
+		cache.save();
+	}
+
+	Ok(binary)
+}
+
+/// Declares `defines` and expands every `#include` in `source`. `#line` directives are added so compiler errors
+/// point at the original files.
+pub fn preprocess(
+	name: &str,
+	source: &str,
+	defines: &[(&str, &str)],
+	include_directories: &[PathBuf],
+) -> std::result::Result<String, String> {
+	let mut result = String::with_capacity(source.len());
+	for (define, value) in defines.iter() {
+		result.push_str(&format!("#define {} {}\n", define, value));
+	}
+
+	let mut included = HashSet::new();
+	expand_includes(
+		name,
+		None,
+		source,
+		include_directories,
+		&mut included,
+		&mut result,
+	)?;
+	Ok(result)
+}
+
+fn expand_includes(
+	name: &str,
+	directory: Option<&Path>,
+	source: &str,
+	include_directories: &[PathBuf],
+	included: &mut HashSet<PathBuf>,
+	result: &mut String,
+) -> std::result::Result<(), String> {
+	result.push_str(&format!("#line 1 \"{}\"\n", name));
+
+	// Sources may contain their own #line directives. Track them so lines after an include are restored to
+	// the file and line the source says they are from.
+	let mut line_name = name.to_string();
+	let mut line_start: isize = 0;
+
+	for (index, line) in source.lines().enumerate() {
+		let line_number = index as isize + 1 - line_start;
+
+		let trimmed = line.trim_start();
+		if let Some(directive) = trimmed.strip_prefix("#line") {
+			let mut parts = directive.trim().splitn(2, ' ');
+			if let Some(number) = parts.next().and_then(|it| it.parse::<usize>().ok()) {
+				line_start = index as isize + 2 - number as isize;
+				if let Some(file) = parts.next() {
+					line_name = file.trim().trim_matches('"').to_string();
+				}
 			}
-			Err(_) => None,
+		}
+
+		let file = match trimmed.strip_prefix("#include") {
+			Some(rest) => rest.trim(),
+			None => {
+				result.push_str(line);
+				result.push('\n');
+				continue;
+			}
+		};
+
+		let file = file
+			.strip_prefix('"')
+			.and_then(|it| it.strip_suffix('"'))
+			.ok_or_else(|| format!("{}({}): expected #include \"file\"", line_name, line_number))?;
+
+		let path = directory
+			.into_iter()
+			.chain(include_directories.iter().map(|it| it.as_path()))
+			.map(|it| it.join(file))
+			.find(|it| it.is_file())
+			.ok_or_else(|| {
+				format!(
+					"{}({}): could not find include \"{}\"",
+					line_name, line_number, file
+				)
+			})?;
+
+		let path = path.canonicalize().unwrap_or(path);
+		if included.insert(path.clone()) {
+			let contents = fs::read_to_string(&path).map_err(|err| {
+				format!(
+					"{}({}): {}: {}",
+					line_name,
+					line_number,
+					path.display(),
+					err
+				)
+			})?;
+			expand_includes(
+				&path.display().to_string(),
+				path.parent(),
+				&contents,
+				include_directories,
+				included,
+				result,
+			)?;
+			result.push_str(&format!("#line {} \"{}\"\n", line_number + 1, line_name));
+		} else {
+			result.push('\n'); // Keeps the line numbers after a skipped include correct
 		}
 	}
+
+	Ok(())
 }
 
+#[cfg(backend = "vulkan")]
 struct CompilerThreadInfo {
 	_dxc: Dxc,
 	compiler: DxcCompiler,
 	library: DxcLibrary,
 }
 
+#[cfg(all(backend = "vulkan", target_os = "windows"))]
+const DXC_LIBRARY: &str = "dxcompiler.dll";
+
+#[cfg(all(backend = "vulkan", target_os = "macos"))]
+const DXC_LIBRARY: &str = "libdxcompiler.dylib";
+
+#[cfg(all(
+	backend = "vulkan",
+	not(any(target_os = "windows", target_os = "macos"))
+))]
+const DXC_LIBRARY: &str = "libdxcompiler.so";
+
+#[cfg(backend = "vulkan")]
 impl CompilerThreadInfo {
-	fn new() -> Self {
+	/// Looks for dxc in `DXC_PATH`, next to the build output, next to the executable and then in the Vulkan SDK
+	fn new() -> std::result::Result<Self, String> {
+		let mut directories = Vec::with_capacity(4);
+		if let Some(path) = env::var_os("DXC_PATH") {
+			directories.push(PathBuf::from(path));
+		}
+
 		let out_dir = env!("OUT_DIR");
-		let target_index = out_dir.find("target").unwrap();
-		let (_, relative_out_dir) = out_dir.split_at(target_index);
+		if let Some(target_index) = out_dir.find("target") {
+			directories.push(PathBuf::from(&out_dir[target_index..]));
+		}
+
+		if let Some(exe_directory) = env::current_exe()
+			.ok()
+			.and_then(|it| it.parent().map(|it| it.to_path_buf()))
+		{
+			directories.push(exe_directory);
+		}
 
-		let mut library_path = PathBuf::from(relative_out_dir);
-		library_path.push("dxcompiler.dll");
+		if let Some(sdk) = env::var_os("VULKAN_SDK") {
+			let sdk = PathBuf::from(sdk);
+			directories.push(sdk.join(if cfg!(target_os = "windows") {
+				"Bin"
+			} else {
+				"lib"
+			}));
+		}
 
-		let dxc = Dxc::new(Some(library_path)).unwrap();
+		let library_path = directories
+			.iter()
+			.map(|it| it.join(DXC_LIBRARY))
+			.find(|it| it.is_file())
+			.ok_or_else(|| {
+				format!(
+					"Could not find {}. Set DXC_PATH to the directory containing it",
+					DXC_LIBRARY
+				)
+			})?;
 
-		let compiler = dxc.create_compiler().unwrap();
-		let library = dxc.create_library().unwrap();
+		let dxc = Dxc::new(Some(library_path)).map_err(|err| err.to_string())?;
 
-		Self {
+		let compiler = dxc.create_compiler().map_err(|err| err.to_string())?;
+		let library = dxc.create_library().map_err(|err| err.to_string())?;
+
+		Ok(Self {
 			_dxc: dxc,
 			compiler,
 			library,
-		}
+		})
 	}
 }
 
+#[cfg(backend = "vulkan")]
 thread_local! {
-	static DXC_COMPILER: CompilerThreadInfo = CompilerThreadInfo::new();
+	static DXC_COMPILER: std::result::Result<CompilerThreadInfo, String> = CompilerThreadInfo::new();
 }
 
-pub fn compile(
+#[cfg(backend = "vulkan")]
+fn compile_preprocessed(
 	name: &str,
 	source: &str,
 	main: &str,
 	variant: ShaderVariant,
 ) -> std::result::Result<Vec<u8>, String> {
 	DXC_COMPILER.with(|f| {
+		let f = f.as_ref().map_err(|err| err.clone())?;
+
 		let target_profile = match variant {
 			ShaderVariant::Pixel => "ps_6_1",
 			ShaderVariant::Vertex => "vs_6_1",
+			ShaderVariant::Compute => "cs_6_1",
 		};
 
 		let blob = f
 			.library
 			.create_blob_with_encoding_from_str(source)
-			.unwrap();
+			.map_err(|err| err.to_string())?;
 
 		let mut args = Vec::with_capacity(4); // TODO: Temp allocator
 
-		#[cfg(feature = "vulkan")]
-		{
-			args.push("-spirv");
-			args.push("-Zpc"); // Column major matrices
+		args.push("-spirv");
+		args.push("-Zpc"); // Column major matrices
 
-			if variant == ShaderVariant::Vertex {
-				args.push("-fvk-invert-y");
-			}
+		if variant == ShaderVariant::Vertex {
+			args.push("-fvk-invert-y");
 		}
 
-		let result = f.compiler.compile(
-			&blob,
-			name,
-			main,
-			target_profile,
-			&args[..],
-			Some(Box::new(DefaultIncludeHandler {})),
-			&[],
-		);
+		// Includes were expanded by preprocess so no include handler is needed
+		let result = f
+			.compiler
+			.compile(&blob, name, main, target_profile, &args[..], None, &[]);
 
 		match result {
 			Err(result) => {
-				let error_blob = result.0.get_error_buffer().unwrap();
+				let error_blob = result.0.get_error_buffer().map_err(|err| err.to_string())?;
 
 				let err_string = f
 					.library
-					.get_blob_as_string(&error_blob)
+					.get_blob_as_string(&error_blob.into())
+					.map_err(|err| err.to_string())?
 					.replace("\\n", "\n");
 				Err(err_string)
 			}
@@ -180,3 +415,68 @@ pub fn compile(
 		}
 	})
 }
+
+/// The null back end never runs shaders so the generated source is kept as the binary. This lets tests assert on
+/// the code generated by importers.
+#[cfg(backend = "null")]
+fn compile_preprocessed(
+	_name: &str,
+	source: &str,
+	_main: &str,
+	_variant: ShaderVariant,
+) -> std::result::Result<Vec<u8>, String> {
+	Ok(source.as_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn preprocess_expands_includes_once() {
+		let directory = std::env::temp_dir().join("gpu_preprocess_expands_includes_once");
+		fs::create_dir_all(directory.join("shaders")).unwrap();
+		fs::write(
+			directory.join("shaders/common.hlsli"),
+			"#include \"math.hlsli\"\nfloat common();\n",
+		)
+		.unwrap();
+		fs::write(directory.join("shaders/math.hlsli"), "float math();\n").unwrap();
+
+		let source =
+			"#include \"shaders/common.hlsli\"\n#include \"shaders/math.hlsli\"\nvoid main() {}\n";
+		let result = preprocess(
+			"pixel.hlsl",
+			source,
+			&[("USE_FOG", "1")],
+			std::slice::from_ref(&directory),
+		)
+		.unwrap();
+
+		let lines: Vec<&str> = result
+			.lines()
+			.filter(|it| !it.is_empty() && !it.starts_with("#line"))
+			.collect();
+		assert_eq!(
+			lines,
+			[
+				"#define USE_FOG 1",
+				"float math();",
+				"float common();",
+				"void main() {}"
+			]
+		);
+		assert!(result.ends_with("#line 2 \"pixel.hlsl\"\n\nvoid main() {}\n"));
+
+		let missing = preprocess(
+			"pixel.hlsl",
+			"#include \"missing.hlsli\"",
+			&[],
+			&[directory],
+		);
+		assert_eq!(
+			missing.unwrap_err(),
+			"pixel.hlsl(1): could not find include \"missing.hlsli\""
+		);
+	}
+}
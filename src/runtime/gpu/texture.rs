@@ -1,11 +1,17 @@
 use crate::Device;
 use crate::{
 	api,
+	texture_file::TextureFile,
+	texture_image::{
+		Channel,
+		TextureImage,
+	},
 	BufferUsage,
 	Gpu,
 	GraphicsRecorder,
 	MemoryType,
 	Result,
+	TransferRecorder,
 };
 
 use bitflags::bitflags;
@@ -21,21 +27,27 @@ use serde::{
 	Serialize,
 };
 
-use std::sync::Arc;
-
-use image::LoadResult;
-use stb_image::image;
+use std::{
+	path::Path,
+	sync::Arc,
+};
 
 #[allow(non_camel_case_types)]
-#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum Format {
 	Undefined,
 
+	R_U8,
+	RG_U8,
 	RGB_U8,
 	RGB_U8_SRGB,
 	RGBA_U8,
 	RGBA_U8_SRGB,
 
+	R_U16,
+	RG_U16,
+	RGBA_U16,
+
 	RGBA_F16,
 
 	RGBA_F32,
@@ -44,6 +56,19 @@ pub enum Format {
 
 	Depth16,
 	Depth24_Stencil8,
+
+	// Block compressed formats. Every 4x4 block of pixels is stored in 8 or 16 bytes
+	BC1_RGBA_UNORM,
+	BC1_RGBA_SRGB,
+	BC2_UNORM,
+	BC2_SRGB,
+	BC3_UNORM,
+	BC3_SRGB,
+	BC4_UNORM,
+	BC5_UNORM,
+	BC6H_UFLOAT,
+	BC7_UNORM,
+	BC7_SRGB,
 }
 
 impl Format {
@@ -58,6 +83,124 @@ impl Format {
 	pub fn is_color(self) -> bool {
 		!self.is_depth()
 	}
+
+	pub fn is_compressed(self) -> bool {
+		matches!(
+			self,
+			Self::BC1_RGBA_UNORM
+				| Self::BC1_RGBA_SRGB
+				| Self::BC2_UNORM
+				| Self::BC2_SRGB
+				| Self::BC3_UNORM
+				| Self::BC3_SRGB
+				| Self::BC4_UNORM
+				| Self::BC5_UNORM
+				| Self::BC6H_UFLOAT
+				| Self::BC7_UNORM
+				| Self::BC7_SRGB
+		)
+	}
+
+	pub fn is_srgb(self) -> bool {
+		matches!(
+			self,
+			Self::RGB_U8_SRGB
+				| Self::RGBA_U8_SRGB
+				| Self::BGR_U8_SRGB
+				| Self::BC1_RGBA_SRGB
+				| Self::BC2_SRGB
+				| Self::BC3_SRGB
+				| Self::BC7_SRGB
+		)
+	}
+
+	/// Returns the sRGB version of this format. Formats without one are returned unchanged
+	pub fn to_srgb(self) -> Self {
+		match self {
+			Self::RGB_U8 => Self::RGB_U8_SRGB,
+			Self::RGBA_U8 => Self::RGBA_U8_SRGB,
+			Self::BC1_RGBA_UNORM => Self::BC1_RGBA_SRGB,
+			Self::BC2_UNORM => Self::BC2_SRGB,
+			Self::BC3_UNORM => Self::BC3_SRGB,
+			Self::BC7_UNORM => Self::BC7_SRGB,
+			_ => self,
+		}
+	}
+
+	/// Returns the size of a single pixel in bytes. Compressed formats return the size of a 4x4 block
+	pub fn size(self) -> usize {
+		match self {
+			Self::Undefined => 0,
+			Self::R_U8 => 1,
+			Self::RG_U8 | Self::R_U16 => 2,
+			Self::RGB_U8 | Self::RGB_U8_SRGB => 3,
+			Self::RGBA_U8 | Self::RGBA_U8_SRGB | Self::BGR_U8_SRGB => 4,
+			Self::RG_U16 => 4,
+			Self::RGBA_U16 | Self::RGBA_F16 => 8,
+			Self::RGBA_F32 => 16,
+			Self::Depth16 => 2,
+			Self::Depth24_Stencil8 => 4,
+			Self::BC1_RGBA_UNORM | Self::BC1_RGBA_SRGB | Self::BC4_UNORM => 8,
+			Self::BC2_UNORM
+			| Self::BC2_SRGB
+			| Self::BC3_UNORM
+			| Self::BC3_SRGB
+			| Self::BC5_UNORM
+			| Self::BC6H_UFLOAT
+			| Self::BC7_UNORM
+			| Self::BC7_SRGB => 16,
+		}
+	}
+
+	/// Returns the size in bytes of a single `width` by `height` image in this format
+	pub fn size_of(self, width: u32, height: u32) -> usize {
+		if self.is_compressed() {
+			let blocks_x = width.div_ceil(4) as usize;
+			let blocks_y = height.div_ceil(4) as usize;
+			blocks_x * blocks_y * self.size()
+		} else {
+			width as usize * height as usize * self.size()
+		}
+	}
+
+	/// Converts tightly packed pixels in this format to RGBA bytes. Only uncompressed color formats can be converted
+	pub fn to_rgba_u8(self, bytes: Vec<u8>) -> Vec<u8> {
+		match self {
+			Self::R_U8 => bytes.iter().flat_map(|r| [*r, 0, 0, 255]).collect(),
+			Self::RG_U8 => bytes
+				.chunks_exact(2)
+				.flat_map(|p| [p[0], p[1], 0, 255])
+				.collect(),
+			Self::RGBA_U8 | Format::RGBA_U8_SRGB => bytes,
+			Self::BGR_U8_SRGB => bytes
+				.chunks_exact(4)
+				.flat_map(|p| [p[2], p[1], p[0], p[3]])
+				.collect(),
+			Self::RGB_U8 | Format::RGB_U8_SRGB => bytes
+				.chunks_exact(3)
+				.flat_map(|p| [p[0], p[1], p[2], 255])
+				.collect(),
+			// 16 bit channels keep their most significant byte
+			Self::R_U16 => bytes
+				.chunks_exact(2)
+				.flat_map(|c| [c[1], 0, 0, 255])
+				.collect(),
+			Self::RG_U16 => bytes
+				.chunks_exact(4)
+				.flat_map(|c| [c[1], c[3], 0, 255])
+				.collect(),
+			Self::RGBA_U16 => bytes.chunks_exact(2).map(|c| c[1]).collect(),
+			Self::RGBA_F16 => bytes
+				.chunks_exact(2)
+				.map(|c| unorm_to_u8(f16_to_f32(u16::from_le_bytes([c[0], c[1]]))))
+				.collect(),
+			Self::RGBA_F32 => bytes
+				.chunks_exact(4)
+				.map(|c| unorm_to_u8(f32::from_le_bytes([c[0], c[1], c[2], c[3]])))
+				.collect(),
+			_ => panic!("{:?} can not be converted to RGBA", self),
+		}
+	}
 }
 
 bitflags! {
@@ -68,10 +211,11 @@ bitflags! {
 		const COLOR_ATTACHMENT  = 0b001000;
 		const DEPTH_ATTACHMENT  = 0b010000;
 		const SWAPCHAIN         = 0b100000;
+		const STORAGE           = 0b1000000;
 	}
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Layout {
 	Undefined,
 	General,
@@ -83,27 +227,84 @@ pub enum Layout {
 	Present,
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum Wrap {
 	Clamp,
 	Repeat,
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum Filter {
 	Nearest,
 	Linear,
 }
 
+/// Size of a texture and how many mip levels and array layers it holds
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TextureExtent {
+	pub width: u32,
+	pub height: u32,
+	pub depth: u32,
+
+	pub mip_levels: u32,
+	pub array_layers: u32,
+
+	/// Array layers are viewed as the 6 faces of a cube
+	pub cube: bool,
+
+	/// Number of samples per pixel. Multisampled textures are drawn to and then resolved
+	pub samples: u32,
+}
+
+impl TextureExtent {
+	/// Extent with a single mip, array layer and sample
+	pub fn new(width: u32, height: u32, depth: u32) -> Self {
+		Self {
+			width,
+			height,
+			depth,
+
+			mip_levels: 1,
+			array_layers: 1,
+
+			cube: false,
+
+			samples: 1,
+		}
+	}
+
+	/// Returns the size of `mip` in pixels
+	pub fn mip_size(&self, mip: u32) -> (u32, u32, u32) {
+		(
+			(self.width >> mip).max(1),
+			(self.height >> mip).max(1),
+			(self.depth >> mip).max(1),
+		)
+	}
+
+	/// Returns the size in bytes of `mip` including every array layer
+	pub fn mip_size_in_bytes(&self, format: Format, mip: u32) -> usize {
+		let (width, height, depth) = self.mip_size(mip);
+		format.size_of(width, height) * depth as usize * self.array_layers as usize
+	}
+
+	/// Returns the size in bytes of the first `mip_levels` mips. Mips are packed from largest to smallest with
+	/// every array layer of a mip stored next to each other.
+	pub fn size_in_bytes(&self, format: Format, mip_levels: u32) -> usize {
+		(0..mip_levels)
+			.map(|mip| self.mip_size_in_bytes(format, mip))
+			.sum()
+	}
+}
+
 pub struct TextureBuilder<'a> {
 	usage: TextureUsage,
 	memory: MemoryType,
 	format: Format,
-	width: u32,
-	height: u32,
-	depth: u32,
+	extent: TextureExtent,
 
 	device: Option<&'a Device>,
+	debug_name: Option<String>,
 }
 
 impl<'a> TextureBuilder<'a> {
@@ -112,26 +313,93 @@ impl<'a> TextureBuilder<'a> {
 		self
 	}
 
+	/// Sets how many mips the texture has. See [`Texture::max_mip_levels`] for a full mip chain
+	pub fn mip_levels(mut self, mip_levels: u32) -> Self {
+		self.extent.mip_levels = mip_levels;
+		self
+	}
+
+	/// Makes the texture a texture array. Sampled using [`crate::PipelineResource::TextureArray`]
+	pub fn array_layers(mut self, array_layers: u32) -> Self {
+		self.extent.array_layers = array_layers;
+		self
+	}
+
+	/// Makes the texture a cube map with 6 layers. Sampled using [`crate::PipelineResource::TextureCube`]
+	pub fn cube(mut self) -> Self {
+		self.extent.array_layers = 6;
+		self.extent.cube = true;
+		self
+	}
+
+	/// Makes the texture multisampled. Only attachments can have more than one sample
+	pub fn samples(mut self, samples: u32) -> Self {
+		self.extent.samples = samples;
+		self
+	}
+
 	pub fn device(mut self, device: &'a Device) -> Self {
 		self.device = Some(device);
 		self
 	}
 
+	/// Names the texture in GPU captures and validation messages
+	pub fn debug_name(mut self, name: impl ToString) -> Self {
+		self.debug_name = Some(name.to_string());
+		self
+	}
+
 	pub fn spawn(self) -> Result<Texture> {
 		let device = match self.device {
 			Some(device) => device,
 			None => Gpu::device(),
 		};
 
-		Ok(Texture(api::Texture::new(
+		let TextureExtent {
+			width,
+			height,
+			mip_levels,
+			array_layers,
+			cube,
+			samples,
+			..
+		} = self.extent;
+		assert!(
+			mip_levels >= 1 && mip_levels <= Texture::max_mip_levels(width, height),
+			"Texture can not have {} mip levels",
+			mip_levels
+		);
+		assert!(array_layers >= 1);
+		assert!(
+			!cube || (array_layers == 6 && width == height),
+			"Cube maps must be square with 6 layers"
+		);
+		assert!(
+			samples.is_power_of_two() && samples <= 64,
+			"Texture can not have {} samples",
+			samples
+		);
+		assert!(
+			samples == 1
+				|| (mip_levels == 1
+					&& self.usage.intersects(
+						TextureUsage::COLOR_ATTACHMENT | TextureUsage::DEPTH_ATTACHMENT
+					)),
+			"Multisampled textures must be attachments with a single mip"
+		);
+
+		let texture = api::Texture::new(
 			device.0.clone(),
 			self.memory,
 			self.usage,
 			self.format,
-			self.width,
-			self.height,
-			self.depth,
-		)?))
+			self.extent,
+		)?;
+		if let Some(name) = &self.debug_name {
+			texture.set_debug_name(name);
+		}
+
+		Ok(Texture(texture))
 	}
 }
 
@@ -155,10 +423,76 @@ impl Texture {
 		self.0.depth()
 	}
 
+	pub fn extent(&self) -> TextureExtent {
+		self.0.extent
+	}
+
+	pub fn mip_levels(&self) -> u32 {
+		self.0.extent.mip_levels
+	}
+
+	pub fn array_layers(&self) -> u32 {
+		self.0.extent.array_layers
+	}
+
+	pub fn is_cube(&self) -> bool {
+		self.0.extent.cube
+	}
+
+	pub fn samples(&self) -> u32 {
+		self.0.extent.samples
+	}
+
+	/// Returns the number of mips in a full mip chain for a `width` by `height` texture
+	pub fn max_mip_levels(width: u32, height: u32) -> u32 {
+		32 - width.max(height).max(1).leading_zeros()
+	}
+
+	/// Index of the texture in the bindless arrays. Only textures with [`TextureUsage::SAMPLED`] or
+	/// [`TextureUsage::STORAGE`] have one. Each array holds 2048 textures and creating more fails.
 	pub fn bindless(&self) -> Option<u32> {
 		self.0.bindless()
 	}
 
+	#[cfg(backend = "null")]
+	pub fn id(&self) -> crate::ResourceId {
+		self.0.id
+	}
+
+	/// Size of the first layer of mip 0 which is what gets copied back to the CPU
+	pub(crate) fn size_in_bytes(&self) -> usize {
+		self.format().size_of(self.width(), self.height()) * self.depth() as usize
+	}
+
+	/// Copies the texture back to the CPU and converts it to tightly packed RGBA bytes with [`Format::to_rgba_u8`].
+	/// `layout` is the layout the texture is currently in and is restored once the copy is done.
+	///
+	/// Blocks until all submitted work on the device has finished. Only color formats can be read.
+	pub fn read_pixels(&self, layout: Layout) -> Result<Vec<u8>> {
+		let format = self.format();
+		assert!(
+			format.is_color() && !format.is_compressed() && format != Format::Undefined,
+			"Only uncompressed color textures can be read back"
+		);
+
+		let device = Device(self.0.owner.clone());
+		let buffer: crate::Buffer<u8> = crate::Buffer::new_in(
+			BufferUsage::TRANSFER_DST,
+			MemoryType::HostVisible,
+			self.size_in_bytes(),
+			&device,
+		)?;
+
+		GraphicsRecorder::new_in(&device)
+			.texture_barrier(self, layout, Layout::TransferSrc)
+			.copy_texture_to_buffer(&buffer, self)
+			.texture_barrier(self, Layout::TransferSrc, layout)
+			.submit()
+			.wait();
+
+		Ok(format.to_rgba_u8(buffer.read()?))
+	}
+
 	pub fn builder<'a>(
 		usage: TextureUsage,
 		format: Format,
@@ -170,11 +504,10 @@ impl Texture {
 			usage,
 			memory: MemoryType::DeviceLocal,
 			format,
-			width,
-			height,
-			depth,
+			extent: TextureExtent::new(width, height, depth),
 
 			device: None,
+			debug_name: None,
 		}
 	}
 
@@ -202,90 +535,240 @@ impl Texture {
 	}
 }
 
+fn unorm_to_u8(x: f32) -> u8 {
+	(x.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+}
+
+pub(crate) fn f16_to_f32(half: u16) -> f32 {
+	let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+	let exponent = ((half >> 10) & 0x1f) as i32;
+	let mantissa = (half & 0x3ff) as f32;
+
+	match exponent {
+		0 => sign * mantissa * 2.0f32.powi(-24),
+		0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+		0x1f => f32::NAN,
+		_ => sign * (1.0 + mantissa / 1024.0) * 2.0f32.powi(exponent - 15),
+	}
+}
+
 #[derive(Serialize, Deserialize)]
 pub(crate) struct TextureImporter {
 	#[serde(default)]
 	srgb: bool,
+
+	/// Generates a full mip chain for images that do not store their own mips
+	#[serde(default = "TextureImporter::default_generate_mips")]
+	generate_mips: bool,
+
+	/// Stores the last row of the image first. Containers are never flipped
+	#[serde(default = "TextureImporter::default_flip_y")]
+	flip_y: bool,
+
+	/// Multiplies color by alpha before uploading
+	#[serde(default)]
+	premultiply_alpha: bool,
+
+	/// Where each channel of the texture reads from. Swizzled images always have four channels
+	#[serde(default)]
+	swizzle: Option<[Channel; 4]>,
+
+	/// Format of HDR images. Either `RGBA_F16` or `RGBA_F32`
+	#[serde(default = "TextureImporter::default_hdr_format")]
+	hdr_format: Format,
+}
+
+impl TextureImporter {
+	fn default_generate_mips() -> bool {
+		true
+	}
+
+	fn default_flip_y() -> bool {
+		true
+	}
+
+	fn default_hdr_format() -> Format {
+		Format::RGBA_F16
+	}
+
+	fn upload(
+		&self,
+		name: &str,
+		format: Format,
+		extent: TextureExtent,
+		pixels: &[u8],
+	) -> resources::Result<Texture> {
+		let generate_mips = self.generate_mips
+			&& extent.mip_levels == 1
+			&& !format.is_compressed()
+			&& extent.width.max(extent.height) > 1;
+
+		let mut usage = TextureUsage::TRANSFER_DST | TextureUsage::SAMPLED;
+		let mut mip_levels = extent.mip_levels;
+		if generate_mips {
+			usage |= TextureUsage::TRANSFER_SRC;
+			mip_levels = Texture::max_mip_levels(extent.width, extent.height);
+		}
+
+		let pixel_buffer = crate::Buffer::new(
+			BufferUsage::TRANSFER_SRC,
+			MemoryType::HostVisible,
+			pixels.len(),
+		)?;
+		pixel_buffer.copy_to(pixels).unwrap();
+
+		let mut builder = Texture::builder(usage, format, extent.width, extent.height, 1)
+			.mip_levels(mip_levels)
+			.array_layers(extent.array_layers)
+			.debug_name(name);
+		if extent.cube {
+			builder = builder.cube();
+		}
+		let gpu_texture = builder.spawn()?;
+
+		// Pixels are uploaded on the transfer queue so loading does not wait on rendering. Mips are blitted which
+		// only the graphics queue can do
+		let recorder = TransferRecorder::new().texture_barrier(
+			&gpu_texture,
+			Layout::Undefined,
+			Layout::TransferDst,
+		);
+		let receipt = if generate_mips {
+			let upload = recorder
+				.copy_buffer_to_texture(&gpu_texture, &pixel_buffer)
+				.submit();
+			GraphicsRecorder::new()
+				.generate_mips(&gpu_texture)
+				.texture_barrier(&gpu_texture, Layout::TransferSrc, Layout::ShaderReadOnly)
+				.submit_but_wait_on(&[upload])
+		} else {
+			recorder
+				.copy_buffer_to_texture_mips(&gpu_texture, &pixel_buffer)
+				.texture_barrier(&gpu_texture, Layout::TransferDst, Layout::ShaderReadOnly)
+				.submit()
+		};
+		// Rendering waits on the upload instead of the importing thread
+		Gpu::device().wait_before_rendering(&receipt);
+
+		Ok(gpu_texture)
+	}
 }
 
 impl Importer for TextureImporter {
 	type Target = Texture;
 
-	fn import(&self, bytes: &[u8]) -> resources::Result<Self::Target> {
-		Ok(match image::load_from_memory(bytes) {
-			LoadResult::Error(err) => panic!("Failed to load texture from file due to {}", err),
-			LoadResult::ImageU8(image) => {
-				let mut pixels = if image.depth == 3 {
-					let cap = (image.data.len() / 3) * 4;
-					let mut result = Vec::with_capacity(cap);
-
-					for index in 0..image.data.len() / 3 {
-						let base = index * 3;
-						let r = image.data[base];
-						let g = image.data[base + 1];
-						let b = image.data[base + 2];
-						let a = 255;
-
-						result.push(r);
-						result.push(g);
-						result.push(b);
-						result.push(a);
-					}
-
-					result
-				} else {
-					assert_eq!(
-						image.depth, 4,
-						"Currently vulkan only supports 4 byte formats"
-					);
-					image.data.clone()
-				};
-
-				// UNSAFE: Casting slice of bytes to &[u32] to reverse properly
-				unsafe {
-					std::slice::from_raw_parts_mut(
-						pixels.as_mut_ptr() as *mut u32,
-						pixels.len() / 4,
-					)
-				}
-				.reverse();
-
-				let pixel_buffer = crate::Buffer::new(
-					BufferUsage::TRANSFER_SRC,
-					MemoryType::HostVisible,
-					pixels.len(),
-				)?;
-				pixel_buffer.copy_to(&pixels[..]).unwrap();
-
-				let format = if self.srgb {
-					Format::RGBA_U8_SRGB
-				} else {
-					Format::RGBA_U8
-				};
-
-				let gpu_texture = Texture::builder(
-					TextureUsage::TRANSFER_DST | TextureUsage::SAMPLED,
-					format,
-					image.width as u32,
-					image.height as u32,
-					1,
-				)
-				.spawn()?;
-
-				GraphicsRecorder::new()
-					.texture_barrier(&gpu_texture, Layout::Undefined, Layout::TransferDst)
-					.copy_buffer_to_texture(&gpu_texture, &pixel_buffer)
-					.texture_barrier(&gpu_texture, Layout::TransferDst, Layout::ShaderReadOnly)
-					.submit()
-					.wait();
-
-				gpu_texture
-			}
-			_ => unimplemented!(),
-		})
+	fn import(&self, bytes: &[u8], path: &Path) -> resources::Result<Self::Target> {
+		let name = path.display().to_string();
+
+		// DDS and KTX2 files are uploaded as is including their mips and layers
+		if TextureFile::is_container(bytes) {
+			let file = TextureFile::parse(bytes)?;
+			let format = if self.srgb {
+				file.format.to_srgb()
+			} else {
+				file.format
+			};
+			return self.upload(&name, format, file.extent, &file.pixels);
+		}
+
+		let mut image = TextureImage::decode(bytes)?;
+		if let Some(swizzle) = self.swizzle {
+			image.swizzle(swizzle);
+		}
+		if self.premultiply_alpha {
+			image.premultiply_alpha();
+		}
+		if self.flip_y {
+			image.flip_y();
+		}
+
+		let extent = TextureExtent::new(image.width, image.height, 1);
+		let (format, pixels) = image.into_pixels(self.srgb, self.hdr_format)?;
+		self.upload(&name, format, extent, &pixels)
 	}
 
 	fn export(&self, _resource: &Self::Target, _file: &mut std::fs::File) -> resources::Result<()> {
 		todo!()
 	}
 }
+
+#[cfg(all(test, backend = "null"))]
+mod tests {
+	use crate::*;
+
+	#[test]
+	fn reads_back_pixels() {
+		let device = test_device();
+
+		let pixels: Buffer<u8> = Buffer::new_in(
+			BufferUsage::TRANSFER_SRC,
+			MemoryType::HostVisible,
+			8,
+			&device,
+		)
+		.unwrap();
+		pixels.copy_to(&[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+
+		let texture = Texture::new_in(
+			TextureUsage::TRANSFER_SRC | TextureUsage::TRANSFER_DST,
+			Format::BGR_U8_SRGB,
+			2,
+			1,
+			1,
+			&device,
+		)
+		.unwrap();
+		GraphicsRecorder::new_in(&device)
+			.copy_buffer_to_texture(&texture, &pixels)
+			.submit()
+			.wait();
+
+		assert_eq!(
+			texture.read_pixels(Layout::ShaderReadOnly).unwrap(),
+			vec![3, 2, 1, 4, 7, 6, 5, 8]
+		);
+	}
+
+	#[test]
+	fn generates_mips() {
+		let device = test_device();
+
+		let texture = Texture::builder(
+			TextureUsage::TRANSFER_SRC | TextureUsage::TRANSFER_DST | TextureUsage::SAMPLED,
+			Format::RGBA_U8,
+			2,
+			2,
+			1,
+		)
+		.mip_levels(Texture::max_mip_levels(2, 2))
+		.array_layers(2)
+		.device(&device)
+		.spawn()
+		.unwrap();
+		assert_eq!(texture.mip_levels(), 2);
+
+		// Both layers of mip 0 followed by both layers of mip 1
+		let pixels: Buffer<u8> = Buffer::new_in(
+			BufferUsage::TRANSFER_SRC,
+			MemoryType::HostVisible,
+			texture.extent().size_in_bytes(Format::RGBA_U8, 1),
+			&device,
+		)
+		.unwrap();
+		let mut data = vec![0; 16];
+		data.extend([100; 16]);
+		data[..4].copy_from_slice(&[40, 80, 120, 200]);
+		pixels.copy_to(&data).unwrap();
+
+		let recorder = GraphicsRecorder::new_in(&device)
+			.texture_barrier(&texture, Layout::Undefined, Layout::TransferDst)
+			.copy_buffer_to_texture(&texture, &pixels)
+			.generate_mips(&texture);
+		assert_eq!(recorder.commands()[2], Command::GenerateMips(texture.id()));
+		recorder.submit().wait();
+
+		let memory = texture.0.memory.lock().unwrap();
+		assert_eq!(memory.len(), 40);
+		assert_eq!(&memory[32..], &[10, 20, 30, 50, 100, 100, 100, 100]);
+	}
+}
@@ -0,0 +1,350 @@
+use crate::{
+	Format,
+	TextureExtent,
+};
+
+use std::{
+	error::Error,
+	fmt,
+};
+
+/// Texture loaded from a DDS or KTX2 container. Pixels are stored in the same order
+/// [`crate::GraphicsRecorder::copy_buffer_to_texture_mips`] expects.
+pub(crate) struct TextureFile {
+	pub format: Format,
+	pub extent: TextureExtent,
+	pub pixels: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum TextureFileError {
+	Truncated,
+	UnsupportedFormat(String),
+	Supercompressed(u32),
+}
+
+impl fmt::Display for TextureFileError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Truncated => write!(f, "texture file is truncated"),
+			Self::UnsupportedFormat(format) => write!(f, "unsupported texture format {}", format),
+			Self::Supercompressed(scheme) => {
+				write!(f, "supercompression scheme {} is not supported", scheme)
+			}
+		}
+	}
+}
+
+impl Error for TextureFileError {}
+
+type Result<T> = std::result::Result<T, TextureFileError>;
+
+const DDS_MAGIC: &[u8] = b"DDS ";
+const KTX2_MAGIC: &[u8] = &[
+	0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+impl TextureFile {
+	/// Returns true if `bytes` start with a DDS or KTX2 header
+	pub fn is_container(bytes: &[u8]) -> bool {
+		bytes.starts_with(DDS_MAGIC) || bytes.starts_with(KTX2_MAGIC)
+	}
+
+	pub fn parse(bytes: &[u8]) -> Result<Self> {
+		let file = if bytes.starts_with(DDS_MAGIC) {
+			parse_dds(bytes)?
+		} else if bytes.starts_with(KTX2_MAGIC) {
+			parse_ktx2(bytes)?
+		} else {
+			return Err(TextureFileError::UnsupportedFormat("container".to_string()));
+		};
+
+		if file.extent.cube && file.extent.array_layers != 6 {
+			return Err(TextureFileError::UnsupportedFormat(
+				"cube map array".to_string(),
+			));
+		}
+		Ok(file)
+	}
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32> {
+	let slice = bytes
+		.get(offset..offset + 4)
+		.ok_or(TextureFileError::Truncated)?;
+	Ok(u32::from_le_bytes([slice[0], slice[1], slice[2], slice[3]]))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64> {
+	let low = read_u32(bytes, offset)? as u64;
+	let high = read_u32(bytes, offset + 4)? as u64;
+	Ok(low | (high << 32))
+}
+
+fn dxgi_format(format: u32) -> Result<Format> {
+	Ok(match format {
+		2 => Format::RGBA_F32,
+		10 => Format::RGBA_F16,
+		11 => Format::RGBA_U16,
+		28 => Format::RGBA_U8,
+		29 => Format::RGBA_U8_SRGB,
+		35 => Format::RG_U16,
+		49 => Format::RG_U8,
+		56 => Format::R_U16,
+		61 => Format::R_U8,
+		71 => Format::BC1_RGBA_UNORM,
+		72 => Format::BC1_RGBA_SRGB,
+		74 => Format::BC2_UNORM,
+		75 => Format::BC2_SRGB,
+		77 => Format::BC3_UNORM,
+		78 => Format::BC3_SRGB,
+		80 => Format::BC4_UNORM,
+		83 => Format::BC5_UNORM,
+		95 => Format::BC6H_UFLOAT,
+		98 => Format::BC7_UNORM,
+		99 => Format::BC7_SRGB,
+		_ => {
+			return Err(TextureFileError::UnsupportedFormat(format!(
+				"DXGI {}",
+				format
+			)))
+		}
+	})
+}
+
+fn vk_format(format: u32) -> Result<Format> {
+	Ok(match format {
+		9 => Format::R_U8,
+		16 => Format::RG_U8,
+		37 => Format::RGBA_U8,
+		43 => Format::RGBA_U8_SRGB,
+		70 => Format::R_U16,
+		77 => Format::RG_U16,
+		91 => Format::RGBA_U16,
+		97 => Format::RGBA_F16,
+		109 => Format::RGBA_F32,
+		133 => Format::BC1_RGBA_UNORM,
+		134 => Format::BC1_RGBA_SRGB,
+		135 => Format::BC2_UNORM,
+		136 => Format::BC2_SRGB,
+		137 => Format::BC3_UNORM,
+		138 => Format::BC3_SRGB,
+		139 => Format::BC4_UNORM,
+		141 => Format::BC5_UNORM,
+		143 => Format::BC6H_UFLOAT,
+		145 => Format::BC7_UNORM,
+		146 => Format::BC7_SRGB,
+		_ => {
+			return Err(TextureFileError::UnsupportedFormat(format!(
+				"VkFormat {}",
+				format
+			)))
+		}
+	})
+}
+
+// https://docs.microsoft.com/en-us/windows/win32/direct3ddds/dx-graphics-dds-pguide
+fn parse_dds(bytes: &[u8]) -> Result<TextureFile> {
+	const DDPF_FOURCC: u32 = 0x4;
+	const DDPF_RGB: u32 = 0x40;
+	const DDSCAPS2_CUBEMAP: u32 = 0x200;
+	const DX10_MISC_TEXTURECUBE: u32 = 0x4;
+
+	let height = read_u32(bytes, 12)?;
+	let width = read_u32(bytes, 16)?;
+	let mip_levels = read_u32(bytes, 28)?.max(1);
+	let pixel_flags = read_u32(bytes, 80)?;
+	let four_cc = bytes.get(84..88).ok_or(TextureFileError::Truncated)?;
+	let caps2 = read_u32(bytes, 112)?;
+
+	let mut offset = 128;
+	let mut array_layers = 1;
+	let mut cube = caps2 & DDSCAPS2_CUBEMAP != 0;
+	let format = if pixel_flags & DDPF_FOURCC != 0 {
+		match four_cc {
+			b"DXT1" => Format::BC1_RGBA_UNORM,
+			b"DXT2" | b"DXT3" => Format::BC2_UNORM,
+			b"DXT4" | b"DXT5" => Format::BC3_UNORM,
+			b"ATI1" | b"BC4U" => Format::BC4_UNORM,
+			b"ATI2" | b"BC5U" => Format::BC5_UNORM,
+			b"DX10" => {
+				let format = dxgi_format(read_u32(bytes, 128)?)?;
+				cube = read_u32(bytes, 136)? & DX10_MISC_TEXTURECUBE != 0;
+				array_layers = read_u32(bytes, 140)?.max(1);
+				offset = 148;
+				format
+			}
+			_ => {
+				let four_cc = String::from_utf8_lossy(four_cc).into_owned();
+				return Err(TextureFileError::UnsupportedFormat(four_cc));
+			}
+		}
+	} else if pixel_flags & DDPF_RGB != 0 && read_u32(bytes, 88)? == 32 {
+		// Only RGBA ordered 32 bit pixels are accepted
+		match read_u32(bytes, 92)? {
+			0x0000_00ff => Format::RGBA_U8,
+			mask => {
+				let mask = format!("red mask {:#x}", mask);
+				return Err(TextureFileError::UnsupportedFormat(mask));
+			}
+		}
+	} else {
+		return Err(TextureFileError::UnsupportedFormat(
+			"DDS pixel format".to_string(),
+		));
+	};
+
+	if cube {
+		array_layers *= 6;
+	}
+
+	let extent = TextureExtent {
+		mip_levels,
+		array_layers,
+		cube,
+		..TextureExtent::new(width, height, 1)
+	};
+
+	// DDS stores every mip of a layer before moving on to the next layer. Repack so each mip holds all layers
+	let layer_size = (0..mip_levels)
+		.map(|mip| {
+			let (width, height, _) = extent.mip_size(mip);
+			format.size_of(width, height)
+		})
+		.sum::<usize>();
+	let data = bytes
+		.get(offset..offset + layer_size * array_layers as usize)
+		.ok_or(TextureFileError::Truncated)?;
+
+	let mut pixels = Vec::with_capacity(data.len());
+	let mut mip_offset = 0;
+	for mip in 0..mip_levels {
+		let (width, height, _) = extent.mip_size(mip);
+		let mip_size = format.size_of(width, height);
+		for layer in 0..array_layers as usize {
+			let start = layer * layer_size + mip_offset;
+			pixels.extend_from_slice(&data[start..start + mip_size]);
+		}
+		mip_offset += mip_size;
+	}
+
+	Ok(TextureFile {
+		format,
+		extent,
+		pixels,
+	})
+}
+
+// https://github.khronos.org/KTX-Specification/
+fn parse_ktx2(bytes: &[u8]) -> Result<TextureFile> {
+	let format = vk_format(read_u32(bytes, 12)?)?;
+	let width = read_u32(bytes, 20)?;
+	let height = read_u32(bytes, 24)?.max(1);
+	let layer_count = read_u32(bytes, 32)?.max(1);
+	let face_count = read_u32(bytes, 36)?.max(1);
+	let mip_levels = read_u32(bytes, 40)?.max(1);
+	let supercompression = read_u32(bytes, 44)?;
+	if supercompression != 0 {
+		return Err(TextureFileError::Supercompressed(supercompression));
+	}
+
+	let extent = TextureExtent {
+		mip_levels,
+		array_layers: layer_count * face_count,
+		cube: face_count == 6,
+		..TextureExtent::new(width, height, 1)
+	};
+
+	// The level index starts after the header and lists mips from largest to smallest. Each level already
+	// stores every layer and face next to each other
+	let mut pixels = Vec::with_capacity(extent.size_in_bytes(format, mip_levels));
+	for mip in 0..mip_levels as usize {
+		let entry = 80 + mip * 24;
+		let offset = read_u64(bytes, entry)? as usize;
+		let length = read_u64(bytes, entry + 8)? as usize;
+		let level = bytes
+			.get(offset..offset + length)
+			.ok_or(TextureFileError::Truncated)?;
+		pixels.extend_from_slice(level);
+	}
+
+	Ok(TextureFile {
+		format,
+		extent,
+		pixels,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn dds_header(width: u32, height: u32, mip_levels: u32, four_cc: &[u8], caps2: u32) -> Vec<u8> {
+		let mut bytes = vec![0; 128];
+		bytes[..4].copy_from_slice(DDS_MAGIC);
+		bytes[12..16].copy_from_slice(&height.to_le_bytes());
+		bytes[16..20].copy_from_slice(&width.to_le_bytes());
+		bytes[28..32].copy_from_slice(&mip_levels.to_le_bytes());
+		bytes[80..84].copy_from_slice(&0x4u32.to_le_bytes());
+		bytes[84..88].copy_from_slice(four_cc);
+		bytes[112..116].copy_from_slice(&caps2.to_le_bytes());
+		bytes
+	}
+
+	#[test]
+	fn parses_dds_mips() {
+		// 8x8 BC1 has a 4 block mip 0, then 1 block for 4x4, 2x2 and 1x1
+		let mut bytes = dds_header(8, 8, 4, b"DXT1", 0);
+		bytes.extend((0..7 * 8).map(|it| it as u8));
+
+		let file = TextureFile::parse(&bytes).unwrap();
+		assert_eq!(file.format, Format::BC1_RGBA_UNORM);
+		assert_eq!(file.extent.mip_levels, 4);
+		assert_eq!(file.extent.array_layers, 1);
+		assert_eq!(file.pixels.len(), 7 * 8);
+		assert_eq!(
+			file.pixels.len(),
+			file.extent
+				.size_in_bytes(file.format, file.extent.mip_levels)
+		);
+	}
+
+	#[test]
+	fn repacks_dds_cube_faces() {
+		// Each face stores a 2 block mip 0 followed by a 1 block mip 1. Face index is written into every byte
+		let mut bytes = dds_header(4, 4, 2, b"DXT5", 0x200);
+		for face in 0..6u8 {
+			bytes.extend([face; 16]);
+			bytes.extend([face + 10; 16]);
+		}
+
+		let file = TextureFile::parse(&bytes).unwrap();
+		assert!(file.extent.cube);
+		assert_eq!(file.extent.array_layers, 6);
+
+		// Mip 0 of every face comes first then mip 1 of every face
+		let mip0: Vec<u8> = file.pixels[..6 * 16].iter().step_by(16).copied().collect();
+		let mip1: Vec<u8> = file.pixels[6 * 16..].iter().step_by(16).copied().collect();
+		assert_eq!(mip0, vec![0, 1, 2, 3, 4, 5]);
+		assert_eq!(mip1, vec![10, 11, 12, 13, 14, 15]);
+	}
+
+	#[test]
+	fn rejects_truncated_and_supercompressed() {
+		let mut bytes = dds_header(8, 8, 1, b"DXT1", 0);
+		bytes.extend([0; 8]);
+		assert_eq!(
+			TextureFile::parse(&bytes).err(),
+			Some(TextureFileError::Truncated)
+		);
+
+		let mut bytes = vec![0; 80];
+		bytes[..12].copy_from_slice(KTX2_MAGIC);
+		bytes[12..16].copy_from_slice(&145u32.to_le_bytes());
+		bytes[44..48].copy_from_slice(&2u32.to_le_bytes());
+		assert_eq!(
+			TextureFile::parse(&bytes).err(),
+			Some(TextureFileError::Supercompressed(2))
+		);
+	}
+}
@@ -0,0 +1,463 @@
+use crate::Format;
+
+use serde::{
+	self,
+	Deserialize,
+	Serialize,
+};
+
+use stb_image::image::{
+	self,
+	LoadResult,
+};
+
+use std::{
+	error::Error,
+	fmt,
+	io::Cursor,
+};
+
+/// Where a channel of an imported texture reads from. Channels missing from the source read as zero except
+/// alpha which reads as one. One and two channel images are gray so red, green and blue all read the gray channel
+/// and alpha reads the second channel.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub(crate) enum Channel {
+	R,
+	G,
+	B,
+	A,
+	Zero,
+	One,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum TextureImageError {
+	Decode(String),
+	UnsupportedChannels(usize),
+	UnsupportedHdrFormat(Format),
+}
+
+impl fmt::Display for TextureImageError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Decode(err) => write!(f, "failed to decode image due to {}", err),
+			Self::UnsupportedChannels(channels) => {
+				write!(f, "images with {} channels are not supported", channels)
+			}
+			Self::UnsupportedHdrFormat(format) => {
+				write!(
+					f,
+					"{:?} can not store HDR images. Use RGBA_F16 or RGBA_F32",
+					format
+				)
+			}
+		}
+	}
+}
+
+impl Error for TextureImageError {}
+
+type Result<T> = std::result::Result<T, TextureImageError>;
+
+const PNG_MAGIC: &[u8] = &[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+
+// Offset of the bit depth in the IHDR chunk which must directly follow the signature
+const PNG_BIT_DEPTH: usize = 24;
+
+/// Samples of every pixel in row order starting with the first row of the file
+#[derive(Debug, PartialEq)]
+pub(crate) enum Samples {
+	U8(Vec<u8>),
+	U16(Vec<u16>),
+	F32(Vec<f32>),
+}
+
+/// Image decoded from a PNG, JPG, PSD or HDR file. Stb decodes everything but 16 bit PNGs which it would
+/// truncate to 8 bits.
+#[derive(Debug, PartialEq)]
+pub(crate) struct TextureImage {
+	pub width: u32,
+	pub height: u32,
+	pub channels: usize,
+	pub samples: Samples,
+}
+
+impl TextureImage {
+	pub fn decode(bytes: &[u8]) -> Result<Self> {
+		if bytes.starts_with(PNG_MAGIC) && bytes.get(PNG_BIT_DEPTH) == Some(&16) {
+			return decode_png16(bytes);
+		}
+
+		match image::load_from_memory(bytes) {
+			LoadResult::Error(err) => Err(TextureImageError::Decode(err)),
+			LoadResult::ImageU8(image) => Ok(Self {
+				width: image.width as u32,
+				height: image.height as u32,
+				channels: image.depth,
+				samples: Samples::U8(image.data),
+			}),
+			LoadResult::ImageF32(image) => Ok(Self {
+				width: image.width as u32,
+				height: image.height as u32,
+				channels: image.depth,
+				samples: Samples::F32(image.data),
+			}),
+		}
+	}
+
+	/// Reverses the order of the rows
+	pub fn flip_y(&mut self) {
+		let row = self.width as usize * self.channels;
+		match &mut self.samples {
+			Samples::U8(samples) => flip_rows(samples, row),
+			Samples::U16(samples) => flip_rows(samples, row),
+			Samples::F32(samples) => flip_rows(samples, row),
+		}
+	}
+
+	/// Rebuilds every pixel from `channels`. The image always has four channels afterwards
+	pub fn swizzle(&mut self, channels: [Channel; 4]) {
+		let from = self.channels;
+		self.samples = match &self.samples {
+			Samples::U8(samples) => Samples::U8(swizzle(samples, from, channels)),
+			Samples::U16(samples) => Samples::U16(swizzle(samples, from, channels)),
+			Samples::F32(samples) => Samples::F32(swizzle(samples, from, channels)),
+		};
+		self.channels = 4;
+	}
+
+	/// Multiplies color by alpha. Alpha is the last channel of images with two or four channels. Others
+	/// are opaque and left alone
+	pub fn premultiply_alpha(&mut self) {
+		if self.channels != 2 && self.channels != 4 {
+			return;
+		}
+		let channels = self.channels;
+		match &mut self.samples {
+			Samples::U8(samples) => premultiply_alpha(samples, channels),
+			Samples::U16(samples) => premultiply_alpha(samples, channels),
+			Samples::F32(samples) => premultiply_alpha(samples, channels),
+		}
+	}
+
+	/// Returns the format and tightly packed bytes to upload. One and two channel images keep their channel
+	/// count. Three channel and HDR images are expanded to RGBA with an opaque alpha. Gray HDR images stay gray.
+	pub fn into_pixels(mut self, srgb: bool, hdr_format: Format) -> Result<(Format, Vec<u8>)> {
+		if !(1..=4).contains(&self.channels) {
+			return Err(TextureImageError::UnsupportedChannels(self.channels));
+		}
+		if self.channels == 3 || (matches!(self.samples, Samples::F32(_)) && self.channels != 4) {
+			self.swizzle([Channel::R, Channel::G, Channel::B, Channel::A]);
+		}
+
+		Ok(match (self.samples, self.channels) {
+			(Samples::U8(samples), channels) => {
+				let format = match channels {
+					1 => Format::R_U8,
+					2 => Format::RG_U8,
+					_ if srgb => Format::RGBA_U8_SRGB,
+					_ => Format::RGBA_U8,
+				};
+				(format, samples)
+			}
+			(Samples::U16(samples), channels) => {
+				let format = match channels {
+					1 => Format::R_U16,
+					2 => Format::RG_U16,
+					_ => Format::RGBA_U16,
+				};
+				let bytes = samples.iter().flat_map(|it| it.to_le_bytes()).collect();
+				(format, bytes)
+			}
+			(Samples::F32(samples), _) => {
+				let bytes = match hdr_format {
+					Format::RGBA_F16 => samples
+						.iter()
+						.flat_map(|it| f32_to_f16(*it).to_le_bytes())
+						.collect(),
+					Format::RGBA_F32 => samples.iter().flat_map(|it| it.to_le_bytes()).collect(),
+					_ => return Err(TextureImageError::UnsupportedHdrFormat(hdr_format)),
+				};
+				(hdr_format, bytes)
+			}
+		})
+	}
+}
+
+fn decode_png16(bytes: &[u8]) -> Result<TextureImage> {
+	let decode = |err: png::DecodingError| TextureImageError::Decode(err.to_string());
+
+	// Expands transparency chunks into an alpha channel. 16 bit images never have a palette
+	let mut decoder = png::Decoder::new(Cursor::new(bytes));
+	decoder.set_transformations(png::Transformations::EXPAND);
+	let mut reader = decoder.read_info().map_err(decode)?;
+
+	let size = reader
+		.output_buffer_size()
+		.ok_or_else(|| TextureImageError::Decode("image is too large".to_string()))?;
+	let mut buffer = vec![0; size];
+	let info = reader.next_frame(&mut buffer).map_err(decode)?;
+	buffer.truncate(info.buffer_size());
+
+	// PNG stores samples big endian
+	let samples = buffer
+		.chunks_exact(2)
+		.map(|it| u16::from_be_bytes([it[0], it[1]]))
+		.collect();
+
+	Ok(TextureImage {
+		width: info.width,
+		height: info.height,
+		channels: info.color_type.samples(),
+		samples: Samples::U16(samples),
+	})
+}
+
+trait Sample: Copy {
+	const ZERO: Self;
+	const ONE: Self;
+
+	fn to_f32(self) -> f32;
+	fn from_f32(x: f32) -> Self;
+}
+
+impl Sample for u8 {
+	const ZERO: Self = 0;
+	const ONE: Self = u8::MAX;
+
+	fn to_f32(self) -> f32 {
+		self as f32 / u8::MAX as f32
+	}
+
+	fn from_f32(x: f32) -> Self {
+		(x.clamp(0.0, 1.0) * u8::MAX as f32 + 0.5) as u8
+	}
+}
+
+impl Sample for u16 {
+	const ZERO: Self = 0;
+	const ONE: Self = u16::MAX;
+
+	fn to_f32(self) -> f32 {
+		self as f32 / u16::MAX as f32
+	}
+
+	fn from_f32(x: f32) -> Self {
+		(x.clamp(0.0, 1.0) * u16::MAX as f32 + 0.5) as u16
+	}
+}
+
+impl Sample for f32 {
+	const ZERO: Self = 0.0;
+	const ONE: Self = 1.0;
+
+	fn to_f32(self) -> f32 {
+		self
+	}
+
+	fn from_f32(x: f32) -> Self {
+		x
+	}
+}
+
+fn flip_rows<T: Copy>(samples: &mut [T], row: usize) {
+	let rows = samples.len() / row;
+	for y in 0..rows / 2 {
+		let (top, bottom) = samples.split_at_mut((rows - 1 - y) * row);
+		top[y * row..(y + 1) * row].swap_with_slice(&mut bottom[..row]);
+	}
+}
+
+fn swizzle<T: Sample>(samples: &[T], from: usize, channels: [Channel; 4]) -> Vec<T> {
+	let mut result = Vec::with_capacity(samples.len() / from * 4);
+	for pixel in samples.chunks_exact(from) {
+		for channel in channels {
+			let sample = match (channel, from) {
+				(Channel::Zero, _) => T::ZERO,
+				(Channel::One, _) => T::ONE,
+				(Channel::A, 2 | 4) => pixel[from - 1],
+				(Channel::A, _) => T::ONE,
+				(_, 1 | 2) => pixel[0],
+				_ => pixel.get(channel as usize).copied().unwrap_or(T::ZERO),
+			};
+			result.push(sample);
+		}
+	}
+	result
+}
+
+fn premultiply_alpha<T: Sample>(samples: &mut [T], channels: usize) {
+	for pixel in samples.chunks_exact_mut(channels) {
+		let (alpha, color) = pixel.split_last_mut().unwrap();
+		let alpha = alpha.to_f32();
+		for it in color {
+			*it = T::from_f32(it.to_f32() * alpha);
+		}
+	}
+}
+
+/// Converts to the nearest half float rounding ties to even
+pub(crate) fn f32_to_f16(x: f32) -> u16 {
+	let bits = x.to_bits();
+	let sign = ((bits >> 16) & 0x8000) as u16;
+	let exponent = ((bits >> 23) & 0xff) as i32;
+	let mantissa = bits & 0x7f_ffff;
+
+	// Infinity and NaN
+	if exponent == 0xff {
+		return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+	}
+
+	let exponent = exponent - 127 + 15;
+	if exponent >= 0x1f {
+		return sign | 0x7c00;
+	}
+
+	let (half, rest, halfway) = if exponent <= 0 {
+		// Too small for a normal half so it becomes subnormal or zero
+		if exponent < -10 {
+			return sign;
+		}
+		let mantissa = mantissa | 0x80_0000;
+		let shift = (14 - exponent) as u32;
+		(
+			mantissa >> shift,
+			mantissa & ((1 << shift) - 1),
+			1 << (shift - 1),
+		)
+	} else {
+		(
+			((exponent as u32) << 10) | (mantissa >> 13),
+			mantissa & 0x1fff,
+			0x1000,
+		)
+	};
+
+	// Rounding up may carry into the exponent which is still the correctly rounded result
+	let round = rest > halfway || (rest == halfway && half & 1 == 1);
+	sign | (half + round as u32) as u16
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::texture::f16_to_f32;
+
+	fn image(channels: usize, samples: Vec<u8>) -> TextureImage {
+		TextureImage {
+			width: 2,
+			height: (samples.len() / channels / 2) as u32,
+			channels,
+			samples: Samples::U8(samples),
+		}
+	}
+
+	#[test]
+	fn flips_rows_without_mirroring() {
+		let mut image = image(1, vec![1, 2, 3, 4, 5, 6]);
+		image.flip_y();
+		assert_eq!(image.samples, Samples::U8(vec![5, 6, 3, 4, 1, 2]));
+	}
+
+	#[test]
+	fn keeps_gray_and_expands_rgb() {
+		let (format, pixels) = image(1, vec![1, 2])
+			.into_pixels(true, Format::RGBA_F16)
+			.unwrap();
+		assert_eq!((format, pixels), (Format::R_U8, vec![1, 2]));
+
+		let (format, pixels) = image(3, vec![1, 2, 3, 4, 5, 6])
+			.into_pixels(true, Format::RGBA_F16)
+			.unwrap();
+		assert_eq!(format, Format::RGBA_U8_SRGB);
+		assert_eq!(pixels, [1, 2, 3, 255, 4, 5, 6, 255]);
+
+		let sixteen = TextureImage {
+			width: 1,
+			height: 1,
+			channels: 2,
+			samples: Samples::U16(vec![0x0102, 0xfffe]),
+		};
+		let (format, pixels) = sixteen.into_pixels(false, Format::RGBA_F16).unwrap();
+		assert_eq!((format, pixels), (Format::RG_U16, vec![2, 1, 0xfe, 0xff]));
+	}
+
+	#[test]
+	fn swizzles_and_premultiplies() {
+		let mut gray = image(2, vec![200, 128, 10, 255]);
+		gray.premultiply_alpha();
+		assert_eq!(gray.samples, Samples::U8(vec![100, 128, 10, 255]));
+
+		gray.swizzle([Channel::R, Channel::R, Channel::Zero, Channel::A]);
+		assert_eq!(gray.channels, 4);
+		assert_eq!(
+			gray.samples,
+			Samples::U8(vec![100, 100, 0, 128, 10, 10, 0, 255])
+		);
+
+		let mut rgb = image(3, vec![1, 2, 3, 4, 5, 6]);
+		rgb.swizzle([Channel::B, Channel::G, Channel::R, Channel::A]);
+		assert_eq!(rgb.samples, Samples::U8(vec![3, 2, 1, 255, 6, 5, 4, 255]));
+	}
+
+	#[test]
+	fn swizzles_gray_and_alpha() {
+		let mut gray = image(2, vec![200, 128, 10, 255]);
+		gray.swizzle([Channel::R, Channel::R, Channel::R, Channel::A]);
+		assert_eq!(
+			gray.samples,
+			Samples::U8(vec![200, 200, 200, 128, 10, 10, 10, 255])
+		);
+
+		let mut gray = image(2, vec![200, 128, 10, 255]);
+		gray.swizzle([Channel::G, Channel::B, Channel::Zero, Channel::One]);
+		assert_eq!(
+			gray.samples,
+			Samples::U8(vec![200, 200, 0, 255, 10, 10, 0, 255])
+		);
+
+		let hdr = TextureImage {
+			width: 1,
+			height: 1,
+			channels: 1,
+			samples: Samples::F32(vec![0.5]),
+		};
+		let (_, pixels) = hdr.into_pixels(false, Format::RGBA_F32).unwrap();
+		let pixels: Vec<f32> = pixels
+			.chunks_exact(4)
+			.map(|it| f32::from_le_bytes([it[0], it[1], it[2], it[3]]))
+			.collect();
+		assert_eq!(pixels, [0.5, 0.5, 0.5, 1.0]);
+	}
+
+	#[test]
+	fn converts_hdr_to_half_floats() {
+		for x in [0.0, -2.5, 1.0 / 3.0, 65504.0, 2.0f32.powi(-24), 1.0e-3] {
+			let half = f32_to_f16(x);
+			assert!((f16_to_f32(half) - x).abs() <= x.abs() / 1024.0, "{}", x);
+		}
+		assert_eq!(f32_to_f16(1.0e6), 0x7c00);
+		assert_eq!(f32_to_f16(1.0e-9), 0);
+
+		let hdr = TextureImage {
+			width: 1,
+			height: 1,
+			channels: 3,
+			samples: Samples::F32(vec![0.5, 1.0, 2.0]),
+		};
+		let (format, pixels) = hdr.into_pixels(false, Format::RGBA_F16).unwrap();
+		assert_eq!(format, Format::RGBA_F16);
+		assert_eq!(pixels, [0x00, 0x38, 0x00, 0x3c, 0x00, 0x40, 0x00, 0x3c]);
+
+		let hdr = TextureImage {
+			width: 1,
+			height: 1,
+			channels: 4,
+			samples: Samples::F32(vec![0.0; 4]),
+		};
+		assert_eq!(
+			hdr.into_pixels(false, Format::RGBA_U8),
+			Err(TextureImageError::UnsupportedHdrFormat(Format::RGBA_U8))
+		);
+	}
+}
@@ -1,8 +1,10 @@
 use super::{
+	BindlessBuffer,
 	Device,
 	DeviceAllocation,
 };
 use crate::{
+	BindlessKind,
 	BufferUsage,
 	MemoryType,
 	Result,
@@ -11,6 +13,7 @@ use crate::{
 use ash::version::DeviceV1_0;
 use ash::vk;
 
+use std::mem::MaybeUninit;
 use std::ptr::copy_nonoverlapping;
 use std::sync::{
 	Arc,
@@ -25,6 +28,7 @@ pub struct Buffer {
 	pub size: usize,
 
 	pub usage: BufferUsage,
+	pub memory_type: MemoryType,
 
 	pub bindless: Option<u32>, // Index into owner bindless buffer array
 }
@@ -33,7 +37,7 @@ impl Buffer {
 	pub fn new(
 		owner: Arc<Device>,
 		usage: BufferUsage,
-		memory: MemoryType,
+		memory_type: MemoryType,
 		size: usize,
 	) -> Result<Arc<Buffer>> {
 		let mut vk_usage = vk::BufferUsageFlags::default();
@@ -49,57 +53,40 @@ impl Buffer {
 		if usage.contains(BufferUsage::INDEX) {
 			vk_usage |= vk::BufferUsageFlags::INDEX_BUFFER;
 		}
-		if usage.contains(BufferUsage::CONSTANTS) {
+		if usage.contains(BufferUsage::INDIRECT) {
+			vk_usage |= vk::BufferUsageFlags::INDIRECT_BUFFER;
+		}
+		if usage.intersects(BufferUsage::CONSTANTS | BufferUsage::STORAGE) {
 			vk_usage |= vk::BufferUsageFlags::STORAGE_BUFFER;
 		}
 
+		let queue_family_indices = owner.queue_family_indices();
+
 		unsafe {
 			let create_info = vk::BufferCreateInfo::builder()
 				.size(size as vk::DeviceSize)
 				.usage(vk_usage)
-				.sharing_mode(vk::SharingMode::EXCLUSIVE);
+				.sharing_mode(Device::sharing_mode(&queue_family_indices))
+				.queue_family_indices(&queue_family_indices);
 
 			let handle = owner.logical.create_buffer(&create_info, None)?;
 
 			// Allocate memory for buffer
-			let memory = owner
-				.allocate_memory(owner.logical.get_buffer_memory_requirements(handle), memory)?;
-			owner.logical.bind_buffer_memory(handle, memory.memory, 0)?;
-
-			// Add a weak reference to the device for bindless
-			if usage.contains(BufferUsage::CONSTANTS) {
-				let mut bindless = owner.bindless_info.lock().unwrap();
-
-				let found = bindless
-					.buffers
-					.iter_mut()
-					.enumerate()
-					.find(|(_, x)| x.strong_count() == 0)
-					.map(|(index, _)| index);
-
-				let index = found.unwrap_or(bindless.buffers.len());
-
-				let result = Arc::new(Buffer {
-					owner: owner.clone(),
-
-					handle,
-					memory: RwLock::new(memory),
-					size,
-
-					usage,
-
-					bindless: Some(index as u32),
-				});
-
-				let weak = Arc::downgrade(&result);
-				if found.is_some() {
-					bindless.buffers[index] = weak;
-				} else {
-					bindless.buffers.push(weak);
-				}
+			let memory = owner.allocate_memory(
+				owner.logical.get_buffer_memory_requirements(handle),
+				memory_type,
+				true,
+			)?;
+			owner
+				.logical
+				.bind_buffer_memory(handle, memory.memory, memory.offset)?;
 
-				return Ok(result);
-			}
+			let bindless = if usage.intersects(BufferUsage::CONSTANTS | BufferUsage::STORAGE) {
+				let slot = BindlessBuffer { handle, size };
+				Some(owner.allocate_bindless_buffer(slot, format!("{} bytes", size))?)
+			} else {
+				None
+			};
 
 			Ok(Arc::new(Buffer {
 				owner,
@@ -109,30 +96,41 @@ impl Buffer {
 				size,
 
 				usage,
+				memory_type,
 
-				bindless: None,
+				bindless,
 			}))
 		}
 	}
 
 	pub fn copy_to<T>(&self, data: &[T]) -> Result<()> {
 		let memory = self.memory.write().unwrap();
+		if memory.mapped == 0 {
+			return Err(vk::Result::ERROR_MEMORY_MAP_FAILED);
+		}
 
+		let len = std::mem::size_of_val(data).min(self.size);
 		unsafe {
-			let ptr = self.owner.logical.map_memory(
-				memory.memory,
-				memory.offset,
-				memory.size,
-				vk::MemoryMapFlags::empty(),
-			)?;
+			copy_nonoverlapping(data.as_ptr() as *const u8, memory.mapped as *mut u8, len);
+		}
+		Ok(())
+	}
+
+	pub fn copy_from<T>(&self, data: &mut [MaybeUninit<T>]) -> Result<()> {
+		if self.memory_type != MemoryType::HostVisible {
+			return Err(vk::Result::ERROR_MEMORY_MAP_FAILED);
+		}
+
+		let len = std::mem::size_of_val(data);
+		assert!(len <= self.size);
 
+		let memory = self.memory.read().unwrap();
+		unsafe {
 			copy_nonoverlapping(
-				data.as_ptr() as *const u8,
-				ptr as *mut u8,
-				memory.size as usize,
+				memory.mapped as *const u8,
+				data.as_mut_ptr() as *mut u8,
+				len,
 			);
-
-			self.owner.logical.unmap_memory(memory.memory);
 		}
 		Ok(())
 	}
@@ -140,10 +138,21 @@ impl Buffer {
 	pub fn bindless(&self) -> Option<u32> {
 		self.bindless
 	}
+
+	pub fn set_debug_name(&self, name: &str) {
+		self.owner.set_debug_name(self.handle, name);
+		if let Some(bindless) = self.bindless {
+			self.owner
+				.set_bindless_name(BindlessKind::Buffer, bindless, name);
+		}
+	}
 }
 
 impl Drop for Buffer {
 	fn drop(&mut self) {
+		if let Some(bindless) = self.bindless {
+			self.owner.release_bindless(BindlessKind::Buffer, bindless);
+		}
 		unsafe {
 			self.owner.logical.destroy_buffer(self.handle, None);
 		}
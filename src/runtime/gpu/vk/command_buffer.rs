@@ -1,14 +1,24 @@
 use super::{
 	vk_format_aspect_mask,
+	AttachmentKey,
 	Buffer,
+	ComputePipeline,
 	Device,
 	DeviceThreadInfo,
-	Format,
 	GraphicsPipeline,
+	Sampler,
 	Texture,
 };
 use crate::{
+	ConstantMember,
+	DrawIndexedIndirect,
+	DrawIndirect,
+	GpuReport,
 	Layout,
+	PipelineResource,
+	Queries,
+	Queue,
+	RenderPassAttachment,
 	Result,
 };
 
@@ -20,27 +30,162 @@ use math::{
 use ash::version::DeviceV1_0;
 use ash::vk;
 
+use std::collections::HashMap;
+use std::ffi::CString;
 use std::slice::{
 	from_raw_parts,
 	from_ref,
 };
 use std::sync::Arc;
 
+pub enum BoundPipeline {
+	Graphics(Arc<GraphicsPipeline>),
+	Compute(Arc<ComputePipeline>),
+}
+
+impl BoundPipeline {
+	fn bind_point(&self) -> vk::PipelineBindPoint {
+		match self {
+			Self::Graphics(_) => vk::PipelineBindPoint::GRAPHICS,
+			Self::Compute(_) => vk::PipelineBindPoint::COMPUTE,
+		}
+	}
+
+	fn stage_flags(&self) -> vk::ShaderStageFlags {
+		match self {
+			Self::Graphics(_) => vk::ShaderStageFlags::ALL_GRAPHICS,
+			Self::Compute(_) => vk::ShaderStageFlags::COMPUTE,
+		}
+	}
+
+	fn handle(&self) -> vk::Pipeline {
+		match self {
+			Self::Graphics(pipeline) => pipeline.handle,
+			Self::Compute(pipeline) => pipeline.handle,
+		}
+	}
+
+	fn layout(&self) -> vk::PipelineLayout {
+		match self {
+			Self::Graphics(pipeline) => pipeline.layout,
+			Self::Compute(pipeline) => pipeline.layout,
+		}
+	}
+
+	fn samplers(&self) -> &[(Arc<Sampler>, usize)] {
+		match self {
+			Self::Graphics(pipeline) => &pipeline.samplers,
+			Self::Compute(pipeline) => &pipeline.samplers,
+		}
+	}
+
+	fn constants(&self) -> &HashMap<String, Vec<ConstantMember>> {
+		match self {
+			Self::Graphics(pipeline) => &pipeline.description.constants,
+			Self::Compute(pipeline) => &pipeline.description.constants,
+		}
+	}
+
+	fn resources(&self) -> &HashMap<String, PipelineResource> {
+		match self {
+			Self::Graphics(pipeline) => &pipeline.description.resources,
+			Self::Compute(pipeline) => &pipeline.description.resources,
+		}
+	}
+
+	fn push_constant_size(&self) -> usize {
+		match self {
+			Self::Graphics(pipeline) => pipeline.description.push_constant_size(),
+			Self::Compute(pipeline) => pipeline.description.push_constant_size(),
+		}
+	}
+}
+
 pub struct GraphicsCommandBuffer {
 	pub owner: Arc<Device>,
+	// Resolved by the device so it is only compute or transfer when the device has a dedicated family
+	pub queue: Queue,
 
 	pub command_buffer: vk::CommandBuffer,
 
 	pub framebuffers: Vec<vk::Framebuffer>,
 	pub pipelines: Vec<Arc<GraphicsPipeline>>,
+	pub compute_pipelines: Vec<Arc<ComputePipeline>>,
 	pub textures: Vec<Arc<Texture>>,
 	pub buffers: Vec<Arc<Buffer>>,
 
 	pub current_scissor: Option<Rect>,
+	pub current_viewport: Option<Rect>,
 	pub current_attachments: Option<Vec<Arc<Texture>>>,
-	pub current_pipeline: Option<Arc<GraphicsPipeline>>,
+	pub current_pipeline: Option<BoundPipeline>,
 
 	pub push_constants: [u32; 32],
+
+	pub queries: Queries,
+	// Number of debug markers that have not been popped
+	pub markers: u32,
+	// Breadcrumb ids of the open markers. Empty when the device does not write breadcrumbs
+	pub breadcrumbs: Vec<u32>,
+	// Taken from the device the first time a query is recorded and handed back when dropped
+	pub query_pools: Option<QueryPools>,
+}
+
+const MAX_TIMESTAMPS: u32 = 256;
+const MAX_STATISTICS: u32 = 32;
+
+// Counters read by a statistics query. Results are written in bit order which matches `PipelineStatistics`
+const STATISTICS_FLAGS: vk::QueryPipelineStatisticFlags = vk::QueryPipelineStatisticFlags::from_raw(
+	vk::QueryPipelineStatisticFlags::INPUT_ASSEMBLY_PRIMITIVES.as_raw()
+		| vk::QueryPipelineStatisticFlags::VERTEX_SHADER_INVOCATIONS.as_raw()
+		| vk::QueryPipelineStatisticFlags::CLIPPING_PRIMITIVES.as_raw()
+		| vk::QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS.as_raw()
+		| vk::QueryPipelineStatisticFlags::COMPUTE_SHADER_INVOCATIONS.as_raw(),
+);
+
+/// Timestamp and statistics pools of a command buffer. A pool is null when its queries are not supported.
+/// Pools are kept on the device and reused by later command buffers.
+#[derive(Clone, Copy, Default)]
+pub struct QueryPools {
+	pub timestamps: vk::QueryPool,
+	pub statistics: vk::QueryPool,
+}
+
+impl QueryPools {
+	pub fn new(device: &Device) -> Self {
+		let create = |query_type, count, statistics| {
+			let create_info = vk::QueryPoolCreateInfo::builder()
+				.query_type(query_type)
+				.query_count(count)
+				.pipeline_statistics(statistics);
+			unsafe {
+				device
+					.logical
+					.create_query_pool(&create_info, None)
+					.unwrap()
+			}
+		};
+
+		Self {
+			timestamps: if device.timestamps {
+				create(
+					vk::QueryType::TIMESTAMP,
+					MAX_TIMESTAMPS,
+					vk::QueryPipelineStatisticFlags::empty(),
+				)
+			} else {
+				vk::QueryPool::null()
+			},
+			statistics: if device.pipeline_statistics {
+				create(
+					vk::QueryType::PIPELINE_STATISTICS,
+					MAX_STATISTICS,
+					STATISTICS_FLAGS,
+				)
+			} else {
+				vk::QueryPool::null()
+			},
+		}
+	}
 }
 
 fn layout_to_image_layout(layout: Layout) -> vk::ImageLayout {
@@ -85,19 +230,201 @@ impl GraphicsCommandBuffer {
 		};
 	}
 
-	pub fn copy_buffer_to_texture(&mut self, dst: Arc<Texture>, src: Arc<Buffer>) {
-		let subresource = vk::ImageSubresourceLayers::builder()
-			.aspect_mask(vk::ImageAspectFlags::COLOR)
-			.layer_count(1);
+	// Queries are reset by the command buffer that uses them so pools can be reused without waiting on the host
+	fn query_pools(&mut self) -> QueryPools {
+		if let Some(pools) = self.query_pools {
+			return pools;
+		}
 
-		let extent = vk::Extent3D::builder()
-			.width(dst.width)
-			.height(dst.height)
-			.depth(dst.depth);
+		let pools = self.owner.take_query_pools();
+		unsafe {
+			if pools.timestamps != vk::QueryPool::null() {
+				self.owner.logical.cmd_reset_query_pool(
+					self.command_buffer,
+					pools.timestamps,
+					0,
+					MAX_TIMESTAMPS,
+				);
+			}
+			if pools.statistics != vk::QueryPool::null() {
+				self.owner.logical.cmd_reset_query_pool(
+					self.command_buffer,
+					pools.statistics,
+					0,
+					MAX_STATISTICS,
+				);
+			}
+		}
+		self.query_pools = Some(pools);
+		pools
+	}
 
-		let region = vk::BufferImageCopy::builder()
-			.image_subresource(subresource.build())
-			.image_extent(extent.build());
+	fn write_timestamp(&mut self, index: u32, stage: vk::PipelineStageFlags) {
+		if !self.owner.timestamps {
+			return;
+		}
+		assert!(
+			index < MAX_TIMESTAMPS,
+			"Too many timers in a single command buffer"
+		);
+
+		let pool = self.query_pools().timestamps;
+		unsafe {
+			self.owner
+				.logical
+				.cmd_write_timestamp(self.command_buffer, stage, pool, index)
+		};
+	}
+
+	pub fn begin_timer(&mut self, name: &str) {
+		let index = self.queries.begin_timer(name);
+		self.write_timestamp(index, vk::PipelineStageFlags::TOP_OF_PIPE);
+	}
+
+	pub fn end_timer(&mut self) {
+		let index = self.queries.end_timer();
+		self.write_timestamp(index, vk::PipelineStageFlags::BOTTOM_OF_PIPE);
+	}
+
+	pub fn begin_statistics(&mut self, name: &str) {
+		let index = self.queries.begin_statistics(name);
+		if !self.owner.pipeline_statistics {
+			return;
+		}
+		assert!(
+			index < MAX_STATISTICS,
+			"Too many pipeline statistics in a single command buffer"
+		);
+
+		let pool = self.query_pools().statistics;
+		unsafe {
+			self.owner.logical.cmd_begin_query(
+				self.command_buffer,
+				pool,
+				index,
+				vk::QueryControlFlags::empty(),
+			)
+		};
+	}
+
+	pub fn end_statistics(&mut self) {
+		let index = self.queries.end_statistics();
+		if !self.owner.pipeline_statistics {
+			return;
+		}
+
+		let pool = self.query_pools().statistics;
+		unsafe {
+			self.owner
+				.logical
+				.cmd_end_query(self.command_buffer, pool, index)
+		};
+	}
+
+	/// Opens a labeled region shown by GPU capture tools. Regions are also logged as breadcrumbs when the
+	/// device is lost and crash diagnostics are enabled
+	pub fn push_marker(&mut self, name: &str) {
+		self.markers += 1;
+		if let Some(breadcrumb) = self
+			.owner
+			.diagnostics
+			.begin_marker(self.command_buffer, name)
+		{
+			self.breadcrumbs.push(breadcrumb);
+		}
+
+		let debug_utils = match &self.owner.owner.debug_utils {
+			Some(debug_utils) => debug_utils,
+			None => return,
+		};
+		let name = CString::new(name).unwrap_or_default();
+		let label = vk::DebugUtilsLabelEXT::builder().label_name(&name);
+		unsafe { debug_utils.cmd_begin_debug_utils_label(self.command_buffer, &label) };
+	}
+
+	pub fn pop_marker(&mut self) {
+		assert!(self.markers > 0, "No marker was pushed");
+		self.markers -= 1;
+		if let Some(breadcrumb) = self.breadcrumbs.pop() {
+			self.owner
+				.diagnostics
+				.end_marker(self.command_buffer, breadcrumb);
+		}
+
+		if let Some(debug_utils) = &self.owner.owner.debug_utils {
+			unsafe { debug_utils.cmd_end_debug_utils_label(self.command_buffer) };
+		}
+	}
+
+	/// Reads every query. The command buffer must have finished executing
+	pub fn resolve_queries(&self) -> GpuReport {
+		let pools = self.query_pools.unwrap_or_default();
+
+		let mut timestamps = vec![0u64; self.queries.timestamps() as usize];
+		if pools.timestamps != vk::QueryPool::null() && !timestamps.is_empty() {
+			let result = unsafe {
+				self.owner.logical.get_query_pool_results(
+					pools.timestamps,
+					0,
+					timestamps.len() as u32,
+					&mut timestamps[..],
+					vk::QueryResultFlags::TYPE_64,
+				)
+			};
+			match result {
+				Ok(_) => timestamps
+					.iter_mut()
+					.for_each(|it| *it = (*it as f64 * self.owner.timestamp_period as f64) as u64),
+				Err(_) => timestamps.iter_mut().for_each(|it| *it = 0),
+			}
+		}
+
+		let mut statistics = vec![[0u64; 5]; self.queries.statistics() as usize];
+		if pools.statistics != vk::QueryPool::null() && !statistics.is_empty() {
+			let result = unsafe {
+				self.owner.logical.get_query_pool_results(
+					pools.statistics,
+					0,
+					statistics.len() as u32,
+					&mut statistics[..],
+					vk::QueryResultFlags::TYPE_64,
+				)
+			};
+			if result.is_err() {
+				statistics.iter_mut().for_each(|it| *it = [0; 5]);
+			}
+		}
+
+		self.queries.resolve(&timestamps, &statistics)
+	}
+
+	pub fn copy_buffer_to_texture(&mut self, dst: Arc<Texture>, src: Arc<Buffer>, mip_levels: u32) {
+		// Mips are packed one after another in the buffer with all array layers of a mip next to each other
+		let mut offset = 0;
+		let regions: Vec<vk::BufferImageCopy> = (0..mip_levels)
+			.map(|mip| {
+				let (width, height, depth) = dst.extent.mip_size(mip);
+
+				let subresource = vk::ImageSubresourceLayers::builder()
+					.aspect_mask(vk::ImageAspectFlags::COLOR)
+					.mip_level(mip)
+					.layer_count(dst.extent.array_layers);
+
+				let extent = vk::Extent3D::builder()
+					.width(width)
+					.height(height)
+					.depth(depth);
+
+				let region = vk::BufferImageCopy::builder()
+					.buffer_offset(offset as u64)
+					.image_subresource(subresource.build())
+					.image_extent(extent.build())
+					.build();
+
+				offset += dst.extent.mip_size_in_bytes(dst.format, mip);
+				region
+			})
+			.collect();
 
 		unsafe {
 			self.owner.logical.cmd_copy_buffer_to_image(
@@ -105,9 +432,13 @@ impl GraphicsCommandBuffer {
 				src.handle,
 				dst.image,
 				vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-				&[region.build()],
+				&regions,
 			)
 		};
+
+		// Uploads are not waited on so both must live until the work finishes
+		self.textures.push(dst);
+		self.buffers.push(src);
 	}
 
 	pub fn copy_buffer_to_buffer(&mut self, dst: Arc<Buffer>, src: Arc<Buffer>) {
@@ -123,6 +454,135 @@ impl GraphicsCommandBuffer {
 				&[region],
 			)
 		};
+
+		self.buffers.push(dst);
+		self.buffers.push(src);
+	}
+
+	pub fn copy_texture_to_buffer(&mut self, dst: Arc<Buffer>, src: Arc<Texture>) {
+		let subresource = vk::ImageSubresourceLayers::builder()
+			.aspect_mask(vk_format_aspect_mask(src.format))
+			.layer_count(1);
+
+		let extent = vk::Extent3D::builder()
+			.width(src.extent.width)
+			.height(src.extent.height)
+			.depth(src.extent.depth);
+
+		let region = vk::BufferImageCopy::builder()
+			.image_subresource(subresource.build())
+			.image_extent(extent.build());
+
+		unsafe {
+			self.owner.logical.cmd_copy_image_to_buffer(
+				self.command_buffer,
+				src.image,
+				vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+				dst.handle,
+				&[region.build()],
+			)
+		};
+
+		self.textures.push(src);
+		self.buffers.push(dst);
+	}
+
+	pub fn generate_mips(&mut self, texture: Arc<Texture>) {
+		let aspect_mask = vk_format_aspect_mask(texture.format);
+		let layer_count = texture.extent.array_layers;
+
+		let mip_barrier = |mip: u32| {
+			vk::ImageMemoryBarrier::builder()
+				.old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+				.new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+				.src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+				.dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+				.image(texture.image)
+				.src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+				.dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+				.subresource_range(
+					vk::ImageSubresourceRange::builder()
+						.aspect_mask(aspect_mask)
+						.base_mip_level(mip)
+						.level_count(1)
+						.layer_count(layer_count)
+						.build(),
+				)
+				.build()
+		};
+
+		// Each mip is moved to TransferSrc once written so the next mip can be blitted from it
+		for mip in 1..texture.extent.mip_levels {
+			let (src_width, src_height, src_depth) = texture.extent.mip_size(mip - 1);
+			let (dst_width, dst_height, dst_depth) = texture.extent.mip_size(mip);
+
+			let blit = vk::ImageBlit::builder()
+				.src_subresource(
+					vk::ImageSubresourceLayers::builder()
+						.aspect_mask(aspect_mask)
+						.mip_level(mip - 1)
+						.layer_count(layer_count)
+						.build(),
+				)
+				.src_offsets([
+					vk::Offset3D::default(),
+					vk::Offset3D {
+						x: src_width as i32,
+						y: src_height as i32,
+						z: src_depth as i32,
+					},
+				])
+				.dst_subresource(
+					vk::ImageSubresourceLayers::builder()
+						.aspect_mask(aspect_mask)
+						.mip_level(mip)
+						.layer_count(layer_count)
+						.build(),
+				)
+				.dst_offsets([
+					vk::Offset3D::default(),
+					vk::Offset3D {
+						x: dst_width as i32,
+						y: dst_height as i32,
+						z: dst_depth as i32,
+					},
+				]);
+
+			unsafe {
+				self.owner.logical.cmd_pipeline_barrier(
+					self.command_buffer,
+					vk::PipelineStageFlags::TRANSFER,
+					vk::PipelineStageFlags::TRANSFER,
+					vk::DependencyFlags::default(),
+					&[],
+					&[],
+					&[mip_barrier(mip - 1)],
+				);
+				self.owner.logical.cmd_blit_image(
+					self.command_buffer,
+					texture.image,
+					vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+					texture.image,
+					vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+					&[blit.build()],
+					vk::Filter::LINEAR,
+				);
+			}
+		}
+
+		unsafe {
+			self.owner.logical.cmd_pipeline_barrier(
+				self.command_buffer,
+				vk::PipelineStageFlags::TRANSFER,
+				vk::PipelineStageFlags::TRANSFER,
+				vk::DependencyFlags::default(),
+				&[],
+				&[],
+				&[mip_barrier(texture.extent.mip_levels - 1)],
+			);
+		}
+
+		self.textures.push(texture);
 	}
 
 	pub fn resource_barrier_texture(
@@ -138,14 +598,14 @@ impl GraphicsCommandBuffer {
 			.src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
 			.dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED);
 
-		// TODO: Mips
+		// Barriers always cover every mip and array layer of the texture
 		barrier = barrier.subresource_range(
 			vk::ImageSubresourceRange::builder()
 				.aspect_mask(vk_format_aspect_mask(texture.format))
 				.base_mip_level(0)
-				.level_count(1)
+				.level_count(texture.extent.mip_levels)
 				.base_array_layer(0)
-				.layer_count(1)
+				.layer_count(texture.extent.array_layers)
 				.build(),
 		);
 
@@ -188,6 +648,29 @@ impl GraphicsCommandBuffer {
 				src_stage = vk::PipelineStageFlags::TOP_OF_PIPE;
 				dst_stage = vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS;
 			}
+			(Layout::Undefined, Layout::General) => {
+				barrier = barrier
+					.dst_access_mask(vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE);
+
+				src_stage = vk::PipelineStageFlags::TOP_OF_PIPE;
+				dst_stage = vk::PipelineStageFlags::COMPUTE_SHADER;
+			}
+			(Layout::ShaderReadOnly, Layout::General) => {
+				barrier = barrier
+					.src_access_mask(vk::AccessFlags::SHADER_READ)
+					.dst_access_mask(vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE);
+
+				src_stage = vk::PipelineStageFlags::ALL_COMMANDS;
+				dst_stage = vk::PipelineStageFlags::COMPUTE_SHADER;
+			}
+			(Layout::General, Layout::ShaderReadOnly) => {
+				barrier = barrier
+					.src_access_mask(vk::AccessFlags::SHADER_WRITE)
+					.dst_access_mask(vk::AccessFlags::SHADER_READ);
+
+				src_stage = vk::PipelineStageFlags::COMPUTE_SHADER;
+				dst_stage = vk::PipelineStageFlags::ALL_COMMANDS;
+			}
 			(Layout::Undefined, Layout::ColorAttachment) => {
 				barrier = barrier.dst_access_mask(
 					vk::AccessFlags::COLOR_ATTACHMENT_READ
@@ -197,25 +680,128 @@ impl GraphicsCommandBuffer {
 				src_stage = vk::PipelineStageFlags::TOP_OF_PIPE;
 				dst_stage = vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT;
 			}
-			_ => unimplemented!(),
+			(_, Layout::TransferSrc) => {
+				barrier = barrier
+					.src_access_mask(vk::AccessFlags::MEMORY_WRITE)
+					.dst_access_mask(vk::AccessFlags::TRANSFER_READ);
+
+				src_stage = vk::PipelineStageFlags::ALL_COMMANDS;
+				dst_stage = vk::PipelineStageFlags::TRANSFER;
+			}
+			(Layout::TransferSrc, _) => {
+				barrier = barrier
+					.src_access_mask(vk::AccessFlags::TRANSFER_READ)
+					.dst_access_mask(vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE);
+
+				src_stage = vk::PipelineStageFlags::TRANSFER;
+				dst_stage = vk::PipelineStageFlags::ALL_COMMANDS;
+			}
+			// Transitions without a tuned path wait on all prior work
+			_ => {
+				barrier = barrier
+					.src_access_mask(vk::AccessFlags::MEMORY_WRITE)
+					.dst_access_mask(vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE);
+
+				src_stage = vk::PipelineStageFlags::ALL_COMMANDS;
+				dst_stage = vk::PipelineStageFlags::ALL_COMMANDS;
+			}
 		}
 
+		// Compute and transfer queues can not wait on graphics stages. Later queues see the results through
+		// the semaphore of the submission
+		let mut barrier = barrier.build();
+		barrier.src_access_mask = self.queue_access(barrier.src_access_mask);
+		barrier.dst_access_mask = self.queue_access(barrier.dst_access_mask);
+
 		unsafe {
 			self.owner.logical.cmd_pipeline_barrier(
 				self.command_buffer,
-				src_stage,
-				dst_stage,
+				self.queue_stages(src_stage),
+				self.queue_stages(dst_stage),
 				vk::DependencyFlags::default(),
 				&[],
 				&[],
-				&[barrier.build()],
+				&[barrier],
+			)
+		};
+	}
+
+	/// Waits on every command before it and makes writes to `buffer` visible to every command after it
+	pub fn resource_barrier_buffer(&mut self, buffer: Arc<Buffer>) {
+		let mut barrier = vk::BufferMemoryBarrier::builder()
+			.src_access_mask(vk::AccessFlags::MEMORY_WRITE)
+			.dst_access_mask(vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE)
+			.src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+			.dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+			.buffer(buffer.handle)
+			.offset(0)
+			.size(vk::WHOLE_SIZE)
+			.build();
+		barrier.src_access_mask = self.queue_access(barrier.src_access_mask);
+		barrier.dst_access_mask = self.queue_access(barrier.dst_access_mask);
+
+		unsafe {
+			self.owner.logical.cmd_pipeline_barrier(
+				self.command_buffer,
+				vk::PipelineStageFlags::ALL_COMMANDS,
+				vk::PipelineStageFlags::ALL_COMMANDS,
+				vk::DependencyFlags::default(),
+				&[],
+				&[barrier],
+				&[],
 			)
 		};
+		self.buffers.push(buffer);
+	}
+
+	/// Widens stages the queue does not support to every command the queue runs
+	fn queue_stages(&self, stages: vk::PipelineStageFlags) -> vk::PipelineStageFlags {
+		let mut supported = vk::PipelineStageFlags::TOP_OF_PIPE
+			| vk::PipelineStageFlags::BOTTOM_OF_PIPE
+			| vk::PipelineStageFlags::TRANSFER
+			| vk::PipelineStageFlags::HOST
+			| vk::PipelineStageFlags::ALL_COMMANDS;
+		match self.queue {
+			Queue::Graphics => return stages,
+			Queue::Compute => {
+				supported |=
+					vk::PipelineStageFlags::COMPUTE_SHADER | vk::PipelineStageFlags::DRAW_INDIRECT
+			}
+			Queue::Transfer => {}
+		}
+
+		if supported.contains(stages) {
+			stages
+		} else {
+			vk::PipelineStageFlags::ALL_COMMANDS
+		}
+	}
+
+	/// Removes accesses the queue can not make
+	fn queue_access(&self, access: vk::AccessFlags) -> vk::AccessFlags {
+		let mut supported = vk::AccessFlags::TRANSFER_READ
+			| vk::AccessFlags::TRANSFER_WRITE
+			| vk::AccessFlags::HOST_READ
+			| vk::AccessFlags::HOST_WRITE
+			| vk::AccessFlags::MEMORY_READ
+			| vk::AccessFlags::MEMORY_WRITE;
+		match self.queue {
+			Queue::Graphics => return access,
+			Queue::Compute => {
+				supported |= vk::AccessFlags::SHADER_READ
+					| vk::AccessFlags::SHADER_WRITE
+					| vk::AccessFlags::UNIFORM_READ
+					| vk::AccessFlags::INDIRECT_COMMAND_READ
+			}
+			Queue::Transfer => {}
+		}
+		access & supported
 	}
 }
 
 impl GraphicsCommandBuffer {
-	pub fn new(owner: Arc<Device>) -> Result<GraphicsCommandBuffer> {
+	pub fn new(owner: Arc<Device>, queue: Queue) -> Result<GraphicsCommandBuffer> {
+		let queue = owner.resolve_queue(queue);
 		let handle = {
 			let mut thread_infos = owner.thread_info.lock().unwrap();
 			let thread_id = std::thread::current().id();
@@ -227,17 +813,17 @@ impl GraphicsCommandBuffer {
 			}
 			let thread_info = thread_info.unwrap();
 
-			if thread_info.graphics_pool == vk::CommandPool::default() {
+			let pool = thread_info.pool_mut(queue);
+			if *pool == vk::CommandPool::default() {
 				let create_info = vk::CommandPoolCreateInfo::builder()
 					.flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
-					.queue_family_index(owner.graphics_family_index.unwrap());
+					.queue_family_index(owner.queue_family_index(queue));
 
-				thread_info.graphics_pool =
-					unsafe { owner.logical.create_command_pool(&create_info, None)? };
+				*pool = unsafe { owner.logical.create_command_pool(&create_info, None)? };
 			}
 
 			let alloc_info = vk::CommandBufferAllocateInfo::builder()
-				.command_pool(thread_info.graphics_pool)
+				.command_pool(*pool)
 				.level(vk::CommandBufferLevel::PRIMARY)
 				.command_buffer_count(1);
 
@@ -246,44 +832,64 @@ impl GraphicsCommandBuffer {
 
 		Ok(GraphicsCommandBuffer {
 			owner,
+			queue,
 
 			command_buffer: handle,
 
 			framebuffers: Vec::new(),
 			pipelines: Vec::new(),
+			compute_pipelines: Vec::new(),
 			textures: Vec::new(),
 			buffers: Vec::new(),
 
 			current_scissor: None,
+			current_viewport: None,
 			current_attachments: None,
 			current_pipeline: None,
 
 			push_constants: [0; 32],
+
+			queries: Queries::default(),
+			markers: 0,
+			breadcrumbs: Vec::new(),
+			query_pools: None,
 		})
 	}
 
-	pub fn begin_render_pass(&mut self, attachments: &[Arc<Texture>]) -> Result<()> {
+	pub fn begin_render_pass(&mut self, attachments: &[RenderPassAttachment]) -> Result<()> {
 		let extent = vk::Extent2D::builder()
-			.width(attachments[0].width)
-			.height(attachments[0].height)
+			.width(attachments[0].texture.extent.width)
+			.height(attachments[0].texture.extent.height)
 			.build();
 
-		let formats: Vec<Format> = attachments.iter().map(|a| a.format).collect();
+		let keys: Vec<AttachmentKey> = attachments
+			.iter()
+			.map(|it| AttachmentKey {
+				format: it.texture.format,
+				samples: it.texture.extent.samples,
+				load: it.load,
+				store: it.store,
+				resolve: it.resolve.is_some(),
+			})
+			.collect();
 
-		let render_pass = self.owner.get_or_create_render_pass(&formats)?;
+		let render_pass = self.owner.get_or_create_render_pass(&keys)?;
 
 		let render_pass_handle = render_pass.handle;
 
-		for it in attachments.iter() {
+		// Resolve targets come after every attachment in the render pass
+		let textures: Vec<Arc<Texture>> = attachments
+			.iter()
+			.map(|it| it.texture.clone())
+			.chain(attachments.iter().filter_map(|it| it.resolve.clone()))
+			.collect();
+		for it in textures.iter() {
 			self.textures.push(it.clone());
 		}
-		self.current_attachments = Some(attachments.to_vec()); // TODO: Temp Allocator
+		self.current_attachments = Some(attachments.iter().map(|it| it.texture.clone()).collect()); // TODO: Temp Allocator
 
 		// Make the framebuffer
-		let mut views = Vec::with_capacity(attachments.len()); // TODO: Temp Allocator
-		for it in attachments.iter() {
-			views.push(it.view);
-		}
+		let views: Vec<vk::ImageView> = textures.iter().map(|it| it.view).collect(); // TODO: Temp Allocator
 
 		let create_info = vk::FramebufferCreateInfo::builder()
 			.render_pass(render_pass_handle)
@@ -295,12 +901,34 @@ impl GraphicsCommandBuffer {
 		let framebuffer = unsafe { self.owner.logical.create_framebuffer(&create_info, None)? };
 		self.framebuffers.push(framebuffer);
 
+		let clear_values: Vec<vk::ClearValue> = attachments
+			.iter()
+			.map(|it| {
+				if it.texture.format.is_depth() {
+					vk::ClearValue {
+						depth_stencil: vk::ClearDepthStencilValue {
+							depth: it.clear_depth,
+							stencil: 0,
+						},
+					}
+				} else {
+					let color = it.clear_color;
+					vk::ClearValue {
+						color: vk::ClearColorValue {
+							float32: [color.r, color.g, color.b, color.a],
+						},
+					}
+				}
+			})
+			.collect();
+
 		let render_area = vk::Rect2D::builder().extent(extent);
 
 		let begin_info = vk::RenderPassBeginInfo::builder()
 			.render_pass(render_pass_handle)
 			.framebuffer(framebuffer)
-			.render_area(render_area.build());
+			.render_area(render_area.build())
+			.clear_values(&clear_values);
 
 		unsafe {
 			self.owner.logical.cmd_begin_render_pass(
@@ -310,88 +938,137 @@ impl GraphicsCommandBuffer {
 			)
 		};
 
+		self.set_viewport_and_scissor();
+
 		Ok(())
 	}
 
 	pub fn end_render_pass(&mut self) {
 		unsafe { self.owner.logical.cmd_end_render_pass(self.command_buffer) };
 		self.current_scissor = None;
+		self.current_viewport = None;
 		self.current_attachments = None;
 	}
 
 	pub fn bind_scissor(&mut self, scissor: Option<Rect>) {
 		self.current_scissor = scissor;
+		self.set_viewport_and_scissor();
 	}
 
-	pub fn bind_pipeline(&mut self, pipeline: Arc<GraphicsPipeline>) {
-		self.current_pipeline = Some(pipeline.clone());
+	pub fn bind_viewport(&mut self, viewport: Option<Rect>) {
+		self.current_viewport = viewport;
+		self.set_viewport_and_scissor();
+	}
+
+	// Viewport and scissor are dynamic state so they stay set across pipeline binds in a render pass
+	fn set_viewport_and_scissor(&mut self) {
+		let attachment = match &self.current_attachments {
+			Some(attachments) => &attachments[0],
+			None => return,
+		};
+		let full = Rect::from_min_max(
+			(0.0, 0.0),
+			(
+				attachment.extent.width as f32,
+				attachment.extent.height as f32,
+			),
+		);
+
+		let viewport = self.current_viewport.unwrap_or(full);
+		let size = viewport.size();
+		let viewport = vk::Viewport::builder()
+			.x(viewport.min.x)
+			.y(viewport.min.y)
+			.width(size.x)
+			.height(size.y)
+			.max_depth(1.0);
+
+		let scissor = self.current_scissor.unwrap_or(full);
+		let size = scissor.size();
+		let rect = vk::Rect2D::builder()
+			.offset(
+				vk::Offset2D::builder()
+					.x(scissor.min.x as i32)
+					.y(scissor.min.y as i32)
+					.build(),
+			)
+			.extent(
+				vk::Extent2D::builder()
+					.width(size.x as u32)
+					.height(size.y as u32)
+					.build(),
+			);
+
+		unsafe {
+			self.owner
+				.logical
+				.cmd_set_viewport(self.command_buffer, 0, from_ref(&viewport));
+			self.owner
+				.logical
+				.cmd_set_scissor(self.command_buffer, 0, from_ref(&rect));
+		}
+	}
+
+	fn set_pipeline(&mut self, pipeline: BoundPipeline) {
 		self.push_constants = [0; 32];
 
-		for (sampler, index) in pipeline.samplers.iter() {
+		for (sampler, index) in pipeline.samplers().iter() {
 			self.push_constants[*index] = sampler.bindless;
 		}
 
 		unsafe {
 			self.owner.logical.cmd_bind_pipeline(
 				self.command_buffer,
-				vk::PipelineBindPoint::GRAPHICS,
-				pipeline.handle,
+				pipeline.bind_point(),
+				pipeline.handle(),
 			);
 			self.owner.logical.cmd_bind_descriptor_sets(
 				self.command_buffer,
-				vk::PipelineBindPoint::GRAPHICS,
-				pipeline.layout,
+				pipeline.bind_point(),
+				pipeline.layout(),
 				0,
-				&[pipeline.owner.bindless_set],
+				&[self.owner.bindless_set],
 				&[],
 			);
+		}
 
-			let viewport = vk::Viewport::builder()
-				.width(self.textures.last().unwrap().width as f32)
-				.height(self.textures.last().unwrap().height as f32)
-				.max_depth(1.0);
-			self.owner
-				.logical
-				.cmd_set_viewport(self.command_buffer, 0, from_ref(&viewport));
+		self.current_pipeline = Some(pipeline);
+	}
 
-			if self.current_scissor.is_some() {
-				let scissor = self.current_scissor.unwrap();
-
-				let size = scissor.size();
-				let rect = vk::Rect2D::builder()
-					.offset(
-						vk::Offset2D::builder()
-							.x(scissor.min.x as i32)
-							.y(scissor.min.y as i32)
-							.build(),
-					)
-					.extent(
-						vk::Extent2D::builder()
-							.width(size.x as u32)
-							.height(size.y as u32)
-							.build(),
-					);
-
-				self.owner
-					.logical
-					.cmd_set_scissor(self.command_buffer, 0, from_ref(&rect));
-			} else {
-				let rect = vk::Rect2D::builder()
-					.extent(
-						vk::Extent2D::builder()
-							.width(viewport.width as u32)
-							.height(viewport.height as u32)
-							.build(),
-					)
-					.build();
+	pub fn bind_pipeline(&mut self, pipeline: Arc<GraphicsPipeline>) {
+		self.set_pipeline(BoundPipeline::Graphics(pipeline.clone()));
+		self.pipelines.push(pipeline);
+	}
 
-				self.owner
-					.logical
-					.cmd_set_scissor(self.command_buffer, 0, from_ref(&rect));
-			}
-		}
+	pub fn bind_compute_pipeline(&mut self, pipeline: Arc<ComputePipeline>) {
+		assert!(
+			self.current_attachments.is_none(),
+			"ComputePipeline can not be bound in a render pass"
+		);
 
-		self.pipelines.push(pipeline);
+		self.set_pipeline(BoundPipeline::Compute(pipeline.clone()));
+		self.compute_pipelines.push(pipeline);
+	}
+
+	pub fn end_compute_pass(&mut self) {
+		self.current_pipeline = None;
+
+		// Make every storage write visible to the commands that follow
+		let barrier = vk::MemoryBarrier::builder()
+			.src_access_mask(vk::AccessFlags::SHADER_WRITE)
+			.dst_access_mask(vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE);
+
+		unsafe {
+			self.owner.logical.cmd_pipeline_barrier(
+				self.command_buffer,
+				vk::PipelineStageFlags::COMPUTE_SHADER,
+				vk::PipelineStageFlags::ALL_COMMANDS,
+				vk::DependencyFlags::default(),
+				&[barrier.build()],
+				&[],
+				&[],
+			)
+		};
 	}
 
 	pub fn bind_vertex_buffer(&mut self, buffer: Arc<Buffer>) {
@@ -423,9 +1100,7 @@ impl GraphicsCommandBuffer {
 	pub fn bind_constants(&mut self, name: &str, buffer: Arc<Buffer>, index: usize) {
 		let current_pipeline = self.current_pipeline.as_ref().unwrap();
 
-		for (bindless_index, (import_name, _)) in
-			current_pipeline.description.constants.iter().enumerate()
-		{
+		for (bindless_index, (import_name, _)) in current_pipeline.constants().iter().enumerate() {
 			if name != import_name {
 				continue;
 			}
@@ -442,21 +1117,40 @@ impl GraphicsCommandBuffer {
 		let current_pipeline = self.current_pipeline.as_ref().unwrap();
 
 		// Resources come after constants in push constants so we need to add the constants len
-		let constants_len = current_pipeline.description.constants.len();
+		let constants_len = current_pipeline.constants().len();
 
-		for (index, (import_name, _)) in current_pipeline.description.resources.iter().enumerate() {
+		for (index, (import_name, _)) in current_pipeline.resources().iter().enumerate() {
 			if name != import_name {
 				continue;
 			}
 
 			self.push_constants[index + constants_len] = texture
 				.bindless()
-				.expect("Texture can only be bound if usage is marked with SAMPLED");
+				.expect("Texture can only be bound if usage is marked with SAMPLED or STORAGE");
 			self.textures.push(texture);
 			break;
 		}
 	}
 
+	pub fn bind_buffer(&mut self, name: &str, buffer: Arc<Buffer>) {
+		let current_pipeline = self.current_pipeline.as_ref().unwrap();
+
+		// Resources come after constants in push constants so we need to add the constants len
+		let constants_len = current_pipeline.constants().len();
+
+		for (index, (import_name, _)) in current_pipeline.resources().iter().enumerate() {
+			if name != import_name {
+				continue;
+			}
+
+			self.push_constants[index + constants_len] = buffer
+				.bindless()
+				.expect("Buffer can only be bound if usage is marked with STORAGE");
+			self.buffers.push(buffer);
+			break;
+		}
+	}
+
 	pub fn draw(&mut self, vertex_count: usize, first_vertex: usize) {
 		self.push_constants();
 		unsafe {
@@ -484,6 +1178,112 @@ impl GraphicsCommandBuffer {
 		};
 	}
 
+	pub fn draw_instanced(
+		&mut self,
+		vertex_count: usize,
+		first_vertex: usize,
+		instance_count: usize,
+		first_instance: usize,
+	) {
+		self.push_constants();
+		unsafe {
+			self.owner.logical.cmd_draw(
+				self.command_buffer,
+				vertex_count as u32,
+				instance_count as u32,
+				first_vertex as u32,
+				first_instance as u32,
+			)
+		};
+	}
+
+	pub fn draw_indexed_instanced(
+		&mut self,
+		index_count: usize,
+		first_index: usize,
+		instance_count: usize,
+		first_instance: usize,
+	) {
+		self.push_constants();
+		unsafe {
+			self.owner.logical.cmd_draw_indexed(
+				self.command_buffer,
+				index_count as u32,
+				instance_count as u32,
+				first_index as u32,
+				0,
+				first_instance as u32,
+			)
+		};
+	}
+
+	pub fn draw_indirect(&mut self, buffer: Arc<Buffer>, first: usize, draw_count: usize) {
+		self.push_constants();
+
+		let stride = std::mem::size_of::<DrawIndirect>();
+		for (offset, count) in self.indirect_batches(first, draw_count, stride) {
+			unsafe {
+				self.owner.logical.cmd_draw_indirect(
+					self.command_buffer,
+					buffer.handle,
+					offset,
+					count,
+					stride as u32,
+				)
+			};
+		}
+		self.buffers.push(buffer);
+	}
+
+	pub fn draw_indexed_indirect(&mut self, buffer: Arc<Buffer>, first: usize, draw_count: usize) {
+		self.push_constants();
+
+		let stride = std::mem::size_of::<DrawIndexedIndirect>();
+		for (offset, count) in self.indirect_batches(first, draw_count, stride) {
+			unsafe {
+				self.owner.logical.cmd_draw_indexed_indirect(
+					self.command_buffer,
+					buffer.handle,
+					offset,
+					count,
+					stride as u32,
+				)
+			};
+		}
+		self.buffers.push(buffer);
+	}
+
+	// Returns the offset and count of every indirect draw call. Devices without multi draw indirect issue one
+	// call per draw
+	fn indirect_batches(
+		&self,
+		first: usize,
+		draw_count: usize,
+		stride: usize,
+	) -> Vec<(vk::DeviceSize, u32)> {
+		if self.owner.multi_draw_indirect || draw_count <= 1 {
+			vec![((first * stride) as vk::DeviceSize, draw_count as u32)]
+		} else {
+			(first..first + draw_count)
+				.map(|it| ((it * stride) as vk::DeviceSize, 1))
+				.collect()
+		}
+	}
+
+	pub fn dispatch(&mut self, x: u32, y: u32, z: u32) {
+		assert!(
+			matches!(self.current_pipeline, Some(BoundPipeline::Compute(_))),
+			"ComputePipeline must be bound to dispatch"
+		);
+
+		self.push_constants();
+		unsafe {
+			self.owner
+				.logical
+				.cmd_dispatch(self.command_buffer, x, y, z)
+		};
+	}
+
 	pub fn clear_color(&mut self, color: Color) {
 		let attachments = self.current_attachments.as_ref().unwrap();
 		assert!(!attachments.is_empty());
@@ -510,8 +1310,8 @@ impl GraphicsCommandBuffer {
 		}
 
 		let extent = vk::Extent2D::builder()
-			.width(attachments[0].width)
-			.height(attachments[0].height)
+			.width(attachments[0].extent.width)
+			.height(attachments[0].extent.height)
 			.build();
 		let clear_rect = vk::ClearRect::builder()
 			.rect(vk::Rect2D::builder().extent(extent).build())
@@ -548,8 +1348,8 @@ impl GraphicsCommandBuffer {
 		}
 
 		let extent = vk::Extent2D::builder()
-			.width(attachments[0].width)
-			.height(attachments[0].height)
+			.width(attachments[0].extent.width)
+			.height(attachments[0].extent.height)
 			.build();
 		let clear_rect = vk::ClearRect::builder()
 			.rect(vk::Rect2D::builder().extent(extent).build())
@@ -566,9 +1366,9 @@ impl GraphicsCommandBuffer {
 		let current_pipeline = self
 			.current_pipeline
 			.as_ref()
-			.expect("GraphicsPipeline must be bound to push constants");
+			.expect("Pipeline must be bound to push constants");
 
-		let push_constant_size = current_pipeline.description.push_constant_size();
+		let push_constant_size = current_pipeline.push_constant_size();
 
 		if push_constant_size == 0 {
 			return;
@@ -577,8 +1377,8 @@ impl GraphicsCommandBuffer {
 		unsafe {
 			self.owner.logical.cmd_push_constants(
 				self.command_buffer,
-				current_pipeline.layout,
-				vk::ShaderStageFlags::ALL_GRAPHICS,
+				current_pipeline.layout(),
+				current_pipeline.stage_flags(),
 				0,
 				from_raw_parts(
 					self.push_constants.as_ptr() as *const u8,
@@ -591,18 +1391,22 @@ impl GraphicsCommandBuffer {
 
 impl Drop for GraphicsCommandBuffer {
 	fn drop(&mut self) {
-		let thread_infos = self.owner.thread_info.lock().unwrap();
+		let mut thread_infos = self.owner.thread_info.lock().unwrap();
 		let thread_id = std::thread::current().id();
 
-		let thread_info = thread_infos.get(&thread_id).unwrap();
+		let thread_info = thread_infos.get_mut(&thread_id).unwrap();
+		let pool = *thread_info.pool_mut(self.queue);
 
 		unsafe {
 			self.owner
 				.logical
-				.free_command_buffers(thread_info.graphics_pool, &[self.command_buffer]);
+				.free_command_buffers(pool, &[self.command_buffer]);
 			self.framebuffers
 				.iter()
 				.for_each(|it| self.owner.logical.destroy_framebuffer(*it, None));
 		}
+		if let Some(pools) = self.query_pools {
+			self.owner.return_query_pools(pools);
+		}
 	}
 }
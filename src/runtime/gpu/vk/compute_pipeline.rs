@@ -0,0 +1,110 @@
+use super::Device;
+use super::Sampler;
+use crate::{
+	ComputePipelineDescription,
+	PipelineResource,
+	Result,
+};
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use std::ffi::CString;
+use std::slice::from_ref;
+use std::sync::Arc;
+
+pub struct ComputePipeline {
+	pub owner: Arc<Device>,
+
+	pub handle: vk::Pipeline,
+	pub layout: vk::PipelineLayout,
+
+	pub description: ComputePipelineDescription,
+
+	// Store samplers with an index which is the push constant index
+	pub samplers: Vec<(Arc<Sampler>, usize)>,
+}
+
+impl ComputePipeline {
+	pub fn new(
+		owner: Arc<Device>,
+		description: ComputePipelineDescription,
+	) -> Result<Arc<ComputePipeline>> {
+		let shader = &description.shader.0;
+		let main = CString::new(shader.main.clone()).unwrap();
+
+		let stage_info = vk::PipelineShaderStageCreateInfo::builder()
+			.stage(vk::ShaderStageFlags::COMPUTE)
+			.module(shader.module)
+			.name(&main)
+			.build();
+
+		let layouts = [owner.bindless_layout];
+		let mut pipeline_layout_info =
+			vk::PipelineLayoutCreateInfo::builder().set_layouts(&layouts);
+
+		let push_constant_size = description.push_constant_size();
+		assert!(push_constant_size <= 128); // Min push contsant size
+
+		let range = vk::PushConstantRange::builder()
+			.size(push_constant_size as u32)
+			.stage_flags(vk::ShaderStageFlags::COMPUTE);
+
+		if push_constant_size > 0 {
+			pipeline_layout_info = pipeline_layout_info.push_constant_ranges(from_ref(&range));
+		}
+
+		let layout = unsafe {
+			owner
+				.logical
+				.create_pipeline_layout(&pipeline_layout_info, None)?
+		};
+
+		let create_info = vk::ComputePipelineCreateInfo::builder()
+			.stage(stage_info)
+			.layout(layout)
+			.base_pipeline_index(-1);
+
+		let handle = unsafe {
+			owner.logical.create_compute_pipelines(
+				owner.pipeline_cache,
+				from_ref(&create_info),
+				None,
+			)
+		};
+
+		if handle.is_err() {
+			return Err(handle.err().unwrap().1);
+		}
+		let handle = handle.unwrap();
+
+		// Create samplers based off resources
+		let samplers = description
+			.resources
+			.iter()
+			.enumerate()
+			.filter(|(_, (_, resource))| matches!(resource, PipelineResource::Sampler(_)))
+			.map(|(index, (_, resource))| match resource {
+				PipelineResource::Sampler(description) => {
+					let sampler = Sampler::new(owner.clone(), *description).unwrap();
+					(sampler, index)
+				}
+				_ => unreachable!(),
+			})
+			.collect();
+
+		Ok(Arc::new(ComputePipeline {
+			owner,
+
+			handle: handle[0],
+			layout,
+
+			description,
+			samplers,
+		}))
+	}
+
+	pub fn set_debug_name(&self, name: &str) {
+		self.owner.set_debug_name(self.handle, name);
+	}
+}
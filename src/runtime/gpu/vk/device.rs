@@ -1,20 +1,38 @@
+use super::{
+	AttachmentKey,
+	RenderPass,
+	Sampler,
+};
 use super::{
 	Buffer,
+	DiagnosticExtensions,
+	Diagnostics,
 	GraphicsCommandBuffer,
 	Instance,
+	QueryPools,
 	Receipt,
 	Texture,
-	ENABLED_LAYER_NAMES,
+	VULKAN_CATEGORY,
 };
-use super::{
-	RenderPass,
-	Sampler,
+use crate::bindless::{
+	BindlessHeap,
+	BINDLESS_CAPACITY,
 };
+use crate::memory::BLOCK_SIZE;
 use crate::{
+	BindlessKind,
+	BindlessView,
 	BufferUsage,
 	Format,
+	GpuReport,
+	MemoryPool,
+	MemoryStats,
 	MemoryType,
+	PresentMode,
+	Queue,
 	Result,
+	SubAllocation,
+	TextureExtent,
 	TextureUsage,
 };
 
@@ -26,14 +44,20 @@ use ash::version::{
 };
 use ash::vk;
 
-use std::collections::HashMap;
+use std::collections::{
+	HashMap,
+	VecDeque,
+};
+use std::ffi::CString;
 use std::slice::from_ref;
 use std::sync::{
 	Arc,
 	Mutex,
-	Weak,
 };
 use std::thread::ThreadId;
+use std::time::Duration;
+
+use engine::warn;
 
 use os::{
 	HasRawWindowHandle,
@@ -42,16 +66,22 @@ use os::{
 };
 
 struct Swapchain {
-	// HACK: Leak the swapchain handle because it crashes when trying to free it. Probably due to it being attached to resources???
-	// TODO: Maybe actually handle this?
 	handle: vk::SwapchainKHR,
 
 	backbuffers: Vec<Arc<Texture>>,
 	current: Option<usize>,
+
+	// Signaled when the acquired backbuffer is no longer read by the presentation engine
+	acquire_fence: vk::Fence,
 }
 
 impl Swapchain {
-	fn new(device: Arc<Device>) -> Result<Self> {
+	/// Returns `None` when the surface has no area, for example while the window is minimized
+	fn new(
+		device: Arc<Device>,
+		settings: &SwapchainSettings,
+		old: Option<&Swapchain>,
+	) -> Result<Option<Self>> {
 		assert!(device.surface.is_some());
 
 		let swapchain_khr = khr::Swapchain::new(&device.owner.instance, &device.logical);
@@ -62,6 +92,30 @@ impl Swapchain {
 				device.physical,
 				device.surface.unwrap(),
 			)?;
+
+			// A current extent of u32::MAX means the surface size is decided by the swapchain
+			let extent = if capabilities.current_extent.width == u32::MAX {
+				vk::Extent2D {
+					width: settings.extent.width.clamp(
+						capabilities.min_image_extent.width,
+						capabilities.max_image_extent.width,
+					),
+					height: settings.extent.height.clamp(
+						capabilities.min_image_extent.height,
+						capabilities.max_image_extent.height,
+					),
+				}
+			} else {
+				capabilities.current_extent
+			};
+			if settings.extent.width == 0
+				|| settings.extent.height == 0
+				|| extent.width == 0
+				|| extent.height == 0
+			{
+				return Ok(None);
+			}
+
 			let formats = surface_khr
 				.get_physical_device_surface_formats(device.physical, device.surface.unwrap())?;
 
@@ -76,6 +130,33 @@ impl Swapchain {
 			}
 			let selected_format = selected_format.unwrap();
 
+			// FIFO is the only mode every device has to support
+			let present_modes = surface_khr.get_physical_device_surface_present_modes(
+				device.physical,
+				device.surface.unwrap(),
+			)?;
+			let present_mode = match settings.present_mode {
+				PresentMode::Fifo => vk::PresentModeKHR::FIFO,
+				PresentMode::Mailbox => vk::PresentModeKHR::MAILBOX,
+				PresentMode::Immediate => vk::PresentModeKHR::IMMEDIATE,
+			};
+			let present_mode = if present_modes.contains(&present_mode) {
+				present_mode
+			} else {
+				warn!(
+					VULKAN_CATEGORY,
+					"Present mode {:?} is not supported. Falling back to Fifo",
+					settings.present_mode
+				);
+				vk::PresentModeKHR::FIFO
+			};
+
+			// One more image than the minimum so acquiring does not wait on the presentation engine
+			let mut image_count = capabilities.min_image_count + 1;
+			if capabilities.max_image_count > 0 {
+				image_count = image_count.min(capabilities.max_image_count);
+			}
+
 			let mut queue_family_indices = Vec::with_capacity(2);
 			if device.graphics_family_index.is_some() {
 				queue_family_indices.push(device.graphics_family_index.unwrap());
@@ -86,18 +167,21 @@ impl Swapchain {
 
 			let create_info = vk::SwapchainCreateInfoKHR::builder()
 				.surface(device.surface.unwrap())
-				.min_image_count(capabilities.min_image_count)
+				.min_image_count(image_count)
 				.image_format(selected_format.format)
 				.image_color_space(selected_format.color_space)
-				.image_extent(capabilities.current_extent)
+				.image_extent(extent)
 				.image_array_layers(1)
-				.image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+				.image_usage(
+					vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+				)
 				.image_sharing_mode(vk::SharingMode::EXCLUSIVE)
 				.queue_family_indices(&queue_family_indices[..])
 				.pre_transform(capabilities.current_transform)
 				.composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
-				.present_mode(vk::PresentModeKHR::FIFO)
+				.present_mode(present_mode)
 				.clipped(true)
+				.old_swapchain(old.map(|it| it.handle).unwrap_or_default())
 				.build();
 
 			let handle = swapchain_khr.create_swapchain(&create_info, None)?;
@@ -137,22 +221,56 @@ impl Swapchain {
 					usage: TextureUsage::SWAPCHAIN,
 					format: Format::BGR_U8_SRGB,
 
-					width: capabilities.current_extent.width,
-					height: capabilities.current_extent.height,
-					depth: 1,
+					extent: TextureExtent::new(extent.width, extent.height, 1),
 
 					bindless: None,
 				}));
 			}
 
-			Ok(Self {
+			let fence_create_info = vk::FenceCreateInfo::builder();
+			let acquire_fence = device.logical.create_fence(&fence_create_info, None)?;
+
+			Ok(Some(Self {
 				handle,
 
 				backbuffers,
 				current: None,
-			})
+
+				acquire_fence,
+			}))
 		}
 	}
+
+	/// Whether a backbuffer is still referenced by work in flight or by whoever acquired it
+	fn in_use(&self) -> bool {
+		self.backbuffers.iter().any(|it| Arc::strong_count(it) > 1)
+	}
+
+	/// Destroying the swapchain destroys its images so it must not be [`Swapchain::in_use`]
+	fn destroy(self, device: &Device) {
+		let Swapchain {
+			handle,
+			backbuffers,
+			acquire_fence,
+			..
+		} = self;
+		drop(backbuffers);
+
+		let swapchain_khr = khr::Swapchain::new(&device.owner.instance, &device.logical);
+		unsafe {
+			device.logical.destroy_fence(acquire_fence, None);
+			swapchain_khr.destroy_swapchain(handle, None);
+		}
+	}
+}
+
+/// What the swapchain is recreated with
+#[derive(Copy, Clone)]
+struct SwapchainSettings {
+	// Size of the window. Presenting is suspended while either side is zero
+	extent: vk::Extent2D,
+	present_mode: PresentMode,
+	frames_in_flight: usize,
 }
 
 #[derive(Default, Copy, Clone)]
@@ -162,11 +280,39 @@ pub struct DeviceThreadInfo {
 	pub transfer_pool: vk::CommandPool,
 }
 
+impl DeviceThreadInfo {
+	/// Pool command buffers for `queue` are allocated from. `queue` must already be resolved
+	pub fn pool_mut(&mut self, queue: Queue) -> &mut vk::CommandPool {
+		match queue {
+			Queue::Graphics => &mut self.graphics_pool,
+			Queue::Compute => &mut self.compute_pool,
+			Queue::Transfer => &mut self.transfer_pool,
+		}
+	}
+}
+
+/// Identifies the pool an allocation was made from. Linear and optimal resources are kept in separate blocks
+/// so `bufferImageGranularity` never has to be accounted for.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MemoryKey {
+	pub memory_type: MemoryType,
+	pub index: u32,
+	pub linear: bool,
+}
+
+pub struct MemoryBlock {
+	pub memory: vk::DeviceMemory,
+	pub mapped: usize, // Host visible blocks stay mapped for their whole lifetime
+}
+
 #[derive(Default, Copy, Clone)]
 pub struct DeviceAllocation {
 	pub memory: vk::DeviceMemory,
 	pub offset: vk::DeviceSize,
 	pub size: vk::DeviceSize,
+	pub mapped: usize, // Pointer to offset when memory is host visible
+
+	pool: Option<(MemoryKey, SubAllocation)>,
 }
 
 pub enum WorkVariant {
@@ -185,14 +331,31 @@ pub struct WorkContainer {
 	pub in_queue: HashMap<usize, WorkEntry>,
 }
 
+/// What is needed to write the descriptors of a bindless texture
+#[derive(Copy, Clone)]
+pub struct BindlessTexture {
+	pub view: vk::ImageView,
+	pub usage: TextureUsage,
+	pub array_layers: u32,
+	pub cube: bool,
+}
+
+#[derive(Copy, Clone)]
+pub struct BindlessBuffer {
+	pub handle: vk::Buffer,
+	pub size: usize,
+}
+
 pub struct BindlessInfo {
-	pub textures: Vec<Weak<Texture>>,
+	pub textures: BindlessHeap<BindlessTexture>,
 	pub null_texture: Option<Arc<Texture>>,
+	pub null_texture_array: Option<Arc<Texture>>,
+	pub null_texture_cube: Option<Arc<Texture>>,
 
-	pub buffers: Vec<Weak<Buffer>>,
+	pub buffers: BindlessHeap<BindlessBuffer>,
 	pub null_buffer: Option<Arc<Buffer>>,
 
-	pub samplers: Vec<Weak<Sampler>>,
+	pub samplers: BindlessHeap<vk::Sampler>,
 	pub null_sampler: Option<Arc<Sampler>>,
 }
 
@@ -204,16 +367,28 @@ pub struct Device {
 
 	pub graphics_queue: Option<Mutex<vk::Queue>>,
 	pub presentation_queue: Option<Mutex<vk::Queue>>,
+	// None when the device has no dedicated family for the queue. Work falls back to the graphics queue
+	pub compute_queue: Option<Mutex<vk::Queue>>,
+	pub transfer_queue: Option<Mutex<vk::Queue>>,
 
 	pub graphics_family_index: Option<u32>,
 	pub surface_family_index: Option<u32>,
+	pub compute_family_index: Option<u32>,
+	pub transfer_family_index: Option<u32>,
 
 	pub work: Mutex<WorkContainer>,
+	// Work ids the next graphics submission waits on so uploads finish before their first use
+	uploads: Mutex<Vec<usize>>,
 
-	#[cfg(target_os = "windows")]
 	pub surface: Option<vk::SurfaceKHR>,
 
+	// None while presenting is suspended
 	swapchain: Mutex<Option<Swapchain>>,
+	swapchain_settings: Mutex<SwapchainSettings>,
+	// Replaced swapchains destroyed once the work presenting their backbuffers has finished
+	retired_swapchains: Mutex<Vec<Swapchain>>,
+	// Work ids waited on by each displayed frame. Oldest first
+	frames_in_flight: Mutex<VecDeque<Vec<usize>>>,
 	pub thread_info: Mutex<HashMap<ThreadId, DeviceThreadInfo>>,
 
 	pub bindless_info: Mutex<BindlessInfo>,
@@ -223,13 +398,80 @@ pub struct Device {
 	pub bindless_set: vk::DescriptorSet,
 
 	pub render_passes: Mutex<Vec<Arc<RenderPass>>>,
+	// Shared by every pipeline. Saved to disk so pipelines compile faster on the next run
+	pub pipeline_cache: vk::PipelineCache,
+
+	memory: Mutex<HashMap<MemoryKey, MemoryPool<MemoryBlock>>>,
+
+	// 1.0 when anisotropic filtering is not supported
+	pub max_sampler_anisotropy: f32,
+
+	// Indirect draws with more than one draw are split up when this is not supported
+	pub multi_draw_indirect: bool,
+	// Pipelines clip depth instead when this is not supported
+	pub depth_clamp: bool,
+	// Every color attachment uses the blend state of the first when this is not supported
+	pub independent_blend: bool,
+
+	// Queries that are not supported are skipped and read as zero
+	pub timestamps: bool,
+	pub pipeline_statistics: bool,
+	// Nanoseconds per timestamp tick
+	pub timestamp_period: f32,
+	// Pools of dropped command buffers waiting to be reused
+	query_pools: Mutex<Vec<QueryPools>>,
+	// Queries of the last finished submission that recorded any
+	report: Mutex<GpuReport>,
+
+	pub diagnostics: Diagnostics,
+}
+
+/// Creates a surface for the window system `window` belongs to
+unsafe fn create_surface(instance: &Instance, window: &Window) -> Result<vk::SurfaceKHR> {
+	match window.raw_window_handle() {
+		#[cfg(target_os = "windows")]
+		RawWindowHandle::Win32(handle) => {
+			let surface_khr = khr::Win32Surface::new(&instance.entry, &instance.instance);
+			let create_info = vk::Win32SurfaceCreateInfoKHR::builder()
+				.hinstance(handle.hinstance)
+				.hwnd(handle.hwnd);
+			surface_khr.create_win32_surface(&create_info, None)
+		}
+		#[cfg(all(unix, not(target_os = "macos")))]
+		RawWindowHandle::Xlib(handle) => {
+			let surface_khr = khr::XlibSurface::new(&instance.entry, &instance.instance);
+			let create_info = vk::XlibSurfaceCreateInfoKHR::builder()
+				.dpy(handle.display as *mut vk::Display)
+				.window(handle.window);
+			surface_khr.create_xlib_surface(&create_info, None)
+		}
+		#[cfg(all(unix, not(target_os = "macos")))]
+		RawWindowHandle::Xcb(handle) => {
+			let surface_khr = khr::XcbSurface::new(&instance.entry, &instance.instance);
+			let create_info = vk::XcbSurfaceCreateInfoKHR::builder()
+				.connection(handle.connection)
+				.window(handle.window);
+			surface_khr.create_xcb_surface(&create_info, None)
+		}
+		#[cfg(all(unix, not(target_os = "macos")))]
+		RawWindowHandle::Wayland(handle) => {
+			let surface_khr = khr::WaylandSurface::new(&instance.entry, &instance.instance);
+			let create_info = vk::WaylandSurfaceCreateInfoKHR::builder()
+				.display(handle.display)
+				.surface(handle.surface);
+			surface_khr.create_wayland_surface(&create_info, None)
+		}
+		_ => Err(vk::Result::ERROR_EXTENSION_NOT_PRESENT),
+	}
 }
 
 impl Device {
+	/// Sub allocates `requirements.size` bytes from a shared block. `linear` is true for buffers.
 	pub fn allocate_memory(
 		&self,
 		requirements: vk::MemoryRequirements,
 		memory_type: MemoryType,
+		linear: bool,
 	) -> Result<DeviceAllocation> {
 		let property_flag = match memory_type {
 			MemoryType::DeviceLocal => vk::MemoryPropertyFlags::DEVICE_LOCAL,
@@ -256,25 +498,92 @@ impl Device {
 			}
 			let index = index.unwrap();
 
-			let alloc_info = vk::MemoryAllocateInfo::builder()
-				.allocation_size(requirements.size)
-				.memory_type_index(index);
+			let key = MemoryKey {
+				memory_type,
+				index,
+				linear,
+			};
+			let mut memory = self.memory.lock().unwrap();
+			let pool = memory
+				.entry(key)
+				.or_insert_with(|| MemoryPool::new(BLOCK_SIZE));
+
+			let (allocation, block) =
+				pool.allocate(requirements.size, requirements.alignment, |size| {
+					let alloc_info = vk::MemoryAllocateInfo::builder()
+						.allocation_size(size)
+						.memory_type_index(index);
+					let memory = self.logical.allocate_memory(&alloc_info, None)?;
+
+					let mapped = if memory_type == MemoryType::HostVisible {
+						let result = self.logical.map_memory(
+							memory,
+							0,
+							vk::WHOLE_SIZE,
+							vk::MemoryMapFlags::empty(),
+						);
+						match result {
+							Ok(ptr) => ptr as usize,
+							Err(err) => {
+								self.logical.free_memory(memory, None);
+								return Err(err);
+							}
+						}
+					} else {
+						0
+					};
+
+					Ok(MemoryBlock { memory, mapped })
+				})?;
 
 			Ok(DeviceAllocation {
-				memory: self.logical.allocate_memory(&alloc_info, None)?,
-				offset: 0,
-				size: requirements.size,
+				memory: block.memory,
+				offset: allocation.offset,
+				size: allocation.size,
+				mapped: if block.mapped != 0 {
+					block.mapped + allocation.offset as usize
+				} else {
+					0
+				},
+
+				pool: Some((key, allocation)),
 			})
 		}
 	}
 
 	pub fn free_memory(&self, allocation: DeviceAllocation) {
-		unsafe {
-			self.logical.free_memory(allocation.memory, None);
+		let (key, allocation) = match allocation.pool {
+			Some(pool) => pool,
+			None => return,
+		};
+
+		let mut memory = self.memory.lock().unwrap();
+		let block = memory.get_mut(&key).and_then(|it| it.free(allocation));
+		if let Some(block) = block {
+			unsafe {
+				if block.mapped != 0 {
+					self.logical.unmap_memory(block.memory);
+				}
+				self.logical.free_memory(block.memory, None);
+			}
 		}
 	}
 
-	pub fn get_or_create_render_pass(&self, attachments: &[Format]) -> Result<Arc<RenderPass>> {
+	pub fn memory_stats(&self) -> MemoryStats {
+		let mut result = MemoryStats::default();
+		for (key, pool) in self.memory.lock().unwrap().iter() {
+			match key.memory_type {
+				MemoryType::HostVisible => result.host_visible += pool.usage(),
+				MemoryType::DeviceLocal => result.device_local += pool.usage(),
+			}
+		}
+		result
+	}
+
+	pub fn get_or_create_render_pass(
+		&self,
+		attachments: &[AttachmentKey],
+	) -> Result<Arc<RenderPass>> {
 		let mut render_passes = self.render_passes.lock().unwrap();
 		match render_passes.iter().find(|a| a.attachments == attachments) {
 			Some(render_pass) => Ok(render_pass.clone()),
@@ -325,6 +634,7 @@ impl Device {
 				is_acceptable &= properties.device_type == vk::PhysicalDeviceType::DISCRETE_GPU
 					&& features.geometry_shader == 1;
 				is_acceptable &= indexing_features.descriptor_binding_partially_bound == 1
+					&& indexing_features.descriptor_binding_update_unused_while_pending == 1
 					&& indexing_features.runtime_descriptor_array == 1;
 				// TODO: Update after bind for descriptor sets
 
@@ -340,33 +650,34 @@ impl Device {
 			physical_device = selected_device.unwrap();
 		}
 
+		// Optional features are enabled when the device has them
+		let supported_features = unsafe {
+			instance
+				.instance
+				.get_physical_device_features(physical_device)
+		};
+		let properties = unsafe {
+			instance
+				.instance
+				.get_physical_device_properties(physical_device)
+		};
+		let max_sampler_anisotropy = if supported_features.sampler_anisotropy == 1 {
+			properties.limits.max_sampler_anisotropy
+		} else {
+			1.0
+		};
+
 		// Create the surface if the builder provided one
-		#[cfg(target_os = "windows")]
-		let surface;
-		unsafe {
-			if let Some(window) = window {
-				let handle = window.raw_window_handle();
-
-				match handle {
-					RawWindowHandle::Win32(handle) => {
-						let surface_khr =
-							khr::Win32Surface::new(&instance.entry, &instance.instance);
-						let create_info = vk::Win32SurfaceCreateInfoKHR::builder()
-							.hinstance(handle.hinstance)
-							.hwnd(handle.hwnd);
-
-						surface = Some(surface_khr.create_win32_surface(&create_info, None)?);
-					}
-					_ => todo!(),
-				}
-			} else {
-				surface = None;
-			};
-		}
+		let surface = match window {
+			Some(window) => Some(unsafe { create_surface(&instance, window)? }),
+			None => None,
+		};
 
 		// Find the proper queue family indices
 		let mut graphics_family_index = None;
 		let mut surface_family_index = None;
+		let mut compute_family_index = None;
+		let mut transfer_family_index = None;
 		unsafe {
 			let queue_family_properties = instance
 				.instance
@@ -376,6 +687,25 @@ impl Device {
 					graphics_family_index = Some(index as u32);
 				}
 
+				// Dedicated families run alongside the graphics queue. Transfer families that can only copy
+				// whole blocks of texels are skipped as uploads copy single mips of any size
+				let dedicated = !it.queue_flags.contains(vk::QueueFlags::GRAPHICS);
+				if dedicated
+					&& it.queue_flags.contains(vk::QueueFlags::COMPUTE)
+					&& compute_family_index.is_none()
+				{
+					compute_family_index = Some(index as u32);
+				}
+				let granularity = it.min_image_transfer_granularity;
+				if dedicated
+					&& !it.queue_flags.contains(vk::QueueFlags::COMPUTE)
+					&& it.queue_flags.contains(vk::QueueFlags::TRANSFER)
+					&& (granularity.width, granularity.height, granularity.depth) == (1, 1, 1)
+					&& transfer_family_index.is_none()
+				{
+					transfer_family_index = Some(index as u32);
+				}
+
 				if window.is_some() {
 					let surface_khr = khr::Surface::new(&instance.entry, &instance.instance);
 					let present_support = surface_khr
@@ -392,12 +722,29 @@ impl Device {
 			}
 		}
 
-		let queue_family_indices = [graphics_family_index, surface_family_index];
+		// A family can only be requested once even if it is used for several queues
+		let mut queue_family_indices = Vec::with_capacity(4);
+		for it in [
+			graphics_family_index,
+			surface_family_index,
+			compute_family_index,
+			transfer_family_index,
+		]
+		.iter()
+		.flatten()
+		{
+			if !queue_family_indices.contains(it) {
+				queue_family_indices.push(*it);
+			}
+		}
 
 		// Create the logical device and the queues
 		let logical_device;
 		let graphics_queue;
 		let presentation_queue;
+		let compute_queue;
+		let transfer_queue;
+		let diagnostics;
 		unsafe {
 			// TODO: Use a custom linear or temp allocator later on when thats created
 			let mut queue_create_infos = Vec::new();
@@ -405,34 +752,49 @@ impl Device {
 			let queue_priorities = [0.0];
 			for it in queue_family_indices.iter() {
 				let create_info = vk::DeviceQueueCreateInfo::builder()
-					.queue_family_index(it.unwrap())
+					.queue_family_index(*it)
 					.queue_priorities(&queue_priorities);
 
 				queue_create_infos.push(create_info.build());
 			}
 
-			let device_features = vk::PhysicalDeviceFeatures::default();
-			let extensions = [b"VK_KHR_swapchain\0".as_ptr() as *const i8];
+			let device_features = vk::PhysicalDeviceFeatures::builder()
+				.sampler_anisotropy(supported_features.sampler_anisotropy == 1)
+				.texture_compression_bc(supported_features.texture_compression_bc == 1)
+				.multi_draw_indirect(supported_features.multi_draw_indirect == 1)
+				.draw_indirect_first_instance(supported_features.draw_indirect_first_instance == 1)
+				.pipeline_statistics_query(supported_features.pipeline_statistics_query == 1)
+				.depth_clamp(supported_features.depth_clamp == 1)
+				.independent_blend(supported_features.independent_blend == 1)
+				.build();
+			let diagnostic_extensions = DiagnosticExtensions::query(&instance, physical_device);
+			let mut extensions = vec![b"VK_KHR_swapchain\0".as_ptr() as *const i8];
+			extensions.append(&mut diagnostic_extensions.names());
 
 			let mut indexing_features = vk::PhysicalDeviceDescriptorIndexingFeatures::builder()
 				.descriptor_binding_partially_bound(true)
+				.descriptor_binding_update_unused_while_pending(true)
 				.runtime_descriptor_array(true)
 				.descriptor_binding_sampled_image_update_after_bind(true)
-				.descriptor_binding_storage_buffer_update_after_bind(true);
+				.descriptor_binding_storage_buffer_update_after_bind(true)
+				.descriptor_binding_storage_image_update_after_bind(true);
 
-			let create_info = vk::DeviceCreateInfo::builder()
+			let mut create_info = vk::DeviceCreateInfo::builder()
 				.push_next(&mut indexing_features)
 				.queue_create_infos(&queue_create_infos[..])
-				.enabled_layer_names(&ENABLED_LAYER_NAMES)
+				.enabled_layer_names(instance.enabled_layer_names())
 				.enabled_extension_names(&extensions)
 				.enabled_features(&device_features);
 
-			#[cfg(feature = "aftermath")]
-			let mut aftermath_features = vk::DeviceDiagnosticsConfigCreateInfoNV::builder()
+			let mut fault_features = diagnostic_extensions.fault_features();
+			let mut diagnostics_config = vk::DeviceDiagnosticsConfigCreateInfoNV::builder()
 				.flags(vk::DeviceDiagnosticsConfigFlagsNV::all());
-
-			#[cfg(feature = "aftermath")]
-			let create_info = create_info.push_next(&mut aftermath_features);
+			if diagnostic_extensions.device_fault {
+				create_info = create_info.push_next(&mut fault_features);
+			}
+			if diagnostic_extensions.diagnostics_config {
+				create_info = create_info.push_next(&mut diagnostics_config);
+			}
 
 			logical_device =
 				instance
@@ -446,6 +808,16 @@ impl Device {
 			} else {
 				presentation_queue = None;
 			}
+
+			compute_queue = compute_family_index.map(|it| logical_device.get_device_queue(it, 0));
+			transfer_queue = transfer_family_index.map(|it| logical_device.get_device_queue(it, 0));
+
+			diagnostics = Diagnostics::new(
+				&instance,
+				&logical_device,
+				physical_device,
+				diagnostic_extensions,
+			);
 		}
 
 		// Do the whole bindless setup thing
@@ -453,25 +825,46 @@ impl Device {
 			vk::DescriptorSetLayoutBinding::builder()
 				.binding(0)
 				.descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
-				.descriptor_count(2048)
-				.stage_flags(vk::ShaderStageFlags::ALL_GRAPHICS)
+				.descriptor_count(BINDLESS_CAPACITY)
+				.stage_flags(vk::ShaderStageFlags::ALL)
 				.build(),
 			vk::DescriptorSetLayoutBinding::builder()
 				.binding(1)
 				.descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
-				.descriptor_count(2048)
-				.stage_flags(vk::ShaderStageFlags::ALL_GRAPHICS)
+				.descriptor_count(BINDLESS_CAPACITY)
+				.stage_flags(vk::ShaderStageFlags::ALL)
 				.build(),
 			vk::DescriptorSetLayoutBinding::builder()
 				.binding(2)
 				.descriptor_type(vk::DescriptorType::SAMPLER)
-				.descriptor_count(2048)
-				.stage_flags(vk::ShaderStageFlags::ALL_GRAPHICS)
+				.descriptor_count(BINDLESS_CAPACITY)
+				.stage_flags(vk::ShaderStageFlags::ALL)
+				.build(),
+			vk::DescriptorSetLayoutBinding::builder()
+				.binding(3)
+				.descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+				.descriptor_count(BINDLESS_CAPACITY)
+				.stage_flags(vk::ShaderStageFlags::ALL)
+				.build(),
+			// Texture arrays and cube maps need their own arrays as the view type must match the shader
+			vk::DescriptorSetLayoutBinding::builder()
+				.binding(4)
+				.descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
+				.descriptor_count(BINDLESS_CAPACITY)
+				.stage_flags(vk::ShaderStageFlags::ALL)
+				.build(),
+			vk::DescriptorSetLayoutBinding::builder()
+				.binding(5)
+				.descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
+				.descriptor_count(BINDLESS_CAPACITY)
+				.stage_flags(vk::ShaderStageFlags::ALL)
 				.build(),
 		];
 
+		// Slots are written while submissions that do not read them are still executing
 		let bind_flags = [vk::DescriptorBindingFlags::PARTIALLY_BOUND_EXT
-			| vk::DescriptorBindingFlags::UPDATE_AFTER_BIND; 3];
+			| vk::DescriptorBindingFlags::UPDATE_AFTER_BIND
+			| vk::DescriptorBindingFlags::UPDATE_UNUSED_WHILE_PENDING; 6];
 
 		let mut extension = vk::DescriptorSetLayoutBindingFlagsCreateInfo::builder()
 			.binding_flags(&bind_flags)
@@ -491,12 +884,16 @@ impl Device {
 				.build(),
 			vk::DescriptorPoolSize::builder()
 				.ty(vk::DescriptorType::SAMPLED_IMAGE)
-				.descriptor_count(1)
+				.descriptor_count(3)
 				.build(),
 			vk::DescriptorPoolSize::builder()
 				.ty(vk::DescriptorType::SAMPLER)
 				.descriptor_count(1)
 				.build(),
+			vk::DescriptorPoolSize::builder()
+				.ty(vk::DescriptorType::STORAGE_IMAGE)
+				.descriptor_count(1)
+				.build(),
 		];
 
 		let create_info = vk::DescriptorPoolCreateInfo::builder()
@@ -513,16 +910,31 @@ impl Device {
 		let bindless_set = unsafe { logical_device.allocate_descriptor_sets(&create_info)? };
 
 		let bindles_info = BindlessInfo {
-			textures: Vec::new(),
+			textures: BindlessHeap::new(BINDLESS_CAPACITY),
 			null_texture: None,
+			null_texture_array: None,
+			null_texture_cube: None,
 
-			buffers: Vec::new(),
+			buffers: BindlessHeap::new(BINDLESS_CAPACITY),
 			null_buffer: None,
 
-			samplers: Vec::new(),
+			samplers: BindlessHeap::new(BINDLESS_CAPACITY),
 			null_sampler: None,
 		};
 
+		let create_info = vk::PipelineCacheCreateInfo::builder();
+		let pipeline_cache = unsafe { logical_device.create_pipeline_cache(&create_info, None)? };
+
+		let extent = window
+			.map(|it| {
+				let size = it.inner_size();
+				vk::Extent2D {
+					width: size.width,
+					height: size.height,
+				}
+			})
+			.unwrap_or_default();
+
 		let result = Arc::new(Device {
 			owner: instance,
 
@@ -531,18 +943,30 @@ impl Device {
 
 			graphics_queue: graphics_queue.map(Mutex::new),
 			presentation_queue: presentation_queue.map(Mutex::new),
+			compute_queue: compute_queue.map(Mutex::new),
+			transfer_queue: transfer_queue.map(Mutex::new),
 
 			graphics_family_index,
 			surface_family_index,
+			compute_family_index,
+			transfer_family_index,
 
 			work: Mutex::new(WorkContainer {
 				last_id: 0,
 				in_queue: HashMap::new(),
 			}),
+			uploads: Mutex::new(Vec::new()),
 
 			surface,
 
 			swapchain: Mutex::new(None),
+			swapchain_settings: Mutex::new(SwapchainSettings {
+				extent,
+				present_mode: PresentMode::Fifo,
+				frames_in_flight: 2,
+			}),
+			retired_swapchains: Mutex::new(Vec::new()),
+			frames_in_flight: Mutex::new(VecDeque::new()),
 			thread_info: Mutex::new(HashMap::new()),
 
 			bindless_info: Mutex::new(bindles_info),
@@ -552,22 +976,62 @@ impl Device {
 			bindless_set: bindless_set[0],
 
 			render_passes: Mutex::new(Vec::with_capacity(128)),
+			pipeline_cache,
+
+			memory: Mutex::new(HashMap::new()),
+
+			max_sampler_anisotropy,
+			multi_draw_indirect: supported_features.multi_draw_indirect == 1,
+			depth_clamp: supported_features.depth_clamp == 1,
+			independent_blend: supported_features.independent_blend == 1,
+
+			timestamps: properties.limits.timestamp_compute_and_graphics == 1,
+			pipeline_statistics: supported_features.pipeline_statistics_query == 1,
+			timestamp_period: properties.limits.timestamp_period,
+			query_pools: Mutex::new(Vec::new()),
+			report: Mutex::new(GpuReport::default()),
+
+			diagnostics,
 		});
 
-		{
+		if result.surface.is_some() {
+			let settings = *result.swapchain_settings.lock().unwrap();
 			let mut swapchain = result.swapchain.lock().unwrap();
-			*swapchain = Some(Swapchain::new(result.clone())?);
+			*swapchain = Swapchain::new(result.clone(), &settings, None)?;
 		}
 
 		// Create null texture
 		let null_texutre = Texture::new(
+			result.clone(),
+			MemoryType::DeviceLocal,
+			TextureUsage::SAMPLED | TextureUsage::STORAGE,
+			Format::RGBA_U8,
+			TextureExtent::new(64, 64, 1),
+		)
+		.unwrap();
+
+		let null_texture_array = Texture::new(
 			result.clone(),
 			MemoryType::DeviceLocal,
 			TextureUsage::SAMPLED,
 			Format::RGBA_U8,
-			64,
-			64,
-			1,
+			TextureExtent {
+				array_layers: 2,
+				..TextureExtent::new(4, 4, 1)
+			},
+		)
+		.unwrap();
+
+		let null_texture_cube = Texture::new(
+			result.clone(),
+			MemoryType::DeviceLocal,
+			TextureUsage::SAMPLED,
+			Format::RGBA_U8,
+			TextureExtent {
+				array_layers: 6,
+				cube: true,
+				..TextureExtent::new(4, 4, 1)
+			},
 		)
 		.unwrap();
 
@@ -585,6 +1049,8 @@ impl Device {
 		{
 			let mut bindless = result.bindless_info.lock().unwrap();
 			bindless.null_texture = Some(null_texutre);
+			bindless.null_texture_array = Some(null_texture_array);
+			bindless.null_texture_cube = Some(null_texture_cube);
 			bindless.null_buffer = Some(null_buffer);
 			bindless.null_sampler = Some(null_sampler);
 		}
@@ -592,39 +1058,62 @@ impl Device {
 		Ok(result)
 	}
 
-	pub fn acquire_backbuffer(&self) -> Result<Arc<Texture>> {
+	pub fn acquire_backbuffer(self: &Arc<Self>) -> Result<Option<Arc<Texture>>> {
 		assert!(self.surface.is_some());
 
 		let mut swapchain = self.swapchain.lock().unwrap();
 
-		let semaphore_create_info = vk::SemaphoreCreateInfo::builder();
-		let semaphore = unsafe {
-			self.logical
-				.create_semaphore(&semaphore_create_info, None)?
+		// Try again after being minimized or failing to recreate the swapchain
+		if swapchain.is_none() {
+			self.recreate_swapchain(&mut swapchain);
+		}
+		let current = match swapchain.as_mut() {
+			Some(current) => current,
+			None => return Ok(None),
 		};
 
 		let swapchain_khr = khr::Swapchain::new(&self.owner.instance, &self.logical);
-		let (index, _) = unsafe {
+		let result = unsafe {
 			swapchain_khr.acquire_next_image(
-				swapchain.as_ref().unwrap().handle,
-				1 << 63,
-				semaphore,
-				vk::Fence::default(),
+				current.handle,
+				u64::MAX,
+				vk::Semaphore::null(),
+				current.acquire_fence,
 			)
-		}?;
-
-		swapchain.as_mut().unwrap().current = Some(index as usize);
+		};
+		let index = match result {
+			// Suboptimal swapchains are recreated after presenting
+			Ok((index, _)) => index,
+			Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+				self.recreate_swapchain(&mut swapchain);
+				return Ok(None);
+			}
+			Err(err) => return Err(err),
+		};
 
-		unsafe { self.logical.destroy_semaphore(semaphore, None) };
+		unsafe {
+			self.logical
+				.wait_for_fences(from_ref(&current.acquire_fence), true, u64::MAX)
+				.map_err(|err| self.check_lost(err))?;
+			self.logical
+				.reset_fences(from_ref(&current.acquire_fence))?;
+		}
 
-		Ok(swapchain.as_ref().unwrap().backbuffers[index as usize].clone())
+		current.current = Some(index as usize);
+		Ok(Some(current.backbuffers[index as usize].clone()))
 	}
 
-	pub fn submit_graphics(
+	pub fn submit(
 		&self,
 		command_buffers: Vec<GraphicsCommandBuffer>,
 		wait_on: &[Receipt],
 	) -> Receipt {
+		let queue = command_buffers[0].queue;
+		assert!(
+			command_buffers.iter().all(|it| it.queue == queue),
+			"Command buffers submitted together must be recorded for the same queue"
+		);
+
 		self.update_bindless();
 
 		let mut buffers = Vec::with_capacity(command_buffers.len());
@@ -645,32 +1134,47 @@ impl Device {
 		let mut submit_info = vk::SubmitInfo::builder()
 			.command_buffers(&buffers[..])
 			.signal_semaphores(from_ref(&semaphore));
+		let graphics = queue == Queue::Graphics;
 
 		unsafe {
-			let queue = self.graphics_queue.as_ref().unwrap().lock().unwrap();
+			let queue = self.queue(queue).lock().unwrap();
 
 			self.remove_finished_work();
 
 			let mut wait_semaphores = Vec::with_capacity(wait_on.len());
 			let mut wait_stages = Vec::with_capacity(wait_on.len());
-			if !wait_on.is_empty() {
-				for it in wait_on.iter() {
-					let sync = it.get();
-					if sync.is_none() {
-						continue;
-					}
-					let (semaphore, _) = sync.unwrap();
+			for it in wait_on.iter() {
+				let sync = it.get();
+				if sync.is_none() {
+					continue;
+				}
+				let (semaphore, _) = sync.unwrap();
 
-					wait_semaphores.push(semaphore);
-					wait_stages.push(vk::PipelineStageFlags::BOTTOM_OF_PIPE);
+				// Receipts can come from another queue so every command waits on them
+				wait_semaphores.push(semaphore);
+				wait_stages.push(vk::PipelineStageFlags::ALL_COMMANDS);
+			}
+
+			// Uploads that already finished have no semaphore left to wait on
+			if graphics {
+				let uploads = std::mem::take(&mut *self.uploads.lock().unwrap());
+				let work = self.work.lock().unwrap();
+				for id in uploads {
+					if let Some(entry) = work.in_queue.get(&id) {
+						wait_semaphores.push(entry.semaphore);
+						wait_stages.push(vk::PipelineStageFlags::ALL_COMMANDS);
+					}
 				}
+			}
 
+			if !wait_semaphores.is_empty() {
 				submit_info = submit_info
 					.wait_semaphores(&wait_semaphores[..])
 					.wait_dst_stage_mask(&wait_stages[..]);
 			}
 			self.logical
 				.queue_submit(*queue, from_ref(&submit_info), fence)
+				.map_err(|err| self.check_lost(err))
 				.expect("Failed to submit graphics commands to gpu");
 		}
 
@@ -685,7 +1189,11 @@ impl Device {
 		Receipt::new(owner, id)
 	}
 
-	pub fn display(&self, wait_on: &[Receipt]) {
+	pub fn wait_before_rendering(&self, receipt: &Receipt) {
+		self.uploads.lock().unwrap().push(receipt.id());
+	}
+
+	pub fn display(self: &Arc<Self>, wait_on: &[Receipt]) {
 		assert!(self.surface.is_some());
 
 		self.remove_finished_work();
@@ -693,14 +1201,16 @@ impl Device {
 		let mut swapchain = self.swapchain.lock().unwrap();
 		let swapchain_khr = khr::Swapchain::new(&self.owner.instance, &self.logical);
 
-		let index = swapchain
-			.as_ref()
-			.unwrap()
-			.current
-			.expect("Backbuffer was not acquired") as u32;
+		// The swapchain was suspended by a resize after the backbuffer was acquired
+		let current = match swapchain.as_mut() {
+			Some(current) => current,
+			None => return,
+		};
+
+		let index = current.current.take().expect("Backbuffer was not acquired") as u32;
 
 		let mut present_info = vk::PresentInfoKHR::builder()
-			.swapchains(from_ref(&swapchain.as_ref().unwrap().handle))
+			.swapchains(from_ref(&current.handle))
 			.image_indices(from_ref(&index));
 
 		let mut wait_semaphores = Vec::with_capacity(wait_on.len());
@@ -723,126 +1233,516 @@ impl Device {
 
 			swapchain_khr.queue_present(*queue, &present_info)
 		};
-		if result.is_err() {
-			*swapchain =
-				Swapchain::new(swapchain.as_ref().unwrap().backbuffers[0].owner.clone()).ok();
+		match result {
+			Ok(false) => {}
+			Ok(true) | Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+				self.recreate_swapchain(&mut swapchain)
+			}
+			Err(err) => {
+				let err = self.check_lost(err);
+				warn!(VULKAN_CATEGORY, "Failed to present: {:?}", err);
+				self.recreate_swapchain(&mut swapchain);
+			}
+		}
+		drop(swapchain);
+
+		// Block until the frame that is `frames_in_flight` behind has finished
+		let frames_in_flight = self.swapchain_settings.lock().unwrap().frames_in_flight;
+		let mut frames = self.frames_in_flight.lock().unwrap();
+		frames.push_back(wait_on.iter().map(|it| it.id()).collect());
+		while frames.len() > frames_in_flight {
+			let frame = frames.pop_front().unwrap();
+			self.wait_for_work(&frame);
+		}
+	}
+
+	/// Waits for the swapchain to stop being used and replaces it with one matching the current settings
+	fn recreate_swapchain(self: &Arc<Self>, swapchain: &mut Option<Swapchain>) {
+		self.wait_for_idle();
+
+		let settings = *self.swapchain_settings.lock().unwrap();
+		let old = swapchain.take();
+		*swapchain = match Swapchain::new(self.clone(), &settings, old.as_ref()) {
+			Ok(swapchain) => swapchain,
+			Err(err) => {
+				warn!(VULKAN_CATEGORY, "Failed to create swapchain: {:?}", err);
+				None
+			}
+		};
+
+		if let Some(old) = old {
+			self.retired_swapchains.lock().unwrap().push(old);
+		}
+		self.destroy_retired_swapchains();
+	}
+
+	fn destroy_retired_swapchains(&self) {
+		let mut retired = self.retired_swapchains.lock().unwrap();
+		let (in_use, unused) = retired.drain(..).partition(|it: &Swapchain| it.in_use());
+		*retired = in_use;
+		drop(retired);
+
+		for it in unused {
+			it.destroy(self);
+		}
+	}
+
+	pub fn resize(self: &Arc<Self>, width: u32, height: u32) {
+		if self.surface.is_none() {
+			return;
+		}
+
+		self.swapchain_settings.lock().unwrap().extent = vk::Extent2D { width, height };
+
+		let mut swapchain = self.swapchain.lock().unwrap();
+		self.recreate_swapchain(&mut swapchain);
+	}
+
+	pub fn present_mode(&self) -> PresentMode {
+		self.swapchain_settings.lock().unwrap().present_mode
+	}
+
+	pub fn set_present_mode(self: &Arc<Self>, present_mode: PresentMode) {
+		{
+			let mut settings = self.swapchain_settings.lock().unwrap();
+			if settings.present_mode == present_mode {
+				return;
+			}
+			settings.present_mode = present_mode;
+		}
+
+		if self.surface.is_some() {
+			let mut swapchain = self.swapchain.lock().unwrap();
+			self.recreate_swapchain(&mut swapchain);
 		}
 	}
 
+	pub fn frames_in_flight(&self) -> usize {
+		self.swapchain_settings.lock().unwrap().frames_in_flight
+	}
+
+	pub fn set_frames_in_flight(&self, frames_in_flight: usize) {
+		self.swapchain_settings.lock().unwrap().frames_in_flight = frames_in_flight;
+	}
+
+	fn wait_for_work(&self, ids: &[usize]) {
+		{
+			let work = self.work.lock().unwrap();
+			let fences: Vec<vk::Fence> = ids
+				.iter()
+				.filter_map(|id| work.in_queue.get(id))
+				.map(|it| it.fence)
+				.collect();
+			if fences.is_empty() {
+				return;
+			}
+
+			unsafe {
+				self.logical
+					.wait_for_fences(&fences[..], true, u64::MAX)
+					.map_err(|err| self.check_lost(err))
+					.unwrap()
+			};
+		}
+
+		self.remove_finished_work();
+	}
+
 	pub fn remove_finished_work(&self) {
-		let mut work = self.work.lock().unwrap();
+		// Finished work is dropped without the lock held as the resources it keeps alive lock it when dropped
+		let finished: Vec<WorkEntry> = {
+			let mut work = self.work.lock().unwrap();
+
+			let mut ids: Vec<usize> = work
+				.in_queue
+				.iter()
+				.filter(|(_, v)| {
+					v.thread_id == std::thread::current().id()
+						&& unsafe { self.logical.get_fence_status(v.fence).unwrap() }
+				})
+				.map(|(id, _)| *id)
+				.collect();
+			// Oldest first so the newest report is kept
+			ids.sort_unstable();
+			ids.iter()
+				.filter_map(|id| work.in_queue.remove(id))
+				.collect()
+		};
 
-		work.in_queue.retain(|_, v| {
-			if v.thread_id != std::thread::current().id() {
-				return true;
+		for it in finished.iter() {
+			// Queries are read once the work is done so reading never stalls
+			let WorkVariant::Graphics(buffers) = &it.variant;
+			let mut report = GpuReport::default();
+			for it in buffers.iter() {
+				report.append(it.resolve_queries());
+			}
+			if !report.is_empty() {
+				*self.report.lock().unwrap() = report;
 			}
 
 			unsafe {
-				let result = self.logical.get_fence_status(v.fence).unwrap();
-				if result {
-					self.logical.destroy_fence(v.fence, None);
-					self.logical.destroy_semaphore(v.semaphore, None);
-				}
-				!result
+				self.logical.destroy_fence(it.fence, None);
+				self.logical.destroy_semaphore(it.semaphore, None);
 			}
-		});
+		}
+		drop(finished);
+		self.destroy_retired_swapchains();
+
+		let pending = {
+			let work = self.work.lock().unwrap();
+			work.in_queue.keys().min().copied().unwrap_or(work.last_id)
+		};
+		let mut bindless = self.bindless_info.lock().unwrap();
+		bindless.textures.recycle(pending);
+		bindless.buffers.recycle(pending);
+		bindless.samplers.recycle(pending);
 	}
 
-	pub fn update_bindless(&self) {
+	/// Hands out a bindless index. Fails when every slot of `kind` is in use
+	pub fn allocate_bindless_texture(&self, texture: BindlessTexture, name: String) -> Result<u32> {
+		let mut bindless = self.bindless_info.lock().unwrap();
+		bindless
+			.textures
+			.allocate(texture, name)
+			.ok_or(vk::Result::ERROR_OUT_OF_POOL_MEMORY)
+	}
+
+	pub fn allocate_bindless_buffer(&self, buffer: BindlessBuffer, name: String) -> Result<u32> {
+		let mut bindless = self.bindless_info.lock().unwrap();
+		bindless
+			.buffers
+			.allocate(buffer, name)
+			.ok_or(vk::Result::ERROR_OUT_OF_POOL_MEMORY)
+	}
+
+	pub fn allocate_bindless_sampler(&self, sampler: vk::Sampler, name: String) -> Result<u32> {
+		let mut bindless = self.bindless_info.lock().unwrap();
+		bindless
+			.samplers
+			.allocate(sampler, name)
+			.ok_or(vk::Result::ERROR_OUT_OF_POOL_MEMORY)
+	}
+
+	/// The slot keeps its descriptor until the work submitted so far has finished. It is then written with a null
+	/// resource and can be reused
+	pub fn release_bindless(&self, kind: BindlessKind, index: u32) {
+		let work = self.work.lock().unwrap().last_id;
+
+		let mut bindless = self.bindless_info.lock().unwrap();
+		match kind {
+			BindlessKind::Buffer => bindless.buffers.release(index, work),
+			BindlessKind::Texture => bindless.textures.release(index, work),
+			BindlessKind::Sampler => bindless.samplers.release(index, work),
+		}
+	}
+
+	pub fn set_bindless_name(&self, kind: BindlessKind, index: u32, name: &str) {
+		let mut bindless = self.bindless_info.lock().unwrap();
+		match kind {
+			BindlessKind::Buffer => bindless.buffers.set_name(index, name),
+			BindlessKind::Texture => bindless.textures.set_name(index, name),
+			BindlessKind::Sampler => bindless.samplers.set_name(index, name),
+		}
+	}
+
+	pub fn bindless_view(&self) -> BindlessView {
 		let bindless = self.bindless_info.lock().unwrap();
+		let slots = bindless
+			.buffers
+			.view(BindlessKind::Buffer)
+			.chain(bindless.textures.view(BindlessKind::Texture))
+			.chain(bindless.samplers.view(BindlessKind::Sampler))
+			.collect();
+		let retired =
+			bindless.buffers.retired() + bindless.textures.retired() + bindless.samplers.retired();
+
+		BindlessView { slots, retired }
+	}
+
+	/// Writes the descriptors of every bindless slot that changed since the last submission
+	pub fn update_bindless(&self) {
+		let mut bindless = self.bindless_info.lock().unwrap();
+		let bindless = &mut *bindless;
 
 		let null_buffer = bindless.null_buffer.as_ref().unwrap();
-		let mut buffers = Vec::with_capacity(bindless.buffers.len());
-		for it in bindless.buffers.iter() {
-			if it.strong_count() == 0 {
+		let buffer_infos: Vec<(u32, vk::DescriptorBufferInfo)> = bindless
+			.buffers
+			.take_dirty()
+			.into_iter()
+			.map(|index| {
+				let (handle, size) = match bindless.buffers.get(index) {
+					Some(buffer) => (buffer.handle, buffer.size),
+					None => (null_buffer.handle, null_buffer.size),
+				};
 				let info = vk::DescriptorBufferInfo::builder()
-					.buffer(null_buffer.handle)
-					.range(null_buffer.size as u64)
+					.buffer(handle)
+					.range(size as u64)
 					.build();
+				(index, info)
+			})
+			.collect();
 
-				buffers.push(info);
-				continue;
-			}
+		// Every texture array shares a bindless index so each texture is written into all of them. Textures
+		// which are missing the usage or view type for an array are replaced with a matching null texture
+		let null_texture = bindless.null_texture.as_ref().unwrap();
+		let null_texture_array = bindless.null_texture_array.as_ref().unwrap();
+		let null_texture_cube = bindless.null_texture_cube.as_ref().unwrap();
+		let texture_infos: Vec<(u32, [vk::DescriptorImageInfo; 4])> = bindless
+			.textures
+			.take_dirty()
+			.into_iter()
+			.map(|index| {
+				let tex = bindless.textures.get(index);
+
+				let sampled_info = |null: &Arc<Texture>, filter: fn(&BindlessTexture) -> bool| {
+					let view = tex
+						.filter(|tex| tex.usage.contains(TextureUsage::SAMPLED) && filter(tex))
+						.map(|tex| tex.view)
+						.unwrap_or(null.view);
+					vk::DescriptorImageInfo::builder()
+						.image_view(view)
+						.image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+						.build()
+				};
 
-			let buffer = it.upgrade().unwrap();
-			let info = vk::DescriptorBufferInfo::builder()
-				.buffer(buffer.handle)
-				.range(buffer.size as u64)
-				.build();
+				let storage = tex
+					.filter(|tex| {
+						tex.usage.contains(TextureUsage::STORAGE) && tex.array_layers == 1
+					})
+					.map(|tex| tex.view)
+					.unwrap_or(null_texture.view);
+				let storage_info = vk::DescriptorImageInfo::builder()
+					.image_view(storage)
+					.image_layout(vk::ImageLayout::GENERAL)
+					.build();
+
+				let infos = [
+					sampled_info(null_texture, |tex| tex.array_layers == 1),
+					storage_info,
+					sampled_info(null_texture_array, |tex| tex.array_layers > 1 && !tex.cube),
+					sampled_info(null_texture_cube, |tex| tex.cube),
+				];
+				(index, infos)
+			})
+			.collect();
+
+		let null_sampler = bindless.null_sampler.as_ref().unwrap();
+		let sampler_infos: Vec<(u32, vk::DescriptorImageInfo)> = bindless
+			.samplers
+			.take_dirty()
+			.into_iter()
+			.map(|index| {
+				let sampler = bindless
+					.samplers
+					.get(index)
+					.copied()
+					.unwrap_or(null_sampler.handle);
+				let info = vk::DescriptorImageInfo::builder().sampler(sampler).build();
+				(index, info)
+			})
+			.collect();
+
+		let write = |binding: u32, descriptor_type: vk::DescriptorType, index: u32| {
+			vk::WriteDescriptorSet::builder()
+				.dst_set(self.bindless_set)
+				.dst_binding(binding)
+				.dst_array_element(index)
+				.descriptor_type(descriptor_type)
+		};
 
-			buffers.push(info);
+		let mut set_writes =
+			Vec::with_capacity(buffer_infos.len() + texture_infos.len() * 4 + sampler_infos.len());
+		for (index, info) in buffer_infos.iter() {
+			set_writes.push(
+				write(0, vk::DescriptorType::STORAGE_BUFFER, *index)
+					.buffer_info(from_ref(info))
+					.build(),
+			);
+		}
+		for (index, infos) in texture_infos.iter() {
+			let bindings = [
+				(1, vk::DescriptorType::SAMPLED_IMAGE),
+				(3, vk::DescriptorType::STORAGE_IMAGE),
+				(4, vk::DescriptorType::SAMPLED_IMAGE),
+				(5, vk::DescriptorType::SAMPLED_IMAGE),
+			];
+			for ((binding, descriptor_type), info) in bindings.iter().zip(infos.iter()) {
+				set_writes.push(
+					write(*binding, *descriptor_type, *index)
+						.image_info(from_ref(info))
+						.build(),
+				);
+			}
+		}
+		for (index, info) in sampler_infos.iter() {
+			set_writes.push(
+				write(2, vk::DescriptorType::SAMPLER, *index)
+					.image_info(from_ref(info))
+					.build(),
+			);
 		}
 
-		let buffers_set_write = vk::WriteDescriptorSet::builder()
-			.dst_set(self.bindless_set)
-			.dst_binding(0)
-			.descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
-			.buffer_info(&buffers[..])
-			.build();
+		if !set_writes.is_empty() {
+			unsafe { self.logical.update_descriptor_sets(&set_writes, &[]) };
+		}
+	}
 
-		let null_texture = bindless.null_texture.as_ref().unwrap();
-		let mut image_infos = Vec::with_capacity(bindless.textures.len()); // TODO: Use temp allocator
-		for it in bindless.textures.iter() {
-			if it.strong_count() == 0 {
-				let image_info = vk::DescriptorImageInfo::builder()
-					.image_view(null_texture.view)
-					.image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-					.build();
+	pub fn wait_for_idle(&self) {
+		unsafe {
+			self.logical
+				.device_wait_idle()
+				.map_err(|err| self.check_lost(err))
+				.unwrap()
+		};
+	}
 
-				image_infos.push(image_info);
-				continue;
-			}
+	/// Logs what the GPU was doing when `err` is `ERROR_DEVICE_LOST`. Returns `err` so it can be passed on
+	pub fn check_lost(&self, err: vk::Result) -> vk::Result {
+		self.diagnostics.check(&self.logical, err)
+	}
 
-			let tex = it.upgrade().unwrap();
+	pub fn wait_for_crash_dump(&self) {
+		// Aftermath writes its dump on another thread and needs time to catch the hang
+		if self.owner.has_aftermath() {
+			std::thread::sleep(Duration::from_millis(3000));
+		}
+	}
 
-			let image_info = vk::DescriptorImageInfo::builder()
-				.image_view(tex.view)
-				.image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-				.build();
+	/// Returns the queue work for `queue` runs on. Queues without a dedicated family use the graphics queue
+	pub fn resolve_queue(&self, queue: Queue) -> Queue {
+		if self.has_dedicated_queue(queue) {
+			queue
+		} else {
+			Queue::Graphics
+		}
+	}
 
-			image_infos.push(image_info);
+	pub fn has_dedicated_queue(&self, queue: Queue) -> bool {
+		match queue {
+			Queue::Graphics => true,
+			Queue::Compute => self.compute_queue.is_some(),
+			Queue::Transfer => self.transfer_queue.is_some(),
 		}
+	}
 
-		let null_sampler = bindless.null_sampler.as_ref().unwrap();
-		let mut sampler_infos = Vec::with_capacity(bindless.samplers.len()); // TODO: Use temp allocator
-		for it in bindless.samplers.iter() {
-			if it.strong_count() == 0 {
-				let sampler_info = vk::DescriptorImageInfo::builder()
-					.sampler(null_sampler.handle)
-					.build();
+	fn queue(&self, queue: Queue) -> &Mutex<vk::Queue> {
+		let result = match self.resolve_queue(queue) {
+			Queue::Graphics => &self.graphics_queue,
+			Queue::Compute => &self.compute_queue,
+			Queue::Transfer => &self.transfer_queue,
+		};
+		result.as_ref().unwrap()
+	}
 
-				sampler_infos.push(sampler_info);
-				continue;
+	pub fn queue_family_index(&self, queue: Queue) -> u32 {
+		let result = match self.resolve_queue(queue) {
+			Queue::Graphics => self.graphics_family_index,
+			Queue::Compute => self.compute_family_index,
+			Queue::Transfer => self.transfer_family_index,
+		};
+		result.unwrap()
+	}
+
+	/// Every family resources can be used on. Resources are shared between them when there is more than one
+	/// so no ownership transfers are needed between queues
+	pub fn queue_family_indices(&self) -> Vec<u32> {
+		let mut result = Vec::with_capacity(3);
+		for it in [Queue::Graphics, Queue::Compute, Queue::Transfer].iter() {
+			let index = self.queue_family_index(*it);
+			if !result.contains(&index) {
+				result.push(index);
 			}
+		}
+		result
+	}
 
-			let sampler = it.upgrade().unwrap();
-			let sampler_info = vk::DescriptorImageInfo::builder()
-				.sampler(sampler.handle)
-				.build();
+	/// Sharing mode of buffers and images that can be used on every queue
+	pub fn sharing_mode(queue_family_indices: &[u32]) -> vk::SharingMode {
+		if queue_family_indices.len() > 1 {
+			vk::SharingMode::CONCURRENT
+		} else {
+			vk::SharingMode::EXCLUSIVE
+		}
+	}
 
-			sampler_infos.push(sampler_info);
+	/// Merges `data` from a previous run into the pipeline cache. Data from another driver or device is ignored
+	pub fn load_pipeline_cache(&self, data: &[u8]) -> Result<()> {
+		// Header is length, version, vendor id, device id and the cache uuid
+		if data.len() < 32 {
+			return Ok(());
 		}
 
-		let image_set_write = vk::WriteDescriptorSet::builder()
-			.dst_set(self.bindless_set)
-			.dst_binding(1)
-			.descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
-			.image_info(&image_infos[..])
-			.build();
+		let properties = unsafe {
+			self.owner
+				.instance
+				.get_physical_device_properties(self.physical)
+		};
+		let matches = data[8..12] == properties.vendor_id.to_le_bytes()
+			&& data[12..16] == properties.device_id.to_le_bytes()
+			&& data[16..32] == properties.pipeline_cache_uuid;
+		if !matches {
+			warn!(
+				VULKAN_CATEGORY,
+				"Ignoring a pipeline cache from another device or driver"
+			);
+			return Ok(());
+		}
 
-		let samplers_set_write = vk::WriteDescriptorSet::builder()
-			.dst_set(self.bindless_set)
-			.dst_binding(2)
-			.descriptor_type(vk::DescriptorType::SAMPLER)
-			.image_info(&sampler_infos[..])
-			.build();
+		let create_info = vk::PipelineCacheCreateInfo::builder().initial_data(data);
+		unsafe {
+			let loaded = self.logical.create_pipeline_cache(&create_info, None)?;
+			// ash has no wrapper for vkMergePipelineCaches
+			let result = self.logical.fp_v1_0().merge_pipeline_caches(
+				self.logical.handle(),
+				self.pipeline_cache,
+				1,
+				&loaded,
+			);
+			self.logical.destroy_pipeline_cache(loaded, None);
+			result.result()?;
+		}
 
-		let set_writes = [buffers_set_write, image_set_write, samplers_set_write];
+		Ok(())
+	}
 
-		unsafe { self.logical.update_descriptor_sets(&set_writes, &[]) };
+	pub fn pipeline_cache_data(&self) -> Result<Vec<u8>> {
+		let data = unsafe { self.logical.get_pipeline_cache_data(self.pipeline_cache)? };
+		Ok(data)
 	}
 
-	pub fn wait_for_idle(&self) {
-		unsafe { self.logical.device_wait_idle().unwrap() };
+	pub fn last_report(&self) -> GpuReport {
+		self.report.lock().unwrap().clone()
+	}
+
+	pub fn take_query_pools(&self) -> QueryPools {
+		match self.query_pools.lock().unwrap().pop() {
+			Some(pools) => pools,
+			None => QueryPools::new(self),
+		}
+	}
+
+	pub fn return_query_pools(&self, pools: QueryPools) {
+		self.query_pools.lock().unwrap().push(pools);
+	}
+
+	/// Names `handle` in captures and validation messages. Does nothing without VK_EXT_debug_utils
+	pub fn set_debug_name<H: vk::Handle>(&self, handle: H, name: &str) {
+		let debug_utils = match &self.owner.debug_utils {
+			Some(debug_utils) => debug_utils,
+			None => return,
+		};
+		let name = match CString::new(name) {
+			Ok(name) => name,
+			Err(_) => return,
+		};
+
+		let info = vk::DebugUtilsObjectNameInfoEXT::builder()
+			.object_type(H::TYPE)
+			.object_handle(handle.as_raw())
+			.object_name(&name);
+		unsafe {
+			// Names are only for debugging so failing to set one is not an error
+			let _ = debug_utils.debug_utils_set_object_name(self.logical.handle(), &info);
+		}
 	}
 }
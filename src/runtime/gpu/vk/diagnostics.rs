@@ -0,0 +1,508 @@
+use super::{
+	Instance,
+	VULKAN_CATEGORY,
+};
+
+use ash::version::{
+	DeviceV1_0,
+	InstanceV1_0,
+	InstanceV1_1,
+};
+use ash::vk;
+
+use std::ffi::{
+	c_void,
+	CStr,
+};
+use std::mem::{
+	transmute,
+	zeroed,
+};
+use std::os::raw::c_char;
+use std::ptr::{
+	null_mut,
+	write_bytes,
+};
+use std::sync::atomic::{
+	AtomicBool,
+	AtomicU32,
+	Ordering,
+};
+use std::sync::Mutex;
+
+use crate::Result;
+
+use engine::{
+	error,
+	warn,
+};
+
+// VK_EXT_device_fault is newer than the headers ash was generated from so its types are declared here
+const DEVICE_FAULT_NAME: &[u8] = b"VK_EXT_device_fault\0";
+const PHYSICAL_DEVICE_FAULT_FEATURES: vk::StructureType =
+	vk::StructureType::from_raw(1_000_341_000);
+const DEVICE_FAULT_COUNTS: vk::StructureType = vk::StructureType::from_raw(1_000_341_001);
+const DEVICE_FAULT_INFO: vk::StructureType = vk::StructureType::from_raw(1_000_341_002);
+
+#[repr(C)]
+pub struct PhysicalDeviceFaultFeatures {
+	s_type: vk::StructureType,
+	p_next: *mut c_void,
+	device_fault: vk::Bool32,
+	device_fault_vendor_binary: vk::Bool32,
+}
+
+unsafe impl vk::ExtendsDeviceCreateInfo for PhysicalDeviceFaultFeatures {}
+
+impl PhysicalDeviceFaultFeatures {
+	fn new(device_fault: bool) -> Self {
+		Self {
+			s_type: PHYSICAL_DEVICE_FAULT_FEATURES,
+			p_next: null_mut(),
+			device_fault: device_fault as vk::Bool32,
+			device_fault_vendor_binary: vk::FALSE,
+		}
+	}
+}
+
+#[repr(C)]
+struct DeviceFaultCounts {
+	s_type: vk::StructureType,
+	p_next: *mut c_void,
+	address_info_count: u32,
+	vendor_info_count: u32,
+	vendor_binary_size: vk::DeviceSize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct DeviceFaultAddressInfo {
+	address_type: i32,
+	reported_address: vk::DeviceAddress,
+	address_precision: vk::DeviceSize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct DeviceFaultVendorInfo {
+	description: [c_char; vk::MAX_DESCRIPTION_SIZE],
+	vendor_fault_code: u64,
+	vendor_fault_data: u64,
+}
+
+#[repr(C)]
+struct DeviceFaultInfo {
+	s_type: vk::StructureType,
+	p_next: *mut c_void,
+	description: [c_char; vk::MAX_DESCRIPTION_SIZE],
+	p_address_infos: *mut DeviceFaultAddressInfo,
+	p_vendor_infos: *mut DeviceFaultVendorInfo,
+	p_vendor_binary_data: *mut c_void,
+}
+
+type GetDeviceFaultInfo = unsafe extern "system" fn(
+	vk::Device,
+	*mut DeviceFaultCounts,
+	*mut DeviceFaultInfo,
+) -> vk::Result;
+
+fn address_type_name(address_type: i32) -> &'static str {
+	match address_type {
+		1 => "invalid read",
+		2 => "invalid write",
+		3 => "invalid execute",
+		4 => "unknown instruction pointer",
+		5 => "invalid instruction pointer",
+		6 => "faulting instruction pointer",
+		_ => "unknown address",
+	}
+}
+
+/// Optional device extensions used to find out why a device was lost
+#[derive(Copy, Clone, Default)]
+pub struct DiagnosticExtensions {
+	pub device_fault: bool,
+	pub buffer_marker: bool,
+	// Lets Aftermath track shaders and resources. Only used when it is loaded
+	pub diagnostics_config: bool,
+}
+
+impl DiagnosticExtensions {
+	/// Returns every extension `physical` supports that the instance asked for
+	pub fn query(instance: &Instance, physical: vk::PhysicalDevice) -> Self {
+		let properties = unsafe {
+			instance
+				.instance
+				.enumerate_device_extension_properties(physical)
+				.unwrap_or_default()
+		};
+		let supported = |name: &CStr| {
+			properties
+				.iter()
+				.any(|it| unsafe { CStr::from_ptr(it.extension_name.as_ptr()) } == name)
+		};
+		let device_fault_name = CStr::from_bytes_with_nul(DEVICE_FAULT_NAME).unwrap();
+
+		let mut result = Self {
+			diagnostics_config: instance.has_aftermath()
+				&& supported(vk::NvDeviceDiagnosticsConfigFn::name()),
+			..Default::default()
+		};
+		if !instance.crash_diagnostics {
+			return result;
+		}
+
+		if supported(device_fault_name) {
+			let mut fault_features = PhysicalDeviceFaultFeatures::new(false);
+			let mut features = vk::PhysicalDeviceFeatures2 {
+				p_next: &mut fault_features as *mut PhysicalDeviceFaultFeatures as *mut c_void,
+				..Default::default()
+			};
+			unsafe {
+				instance
+					.instance
+					.get_physical_device_features2(physical, &mut features)
+			};
+			result.device_fault = fault_features.device_fault == vk::TRUE;
+		}
+		result.buffer_marker = supported(vk::AmdBufferMarkerFn::name());
+
+		if !result.device_fault && !result.buffer_marker {
+			warn!(
+				VULKAN_CATEGORY,
+				"Crash diagnostics were requested but the device supports neither VK_EXT_device_fault nor VK_AMD_buffer_marker"
+			);
+		}
+		result
+	}
+
+	pub fn names(&self) -> Vec<*const c_char> {
+		let mut result = Vec::with_capacity(3);
+		if self.device_fault {
+			result.push(DEVICE_FAULT_NAME.as_ptr() as *const c_char);
+		}
+		if self.buffer_marker {
+			result.push(vk::AmdBufferMarkerFn::name().as_ptr());
+		}
+		if self.diagnostics_config {
+			result.push(vk::NvDeviceDiagnosticsConfigFn::name().as_ptr());
+		}
+		result
+	}
+
+	/// Chained into the device create info to enable the fault report
+	pub fn fault_features(&self) -> PhysicalDeviceFaultFeatures {
+		PhysicalDeviceFaultFeatures::new(self.device_fault)
+	}
+}
+
+// Number of markers that can be tracked at once. Older markers are overwritten
+const BREADCRUMB_SLOTS: u32 = 1024;
+
+// Each slot holds the id of the last marker the GPU began and the last one it ended. Ids start at 1 so a zeroed
+// slot was never reached.
+struct Breadcrumbs {
+	buffer_marker: vk::AmdBufferMarkerFn,
+
+	// Lives as long as the device like the rest of its memory
+	buffer: vk::Buffer,
+	mapped: usize,
+
+	next: AtomicU32,
+	names: Mutex<Vec<(u32, String)>>,
+}
+
+impl Breadcrumbs {
+	fn new(
+		instance: &Instance,
+		logical: &ash::Device,
+		physical: vk::PhysicalDevice,
+	) -> Result<Self> {
+		let size = (BREADCRUMB_SLOTS as usize * 2 * std::mem::size_of::<u32>()) as vk::DeviceSize;
+		let create_info = vk::BufferCreateInfo::builder()
+			.size(size)
+			.usage(vk::BufferUsageFlags::TRANSFER_DST)
+			.sharing_mode(vk::SharingMode::EXCLUSIVE);
+
+		unsafe {
+			let buffer = logical.create_buffer(&create_info, None)?;
+			let requirements = logical.get_buffer_memory_requirements(buffer);
+
+			// Coherent so the markers written before the device was lost can still be read
+			let flags =
+				vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT;
+			let properties = instance
+				.instance
+				.get_physical_device_memory_properties(physical);
+			let index = (0..properties.memory_type_count).find(|i| {
+				requirements.memory_type_bits & (1 << i) != 0
+					&& properties.memory_types[*i as usize]
+						.property_flags
+						.contains(flags)
+			});
+			let index = match index {
+				Some(index) => index,
+				None => {
+					logical.destroy_buffer(buffer, None);
+					return Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY);
+				}
+			};
+
+			let alloc_info = vk::MemoryAllocateInfo::builder()
+				.allocation_size(requirements.size)
+				.memory_type_index(index);
+			let memory = match logical.allocate_memory(&alloc_info, None) {
+				Ok(memory) => memory,
+				Err(err) => {
+					logical.destroy_buffer(buffer, None);
+					return Err(err);
+				}
+			};
+			let mapped = logical
+				.bind_buffer_memory(buffer, memory, 0)
+				.and_then(|_| logical.map_memory(memory, 0, size, vk::MemoryMapFlags::empty()));
+			let mapped = match mapped {
+				Ok(mapped) => mapped,
+				Err(err) => {
+					logical.free_memory(memory, None);
+					logical.destroy_buffer(buffer, None);
+					return Err(err);
+				}
+			};
+			write_bytes(mapped as *mut u8, 0, size as usize);
+
+			let buffer_marker = vk::AmdBufferMarkerFn::load(|name| {
+				transmute(
+					instance
+						.instance
+						.get_device_proc_addr(logical.handle(), name.as_ptr()),
+				)
+			});
+
+			Ok(Self {
+				buffer_marker,
+
+				buffer,
+				mapped: mapped as usize,
+
+				next: AtomicU32::new(1),
+				names: Mutex::new(vec![(0, String::new()); BREADCRUMB_SLOTS as usize]),
+			})
+		}
+	}
+
+	fn write(
+		&self,
+		command_buffer: vk::CommandBuffer,
+		stage: vk::PipelineStageFlags,
+		id: u32,
+		ended: bool,
+	) {
+		let offset =
+			((id % BREADCRUMB_SLOTS) * 2 + ended as u32) * std::mem::size_of::<u32>() as u32;
+		unsafe {
+			self.buffer_marker.cmd_write_buffer_marker_amd(
+				command_buffer,
+				stage,
+				self.buffer,
+				offset as vk::DeviceSize,
+				id,
+			)
+		};
+	}
+
+	fn read(&self, id: u32) -> (u32, u32) {
+		let slot = (id % BREADCRUMB_SLOTS) as usize;
+		let mapped = self.mapped as *const u32;
+		unsafe {
+			(
+				mapped.add(slot * 2).read_volatile(),
+				mapped.add(slot * 2 + 1).read_volatile(),
+			)
+		}
+	}
+
+	fn report(&self) {
+		let names = self.names.lock().unwrap();
+
+		let mut executing: Vec<&(u32, String)> = Vec::new();
+		let mut last_finished: Option<&(u32, String)> = None;
+		for it in names.iter().filter(|it| it.0 != 0) {
+			let (began, ended) = self.read(it.0);
+			if began == it.0 && ended != it.0 {
+				executing.push(it);
+			} else if ended == it.0 && last_finished.is_none_or(|last| last.0 < it.0) {
+				last_finished = Some(it);
+			}
+		}
+		executing.sort_by_key(|it| it.0);
+
+		match last_finished {
+			Some((_, name)) => error!(
+				VULKAN_CATEGORY,
+				"Last marker the GPU finished was \"{}\"", name
+			),
+			None => error!(VULKAN_CATEGORY, "The GPU did not finish any marker"),
+		}
+		for (_, name) in executing {
+			error!(VULKAN_CATEGORY, "The GPU was executing \"{}\"", name);
+		}
+	}
+}
+
+/// Records what the GPU was doing so it can be logged when the device is lost
+pub struct Diagnostics {
+	get_device_fault_info: Option<GetDeviceFaultInfo>,
+	breadcrumbs: Option<Breadcrumbs>,
+
+	lost: AtomicBool,
+}
+
+impl Diagnostics {
+	pub fn new(
+		instance: &Instance,
+		logical: &ash::Device,
+		physical: vk::PhysicalDevice,
+		extensions: DiagnosticExtensions,
+	) -> Self {
+		let get_device_fault_info = if extensions.device_fault {
+			unsafe {
+				let function = instance.instance.get_device_proc_addr(
+					logical.handle(),
+					b"vkGetDeviceFaultInfoEXT\0".as_ptr() as *const c_char,
+				);
+				transmute::<vk::PFN_vkVoidFunction, Option<GetDeviceFaultInfo>>(function)
+			}
+		} else {
+			None
+		};
+
+		let breadcrumbs = if extensions.buffer_marker {
+			match Breadcrumbs::new(instance, logical, physical) {
+				Ok(breadcrumbs) => Some(breadcrumbs),
+				Err(err) => {
+					warn!(
+						VULKAN_CATEGORY,
+						"Failed to create the breadcrumb buffer due to {:?}", err
+					);
+					None
+				}
+			}
+		} else {
+			None
+		};
+
+		Self {
+			get_device_fault_info,
+			breadcrumbs,
+
+			lost: AtomicBool::new(false),
+		}
+	}
+
+	/// Writes a breadcrumb when the GPU reaches the commands recorded after this. Returns the id to pass to
+	/// [`Diagnostics::end_marker`] or `None` without breadcrumbs
+	pub fn begin_marker(&self, command_buffer: vk::CommandBuffer, name: &str) -> Option<u32> {
+		let breadcrumbs = self.breadcrumbs.as_ref()?;
+
+		let id = breadcrumbs.next.fetch_add(1, Ordering::Relaxed);
+		breadcrumbs.names.lock().unwrap()[(id % BREADCRUMB_SLOTS) as usize] =
+			(id, name.to_string());
+		breadcrumbs.write(
+			command_buffer,
+			vk::PipelineStageFlags::TOP_OF_PIPE,
+			id,
+			false,
+		);
+		Some(id)
+	}
+
+	pub fn end_marker(&self, command_buffer: vk::CommandBuffer, id: u32) {
+		if let Some(breadcrumbs) = &self.breadcrumbs {
+			breadcrumbs.write(
+				command_buffer,
+				vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+				id,
+				true,
+			);
+		}
+	}
+
+	/// Logs the fault report and breadcrumbs the first time `result` is `ERROR_DEVICE_LOST`. Returns `result` so
+	/// errors can be passed through
+	pub fn check(&self, logical: &ash::Device, result: vk::Result) -> vk::Result {
+		if result != vk::Result::ERROR_DEVICE_LOST || self.lost.swap(true, Ordering::AcqRel) {
+			return result;
+		}
+
+		error!(VULKAN_CATEGORY, "The device was lost");
+		if let Some(get_device_fault_info) = self.get_device_fault_info {
+			unsafe { report_fault(logical, get_device_fault_info) };
+		}
+		if let Some(breadcrumbs) = &self.breadcrumbs {
+			breadcrumbs.report();
+		}
+		if self.get_device_fault_info.is_none() && self.breadcrumbs.is_none() {
+			error!(
+				VULKAN_CATEGORY,
+				"Enable crash_diagnostics in the Gpu config for more information"
+			);
+		}
+		result
+	}
+}
+
+unsafe fn report_fault(logical: &ash::Device, get_device_fault_info: GetDeviceFaultInfo) {
+	let mut counts: DeviceFaultCounts = zeroed();
+	counts.s_type = DEVICE_FAULT_COUNTS;
+	let result = get_device_fault_info(logical.handle(), &mut counts, null_mut());
+	if result != vk::Result::SUCCESS {
+		error!(
+			VULKAN_CATEGORY,
+			"Failed to read the device fault due to {:?}", result
+		);
+		return;
+	}
+
+	let mut addresses: Vec<DeviceFaultAddressInfo> =
+		vec![zeroed(); counts.address_info_count as usize];
+	let mut vendors: Vec<DeviceFaultVendorInfo> = vec![zeroed(); counts.vendor_info_count as usize];
+	// The vendor binary is only useful to vendor tools
+	counts.vendor_binary_size = 0;
+
+	let mut info: DeviceFaultInfo = zeroed();
+	info.s_type = DEVICE_FAULT_INFO;
+	info.p_address_infos = addresses.as_mut_ptr();
+	info.p_vendor_infos = vendors.as_mut_ptr();
+
+	let result = get_device_fault_info(logical.handle(), &mut counts, &mut info);
+	if result != vk::Result::SUCCESS && result != vk::Result::INCOMPLETE {
+		error!(
+			VULKAN_CATEGORY,
+			"Failed to read the device fault due to {:?}", result
+		);
+		return;
+	}
+
+	let description = CStr::from_ptr(info.description.as_ptr()).to_string_lossy();
+	error!(VULKAN_CATEGORY, "Device fault: {}", description);
+	for it in addresses.iter().take(counts.address_info_count as usize) {
+		error!(
+			VULKAN_CATEGORY,
+			"  {} at {:#x} within {:#x} bytes",
+			address_type_name(it.address_type),
+			it.reported_address,
+			it.address_precision
+		);
+	}
+	for it in vendors.iter().take(counts.vendor_info_count as usize) {
+		let description = CStr::from_ptr(it.description.as_ptr()).to_string_lossy();
+		error!(
+			VULKAN_CATEGORY,
+			"  {} (code {:#x}, data {:#x})",
+			description,
+			it.vendor_fault_code,
+			it.vendor_fault_data
+		);
+	}
+}
@@ -1,4 +1,6 @@
 use super::vk_compare_op;
+use super::vk_sample_count;
+use super::AttachmentKey;
 use super::Device;
 use super::Sampler;
 use crate::Constant;
@@ -12,19 +14,26 @@ use crate::{
 	PipelineResource,
 	Result,
 	ShaderVariant,
+	StencilFaceState,
+	StencilOp,
 };
 
 use ash::version::DeviceV1_0;
 use ash::vk;
 
+use std::collections::HashMap;
 use std::ffi::CString;
 use std::slice::from_ref;
-use std::sync::Arc;
+use std::sync::{
+	Arc,
+	Mutex,
+};
 
 fn shader_variant_to_shader_stage(variant: ShaderVariant) -> vk::ShaderStageFlags {
 	match variant {
 		ShaderVariant::Vertex => vk::ShaderStageFlags::VERTEX,
 		ShaderVariant::Pixel => vk::ShaderStageFlags::FRAGMENT,
+		ShaderVariant::Compute => vk::ShaderStageFlags::COMPUTE,
 	}
 }
 
@@ -35,15 +44,19 @@ pub struct GraphicsPipeline {
 	pub layout: vk::PipelineLayout,
 
 	pub description: GraphicsPipelineDescription,
+	// Variants keyed by the hash of their description
+	pub variants: Mutex<HashMap<u64, Arc<GraphicsPipeline>>>,
 
 	// Store samplers with an index which is the push constant index
 	pub samplers: Vec<(Arc<Sampler>, usize)>,
 }
 
 impl GraphicsPipeline {
+	/// `base` is the pipeline a variant is derived from. Drivers can create derivatives faster
 	pub fn new(
 		owner: Arc<Device>,
 		description: GraphicsPipelineDescription,
+		base: Option<&GraphicsPipeline>,
 	) -> Result<Arc<GraphicsPipeline>> {
 		assert!(!description.shaders.is_empty());
 
@@ -138,20 +151,53 @@ impl GraphicsPipeline {
 			cull |= vk::CullModeFlags::BACK;
 		}
 
+		fn stencil_op(op: StencilOp) -> vk::StencilOp {
+			match op {
+				StencilOp::Keep => vk::StencilOp::KEEP,
+				StencilOp::Zero => vk::StencilOp::ZERO,
+				StencilOp::Replace => vk::StencilOp::REPLACE,
+				StencilOp::IncrementAndClamp => vk::StencilOp::INCREMENT_AND_CLAMP,
+				StencilOp::DecrementAndClamp => vk::StencilOp::DECREMENT_AND_CLAMP,
+				StencilOp::Invert => vk::StencilOp::INVERT,
+				StencilOp::IncrementAndWrap => vk::StencilOp::INCREMENT_AND_WRAP,
+				StencilOp::DecrementAndWrap => vk::StencilOp::DECREMENT_AND_WRAP,
+			}
+		}
+
+		fn stencil_state(state: StencilFaceState) -> vk::StencilOpState {
+			vk::StencilOpState::builder()
+				.fail_op(stencil_op(state.fail_op))
+				.pass_op(stencil_op(state.pass_op))
+				.depth_fail_op(stencil_op(state.depth_fail_op))
+				.compare_op(vk_compare_op(state.compare))
+				.compare_mask(state.compare_mask)
+				.write_mask(state.write_mask)
+				.reference(state.reference)
+				.build()
+		}
+
 		let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
 			.depth_test_enable(description.depth_test)
 			.depth_write_enable(description.depth_write)
-			.depth_compare_op(vk_compare_op(description.depth_compare));
+			.depth_compare_op(vk_compare_op(description.depth_compare))
+			.stencil_test_enable(description.stencil_test)
+			.front(stencil_state(description.stencil_front))
+			.back(stencil_state(description.stencil_back));
 
-		// NOTE: Depth Testing goes around here somewhere
+		let depth_bias = description.depth_bias.unwrap_or_default();
 		let rasterizer_state = vk::PipelineRasterizationStateCreateInfo::builder()
 			.polygon_mode(polygon_mode)
 			.cull_mode(cull)
 			.front_face(vk::FrontFace::CLOCKWISE)
-			.line_width(description.line_width);
+			.line_width(description.line_width)
+			.depth_clamp_enable(description.depth_clamp && owner.depth_clamp)
+			.depth_bias_enable(description.depth_bias.is_some())
+			.depth_bias_constant_factor(depth_bias.constant_factor)
+			.depth_bias_slope_factor(depth_bias.slope_factor)
+			.depth_bias_clamp(depth_bias.clamp);
 
 		let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder()
-			.rasterization_samples(vk::SampleCountFlags::TYPE_1)
+			.rasterization_samples(vk_sample_count(description.samples))
 			.min_sample_shading(1.0);
 
 		// Setting up blending and converting data types
@@ -165,6 +211,13 @@ impl GraphicsPipeline {
 				BlendFactor::OneMinusDstColor => vk::BlendFactor::ONE_MINUS_DST_COLOR,
 				BlendFactor::SrcAlpha => vk::BlendFactor::SRC_ALPHA,
 				BlendFactor::OneMinusSrcAlpha => vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+				BlendFactor::DstAlpha => vk::BlendFactor::DST_ALPHA,
+				BlendFactor::OneMinusDstAlpha => vk::BlendFactor::ONE_MINUS_DST_ALPHA,
+				BlendFactor::ConstantColor => vk::BlendFactor::CONSTANT_COLOR,
+				BlendFactor::OneMinusConstantColor => vk::BlendFactor::ONE_MINUS_CONSTANT_COLOR,
+				BlendFactor::ConstantAlpha => vk::BlendFactor::CONSTANT_ALPHA,
+				BlendFactor::OneMinusConstantAlpha => vk::BlendFactor::ONE_MINUS_CONSTANT_ALPHA,
+				BlendFactor::SrcAlphaSaturate => vk::BlendFactor::SRC_ALPHA_SATURATE,
 			}
 		}
 
@@ -178,34 +231,51 @@ impl GraphicsPipeline {
 			}
 		}
 
-		let mut color_write_mask = vk::ColorComponentFlags::default();
-		if description.color_mask.contains(ColorMask::RED) {
-			color_write_mask |= vk::ColorComponentFlags::R;
-		}
-		if description.color_mask.contains(ColorMask::GREEN) {
-			color_write_mask |= vk::ColorComponentFlags::G;
-		}
-		if description.color_mask.contains(ColorMask::BLUE) {
-			color_write_mask |= vk::ColorComponentFlags::B;
-		}
-		if description.color_mask.contains(ColorMask::ALPHA) {
-			color_write_mask |= vk::ColorComponentFlags::A;
+		fn color_write_mask(mask: ColorMask) -> vk::ColorComponentFlags {
+			let mut result = vk::ColorComponentFlags::default();
+			if mask.contains(ColorMask::RED) {
+				result |= vk::ColorComponentFlags::R;
+			}
+			if mask.contains(ColorMask::GREEN) {
+				result |= vk::ColorComponentFlags::G;
+			}
+			if mask.contains(ColorMask::BLUE) {
+				result |= vk::ColorComponentFlags::B;
+			}
+			if mask.contains(ColorMask::ALPHA) {
+				result |= vk::ColorComponentFlags::A;
+			}
+			result
 		}
 
-		let color_blend_attachment = vk::PipelineColorBlendAttachmentState::builder()
-			.blend_enable(description.blend_enabled)
-			.src_color_blend_factor(blend_factor(description.src_color_blend_factor))
-			.dst_color_blend_factor(blend_factor(description.dst_color_blend_factor))
-			.color_blend_op(blend_op(description.color_blend_op))
-			.src_alpha_blend_factor(blend_factor(description.src_alpha_blend_factor))
-			.dst_alpha_blend_factor(blend_factor(description.dst_alpha_blend_factor))
-			.alpha_blend_op(blend_op(description.alpha_blend_op))
-			.color_write_mask(color_write_mask)
-			.build();
+		// Every attachment must share the first one's state when the device can not blend them independently
+		let color_attachments = description
+			.attachments
+			.iter()
+			.filter(|it| !it.is_depth())
+			.count();
+		let color_blend_attachments: Vec<vk::PipelineColorBlendAttachmentState> = (0
+			..color_attachments)
+			.map(|index| {
+				let index = if owner.independent_blend { index } else { 0 };
+				let blend = description.attachment_blend(index);
+				vk::PipelineColorBlendAttachmentState::builder()
+					.blend_enable(blend.blend_enabled)
+					.src_color_blend_factor(blend_factor(blend.src_color_blend_factor))
+					.dst_color_blend_factor(blend_factor(blend.dst_color_blend_factor))
+					.color_blend_op(blend_op(blend.color_blend_op))
+					.src_alpha_blend_factor(blend_factor(blend.src_alpha_blend_factor))
+					.dst_alpha_blend_factor(blend_factor(blend.dst_alpha_blend_factor))
+					.alpha_blend_op(blend_op(blend.alpha_blend_op))
+					.color_write_mask(color_write_mask(blend.color_mask))
+					.build()
+			})
+			.collect();
 
 		let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
 			.logic_op(vk::LogicOp::COPY)
-			.attachments(from_ref(&color_blend_attachment));
+			.attachments(&color_blend_attachments)
+			.blend_constants(description.blend_constants);
 
 		let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
 
@@ -233,7 +303,12 @@ impl GraphicsPipeline {
 				.create_pipeline_layout(&pipeline_layout_info, None)?
 		};
 
-		let render_pass = owner.get_or_create_render_pass(&description.attachments)?;
+		let attachments: Vec<AttachmentKey> = description
+			.attachments
+			.iter()
+			.map(|it| AttachmentKey::compatible(*it, description.samples))
+			.collect();
+		let render_pass = owner.get_or_create_render_pass(&attachments)?;
 
 		let create_info = vk::GraphicsPipelineCreateInfo::builder()
 			.stages(&shader_stages[..])
@@ -248,10 +323,16 @@ impl GraphicsPipeline {
 			.layout(layout)
 			.render_pass(render_pass.handle)
 			.base_pipeline_index(-1);
+		let create_info = match base {
+			Some(base) => create_info
+				.flags(vk::PipelineCreateFlags::DERIVATIVE)
+				.base_pipeline_handle(base.handle),
+			None => create_info.flags(vk::PipelineCreateFlags::ALLOW_DERIVATIVES),
+		};
 
 		let handle = unsafe {
 			owner.logical.create_graphics_pipelines(
-				vk::PipelineCache::default(),
+				owner.pipeline_cache,
 				from_ref(&create_info),
 				None,
 			)
@@ -285,7 +366,12 @@ impl GraphicsPipeline {
 			layout,
 
 			description,
+			variants: Mutex::new(HashMap::new()),
 			samplers,
 		}))
 	}
+
+	pub fn set_debug_name(&self, name: &str) {
+		self.owner.set_debug_name(self.handle, name);
+	}
 }
@@ -1,7 +1,8 @@
-use super::ENABLED_LAYER_NAMES;
+use super::VALIDATION_LAYER_NAMES;
 use crate::InstanceCreateError;
 
 use ash::extensions::ext::DebugUtils;
+use ash::extensions::khr;
 use ash::version::EntryV1_0;
 use ash::vk;
 
@@ -21,13 +22,21 @@ pub struct Instance {
 	pub entry: ash::Entry,
 	pub instance: ash::Instance,
 
-	#[cfg(feature = "aftermath")]
+	#[cfg(all(feature = "aftermath", target_os = "windows"))]
 	pub aftermath: Option<aftermath::Aftermath>,
-	_debug_utils: DebugUtils,
+	// None when VK_EXT_debug_utils is not available
+	pub debug_utils: Option<DebugUtils>,
+
+	// False when validation was requested but the layer is not installed
+	pub validation: bool,
+	pub crash_diagnostics: bool,
 }
 
 impl Instance {
-	pub fn new() -> Result<Arc<Self>, InstanceCreateError> {
+	pub fn new(
+		validation: bool,
+		crash_diagnostics: bool,
+	) -> Result<Arc<Self>, InstanceCreateError> {
 		let entry = unsafe {
 			let entry = ash::Entry::new();
 			if entry.is_err() {
@@ -41,15 +50,49 @@ impl Instance {
 			..Default::default()
 		};
 
-		#[cfg(target_os = "windows")]
-		let enabled_extension_names = [
-			b"VK_KHR_surface\0".as_ptr() as *const i8,
-			b"VK_KHR_win32_surface\0".as_ptr() as *const i8,
-			b"VK_KHR_get_physical_device_properties2\0".as_ptr() as *const i8,
-			// b"VK_KHR_maintenance3\0".as_ptr() as *const i8,
-			// b"VK_EXT_descriptor_indexing\0".as_ptr() as *const i8,
-			b"VK_EXT_debug_utils\0".as_ptr() as *const i8,
-		];
+		let available = entry
+			.enumerate_instance_extension_properties()
+			.unwrap_or_default();
+		let has_extension = |name: &ffi::CStr| {
+			available
+				.iter()
+				.any(|it| unsafe { ffi::CStr::from_ptr(it.extension_name.as_ptr()) } == name)
+		};
+
+		let mut enabled_extension_names =
+			vec![vk::KhrGetPhysicalDeviceProperties2Fn::name().as_ptr()];
+
+		// Every surface the loader supports is enabled as the window system is only known once a window is
+		// created. Without any the instance can still be used headless
+		for it in std::iter::once(khr::Surface::name()).chain(surface_extension_names()) {
+			if has_extension(it) {
+				enabled_extension_names.push(it.as_ptr());
+			}
+		}
+
+		// Debug utils name objects and label commands for captures. Only enabled when the loader has them
+		let has_debug_utils = has_extension(DebugUtils::name());
+		if has_debug_utils {
+			enabled_extension_names.push(DebugUtils::name().as_ptr());
+		}
+
+		let validation = validation && {
+			let installed = entry
+				.enumerate_instance_layer_properties()
+				.unwrap_or_default()
+				.iter()
+				.any(|it| unsafe {
+					ffi::CStr::from_ptr(it.layer_name.as_ptr())
+						== ffi::CStr::from_ptr(VALIDATION_LAYER_NAMES[0])
+				});
+			if !installed {
+				warn!(
+					VULKAN_CATEGORY,
+					"Validation was requested but VK_LAYER_KHRONOS_validation is not installed"
+				);
+			}
+			installed
+		};
 
 		unsafe extern "system" fn debug_callback(
 			message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
@@ -89,11 +132,15 @@ impl Instance {
 			.pfn_user_callback(Some(debug_callback))
 			.build();
 
-		let create_info = vk::InstanceCreateInfo::builder()
+		let mut create_info = vk::InstanceCreateInfo::builder()
 			.application_info(&app_info)
 			.enabled_extension_names(&enabled_extension_names)
-			.enabled_layer_names(&ENABLED_LAYER_NAMES)
-			.push_next(&mut debug_create_info);
+			.enabled_layer_names(Self::layer_names(validation));
+		// Messages are only sent by the validation layer
+		let messenger = has_debug_utils && validation;
+		if messenger {
+			create_info = create_info.push_next(&mut debug_create_info);
+		}
 
 		let instance = unsafe {
 			let instance = entry.create_instance(&create_info, None);
@@ -114,17 +161,27 @@ impl Instance {
 			instance.unwrap()
 		};
 
-		let debug_utils = unsafe {
-			let debug_utils = DebugUtils::new(&entry, &instance);
+		let debug_utils = if has_debug_utils {
+			unsafe {
+				let debug_utils = DebugUtils::new(&entry, &instance);
 
-			debug_utils
-				.create_debug_utils_messenger(&debug_create_info, None)
-				.unwrap();
+				if messenger {
+					debug_utils
+						.create_debug_utils_messenger(&debug_create_info, None)
+						.unwrap();
+				}
 
-			debug_utils
+				Some(debug_utils)
+			}
+		} else {
+			warn!(
+				VULKAN_CATEGORY,
+				"VK_EXT_debug_utils is not available. Objects will not be named"
+			);
+			None
 		};
 
-		#[cfg(feature = "aftermath")]
+		#[cfg(all(feature = "aftermath", target_os = "windows"))]
 		let aftermath = match aftermath::Aftermath::new() {
 			Ok(aftermath) => {
 				aftermath.enable_gpu_crash_dumps().unwrap();
@@ -143,9 +200,55 @@ impl Instance {
 		Ok(Arc::new(Self {
 			entry,
 			instance,
-			#[cfg(feature = "aftermath")]
+			#[cfg(all(feature = "aftermath", target_os = "windows"))]
 			aftermath,
-			_debug_utils: debug_utils,
+			debug_utils,
+
+			validation,
+			crash_diagnostics,
 		}))
 	}
+
+	fn layer_names(validation: bool) -> &'static [*const i8] {
+		if validation {
+			&VALIDATION_LAYER_NAMES
+		} else {
+			&[]
+		}
+	}
+
+	/// Layers enabled on the instance. Devices enable the same ones for older loaders
+	pub fn enabled_layer_names(&self) -> &'static [*const i8] {
+		Self::layer_names(self.validation)
+	}
+
+	/// Returns true when Aftermath is loaded and writing crash dumps
+	#[cfg(all(feature = "aftermath", target_os = "windows"))]
+	pub fn has_aftermath(&self) -> bool {
+		self.aftermath.is_some()
+	}
+
+	#[cfg(not(all(feature = "aftermath", target_os = "windows")))]
+	pub fn has_aftermath(&self) -> bool {
+		false
+	}
+}
+
+#[cfg(target_os = "windows")]
+fn surface_extension_names() -> Vec<&'static ffi::CStr> {
+	vec![khr::Win32Surface::name()]
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn surface_extension_names() -> Vec<&'static ffi::CStr> {
+	vec![
+		khr::XlibSurface::name(),
+		khr::XcbSurface::name(),
+		khr::WaylandSurface::name(),
+	]
+}
+
+#[cfg(not(any(target_os = "windows", all(unix, not(target_os = "macos")))))]
+fn surface_extension_names() -> Vec<&'static ffi::CStr> {
+	Vec::new()
 }
@@ -1,10 +1,12 @@
 use crate::{
 	CompareOp,
 	Format,
+	LoadOp,
+	StoreOp,
 };
 use ash::vk;
 
-pub const ENABLED_LAYER_NAMES: [*const i8; 1] =
+pub const VALIDATION_LAYER_NAMES: [*const i8; 1] =
 	[b"VK_LAYER_KHRONOS_validation\0".as_ptr() as *const i8];
 
 mod instance;
@@ -31,24 +33,46 @@ pub use shader::*;
 mod graphics_pipeline;
 pub use graphics_pipeline::*;
 
+mod compute_pipeline;
+pub use compute_pipeline::*;
+
 mod command_buffer;
 pub use command_buffer::*;
 
 mod sampler;
 pub use sampler::*;
 
+mod diagnostics;
+pub use diagnostics::*;
+
 pub fn vk_format(format: Format) -> vk::Format {
 	match format {
 		Format::Undefined => vk::Format::UNDEFINED,
+		Format::R_U8 => vk::Format::R8_UNORM,
+		Format::RG_U8 => vk::Format::R8G8_UNORM,
 		Format::RGB_U8 => vk::Format::R8G8B8_UINT,
 		Format::RGB_U8_SRGB => vk::Format::R8G8B8_SRGB,
 		Format::RGBA_U8 => vk::Format::R8G8B8A8_UNORM,
 		Format::RGBA_U8_SRGB => vk::Format::R8G8B8A8_SRGB,
+		Format::R_U16 => vk::Format::R16_UNORM,
+		Format::RG_U16 => vk::Format::R16G16_UNORM,
+		Format::RGBA_U16 => vk::Format::R16G16B16A16_UNORM,
 		Format::RGBA_F16 => vk::Format::R16G16B16A16_SFLOAT,
 		Format::RGBA_F32 => vk::Format::R32G32B32A32_SFLOAT,
 		Format::BGR_U8_SRGB => vk::Format::B8G8R8A8_SRGB,
 		Format::Depth24_Stencil8 => vk::Format::D24_UNORM_S8_UINT,
 		Format::Depth16 => vk::Format::D16_UNORM,
+		Format::BC1_RGBA_UNORM => vk::Format::BC1_RGBA_UNORM_BLOCK,
+		Format::BC1_RGBA_SRGB => vk::Format::BC1_RGBA_SRGB_BLOCK,
+		Format::BC2_UNORM => vk::Format::BC2_UNORM_BLOCK,
+		Format::BC2_SRGB => vk::Format::BC2_SRGB_BLOCK,
+		Format::BC3_UNORM => vk::Format::BC3_UNORM_BLOCK,
+		Format::BC3_SRGB => vk::Format::BC3_SRGB_BLOCK,
+		Format::BC4_UNORM => vk::Format::BC4_UNORM_BLOCK,
+		Format::BC5_UNORM => vk::Format::BC5_UNORM_BLOCK,
+		Format::BC6H_UFLOAT => vk::Format::BC6H_UFLOAT_BLOCK,
+		Format::BC7_UNORM => vk::Format::BC7_UNORM_BLOCK,
+		Format::BC7_SRGB => vk::Format::BC7_SRGB_BLOCK,
 	}
 }
 
@@ -81,4 +105,24 @@ pub fn vk_compare_op(compare_op: CompareOp) -> vk::CompareOp {
 	}
 }
 
+pub fn vk_sample_count(samples: u32) -> vk::SampleCountFlags {
+	// Sample count flags are the number of samples
+	vk::SampleCountFlags::from_raw(samples)
+}
+
+pub fn vk_load_op(load: LoadOp) -> vk::AttachmentLoadOp {
+	match load {
+		LoadOp::Load => vk::AttachmentLoadOp::LOAD,
+		LoadOp::Clear => vk::AttachmentLoadOp::CLEAR,
+		LoadOp::DontCare => vk::AttachmentLoadOp::DONT_CARE,
+	}
+}
+
+pub fn vk_store_op(store: StoreOp) -> vk::AttachmentStoreOp {
+	match store {
+		StoreOp::Store => vk::AttachmentStoreOp::STORE,
+		StoreOp::DontCare => vk::AttachmentStoreOp::DONT_CARE,
+	}
+}
+
 pub use ash::vk::Result as Error;
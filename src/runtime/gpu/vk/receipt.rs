@@ -16,6 +16,10 @@ impl Receipt {
 		Self { owner, id }
 	}
 
+	pub(super) fn id(&self) -> usize {
+		self.id
+	}
+
 	pub(crate) fn get(&self) -> Option<(vk::Semaphore, vk::Fence)> {
 		let work = self.owner.work.lock().unwrap();
 		let result = work.in_queue.get(&self.id)?;
@@ -36,6 +40,7 @@ impl Receipt {
 				self.owner
 					.logical
 					.wait_for_fences(&[entry.fence], true, u64::MAX)
+					.map_err(|err| self.owner.check_lost(err))
 					.unwrap()
 			};
 		}
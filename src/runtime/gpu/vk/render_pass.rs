@@ -1,10 +1,15 @@
 use super::{
 	vk_format,
+	vk_load_op,
+	vk_sample_count,
+	vk_store_op,
 	Device,
 };
 use crate::{
 	Format,
+	LoadOp,
 	Result,
+	StoreOp,
 };
 
 use ash::version::DeviceV1_0;
@@ -13,63 +18,119 @@ use ash::vk;
 use std::slice::from_ref;
 use std::sync::Arc;
 
+/// Everything about an attachment that changes the render pass it is drawn in
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct AttachmentKey {
+	pub format: Format,
+	pub samples: u32,
+	pub load: LoadOp,
+	pub store: StoreOp,
+	pub resolve: bool,
+}
+
+impl AttachmentKey {
+	/// Key used by pipelines. Load and store ops and resolves do not affect render pass compatibility
+	pub fn compatible(format: Format, samples: u32) -> Self {
+		Self {
+			format,
+			samples,
+			load: LoadOp::Load,
+			store: StoreOp::Store,
+			resolve: false,
+		}
+	}
+}
+
 pub struct RenderPass {
 	pub handle: vk::RenderPass,
 
-	pub attachments: Vec<Format>,
+	pub attachments: Vec<AttachmentKey>,
 }
 
 impl RenderPass {
-	pub fn new(owner: &Device, attachment_values: Vec<Format>) -> Result<Arc<RenderPass>> {
+	pub fn new(owner: &Device, attachment_values: Vec<AttachmentKey>) -> Result<Arc<RenderPass>> {
 		let mut color_refs = Vec::with_capacity(attachment_values.len());
+		let mut resolve_refs = Vec::with_capacity(attachment_values.len());
 		let mut depth_ref = None;
 
 		let mut attachments = Vec::with_capacity(attachment_values.len());
 
 		for (index, it) in attachment_values.iter().enumerate() {
-			let format = vk_format(*it);
-
-			if it.is_depth() {
-				let attachment = vk::AttachmentDescription::builder()
-					.format(format)
-					.samples(vk::SampleCountFlags::TYPE_1)
-					.load_op(vk::AttachmentLoadOp::DONT_CARE)
-					.store_op(vk::AttachmentStoreOp::STORE)
-					.stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
-					.stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
-					.initial_layout(vk::ImageLayout::UNDEFINED)
-					.final_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
-				attachments.push(attachment.build());
-
-				let the_ref = vk::AttachmentReference::builder()
-					.attachment((attachment_values.len() - 1) as u32)
-					.layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
-
-				depth_ref = Some(the_ref.build())
+			let format = vk_format(it.format);
+
+			// Loaded attachments are expected to already be in their attachment layout
+			let layout = if it.format.is_depth() {
+				vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL
 			} else {
-				let attachment = vk::AttachmentDescription::builder()
-					.format(format)
-					.samples(vk::SampleCountFlags::TYPE_1)
-					.load_op(vk::AttachmentLoadOp::DONT_CARE)
-					.store_op(vk::AttachmentStoreOp::STORE)
-					.stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
-					.stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
-					.initial_layout(vk::ImageLayout::UNDEFINED)
-					.final_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
-				attachments.push(attachment.build());
-
-				let the_ref = vk::AttachmentReference::builder()
-					.attachment(index as u32)
-					.layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
-				color_refs.push(the_ref.build());
+				vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+			};
+			let initial_layout = match it.load {
+				LoadOp::Load => layout,
+				_ => vk::ImageLayout::UNDEFINED,
+			};
+
+			let attachment = vk::AttachmentDescription::builder()
+				.format(format)
+				.samples(vk_sample_count(it.samples))
+				.load_op(vk_load_op(it.load))
+				.store_op(vk_store_op(it.store))
+				.stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+				.stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+				.initial_layout(initial_layout)
+				.final_layout(layout);
+			attachments.push(attachment.build());
+
+			let the_ref = vk::AttachmentReference::builder()
+				.attachment(index as u32)
+				.layout(layout)
+				.build();
+
+			if it.format.is_depth() {
+				depth_ref = Some(the_ref)
+			} else {
+				color_refs.push(the_ref);
 			}
 		}
 
+		// Resolve targets are placed after every attachment in the same order as the color attachments
+		let any_resolve = attachment_values.iter().any(|it| it.resolve);
+		for it in attachment_values.iter().filter(|it| it.format.is_color()) {
+			if !it.resolve {
+				resolve_refs.push(
+					vk::AttachmentReference::builder()
+						.attachment(vk::ATTACHMENT_UNUSED)
+						.build(),
+				);
+				continue;
+			}
+
+			let attachment = vk::AttachmentDescription::builder()
+				.format(vk_format(it.format))
+				.samples(vk::SampleCountFlags::TYPE_1)
+				.load_op(vk::AttachmentLoadOp::DONT_CARE)
+				.store_op(vk::AttachmentStoreOp::STORE)
+				.stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+				.stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+				.initial_layout(vk::ImageLayout::UNDEFINED)
+				.final_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+			resolve_refs.push(
+				vk::AttachmentReference::builder()
+					.attachment(attachments.len() as u32)
+					.layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+					.build(),
+			);
+			attachments.push(attachment.build());
+		}
+
 		// Currently we're only going to support 1 subpass as no other API has subpasses
 		let mut subpass = vk::SubpassDescription::builder()
 			.pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
 			.color_attachments(&color_refs[..]);
 
+		if any_resolve {
+			subpass = subpass.resolve_attachments(&resolve_refs[..]);
+		}
+
 		if let Some(depth) = &depth_ref {
 			subpass = subpass.depth_stencil_attachment(depth);
 		}
@@ -4,6 +4,7 @@ use ash::version::DeviceV1_0;
 use ash::vk;
 
 use crate::{
+	BindlessKind,
 	Filter,
 	SamplerDescription,
 	Wrap,
@@ -36,12 +37,23 @@ impl Sampler {
 			}
 		}
 
+		let mipmap_mode = match description.mip_filter {
+			Filter::Nearest => vk::SamplerMipmapMode::NEAREST,
+			Filter::Linear => vk::SamplerMipmapMode::LINEAR,
+		};
+
+		let max_anisotropy = description.max_anisotropy.min(owner.max_sampler_anisotropy);
+
 		let create_info = vk::SamplerCreateInfo::builder()
 			.min_filter(filter_to_vk(description.min_filter))
 			.mag_filter(filter_to_vk(description.mag_filter))
+			.mipmap_mode(mipmap_mode)
 			.address_mode_u(wrap_to_vk(description.address_u))
 			.address_mode_v(wrap_to_vk(description.address_v))
 			.address_mode_w(wrap_to_vk(description.address_w))
+			.anisotropy_enable(max_anisotropy > 1.0)
+			.max_anisotropy(max_anisotropy)
+			.max_lod(vk::LOD_CLAMP_NONE)
 			.border_color(vk::BorderColor::INT_OPAQUE_BLACK);
 
 		let sampler = unsafe { owner.logical.create_sampler(&create_info, None) };
@@ -50,30 +62,28 @@ impl Sampler {
 		}
 		let sampler = sampler.unwrap();
 
-		let mut bindless = owner.bindless_info.lock().unwrap();
-
-		let found = bindless
-			.samplers
-			.iter_mut()
-			.enumerate()
-			.find(|(_, x)| x.strong_count() == 0)
-			.map(|(index, _)| index);
-
-		let index = found.unwrap_or(bindless.samplers.len());
+		let bindless = match owner.allocate_bindless_sampler(sampler, "sampler".to_string()) {
+			Ok(bindless) => bindless,
+			Err(_) => {
+				unsafe { owner.logical.destroy_sampler(sampler, None) };
+				return Err(());
+			}
+		};
 
-		let result = Arc::new(Sampler {
-			owner: owner.clone(),
+		Ok(Arc::new(Sampler {
+			owner,
 			handle: sampler,
-			bindless: index as u32,
-		});
+			bindless,
+		}))
+	}
+}
 
-		let weak = Arc::downgrade(&result);
-		if found.is_some() {
-			bindless.samplers[index] = weak;
-		} else {
-			bindless.samplers.push(weak);
+impl Drop for Sampler {
+	fn drop(&mut self) {
+		self.owner
+			.release_bindless(BindlessKind::Sampler, self.bindless);
+		unsafe {
+			self.owner.logical.destroy_sampler(self.handle, None);
 		}
-
-		Ok(result)
 	}
 }
@@ -1,13 +1,17 @@
 use super::{
 	vk_format,
 	vk_format_aspect_mask,
+	vk_sample_count,
+	BindlessTexture,
 	Device,
 	DeviceAllocation,
 };
 use crate::{
+	BindlessKind,
 	Format,
 	MemoryType,
 	Result,
+	TextureExtent,
 	TextureUsage,
 };
 
@@ -16,7 +20,6 @@ use ash::vk;
 
 use std::sync::Arc;
 
-// TODO: Do mip mapping
 pub struct Texture {
 	pub owner: Arc<Device>,
 
@@ -29,9 +32,7 @@ pub struct Texture {
 	pub usage: TextureUsage,
 	pub format: Format,
 
-	pub width: u32,
-	pub height: u32,
-	pub depth: u32,
+	pub extent: TextureExtent,
 
 	// Index into the devices bindless array
 	pub bindless: Option<u32>,
@@ -43,10 +44,18 @@ impl Texture {
 		memory_type: MemoryType,
 		usage: TextureUsage,
 		format: Format,
-		width: u32,
-		height: u32,
-		depth: u32,
+		extent: TextureExtent,
 	) -> Result<Arc<Texture>> {
+		let TextureExtent {
+			width,
+			height,
+			depth,
+			mip_levels,
+			array_layers,
+			cube,
+			samples,
+		} = extent;
+
 		let mut image_type = vk::ImageType::TYPE_3D;
 		if depth == 1 {
 			image_type = vk::ImageType::TYPE_2D;
@@ -65,6 +74,9 @@ impl Texture {
 		if usage.contains(TextureUsage::SAMPLED) {
 			image_usage |= vk::ImageUsageFlags::SAMPLED;
 		}
+		if usage.contains(TextureUsage::STORAGE) {
+			image_usage |= vk::ImageUsageFlags::STORAGE;
+		}
 		if usage.contains(TextureUsage::COLOR_ATTACHMENT) {
 			image_usage |= vk::ImageUsageFlags::COLOR_ATTACHMENT;
 		}
@@ -72,27 +84,35 @@ impl Texture {
 			image_usage |= vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT;
 		}
 
-		let extent = vk::Extent3D::builder()
+		let mut flags = vk::ImageCreateFlags::empty();
+		if cube {
+			flags |= vk::ImageCreateFlags::CUBE_COMPATIBLE;
+		}
+
+		let image_extent = vk::Extent3D::builder()
 			.width(width)
 			.height(height)
 			.depth(depth)
 			.build();
 
+		let queue_family_indices = owner.queue_family_indices();
 		let create_info = vk::ImageCreateInfo::builder()
+			.flags(flags)
 			.image_type(image_type)
 			.format(vk_format(format))
-			.mip_levels(1)
-			.array_layers(1)
-			.samples(vk::SampleCountFlags::TYPE_1)
+			.mip_levels(mip_levels)
+			.array_layers(array_layers)
+			.samples(vk_sample_count(samples))
 			.tiling(vk::ImageTiling::OPTIMAL)
 			.usage(image_usage)
-			.sharing_mode(vk::SharingMode::EXCLUSIVE)
-			.extent(extent);
+			.sharing_mode(Device::sharing_mode(&queue_family_indices))
+			.queue_family_indices(&queue_family_indices)
+			.extent(image_extent);
 
 		let image = unsafe { owner.logical.create_image(&create_info, None)? };
 
 		let requirements = unsafe { owner.logical.get_image_memory_requirements(image) };
-		let memory = owner.allocate_memory(requirements, memory_type)?;
+		let memory = owner.allocate_memory(requirements, memory_type, false)?;
 
 		unsafe {
 			owner
@@ -101,7 +121,11 @@ impl Texture {
 		};
 
 		let mut image_view_type = vk::ImageViewType::TYPE_3D;
-		if depth == 1 {
+		if cube {
+			image_view_type = vk::ImageViewType::CUBE;
+		} else if array_layers > 1 {
+			image_view_type = vk::ImageViewType::TYPE_2D_ARRAY;
+		} else if depth == 1 {
 			image_view_type = vk::ImageViewType::TYPE_2D;
 			if height == 1 {
 				image_view_type = vk::ImageViewType::TYPE_1D;
@@ -115,54 +139,25 @@ impl Texture {
 			.subresource_range(
 				vk::ImageSubresourceRange::builder()
 					.aspect_mask(vk_format_aspect_mask(format))
-					.level_count(1)
-					.layer_count(1)
+					.level_count(mip_levels)
+					.layer_count(array_layers)
 					.build(),
 			);
 
 		let view = unsafe { owner.logical.create_image_view(&create_info, None)? };
 
-		// Add a weak reference to the device for bindless
-		if usage.contains(TextureUsage::SAMPLED) {
-			let mut bindless = owner.bindless_info.lock().unwrap();
-
-			let found = bindless
-				.textures
-				.iter_mut()
-				.enumerate()
-				.find(|(_, x)| x.strong_count() == 0)
-				.map(|(index, _)| index);
-
-			let index = found.unwrap_or(bindless.textures.len());
-
-			let result = Arc::new(Texture {
-				owner: owner.clone(), // SPEED: Exra ref count due to mutex lock.
-
-				image,
+		let bindless = if usage.intersects(TextureUsage::SAMPLED | TextureUsage::STORAGE) {
+			let slot = BindlessTexture {
 				view,
-				memory,
-
-				memory_type,
-
 				usage,
-				format,
-
-				width,
-				height,
-				depth,
-
-				bindless: Some(index as u32),
-			});
-
-			let weak = Arc::downgrade(&result);
-			if found.is_some() {
-				bindless.textures[index] = weak;
-			} else {
-				bindless.textures.push(weak);
-			}
-
-			return Ok(result);
-		}
+				array_layers: extent.array_layers,
+				cube: extent.cube,
+			};
+			let name = format!("{:?} {}x{}", format, extent.width, extent.height);
+			Some(owner.allocate_bindless_texture(slot, name)?)
+		} else {
+			None
+		};
 
 		Ok(Arc::new(Texture {
 			owner,
@@ -176,11 +171,9 @@ impl Texture {
 			usage,
 			format,
 
-			width,
-			height,
-			depth,
+			extent,
 
-			bindless: None,
+			bindless,
 		}))
 	}
 
@@ -188,22 +181,34 @@ impl Texture {
 		self.format
 	}
 	pub fn width(&self) -> u32 {
-		self.width
+		self.extent.width
 	}
 	pub fn height(&self) -> u32 {
-		self.height
+		self.extent.height
 	}
 	pub fn depth(&self) -> u32 {
-		self.depth
+		self.extent.depth
 	}
 
 	pub fn bindless(&self) -> Option<u32> {
 		self.bindless
 	}
+
+	pub fn set_debug_name(&self, name: &str) {
+		self.owner.set_debug_name(self.image, name);
+		self.owner.set_debug_name(self.view, name);
+		if let Some(bindless) = self.bindless {
+			self.owner
+				.set_bindless_name(BindlessKind::Texture, bindless, name);
+		}
+	}
 }
 
 impl Drop for Texture {
 	fn drop(&mut self) {
+		if let Some(bindless) = self.bindless {
+			self.owner.release_bindless(BindlessKind::Texture, bindless);
+		}
 		if self.usage.contains(TextureUsage::SWAPCHAIN) {
 			unsafe {
 				self.owner.logical.destroy_image_view(self.view, None);
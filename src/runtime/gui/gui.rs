@@ -5,20 +5,26 @@ use {
 		Draw2d,
 		FontCollection,
 		Painter,
+		PainterVertex,
 	},
 	engine::{
 		Builder,
 		Engine,
 		Event as EngineEvent,
 		Module,
+		Priority,
+		Propagation,
 	},
 	gpu::{
-		Buffer,
 		BufferUsage,
 		Gpu,
+		GpuConstants,
 		GraphicsPipeline,
-		GraphicsRecorder,
-		MemoryType,
+		PresentLayer,
+		PresentPass,
+		RenderGraph,
+		RingBuffer,
+		TextureId,
 	},
 	input::*,
 	math::{
@@ -51,6 +57,16 @@ pub struct Gui {
 
 	hovered: Option<WidgetHandle>,
 	focused: Option<WidgetHandle>,
+
+	imports: RingBuffer<Imports>,
+	vertices: RingBuffer<PainterVertex>,
+	indices: RingBuffer<u32>,
+}
+
+#[derive(GpuConstants)]
+#[repr(C)]
+struct Imports {
+	view: Mat4,
 }
 
 impl Gui {
@@ -101,6 +117,8 @@ impl Module for Gui {
 	const LOCAL: bool = true;
 
 	fn new() -> Self {
+		// Painted buffers are reused once the frames that read them have been presented
+		let frames = Gpu::device().frames_in_flight() + 1;
 		Self {
 			canvas: WidgetHandle::new(Canvas),
 			pipeline: Handle::find_or_load("{1e1526a8-852c-47f7-8436-2bbb01fe8a22}")
@@ -109,6 +127,10 @@ impl Module for Gui {
 
 			hovered: None,
 			focused: None,
+
+			imports: RingBuffer::new(BufferUsage::CONSTANTS, frames),
+			vertices: RingBuffer::new(BufferUsage::VERTEX, frames),
+			indices: RingBuffer::new(BufferUsage::INDEX, frames),
 		}
 	}
 
@@ -116,7 +138,7 @@ impl Module for Gui {
 		builder
 			.module::<Draw2d>()
 			.module::<ResourceManager>()
-			.process_input(|event| {
+			.listen(Priority::GUI, |event: &EngineEvent| {
 				let gui: &mut Gui = Engine::module_mut_checked().unwrap();
 
 				match event {
@@ -171,6 +193,8 @@ impl Module for Gui {
 										pressed: *pressed,
 									},
 								);
+								// Clicks on a widget should not reach the game
+								return Propagation::Consume;
 							}
 						} else {
 							let event = Event::Button {
@@ -179,8 +203,10 @@ impl Module for Gui {
 							};
 							if let Some(focused) = gui.focused.clone() {
 								gui.handle_event(focused, event);
+								return Propagation::Consume;
 							} else if let Some(hovered) = gui.hovered.clone() {
 								gui.handle_event(hovered, event);
+								return Propagation::Consume;
 							}
 						}
 					}
@@ -197,64 +223,61 @@ impl Module for Gui {
 					}
 					_ => {}
 				}
+				Propagation::Continue
 			})
-			.display(|| {
-				let gui: &mut Gui = Engine::module_mut_checked().unwrap();
-
-				let window = Engine::window().unwrap();
-				let dpi = window.scale_factor() as f32;
-				let viewport = window.inner_size();
-				let viewport = Vec2::new(viewport.width as f32 / dpi, viewport.height as f32 / dpi);
-
-				let mut canvas = gui.canvas.borrow_mut();
-				if canvas.layout().is_none() || gui.viewport != viewport {
-					gui.viewport = viewport;
-					canvas.update_layout(Layout {
-						local_bounds: Rect::from_min_max(Point2::ZERO, viewport),
-						local_to_absolute: Mat3::IDENTITY,
-					});
-				}
-
-				let mut painter = Painter::new();
-				canvas.paint(&mut painter);
-				if !painter.is_empty() {
-					let (vertices, indices) = painter.finish().unwrap();
-					let device = Gpu::device();
-					let backbuffer = device.acquire_backbuffer().unwrap();
-					let pipeline = gui.pipeline.read();
-
-					let proj = Mat4::ortho(viewport.x, viewport.y, 1000.0, 0.1);
-					let view = Mat4::translate([-viewport.x / 2.0, -viewport.y / 2.0, 0.0]);
+			.register(PresentPass::new(PresentLayer::GUI, Gui::present))
+			.display(Gpu::present)
+	}
+}
 
-					#[allow(dead_code)]
-					struct Imports {
-						view: Mat4,
-					}
+impl Gui {
+	/// Paints the canvas over everything already drawn into the backbuffer
+	fn present(graph: &mut RenderGraph, backbuffer: TextureId) {
+		let gui: &mut Gui = Engine::module_mut_checked().unwrap();
+
+		let window = Engine::window().unwrap();
+		let dpi = window.scale_factor() as f32;
+		let viewport = window.inner_size();
+		let viewport = Vec2::new(viewport.width as f32 / dpi, viewport.height as f32 / dpi);
+
+		let mut canvas = gui.canvas.borrow_mut();
+		if canvas.layout().is_none() || gui.viewport != viewport {
+			gui.viewport = viewport;
+			canvas.update_layout(Layout {
+				local_bounds: Rect::from_min_max(Point2::ZERO, viewport),
+				local_to_absolute: Mat3::IDENTITY,
+			});
+		}
 
-					let imports =
-						Buffer::new(BufferUsage::CONSTANTS, MemoryType::HostVisible, 1).unwrap();
-					imports.copy_to(&[Imports { view: proj * view }]).unwrap();
-
-					let receipt = GraphicsRecorder::new()
-						.render_pass(&[&backbuffer], |ctx| {
-							ctx.clear_color(Color::BLACK)
-								.set_pipeline(&pipeline)
-								.set_vertex_buffer(&vertices)
-								.set_index_buffer(&indices)
-								.set_constants("imports", &imports, 0)
-								.draw_indexed(indices.len(), 0)
-						})
-						.texture_barrier(
-							&backbuffer,
-							gpu::Layout::ColorAttachment,
-							gpu::Layout::Present,
-						)
-						.submit();
-
-					device.display(&[receipt]);
-					device.wait_for_idle();
-				}
-			})
+		let mut painter = Painter::new();
+		canvas.paint(&mut painter);
+		if !painter.is_empty() {
+			let (vertices, indices) = painter
+				.finish_into(&mut gui.vertices, &mut gui.indices)
+				.unwrap();
+			let index_count = painter.index_count();
+			let pipeline = gui.pipeline.read();
+
+			let proj = Mat4::ortho(viewport.x, viewport.y, 1000.0, 0.1);
+			let view = Mat4::translate([-viewport.x / 2.0, -viewport.y / 2.0, 0.0]);
+
+			let imports = gui
+				.imports
+				.upload(&[Imports { view: proj * view }])
+				.unwrap()
+				.clone();
+
+			graph
+				.add_pass("gui")
+				.color(backbuffer)
+				.render(move |ctx, _| {
+					ctx.set_pipeline(&pipeline)
+						.set_vertex_buffer(&vertices)
+						.set_index_buffer(&indices)
+						.set_constants("imports", &imports, 0)
+						.draw_indexed(index_count, 0);
+				});
+		}
 	}
 }
 
@@ -0,0 +1,209 @@
+//! Swaps a game module compiled as a `cdylib` while the engine keeps running.
+//!
+//! The library is expected to export its module using [`engine::define_library_module`]. Every time the
+//! library is rebuilt the [`ecs::World`] is snapshotted, the module is dropped and spawned from the new
+//! library and the snapshot is restored using the new [`ecs::ComponentVariant`]s.
+//!
+//! The host and the library must be built by the same compiler against the same engine as Rust types cross
+//! the library boundary.
+//!
+//! Only the top level module is replaced. Dependencies such as [`Game`] keep running from the library they
+//! were first loaded from, so changes to them still require a restart.
+
+use {
+	ecs::WorldSnapshot,
+	engine::{
+		define_log_category,
+		error,
+		info,
+		Builder,
+		Engine,
+		Module,
+	},
+	game::Game,
+	os::{
+		Library,
+		LibraryError,
+	},
+	std::{
+		any::TypeId,
+		env::consts::{
+			DLL_EXTENSION,
+			DLL_PREFIX,
+			DLL_SUFFIX,
+		},
+		fs,
+		io,
+		path::{
+			Path,
+			PathBuf,
+		},
+		sync::{
+			Arc,
+			Mutex,
+		},
+		time::{
+			Duration,
+			SystemTime,
+		},
+	},
+};
+
+define_log_category!(HotReload, HOT_RELOAD_CATEGORY);
+
+// Must match the exports of `define_library_module!`
+type AttachFn = unsafe extern "C" fn(*mut Engine);
+#[allow(improper_ctypes_definitions)]
+type RegisterFn = extern "C" fn(&mut Builder) -> TypeId;
+
+// The linker may still be writing the library when its modified time first changes
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// Library loaded through [`HotReload::load`]. Registered so [`HotReload`] can attach it at initialization.
+#[derive(Clone)]
+pub struct HotReloadLibrary {
+	path: PathBuf,
+	library: Arc<Library>,
+	module: TypeId,
+}
+
+struct Inner {
+	path: PathBuf,
+	modified: SystemTime,
+	generation: u32,
+	module: TypeId,
+
+	// Old libraries are never unloaded. Callbacks and vtables handed to the engine may still point into them.
+	libraries: Vec<Arc<Library>>,
+}
+
+/// Module that watches a game library and swaps it in when it is rebuilt
+pub struct HotReload(Mutex<Option<Inner>>);
+
+impl HotReload {
+	/// Loads the library `name` found next to the running executable and registers its module on `builder`
+	///
+	/// The library is copied before being loaded so the original can be overwritten by the next build.
+	pub fn load<'a>(builder: &'a mut Builder, name: &str) -> io::Result<&'a mut Builder> {
+		let exe_path = std::env::current_exe()?;
+		let directory = exe_path.parent().unwrap();
+		let path = directory.join(format!("{}{}{}", DLL_PREFIX, name, DLL_SUFFIX));
+
+		let library = Arc::new(load_copy(&path, 0)?);
+		let register: RegisterFn = *library
+			.get::<RegisterFn>("newport_library_register")
+			.map_err(to_io_error)?;
+
+		// Must be the first module so the library is attached before any of its modules are spawned
+		builder.module::<HotReload>();
+		let module = register(builder);
+
+		Ok(builder.register(HotReloadLibrary {
+			path,
+			library,
+			module,
+		}))
+	}
+
+	fn reload(inner: &mut Inner) -> io::Result<()> {
+		inner.generation += 1;
+		let library = Arc::new(load_copy(&inner.path, inner.generation)?);
+		let attach: AttachFn = *library
+			.get::<AttachFn>("newport_library_attach")
+			.map_err(to_io_error)?;
+		let register: RegisterFn = *library
+			.get::<RegisterFn>("newport_library_register")
+			.map_err(to_io_error)?;
+
+		let game: &Game = Engine::module().unwrap();
+		let snapshot: WorldSnapshot = game
+			.world
+			.snapshot()
+			.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+		unsafe {
+			attach(Engine::as_ptr());
+
+			let mut builder = Builder::new();
+			let module = register(&mut builder);
+			Engine::extend(&mut builder, &[inner.module, module]);
+			inner.module = module;
+
+			let game: &mut Game = Engine::module_mut().unwrap();
+			game.world.reload_variants();
+			game.world.restore(&snapshot);
+		}
+
+		inner.libraries.push(library);
+		Ok(())
+	}
+}
+
+impl Module for HotReload {
+	fn new() -> Self {
+		let libraries: &[HotReloadLibrary] = Engine::register();
+		let inner = libraries.first().map(|it| {
+			let attach: AttachFn = *it.library.get::<AttachFn>("newport_library_attach").expect(
+				"Library is missing \"newport_library_attach\". Use `define_library_module!`.",
+			);
+			unsafe { attach(Engine::as_ptr()) };
+
+			Inner {
+				path: it.path.clone(),
+				modified: modified(&it.path).unwrap_or(SystemTime::UNIX_EPOCH),
+				generation: 0,
+				module: it.module,
+
+				libraries: vec![it.library.clone()],
+			}
+		});
+
+		Self(Mutex::new(inner))
+	}
+
+	fn depends_on(builder: &mut Builder) -> &mut Builder {
+		builder.tick(|_| {
+			let hot_reload: &HotReload = Engine::module().unwrap();
+			let mut inner = hot_reload.0.lock().unwrap();
+			let inner = match inner.as_mut() {
+				Some(inner) => inner,
+				None => return,
+			};
+
+			let modified = match modified(&inner.path) {
+				Ok(modified) => modified,
+				Err(_) => return,
+			};
+			let settled = SystemTime::now()
+				.duration_since(modified)
+				.map(|elapsed| elapsed >= SETTLE_TIME)
+				.unwrap_or(false);
+			if modified <= inner.modified || !settled {
+				return;
+			}
+			inner.modified = modified;
+
+			info!(HOT_RELOAD_CATEGORY, "Reloading {:?}", inner.path);
+			match HotReload::reload(inner) {
+				Ok(_) => info!(HOT_RELOAD_CATEGORY, "Reload {} finished", inner.generation),
+				Err(err) => error!(HOT_RELOAD_CATEGORY, "Reload failed: {}", err),
+			}
+		})
+	}
+}
+
+fn modified(path: &Path) -> io::Result<SystemTime> {
+	fs::metadata(path)?.modified()
+}
+
+fn load_copy(path: &Path, generation: u32) -> io::Result<Library> {
+	let mut copy = path.to_path_buf();
+	copy.set_extension(format!("{}.{}", generation, DLL_EXTENSION));
+	fs::copy(path, &copy)?;
+
+	Library::new(&copy).map_err(to_io_error)
+}
+
+fn to_io_error(err: LibraryError) -> io::Error {
+	io::Error::new(io::ErrorKind::NotFound, format!("{:?}", err))
+}
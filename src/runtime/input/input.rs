@@ -9,6 +9,8 @@ use {
 		Engine,
 		Event,
 		Module,
+		Priority,
+		Propagation,
 	},
 	serde::{
 		Deserialize,
@@ -35,9 +37,10 @@ impl Module for GameInput {
 	fn depends_on(builder: &mut Builder) -> &mut Builder {
 		builder
 			.register(InputManager::variant())
-			.process_input(|event| {
+			.listen(Priority::GAME, |event: &Event| {
 				let input: &mut GameInput = unsafe { Engine::module_mut().unwrap() };
-				input.event_queue.push(*event);
+				input.event_queue.push(event.clone());
+				Propagation::Continue
 			})
 	}
 }
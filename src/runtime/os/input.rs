@@ -163,6 +163,9 @@ pub const KEY_F24: Input = Input::key("F24");
 pub const MOUSE_BUTTON_LEFT: Input = Input::mouse_button("Left Mouse Button");
 pub const MOUSE_BUTTON_MIDDLE: Input = Input::mouse_button("Middle Mouse Button");
 pub const MOUSE_BUTTON_RIGHT: Input = Input::mouse_button("Right Mouse Button");
+pub const MOUSE_BUTTON_BACK: Input = Input::mouse_button("Back Mouse Button");
+pub const MOUSE_BUTTON_FORWARD: Input = Input::mouse_button("Forward Mouse Button");
+pub const MOUSE_BUTTON_OTHER: Input = Input::mouse_button("Other Mouse Button");
 
 pub const MOUSE_AXIS_X: Input = Input::mouse_axis("Mouse Axis X");
 pub const MOUSE_AXIS_Y: Input = Input::mouse_axis("Mouse Axis Y");
@@ -12,6 +12,7 @@ pub use winit::{
 	self,
 	event::{
 		Event,
+		MouseButton,
 		VirtualKeyCode,
 		WindowEvent,
 	},
@@ -30,20 +31,23 @@ pub use raw_window_handle::{
 	RawWindowHandle,
 };
 
+use std::{
+	ffi::{
+		c_void,
+		OsStr,
+	},
+	ops::Deref,
+};
+
 #[cfg(target_os = "windows")]
 use std::{
-	ffi::OsStr,
 	iter::Iterator,
-	ops::Deref,
 	os::windows::ffi::OsStrExt,
 };
 
 #[cfg(target_os = "windows")]
 use windows::{
-	shared::minwindef::{
-		FARPROC,
-		HMODULE,
-	},
+	shared::minwindef::HMODULE,
 	um::libloaderapi::{
 		FreeLibrary,
 		GetProcAddress,
@@ -51,9 +55,15 @@ use windows::{
 	},
 };
 
-#[cfg(target_os = "windows")]
+#[cfg(unix)]
+use std::{
+	ffi::CString,
+	os::unix::ffi::OsStrExt,
+};
+
+/// Function or data pointer resolved from a [`Library`]. Only valid for the lifetime of the library.
 pub struct Symbol<'a, T> {
-	ptr: FARPROC,
+	ptr: *mut c_void,
 	phantom: std::marker::PhantomData<&'a T>,
 }
 
@@ -78,15 +88,19 @@ pub enum LibraryError {
 	NotFound,
 }
 
-#[cfg(target_os = "windows")]
+/// Dynamically loaded library. Wraps `LoadLibraryW` on windows and `dlopen` on unix.
 pub struct Library {
+	#[cfg(target_os = "windows")]
 	hmodule: HMODULE,
+	#[cfg(unix)]
+	handle: *mut c_void,
 }
 
+#[cfg(target_os = "windows")]
 impl Library {
 	pub fn new(path: impl AsRef<OsStr>) -> Result<Self, LibraryError> {
 		unsafe {
-			let path: Vec<u16> = path.as_ref().encode_wide().collect();
+			let path: Vec<u16> = path.as_ref().encode_wide().chain(Some(0)).collect();
 			let hmodule = LoadLibraryW(path.as_ptr());
 			if hmodule.is_null() {
 				Err(LibraryError::NotFound)
@@ -98,7 +112,35 @@ impl Library {
 
 	pub fn get<T>(&self, symbol: &str) -> Result<Symbol<'_, T>, LibraryError> {
 		assert!(symbol.is_ascii());
+		let symbol = format!("{}\0", symbol);
 		let ptr = unsafe { GetProcAddress(self.hmodule, symbol.as_ptr() as *const i8) };
+		if ptr.is_null() {
+			Err(LibraryError::NotFound)
+		} else {
+			Ok(Symbol {
+				ptr: ptr as *mut _,
+				phantom: std::marker::PhantomData,
+			})
+		}
+	}
+}
+
+#[cfg(unix)]
+impl Library {
+	pub fn new(path: impl AsRef<OsStr>) -> Result<Self, LibraryError> {
+		let path = CString::new(path.as_ref().as_bytes()).map_err(|_| LibraryError::NotFound)?;
+		let handle = unsafe { libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+		if handle.is_null() {
+			Err(LibraryError::NotFound)
+		} else {
+			Ok(Self { handle })
+		}
+	}
+
+	pub fn get<T>(&self, symbol: &str) -> Result<Symbol<'_, T>, LibraryError> {
+		assert!(symbol.is_ascii());
+		let symbol = CString::new(symbol).map_err(|_| LibraryError::NotFound)?;
+		let ptr = unsafe { libc::dlsym(self.handle, symbol.as_ptr()) };
 		if ptr.is_null() {
 			Err(LibraryError::NotFound)
 		} else {
@@ -114,10 +156,39 @@ unsafe impl Send for Library {}
 unsafe impl Sync for Library {}
 
 impl Drop for Library {
+	#[cfg(target_os = "windows")]
 	fn drop(&mut self) {
 		let result = unsafe { FreeLibrary(self.hmodule) };
 		assert!(result > 0);
 	}
+
+	#[cfg(unix)]
+	fn drop(&mut self) {
+		let result = unsafe { libc::dlclose(self.handle) };
+		assert_eq!(result, 0);
+	}
+}
+
+/// Maps a winit mouse button to an [`Input`]. The ids of the side buttons differ per platform.
+pub fn mouse_button_to_input(button: MouseButton) -> Input {
+	match button {
+		MouseButton::Left => MOUSE_BUTTON_LEFT,
+		MouseButton::Right => MOUSE_BUTTON_RIGHT,
+		MouseButton::Middle => MOUSE_BUTTON_MIDDLE,
+		#[cfg(target_os = "windows")]
+		MouseButton::Other(1) => MOUSE_BUTTON_BACK,
+		#[cfg(target_os = "windows")]
+		MouseButton::Other(2) => MOUSE_BUTTON_FORWARD,
+		#[cfg(target_os = "macos")]
+		MouseButton::Other(3) => MOUSE_BUTTON_BACK,
+		#[cfg(target_os = "macos")]
+		MouseButton::Other(4) => MOUSE_BUTTON_FORWARD,
+		#[cfg(all(unix, not(target_os = "macos")))]
+		MouseButton::Other(8) => MOUSE_BUTTON_BACK,
+		#[cfg(all(unix, not(target_os = "macos")))]
+		MouseButton::Other(9) => MOUSE_BUTTON_FORWARD,
+		MouseButton::Other(_) => MOUSE_BUTTON_OTHER,
+	}
 }
 
 pub fn virtual_keycode_to_input(vk: VirtualKeyCode) -> Input {
@@ -19,6 +19,7 @@ pub struct SystemDate {
 
 impl SystemDate {
 	/// Returns a `SystemDate` at the local time
+	#[cfg(target_os = "windows")]
 	pub fn now() -> Self {
 		let mut system_time = SYSTEMTIME::default();
 		unsafe { GetLocalTime(&mut system_time) };
@@ -33,4 +34,30 @@ impl SystemDate {
 			milli: system_time.wMilliseconds,
 		}
 	}
+
+	/// Returns a `SystemDate` at the local time
+	#[cfg(unix)]
+	pub fn now() -> Self {
+		let mut spec = libc::timespec {
+			tv_sec: 0,
+			tv_nsec: 0,
+		};
+		let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+		unsafe {
+			libc::clock_gettime(libc::CLOCK_REALTIME, &mut spec);
+			libc::localtime_r(&spec.tv_sec, &mut tm);
+		}
+
+		// `tm` stores years since 1900 and zero based months. Match the windows `SYSTEMTIME` layout.
+		Self {
+			year: (tm.tm_year + 1900) as u16,
+			month: (tm.tm_mon + 1) as u16,
+			day_of_week: tm.tm_wday as u16,
+			day_of_month: tm.tm_mday as u16,
+			hour: tm.tm_hour as u16,
+			minute: tm.tm_min as u16,
+			second: tm.tm_sec.min(59) as u16,
+			milli: (spec.tv_nsec / 1_000_000) as u16,
+		}
+	}
 }
@@ -266,7 +266,7 @@ impl System for PhysicsStep {
 
 		let gravity = vector![0.0, -9.81];
 
-		// TODO: Should this just be 60 fps for stability?
+		// Expected to run in `Phase::Fixed` so `dt` is the constant fixed delta
 		integration_parameters.dt = dt;
 
 		physics_pipeline.step(
@@ -12,7 +12,6 @@ use {
 pub struct Collider {
 	pub(crate) handle: Option<ColliderHandle>,
 
-	#[serde(flatten)]
 	pub(crate) description: ColliderDescription,
 }
 
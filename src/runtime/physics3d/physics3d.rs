@@ -47,8 +47,13 @@ use {
 		},
 	},
 	serde::{
+		bincode,
+		de,
+		ser,
 		Deserialize,
+		Deserializer,
 		Serialize,
+		Serializer,
 	},
 	std::ops::DerefMut,
 };
@@ -115,10 +120,8 @@ impl Movement {
 	}
 }
 
-#[derive(Serialize, Deserialize)]
 pub struct PhysicsManager {
 	integration_parameters: IntegrationParameters,
-	#[serde(skip, default = "PhysicsPipeline::new")]
 	physics_pipeline: PhysicsPipeline,
 	island_manager: IslandManager,
 	broad_phase: BroadPhase,
@@ -130,7 +133,6 @@ pub struct PhysicsManager {
 	query_pipeline: QueryPipeline,
 
 	gravity: Vec3,
-	timer: f32,
 }
 
 impl PhysicsManager {
@@ -148,7 +150,6 @@ impl PhysicsManager {
 			query_pipeline: QueryPipeline::new(),
 
 			gravity: Vec3::new(0.0, 0.0, -9.8),
-			timer: 0.0,
 		}
 	}
 
@@ -228,6 +229,87 @@ impl PhysicsManager {
 
 impl Component for PhysicsManager {}
 
+/// Serialized form of [`PhysicsManager`]
+///
+/// RON can not round trip the SIMD bounding boxes inside rapier's sets once the simulation has stepped, so
+/// everything but the pipeline is packed with bincode. This keeps the simulation alive across a hot reload.
+#[derive(Serialize, Deserialize)]
+struct PhysicsState {
+	simulation: Vec<u8>,
+	gravity: Vec3,
+}
+
+type Simulation = (
+	IntegrationParameters,
+	IslandManager,
+	BroadPhase,
+	NarrowPhase,
+	JointSet,
+	CCDSolver,
+	RigidBodySet,
+	ColliderSet,
+	QueryPipeline,
+);
+
+impl Serialize for PhysicsManager {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		let simulation = bincode::serialize(&(
+			&self.integration_parameters,
+			&self.island_manager,
+			&self.broad_phase,
+			&self.narrow_phase,
+			&self.joint_set,
+			&self.ccd_solver,
+			&self.rigid_body_set,
+			&self.collider_set,
+			&self.query_pipeline,
+		))
+		.map_err(ser::Error::custom)?;
+
+		PhysicsState {
+			simulation,
+			gravity: self.gravity,
+		}
+		.serialize(serializer)
+	}
+}
+
+impl<'de> Deserialize<'de> for PhysicsManager {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let PhysicsState {
+			simulation,
+			gravity,
+		} = PhysicsState::deserialize(deserializer)?;
+
+		let (
+			integration_parameters,
+			island_manager,
+			broad_phase,
+			narrow_phase,
+			joint_set,
+			ccd_solver,
+			rigid_body_set,
+			collider_set,
+			query_pipeline,
+		): Simulation = bincode::deserialize(&simulation).map_err(de::Error::custom)?;
+
+		Ok(Self {
+			integration_parameters,
+			physics_pipeline: PhysicsPipeline::new(),
+			island_manager,
+			broad_phase,
+			narrow_phase,
+			joint_set,
+			ccd_solver,
+			rigid_body_set,
+			collider_set,
+			query_pipeline,
+
+			gravity,
+		})
+	}
+}
+
 impl Default for PhysicsManager {
 	fn default() -> Self {
 		Self::new()
@@ -249,7 +331,6 @@ impl Clone for PhysicsManager {
 			query_pipeline: self.query_pipeline.clone(),
 
 			gravity: self.gravity,
-			timer: self.timer,
 		}
 	}
 }
@@ -271,7 +352,6 @@ impl System for PhysicsSystem {
 			rigid_body_set,
 			collider_set,
 			gravity,
-			timer,
 			query_pipeline,
 		} = physics_manager.deref_mut();
 
@@ -359,55 +439,130 @@ impl System for PhysicsSystem {
 			}
 		}
 
-		const TIME_STEP: f32 = 1.0 / 60.0;
-		*timer += dt;
-		if *timer >= TIME_STEP {
-			*timer -= TIME_STEP;
-			let physics_hooks = ();
-			let event_handler = ();
-
-			let gravity = vector![gravity.x, gravity.y, gravity.z];
-
-			physics_pipeline.step(
-				&gravity,
-				integration_parameters,
-				island_manager,
-				broad_phase,
-				narrow_phase,
-				rigid_body_set,
-				collider_set,
-				joint_set,
-				ccd_solver,
-				&physics_hooks,
-				&event_handler,
-			);
+		let physics_hooks = ();
+		let event_handler = ();
 
-			query_pipeline.update(island_manager, rigid_body_set, collider_set);
-
-			// Iterate through every entity with a rigid body and update their locations and rotations.
-			// FIXME: Only update the entities that actually changed
-			for e in entities.iter().copied() {
-				if let Some(rigid_body) = rigid_bodies.get_mut(e) {
-					let mut transform = transforms.get_mut(e).unwrap();
-					let rigid_body = rigid_body_set
-						.get(rigid_body.handle.unwrap())
-						.expect("Should be registered");
-
-					let location = rigid_body.translation();
-					let rotation = rigid_body.rotation();
-					transform.set_local_location_and_rotation(
-						[location[0], location[1], location[2]],
-						Quat {
-							x: rotation.i,
-							y: rotation.j,
-							z: rotation.k,
-							w: rotation.w,
-						},
-						&transforms,
-					);
-					transform.set_changed(false);
-				}
+		let gravity = vector![gravity.x, gravity.y, gravity.z];
+
+		// Expected to run in `Phase::Fixed` so `dt` is the constant fixed delta
+		integration_parameters.dt = dt;
+
+		physics_pipeline.step(
+			&gravity,
+			integration_parameters,
+			island_manager,
+			broad_phase,
+			narrow_phase,
+			rigid_body_set,
+			collider_set,
+			joint_set,
+			ccd_solver,
+			&physics_hooks,
+			&event_handler,
+		);
+
+		query_pipeline.update(island_manager, rigid_body_set, collider_set);
+
+		// Iterate through every entity with a rigid body and update their locations and rotations.
+		// FIXME: Only update the entities that actually changed
+		for e in entities.iter().copied() {
+			if let Some(rigid_body) = rigid_bodies.get_mut(e) {
+				let mut transform = transforms.get_mut(e).unwrap();
+				let rigid_body = rigid_body_set
+					.get(rigid_body.handle.unwrap())
+					.expect("Should be registered");
+
+				let location = rigid_body.translation();
+				let rotation = rigid_body.rotation();
+				transform.set_local_location_and_rotation(
+					[location[0], location[1], location[2]],
+					Quat {
+						x: rotation.i,
+						y: rotation.j,
+						z: rotation.k,
+						w: rotation.w,
+					},
+					&transforms,
+				);
+				transform.set_changed(false);
 			}
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		engine::Builder,
+	};
+
+	#[test]
+	fn physics_survives_snapshot() {
+		Builder::new()
+			.register(Transform::variant())
+			.register(PhysicsManager::variant())
+			.register(Collider::variant())
+			.register(RigidBody::variant())
+			.test()
+			.unwrap();
+
+		let mut world = World::new();
+		let ball = {
+			let mut transforms = world.write::<Transform>();
+			let mut colliders = world.write::<Collider>();
+			let mut rigid_bodies = world.write::<RigidBody>();
+			world
+				.spawn()
+				.with(
+					Transform::builder().location([0.0, 0.0, 10.0]).finish(),
+					&mut transforms,
+				)
+				.with(
+					Collider::builder(Shape::cube([1.0, 1.0, 1.0])).build(),
+					&mut colliders,
+				)
+				.with(
+					RigidBody::builder(RigidBodyVariant::Dynamic).build(),
+					&mut rigid_bodies,
+				)
+				.finish()
+		};
+
+		let location = |world: &World| {
+			world
+				.read::<Transform>()
+				.get(ball)
+				.unwrap()
+				.local_location()
+		};
+		for _ in 0..10 {
+			PhysicsSystem.run(&world, 1.0 / 60.0);
+		}
+		let before = location(&world);
+
+		let snapshot = world.snapshot().unwrap();
+		world.reload_variants();
+		world.restore(&snapshot);
+		{
+			let physics_managers = world.read::<PhysicsManager>();
+			let physics_manager = physics_managers.get(world.singleton).unwrap();
+			assert_eq!(physics_manager.rigid_body_set.len(), 1);
+			assert_eq!(physics_manager.collider_set.len(), 1);
+		}
+		assert_eq!(location(&world), before);
+
+		// The body keeps falling from where it was instead of being registered again
+		PhysicsSystem.run(&world, 1.0 / 60.0);
+		assert!(location(&world).z < before.z);
+		let physics_managers = world.read::<PhysicsManager>();
+		assert_eq!(
+			physics_managers
+				.get(world.singleton)
+				.unwrap()
+				.rigid_body_set
+				.len(),
+			1
+		);
+	}
+}
@@ -12,7 +12,6 @@ use {
 pub struct RigidBody {
 	pub(crate) handle: Option<RigidBodyHandle>,
 
-	#[serde(flatten)]
 	pub(crate) description: RigidBodyDescription,
 }
 
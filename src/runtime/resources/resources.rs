@@ -160,7 +160,11 @@ impl<T: Resource> Handle<T> {
 			let meta_file = fs::read(meta_path)?;
 			let meta = (importer_variant.load_meta)(&meta_file[..])?.1;
 
-			let arc = Arc::new((importer_variant.load_resource)(&meta, &resource_file[..])?);
+			let arc = Arc::new((importer_variant.load_resource)(
+				&meta,
+				&resource_file[..],
+				path,
+			)?);
 			let dur = Instant::now().duration_since(now).as_secs_f64() * 1000.0;
 
 			info!(
@@ -322,7 +326,7 @@ pub struct ImporterVariant {
 	extensions: &'static [&'static str],
 
 	#[allow(clippy::type_complexity)]
-	load_resource: fn(&Box<dyn Any>, &[u8]) -> Result<Box<dyn Any>>,
+	load_resource: fn(&Box<dyn Any>, &[u8], &Path) -> Result<Box<dyn Any>>,
 	#[allow(clippy::type_complexity)]
 	load_meta: fn(&[u8]) -> Result<(Uuid, Box<dyn Any>)>,
 
@@ -337,13 +341,18 @@ pub struct ImporterVariant {
 pub trait Importer: Sized + Serialize + DeserializeOwned + 'static {
 	type Target: Resource;
 
-	fn import(&self, bytes: &[u8]) -> Result<Self::Target>;
+	/// Creates the resource from the contents of the file at `path`
+	fn import(&self, bytes: &[u8], path: &Path) -> Result<Self::Target>;
 	fn export(&self, resource: &Self::Target, file: &mut File) -> Result<()>;
 
 	fn variant(extensions: &'static [&'static str]) -> ImporterVariant {
-		fn load_resource<T: Importer>(meta: &Box<dyn Any>, bytes: &[u8]) -> Result<Box<dyn Any>> {
+		fn load_resource<T: Importer>(
+			meta: &Box<dyn Any>,
+			bytes: &[u8],
+			path: &Path,
+		) -> Result<Box<dyn Any>> {
 			let meta = meta.downcast_ref::<T>().unwrap();
-			Ok(Box::new(meta.import(bytes)?))
+			Ok(Box::new(meta.import(bytes, path)?))
 		}
 
 		fn load_meta<T: Importer>(bytes: &[u8]) -> Result<(Uuid, Box<dyn Any>)> {
@@ -411,7 +420,7 @@ pub struct NativeImporter<T: Resource> {
 impl<T: Resource + Serialize + DeserializeOwned> Importer for NativeImporter<T> {
 	type Target = T;
 
-	fn import(&self, bytes: &[u8]) -> Result<Self::Target> {
+	fn import(&self, bytes: &[u8], _path: &Path) -> Result<Self::Target> {
 		let contents = std::str::from_utf8(bytes)?;
 		Ok(ron::from_str(contents)?)
 	}
@@ -430,7 +439,7 @@ pub struct BinaryImporter<T: Resource> {
 impl<T: Resource + Serialize + DeserializeOwned> Importer for BinaryImporter<T> {
 	type Target = T;
 
-	fn import(&self, bytes: &[u8]) -> Result<Self::Target> {
+	fn import(&self, bytes: &[u8], _path: &Path) -> Result<Self::Target> {
 		Ok(bincode::deserialize(bytes)?)
 	}
 
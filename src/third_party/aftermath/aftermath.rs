@@ -1,3 +1,6 @@
+//! Bindings to Nvidia Aftermath. Only available on Windows
+#![cfg(target_os = "windows")]
+
 pub mod sys;
 use libloading::Library;
 use sys::*;
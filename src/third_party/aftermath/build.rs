@@ -2,6 +2,11 @@ use std::env;
 use std::path::PathBuf;
 
 fn main() {
+	// The SDK only ships for Windows. The crate is empty everywhere else
+	if env::var("CARGO_CFG_TARGET_OS").as_deref() != Ok("windows") {
+		return;
+	}
+
 	// copy the dll to the out dir
 	let out_dir = env::var("OUT_DIR").unwrap();
 	let mut path = PathBuf::from(out_dir);
@@ -6,10 +6,13 @@ use {
 		Engine,
 		Event as EngineEvent,
 		Module,
+		Priority,
+		Propagation,
 	},
 
 	gpu::{
 		Buffer,
+		GpuConstants,
 		GraphicsPipeline,
 		GraphicsRecorder,
 		Texture,
@@ -74,7 +77,7 @@ impl Module for Egui {
 		builder
 			.module::<gpu::Gpu>()
 			.module::<resources::ResourceManager>()
-			.process_input(|event| {
+			.listen(Priority::GUI, |event: &EngineEvent| {
 				let egui: &mut Egui = unsafe { Engine::module_mut().unwrap() };
 				if egui.input.is_none() {
 					egui.input = Some(RawInput::default());
@@ -153,7 +156,7 @@ impl Module for Egui {
 						};
 
 						if key.is_none() {
-							return;
+							return Propagation::Continue;
 						}
 						let key = key.unwrap();
 						input.events.push(Event::Key {
@@ -173,7 +176,7 @@ impl Module for Egui {
 							_ => None,
 						};
 						if button.is_none() {
-							return;
+							return Propagation::Continue;
 						}
 						let button = button.unwrap();
 
@@ -200,6 +203,7 @@ impl Module for Egui {
 					}
 					_ => {}
 				}
+				Propagation::Continue
 			})
 			.tick(|dt| {
 				let egui: &mut Egui = unsafe { Engine::module_mut().unwrap() };
@@ -370,7 +374,8 @@ impl Module for Egui {
 				let proj = Mat4::ortho(viewport.x, -viewport.y, 1000.0, 0.1);
 				let view = Mat4::translate(Vec3::new(-viewport.x / 2.0, -viewport.y / 2.0, 0.0));
 
-				#[allow(dead_code)]
+				#[derive(GpuConstants)]
+				#[repr(C)]
 				struct Imports {
 					view: Mat4,
 				}
@@ -381,10 +386,18 @@ impl Module for Egui {
 				imports.copy_to(&[Imports { view: proj * view }]).unwrap();
 
 				let device = gpu::Gpu::device();
-				let backbuffer = device.acquire_backbuffer().unwrap();
+				let backbuffer = match device.acquire_backbuffer().unwrap() {
+					Some(backbuffer) => backbuffer,
+					None => return,
+				};
 
 				let pipeline = egui.pipeline.read();
 				let receipt = GraphicsRecorder::new()
+					.texture_barrier(
+						&backbuffer,
+						gpu::Layout::Undefined,
+						gpu::Layout::ColorAttachment,
+					)
 					.render_pass(&[&backbuffer], |ctx| {
 						ctx.clear_color(Color::BLACK)
 							.set_pipeline(&pipeline)